@@ -0,0 +1,171 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) major: f32,
+    @location(2) distance: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) major: f32,
+    @location(2) distance: f32,
+    @location(3) normal: vec3<f32>,
+    // Displacement of the vertex and its distance from the camera
+    @location(4) height: f32,
+    @location(5) view_distance: f32,
+};
+
+struct Uniforms {
+    @location(0) time: f32,
+    @location(1) motion: f32,
+    @location(2) line_style: u32,
+    @location(3) view_proj: mat4x4<f32>,
+    @location(4) major_color: vec4<f32>,
+    @location(5) minor_color: vec4<f32>,
+    @location(6) model: mat4x4<f32>,
+    @location(7) palette_mode: u32,
+    @location(8) palette_last_stop: u32,
+    @location(9) palette_range: f32,
+    @location(10) palette: array<vec4<f32>, 8>,
+    @location(11) fog_color: vec4<f32>,
+    @location(12) fog_mode: u32,
+    @location(13) fog_start: f32,
+    @location(14) fog_end: f32,
+    @location(15) fog_density: f32,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+// Square height field written by the compute pass each frame
+@group(0) @binding(1) var<storage, read> heights: array<f32>;
+
+// Dash patterns in grid units; dashes march along the lines over time
+const DASH_PERIOD: f32 = 0.1;
+const DOT_PERIOD: f32 = 0.05;
+const DASH_SPEED: f32 = 0.05;
+
+// Light from above and behind the default camera. Slopes facing it are
+// brightened and slopes facing away darkened; flat areas keep their color.
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.3, 0.8, -0.52);
+
+// Bilinearly sample the height field, which covers -1..1 on X and Z
+fn sample_height(x: f32, z: f32) -> f32 {
+    let size = u32(round(sqrt(f32(arrayLength(&heights)))));
+    let uv = clamp((vec2<f32>(x, z) + 1.0) * 0.5, vec2<f32>(0.0), vec2<f32>(1.0)) * f32(size - 1u);
+    let cell = min(vec2<u32>(floor(uv)), vec2<u32>(size - 2u));
+    let f = uv - vec2<f32>(cell);
+    let i = cell.y * size + cell.x;
+    let bottom = mix(heights[i], heights[i + 1u], f.x);
+    let top = mix(heights[i + size], heights[i + size + 1u], f.x);
+    return mix(bottom, top, f.y);
+}
+
+// Surface normal in grid space from central differences one height field
+// cell apart, scaled like the displacement
+fn sample_normal(x: f32, z: f32) -> vec3<f32> {
+    let step = 2.0 / (sqrt(f32(arrayLength(&heights))) - 1.0);
+    let dx = sample_height(x + step, z) - sample_height(x - step, z);
+    let dz = sample_height(x, z + step) - sample_height(x, z - step);
+    return normalize(vec3<f32>(-dx * uniforms.motion, 2.0 * step, -dz * uniforms.motion));
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    var pos = model.position;
+
+    // Apply the height field, scaled down when reduced motion is on
+    let height = sample_height(pos.x, pos.z) * uniforms.motion;
+    pos.y += height;
+
+    // Heights are sampled in grid space, before the model transform
+    let world = uniforms.model * vec4<f32>(pos, 1.0);
+    let normal = uniforms.model * vec4<f32>(sample_normal(pos.x, pos.z), 0.0);
+
+    out.world_position = world.xyz;
+    out.major = model.major;
+    out.distance = model.distance;
+    out.normal = normal.xyz;
+    out.height = height;
+    out.clip_position = uniforms.view_proj * world;
+    out.view_distance = out.clip_position.w;
+
+    return out;
+}
+
+// Blend through the palette stops, with t = 0 at the first and 1 at the last
+fn palette_color(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0) * f32(uniforms.palette_last_stop);
+    let i = min(u32(x), uniforms.palette_last_stop);
+    let j = min(i + 1u, uniforms.palette_last_stop);
+    return mix(uniforms.palette[i].rgb, uniforms.palette[j].rgb, x - f32(i));
+}
+
+// Share of the fog color at a distance from the camera
+fn fog_amount(distance: f32) -> f32 {
+    let past = max(distance - uniforms.fog_start, 0.0);
+    switch uniforms.fog_mode {
+        case 1u: {
+            return clamp(past / (uniforms.fog_end - uniforms.fog_start), 0.0, 1.0);
+        }
+        case 2u: {
+            return 1.0 - exp(-uniforms.fog_density * past);
+        }
+        case 3u: {
+            let thickness = uniforms.fog_density * past;
+            return 1.0 - exp(-thickness * thickness);
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
+fn on_dash(distance: f32) -> bool {
+    let offset = distance - uniforms.time * DASH_SPEED;
+    switch uniforms.line_style {
+        case 1u: {
+            return fract(offset / DASH_PERIOD) < 0.5;
+        }
+        case 2u: {
+            return fract(offset / DOT_PERIOD) < 0.25;
+        }
+        default: {
+            return true;
+        }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Skip the gaps between dashes and dots, and hidden connectors
+    if in.major < 0.0 || !on_dash(in.distance) {
+        discard;
+    }
+
+    // Calculate depth-based fade
+    let depth = 1.0 - (in.clip_position.z / in.clip_position.w);
+    let fade = pow(depth, 1.5);
+
+    // Major lines stand out from the minor ones
+    var color = mix(uniforms.minor_color, uniforms.major_color, in.major);
+    if uniforms.palette_mode != 0u {
+        let t = select(
+            in.view_distance / uniforms.palette_range,
+            in.height + 0.5,
+            uniforms.palette_mode == 1u,
+        );
+        // Minor lines are dimmer, like the default flat colors
+        color = vec4<f32>(palette_color(t) * mix(0.45, 1.0, in.major), 1.0);
+    }
+
+    let light = normalize(LIGHT_DIRECTION);
+    let shade = clamp(dot(normalize(in.normal), light) / light.y, 0.4, 1.4);
+
+    // Far rows disappear into the fog before they reach the far clip plane
+    let fogged = mix(color.rgb * shade, uniforms.fog_color.rgb, fog_amount(in.view_distance));
+
+    // Return shaded line color with depth-based fade
+    return vec4<f32>(fogged, fade);
+}
\ No newline at end of file
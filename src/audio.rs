@@ -0,0 +1,761 @@
+use crate::chroma::{self, Key, PITCH_CLASSES};
+use crate::config::AudioConfig;
+use crate::loudness::{Loudness, LoudnessMeter};
+use crate::pitch;
+use crate::playback::Playback;
+#[cfg(feature = "microphone")]
+use crate::resample::Resampler;
+use crate::stereo::{Stereo, StereoMeter};
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Samples per FFT window and number of log-spaced bands handed to the shader
+pub const FFT_SIZE: usize = 2048;
+pub const SPECTRUM_BANDS: usize = 64;
+
+// Every source is resampled to this rate before analysis, so the FFT bins
+// and band edges fall on the same frequencies whatever the device or file
+pub const ANALYSIS_RATE: u32 = 48000;
+
+// Lowest band edge; anything below is mostly rumble
+const MIN_FREQUENCY: f32 = 40.0;
+
+// Fraction of the previous level kept per window when a band falls
+const DECAY: f32 = 0.85;
+// Fraction of the way the chroma the key is found from moves to each
+// window's, so the key follows the harmony over several seconds
+const KEY_SMOOTHING: f32 = 0.005;
+
+// Windows per second the decay and smoothing above are tuned for: one per
+// frame at 60 fps. At a fixed analysis rate they are scaled to move as far
+// per second.
+const TUNED_RATE: f32 = 60.0;
+
+// Most a frame catches up on at a fixed analysis rate, in samples; past it,
+// e.g. after a seek or a stall, analysis starts over from the position
+const MAX_CATCH_UP: u64 = ANALYSIS_RATE as u64 / 4;
+
+// Width of the compressor's soft knee, in dB
+const KNEE: f32 = 6.0;
+// Fraction of the way the dynamics gain moves to its target per window,
+// when turning down and up
+const ATTACK: f32 = 0.5;
+const RELEASE: f32 = 0.1;
+
+// Anything that can feed band levels to the renderer each frame
+pub trait SpectrumSource {
+    fn spectrum(&mut self) -> &[f32];
+
+    // Stream errors reported by the audio backend, such as buffer overruns
+    // and underruns
+    fn xruns(&self) -> u64 {
+        0
+    }
+
+    // The file being played, for sources that play one, to show and move
+    // the play position
+    fn playback(&mut self) -> Option<&mut Playback> {
+        None
+    }
+
+    // Track started since the last call, for sources playing several
+    fn track_change(&mut self) -> Option<PathBuf> {
+        None
+    }
+
+    // Level shaping applied before analysis
+    fn set_dynamics(&mut self, _settings: &AudioConfig) {}
+
+    // Levels of the last window analyzed, for sources capturing live input
+    // that can be calibrated
+    fn levels(&self) -> Option<Levels> {
+        None
+    }
+
+    // The analysis behind the spectrum, for measurements beyond it
+    fn analyzer(&mut self) -> Option<&mut SpectrumAnalyzer> {
+        None
+    }
+
+    // Stereo image of the audio behind the last spectrum, for stereo
+    // sources
+    fn stereo(&mut self) -> Option<Stereo> {
+        None
+    }
+
+    // Loudness of the audio up to the last spectrum, for sources that
+    // measure it as it goes
+    fn loudness(&mut self) -> Option<Loudness> {
+        None
+    }
+}
+
+// How loud the last analysis window was, in dB
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Levels {
+    // RMS level of the input in dBFS, before the dynamics
+    pub rms: f32,
+    // Loudest band, after the dynamics, on the scale of the band range
+    pub loudest_band: f32,
+}
+
+// Gain, gate and compressor ahead of the FFT, so quiet rooms and hot
+// signals both fill the band range. Each window is measured as a whole and
+// scaled by one gain, which follows its target quickly down and slowly up.
+pub struct Dynamics {
+    settings: AudioConfig,
+    // Gain applied to the last window, as a factor
+    gain: f32,
+}
+
+impl Default for Dynamics {
+    fn default() -> Self {
+        Self {
+            settings: AudioConfig::default(),
+            gain: 1.0,
+        }
+    }
+}
+
+impl Dynamics {
+    pub fn settings(&self) -> &AudioConfig {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: &AudioConfig) {
+        self.settings = settings.clone();
+    }
+
+    // Gain for a window of `samples`, as a factor
+    pub fn gain(&mut self, samples: &[f32]) -> f32 {
+        let target = match gain_db(&self.settings, rms_db(samples)) {
+            Some(db) => 10.0f32.powf(db / 20.0),
+            None => 0.0,
+        };
+        let rate = if target < self.gain { ATTACK } else { RELEASE };
+        let rate = 1.0 - kept_per_window(1.0 - rate, self.settings.analysis_rate);
+        self.gain += (target - self.gain) * rate;
+        self.gain
+    }
+}
+
+// Share of a value kept per window, for `kept` at TUNED_RATE and windows
+// analyzed `rate` times a second, or once per frame for 0
+fn kept_per_window(kept: f32, rate: f32) -> f32 {
+    if rate > 0.0 {
+        kept.powf(TUNED_RATE / rate)
+    } else {
+        kept
+    }
+}
+
+// Level of `samples` in dBFS
+fn rms_db(samples: &[f32]) -> f32 {
+    let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32;
+    10.0 * (power + 1e-12).log10()
+}
+
+// Gain in dB for a window at `level` dBFS, or None when it is gated
+fn gain_db(settings: &AudioConfig, level: f32) -> Option<f32> {
+    let level = level + settings.gain;
+    if level < settings.gate {
+        return None;
+    }
+    // Reduction grows quadratically through the knee, then linearly
+    let over = level - settings.threshold;
+    let slope = 1.0 / settings.ratio - 1.0;
+    let reduction = if over <= -KNEE / 2.0 {
+        0.0
+    } else if over < KNEE / 2.0 {
+        slope * (over + KNEE / 2.0).powi(2) / (2.0 * KNEE)
+    } else {
+        slope * over
+    };
+    Some(settings.gain + reduction)
+}
+
+// Hann-windowed FFT reduced to smoothed, log-spaced band levels in 0..1
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    window_sum: f32,
+    buffer: Vec<Complex<f32>>,
+    band_edges: Vec<usize>,
+    bands: Vec<f32>,
+    dynamics: Dynamics,
+    levels: Levels,
+    // The last window analyzed, as it came in
+    samples: Vec<f32>,
+    // Power of each FFT bin up to Nyquist, reused between windows
+    power: Vec<f32>,
+    // Power per pitch class, falling like the bands, and its long-term
+    // average, which the key is found from
+    chroma: [f32; PITCH_CLASSES],
+    key_chroma: [f32; PITCH_CLASSES],
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpectrumAnalyzer {
+    // Analysis of input at ANALYSIS_RATE
+    pub fn new() -> Self {
+        let sample_rate = ANALYSIS_RATE;
+        let window: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| {
+                let phase = i as f32 / (FFT_SIZE - 1) as f32 * std::f32::consts::TAU;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        let window_sum = window.iter().sum();
+
+        // FFT bin at each band edge, spaced logarithmically up to Nyquist
+        let nyquist = sample_rate as f32 / 2.0;
+        let bin_width = sample_rate as f32 / FFT_SIZE as f32;
+        let band_edges = (0..=SPECTRUM_BANDS)
+            .map(|i| {
+                let t = i as f32 / SPECTRUM_BANDS as f32;
+                let frequency = MIN_FREQUENCY * (nyquist / MIN_FREQUENCY).powf(t);
+                ((frequency / bin_width) as usize).min(FFT_SIZE / 2)
+            })
+            .collect();
+
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            window_sum,
+            buffer: vec![Complex::default(); FFT_SIZE],
+            band_edges,
+            bands: vec![0.0; SPECTRUM_BANDS],
+            dynamics: Dynamics::default(),
+            levels: Levels::default(),
+            samples: Vec::with_capacity(FFT_SIZE),
+            power: vec![0.0; FFT_SIZE / 2],
+            chroma: [0.0; PITCH_CLASSES],
+            key_chroma: [0.0; PITCH_CLASSES],
+        }
+    }
+
+    pub fn bands(&self) -> &[f32] {
+        &self.bands
+    }
+
+    pub fn dynamics(&self) -> &Dynamics {
+        &self.dynamics
+    }
+
+    pub fn levels(&self) -> Levels {
+        self.levels
+    }
+
+    // Fundamental frequency of the last window, if it has a clear one
+    pub fn pitch(&self) -> Option<f32> {
+        pitch::detect(&self.samples, ANALYSIS_RATE)
+    }
+
+    // Power per pitch class, C first, with the strongest near 1
+    pub fn chroma(&self) -> &[f32; PITCH_CLASSES] {
+        &self.chroma
+    }
+
+    // Key of the last several seconds, if the harmony suggests one
+    pub fn key(&self) -> Option<Key> {
+        chroma::detect_key(&self.key_chroma)
+    }
+
+    pub fn set_dynamics(&mut self, settings: &AudioConfig) {
+        self.dynamics.set_settings(settings);
+    }
+
+    // Analyze the most recent FFT_SIZE samples; shorter input is zero padded
+    pub fn process(&mut self, samples: &[f32]) -> &[f32] {
+        let samples = &samples[samples.len().saturating_sub(FFT_SIZE)..];
+        self.samples.clear();
+        self.samples.extend_from_slice(samples);
+        self.levels.rms = rms_db(samples);
+        self.levels.loudest_band = f32::NEG_INFINITY;
+        let gain = self.dynamics.gain(samples);
+        let rate = self.dynamics.settings().analysis_rate;
+        let decay = kept_per_window(DECAY, rate);
+        let key_smoothing = 1.0 - kept_per_window(1.0 - KEY_SMOOTHING, rate);
+        for (i, value) in self.buffer.iter_mut().enumerate() {
+            let sample = samples.get(i).copied().unwrap_or(0.0);
+            *value = Complex::new(sample * gain * self.window[i], 0.0);
+        }
+        self.fft.process(&mut self.buffer);
+
+        for (band, edges) in self.bands.iter_mut().zip(self.band_edges.windows(2)) {
+            // Low bands can be narrower than one bin
+            let end = edges[1].max(edges[0] + 1).min(FFT_SIZE / 2);
+            let magnitude = self.buffer[edges[0]..end]
+                .iter()
+                .map(|c| c.norm())
+                .fold(0.0, f32::max)
+                * 2.0
+                / self.window_sum;
+
+            let db = 20.0 * (magnitude + 1e-9).log10();
+            self.levels.loudest_band = self.levels.loudest_band.max(db);
+            let [min_db, max_db] = self.dynamics.settings().range;
+            let level = ((db - min_db) / (max_db - min_db)).clamp(0.0, 1.0);
+            *band = level.max(*band * decay);
+        }
+
+        let scale = 2.0 / self.window_sum;
+        for (power, value) in self.power.iter_mut().zip(&self.buffer) {
+            *power = (value.norm() * scale).powi(2);
+        }
+        let current = chroma::chromagram(&self.power, ANALYSIS_RATE as f32 / FFT_SIZE as f32);
+        for (class, power) in current.iter().enumerate() {
+            self.chroma[class] = power.max(self.chroma[class] * decay);
+            self.key_chroma[class] += (power - self.key_chroma[class]) * key_smoothing;
+        }
+
+        &self.bands
+    }
+}
+
+// Analysis at a fixed rate rather than once per rendered frame. Windows end
+// every `hop` samples of the stream, stamped with the sample they end on,
+// and the bands handed on are interpolated between the last two for the
+// position being drawn, one hop behind it. A slow frame catches up on every
+// window it missed, so how the bands move doesn't depend on the frame rate.
+pub struct FixedRate {
+    hop: u64,
+    // Sample the next window ends on
+    next: u64,
+    previous: Vec<f32>,
+    bands: Vec<f32>,
+}
+
+impl FixedRate {
+    // Analysis `rate` times a second
+    pub fn new(rate: f32) -> Self {
+        Self {
+            hop: (ANALYSIS_RATE as f32 / rate).round().max(1.0) as u64,
+            next: 0,
+            previous: vec![0.0; SPECTRUM_BANDS],
+            bands: vec![0.0; SPECTRUM_BANDS],
+        }
+    }
+
+    // Set `fixed_rate` up for the analysis rate in `settings`, None for once
+    // per frame, keeping it as it is if the rate is unchanged
+    pub fn configure(fixed_rate: &mut Option<Self>, settings: &AudioConfig) {
+        let rate = settings.analysis_rate;
+        if rate <= 0.0 {
+            *fixed_rate = None;
+        } else if fixed_rate.as_ref().map(|fixed| fixed.hop) != Some(Self::new(rate).hop) {
+            *fixed_rate = Some(Self::new(rate));
+        }
+    }
+
+    // Bands at `position`, in samples at ANALYSIS_RATE, after running
+    // `analyzer` on each window due up to it; `window` gives the samples
+    // ending on a position
+    pub fn process(
+        &mut self,
+        analyzer: &mut SpectrumAnalyzer,
+        position: u64,
+        mut window: impl FnMut(u64) -> Vec<f32>,
+    ) -> &[f32] {
+        let latest = self.next.saturating_sub(self.hop);
+        if position < latest || position > self.next + MAX_CATCH_UP {
+            self.next = position / self.hop * self.hop;
+        }
+        while self.next <= position {
+            self.previous.copy_from_slice(analyzer.bands());
+            analyzer.process(&window(self.next));
+            self.next += self.hop;
+        }
+        let latest = self.next - self.hop;
+        let t = (position - latest) as f32 / self.hop as f32;
+        for ((band, previous), current) in self
+            .bands
+            .iter_mut()
+            .zip(&self.previous)
+            .zip(analyzer.bands())
+        {
+            *band = previous + (current - previous) * t;
+        }
+        &self.bands
+    }
+}
+
+// Samples captured at ANALYSIS_RATE, enough for a window and catching up
+// on the ones missed since the last frame
+#[derive(Default)]
+struct Captured {
+    samples: VecDeque<f32>,
+    // Samples captured in all, the position of the newest
+    total: u64,
+}
+
+impl Captured {
+    #[cfg(feature = "microphone")]
+    fn push(&mut self, sample: f32) {
+        if self.samples.len() == FFT_SIZE + MAX_CATCH_UP as usize {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.total += 1;
+    }
+
+    // The window ending on position `end`, shorter if it goes back further
+    // than what is kept
+    fn window(&self, end: u64) -> Vec<f32> {
+        let after = self.total.saturating_sub(end) as usize;
+        let stop = self.samples.len().saturating_sub(after);
+        let start = stop.saturating_sub(FFT_SIZE);
+        self.samples.range(start..stop).copied().collect()
+    }
+}
+
+// Frames captured for a recording once started, interleaved at the device's
+// rate, until taken
+#[derive(Default)]
+pub struct Recording {
+    active: AtomicBool,
+    samples: Mutex<Vec<f32>>,
+}
+
+impl Recording {
+    // Keep every frame captured from now on
+    pub fn start(&self) {
+        self.active.store(true, Ordering::Relaxed);
+    }
+
+    // The samples captured since the last call
+    pub fn take(&self) -> Vec<f32> {
+        std::mem::take(&mut *self.samples.lock().unwrap())
+    }
+
+    #[cfg(feature = "microphone")]
+    fn add(&self, samples: &[f32]) {
+        if self.active.load(Ordering::Relaxed) {
+            self.samples.lock().unwrap().extend_from_slice(samples);
+        }
+    }
+}
+
+// Live capture from the default input device, mixed down to mono
+pub struct AudioInput {
+    samples: Arc<Mutex<Captured>>,
+    analyzer: SpectrumAnalyzer,
+    fixed_rate: Option<FixedRate>,
+    // Frames captured since the last spectrum, and their stereo image
+    stereo_meter: Arc<Mutex<StereoMeter>>,
+    stereo: Option<Stereo>,
+    // Every frame captured goes through this, at the device's rate
+    loudness_meter: Arc<Mutex<LoudnessMeter>>,
+    recording: Arc<Recording>,
+    sample_rate: u32,
+    channels: u16,
+    xruns: Arc<AtomicU64>,
+    #[cfg(feature = "microphone")]
+    _stream: cpal::Stream,
+}
+
+impl AudioInput {
+    #[cfg(feature = "microphone")]
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or("no audio input device available")?;
+        let config = device.default_input_config()?;
+        log::info!(
+            "Capturing audio from {} at {} Hz",
+            device.name().unwrap_or_default(),
+            config.sample_rate().0
+        );
+
+        let samples = Arc::new(Mutex::new(Captured::default()));
+        let xruns = Arc::new(AtomicU64::new(0));
+        let stereo_meter = Arc::new(Mutex::new(StereoMeter::default()));
+        let stream_config = config.config();
+        let loudness_meter = Arc::new(Mutex::new(LoudnessMeter::new(
+            stream_config.sample_rate.0,
+            stream_config.channels as usize,
+        )));
+        let recording = Arc::new(Recording::default());
+        let meters = (&stereo_meter, &loudness_meter, &recording);
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => {
+                build_stream::<f32>(&device, &stream_config, &samples, meters, &xruns)?
+            }
+            cpal::SampleFormat::I16 => {
+                build_stream::<i16>(&device, &stream_config, &samples, meters, &xruns)?
+            }
+            cpal::SampleFormat::U16 => {
+                build_stream::<u16>(&device, &stream_config, &samples, meters, &xruns)?
+            }
+            format => return Err(format!("unsupported sample format {}", format).into()),
+        };
+        stream.play()?;
+
+        Ok(Self {
+            samples,
+            analyzer: SpectrumAnalyzer::new(),
+            fixed_rate: None,
+            stereo_meter,
+            stereo: None,
+            loudness_meter,
+            recording,
+            sample_rate: stream_config.sample_rate.0,
+            channels: stream_config.channels,
+            xruns,
+            _stream: stream,
+        })
+    }
+
+    #[cfg(not(feature = "microphone"))]
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Err("built without the `microphone` feature".into())
+    }
+
+    // Capture rate and channels of the input
+    pub fn format(&self) -> (u32, u16) {
+        (self.sample_rate, self.channels)
+    }
+
+    // Where frames captured for a recording are kept once it is started
+    pub fn recording(&self) -> Arc<Recording> {
+        self.recording.clone()
+    }
+}
+
+impl SpectrumSource for AudioInput {
+    // Spectrum of the latest captured window
+    fn spectrum(&mut self) -> &[f32] {
+        self.stereo = self.stereo_meter.lock().unwrap().take();
+        let captured = self.samples.lock().unwrap();
+        match &mut self.fixed_rate {
+            Some(fixed_rate) => fixed_rate.process(&mut self.analyzer, captured.total, |end| {
+                captured.window(end)
+            }),
+            None => {
+                let window = captured.window(captured.total);
+                drop(captured);
+                self.analyzer.process(&window)
+            }
+        }
+    }
+
+    fn xruns(&self) -> u64 {
+        self.xruns.load(Ordering::Relaxed)
+    }
+
+    fn set_dynamics(&mut self, settings: &AudioConfig) {
+        self.analyzer.set_dynamics(settings);
+        FixedRate::configure(&mut self.fixed_rate, settings);
+    }
+
+    fn levels(&self) -> Option<Levels> {
+        Some(self.analyzer.levels())
+    }
+
+    fn analyzer(&mut self) -> Option<&mut SpectrumAnalyzer> {
+        Some(&mut self.analyzer)
+    }
+
+    fn stereo(&mut self) -> Option<Stereo> {
+        self.stereo.clone()
+    }
+
+    fn loudness(&mut self) -> Option<Loudness> {
+        Some(self.loudness_meter.lock().unwrap().loudness())
+    }
+}
+
+#[cfg(feature = "microphone")]
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    samples: &Arc<Mutex<Captured>>,
+    (stereo_meter, loudness_meter, recording): (
+        &Arc<Mutex<StereoMeter>>,
+        &Arc<Mutex<LoudnessMeter>>,
+        &Arc<Recording>,
+    ),
+    xruns: &Arc<AtomicU64>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    use cpal::traits::DeviceTrait;
+
+    let channels = config.channels as usize;
+    let samples = samples.clone();
+    let stereo_meter = stereo_meter.clone();
+    let loudness_meter = loudness_meter.clone();
+    let recording = recording.clone();
+    let xruns = xruns.clone();
+    let mut resampler = Resampler::new(config.sample_rate.0, ANALYSIS_RATE);
+    // Reused between callbacks so they don't allocate once warmed up
+    let mut interleaved = Vec::new();
+    let mut mono = Vec::new();
+    let mut resampled = Vec::new();
+    device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            interleaved.clear();
+            interleaved.extend(data.iter().map(|s| s.to_sample::<f32>()));
+            mono.clear();
+            mono.extend(
+                interleaved
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+            );
+            if channels >= 2 {
+                let mut stereo_meter = stereo_meter.lock().unwrap();
+                for frame in interleaved.chunks_exact(channels) {
+                    stereo_meter.add(frame[0], frame[1]);
+                }
+            }
+            loudness_meter.lock().unwrap().process(&interleaved);
+            recording.add(&interleaved);
+            resampled.clear();
+            resampler.process(&mono, &mut resampled);
+            let mut samples = samples.lock().unwrap();
+            for sample in &resampled {
+                samples.push(*sample);
+            }
+        },
+        move |e| {
+            xruns.fetch_add(1, Ordering::Relaxed);
+            log::error!("Audio input error: {}", e);
+        },
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(gate: f32, threshold: f32, ratio: f32) -> AudioConfig {
+        AudioConfig {
+            gain: 6.0,
+            gate,
+            threshold,
+            ratio,
+            ..AudioConfig::default()
+        }
+    }
+
+    #[test]
+    fn gate_applies_after_the_gain() {
+        let settings = settings(-40.0, 0.0, 1.0);
+        assert_eq!(gain_db(&settings, -50.0), None);
+        assert_eq!(gain_db(&settings, -44.0), Some(6.0));
+    }
+
+    #[test]
+    fn compressor_divides_the_excess_past_the_knee() {
+        let settings = settings(-100.0, -20.0, 4.0);
+        // 6 dB gain puts -10 dBFS 16 dB over, reduced to 4 dB over
+        assert_eq!(gain_db(&settings, -10.0), Some(6.0 - 12.0));
+        // Untouched below the knee, and partly reduced inside it
+        assert_eq!(gain_db(&settings, -30.0), Some(6.0));
+        let knee = gain_db(&settings, -26.0).unwrap();
+        assert!(knee < 6.0 && knee > 6.0 - 0.75);
+    }
+
+    #[test]
+    fn gain_turns_down_faster_than_up() {
+        let mut dynamics = Dynamics::default();
+        // Gated, then back to unity
+        dynamics.set_settings(&AudioConfig {
+            gain: -120.0,
+            ..AudioConfig::default()
+        });
+        let down = dynamics.gain(&[0.1; 64]);
+        assert!((down - (1.0 - ATTACK)).abs() < 1e-6);
+        dynamics.set_settings(&AudioConfig::default());
+        let up = dynamics.gain(&[0.1; 64]);
+        assert!((up - (down + (1.0 - down) * RELEASE)).abs() < 1e-6);
+    }
+
+    // A 1 kHz tone that stops at one second
+    fn tone_until_one_second(end: u64) -> Vec<f32> {
+        (end.saturating_sub(FFT_SIZE as u64)..end)
+            .map(|i| {
+                let t = i as f32 / ANALYSIS_RATE as f32;
+                if i < ANALYSIS_RATE as u64 {
+                    (t * 1000.0 * std::f32::consts::TAU).sin() * 0.5
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_rate_analysis_does_not_depend_on_the_frame_rate() {
+        let settings = AudioConfig {
+            analysis_rate: 100.0,
+            ..AudioConfig::default()
+        };
+        let bands_at = |fps: u64| {
+            let mut analyzer = SpectrumAnalyzer::new();
+            analyzer.set_dynamics(&settings);
+            let mut fixed_rate = None;
+            FixedRate::configure(&mut fixed_rate, &settings);
+            let fixed_rate = fixed_rate.as_mut().unwrap();
+            let mut bands = Vec::new();
+            for frame in 0..=fps * 3 / 2 {
+                let position = frame * ANALYSIS_RATE as u64 / fps;
+                bands = fixed_rate
+                    .process(&mut analyzer, position, tone_until_one_second)
+                    .to_vec();
+            }
+            bands
+        };
+        // Falling after the tone stops, the same at 10 and 50 fps
+        let (slow, fast) = (bands_at(10), bands_at(50));
+        assert!(slow.iter().any(|band| *band > 0.0));
+        for (slow, fast) in slow.iter().zip(&fast) {
+            assert!((slow - fast).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn fixed_rate_interpolates_between_windows() {
+        let settings = AudioConfig {
+            analysis_rate: 100.0,
+            ..AudioConfig::default()
+        };
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.set_dynamics(&settings);
+        let mut fixed_rate = FixedRate::new(settings.analysis_rate);
+        let hop = ANALYSIS_RATE as u64 / 100;
+        // The first window with the tone is between silence and the next
+        fixed_rate.process(&mut analyzer, 0, |_| vec![0.0; FFT_SIZE]);
+        let tone = |end| tone_until_one_second(end + hop * 10);
+        let halfway = fixed_rate
+            .process(&mut analyzer, hop + hop / 2, tone)
+            .to_vec();
+        let latest = analyzer.bands().to_vec();
+        for (halfway, latest) in halfway.iter().zip(&latest) {
+            assert!((halfway - latest / 2.0).abs() < 1e-6);
+        }
+        assert!(latest.iter().any(|band| *band > 0.0));
+    }
+
+    #[test]
+    fn decay_moves_as_far_per_second_at_any_rate() {
+        assert_eq!(kept_per_window(DECAY, 0.0), DECAY);
+        assert!((kept_per_window(DECAY, 120.0).powi(2) - DECAY).abs() < 1e-6);
+    }
+}
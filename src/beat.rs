@@ -0,0 +1,77 @@
+// Taps further apart than this start a new tap tempo run
+const MAX_TAP_INTERVAL: f64 = 2.0;
+// Intervals averaged for the tapped tempo
+const TAP_HISTORY: usize = 4;
+
+// Free-running musical clock for beat-locked changes. The tempo comes from
+// the config or is tapped in, and the first tap of a run marks a downbeat.
+pub struct BeatClock {
+    bpm: f32,
+    beats_per_bar: u32,
+    // Beats since the clock started
+    beats: f64,
+    seconds: f64,
+    last_tap: Option<f64>,
+    tap_intervals: Vec<f64>,
+}
+
+impl BeatClock {
+    pub fn new(bpm: f32, beats_per_bar: u32) -> Self {
+        Self {
+            bpm,
+            beats_per_bar,
+            beats: 0.0,
+            seconds: 0.0,
+            last_tap: None,
+            tap_intervals: Vec::new(),
+        }
+    }
+
+    pub fn bpm(&self) -> f32 {
+        self.bpm
+    }
+
+    // Change the tempo without moving the phase
+    pub fn set_tempo(&mut self, bpm: f32, beats_per_bar: u32) {
+        self.bpm = bpm;
+        self.beats_per_bar = beats_per_bar;
+    }
+
+    // Bars started since the clock started, counting the first as zero
+    pub fn bar(&self) -> u64 {
+        (self.beats / self.beats_per_bar as f64) as u64
+    }
+
+    // Advance by `dt` seconds and return how many bars started meanwhile
+    pub fn advance(&mut self, dt: f32) -> u64 {
+        let bar = self.bar();
+        self.seconds += dt as f64;
+        self.beats += dt as f64 * self.bpm as f64 / 60.0;
+        self.bar() - bar
+    }
+
+    // Tap along with the music: the tempo follows the average of the last
+    // few tap intervals and each tap lands on a beat
+    pub fn tap(&mut self) {
+        let interval = self.last_tap.map(|last| self.seconds - last);
+        self.last_tap = Some(self.seconds);
+
+        match interval {
+            Some(interval) if interval <= MAX_TAP_INTERVAL => {
+                if self.tap_intervals.len() == TAP_HISTORY {
+                    self.tap_intervals.remove(0);
+                }
+                self.tap_intervals.push(interval);
+                let average =
+                    self.tap_intervals.iter().sum::<f64>() / self.tap_intervals.len() as f64;
+                self.bpm = (60.0 / average) as f32;
+                self.beats = self.beats.round();
+            }
+            _ => {
+                self.tap_intervals.clear();
+                let beats_per_bar = self.beats_per_bar as f64;
+                self.beats = (self.beats / beats_per_bar).ceil() * beats_per_bar;
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::Config;
+use crate::grid::Vertex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Fewest grid lines across or along that quality is lowered to
+const MIN_GRID_LINES: u32 = 16;
+
+// Keeps the settings within what the GPU can hold. Settings are clamped to
+// the adapter's limits and an optional memory budget before they are
+// applied, and running out of memory lowers quality a step at a time instead
+// of failing. Limits only ever go down, so later configs stay within what
+// fit.
+pub struct QualityBudget {
+    // Bytes the estimate must stay under; None trusts the allocator
+    memory: Option<u64>,
+    // Grid lines across and along
+    max_grid: (u32, u32),
+    effects: bool,
+    out_of_memory: Arc<AtomicBool>,
+}
+
+impl QualityBudget {
+    // Also takes over the device's uncaptured error handler, so that running
+    // out of memory is recorded rather than fatal. Other errors still panic.
+    pub fn new(device: &wgpu::Device, memory: Option<u64>) -> Self {
+        let out_of_memory = Arc::new(AtomicBool::new(false));
+        let flag = out_of_memory.clone();
+        device.on_uncaptured_error(Box::new(move |error| match error {
+            wgpu::Error::OutOfMemory { .. } => flag.store(true, Ordering::Relaxed),
+            error => panic!("wgpu error: {}", error),
+        }));
+
+        // A square grid has a line strip vertex per crossing in each direction
+        let vertex_size = std::mem::size_of::<Vertex>() as u64;
+        let max_lines = (device.limits().max_buffer_size / (2 * vertex_size)).isqrt();
+        let max_lines = max_lines.min(u32::MAX as u64) as u32;
+
+        Self {
+            memory,
+            max_grid: (max_lines, max_lines),
+            effects: true,
+            out_of_memory,
+        }
+    }
+
+    // Bytes to keep the estimate under, e.g. from a reloaded config
+    pub fn set_memory(&mut self, memory: Option<u64>) {
+        self.memory = memory;
+    }
+
+    // Whether post-processing effects and MSAA may be enabled
+    pub fn effects_allowed(&self) -> bool {
+        self.effects
+    }
+
+    // Whether an allocation failed since the last call
+    pub fn take_out_of_memory(&self) -> bool {
+        self.out_of_memory.swap(false, Ordering::Relaxed)
+    }
+
+    // Lower `config` until it fits a `width` x `height` target, first
+    // turning off bloom, then halving the MSAA samples and then the grid.
+    // Returns whether anything was lowered.
+    pub fn fit(&self, config: &mut Config, width: u32, height: u32) -> bool {
+        let before = (config.grid, config.window.msaa, config.bloom.enabled);
+        config.grid.width = config.grid.width.min(self.max_grid.0);
+        config.grid.depth = config.grid.depth.min(self.max_grid.1);
+        if !self.effects {
+            config.bloom.enabled = false;
+            config.window.msaa = 1;
+        }
+        if let Some(memory) = self.memory {
+            while estimate(config, width, height) > memory {
+                if config.bloom.enabled {
+                    config.bloom.enabled = false;
+                } else if config.window.msaa > 1 {
+                    config.window.msaa /= 2;
+                } else if !halve_grid(config) {
+                    break;
+                }
+            }
+        }
+        (config.grid, config.window.msaa, config.bloom.enabled) != before
+    }
+
+    // Lower the limits a step after running out of memory with `config` in
+    // use. Effects go first if any are on, then the grid. Returns false when
+    // there is nothing left to lower.
+    pub fn reduce(&mut self, config: &Config, effects_in_use: bool) -> bool {
+        if self.effects && effects_in_use {
+            self.effects = false;
+            return true;
+        }
+        self.effects = false;
+        let mut lowered = config.clone();
+        if !halve_grid(&mut lowered) {
+            return false;
+        }
+        self.max_grid = (lowered.grid.width, lowered.grid.depth);
+        true
+    }
+}
+
+fn halve_grid(config: &mut Config) -> bool {
+    let grid = &mut config.grid;
+    if grid.width.max(grid.depth) <= MIN_GRID_LINES {
+        return false;
+    }
+    grid.width = (grid.width / 2).max(MIN_GRID_LINES.min(grid.width));
+    grid.depth = (grid.depth / 2).max(MIN_GRID_LINES.min(grid.depth));
+    true
+}
+
+// Rough GPU memory in bytes taken by the renderer's targets and grid buffers
+// for a `width` x `height` target
+pub fn estimate(config: &Config, width: u32, height: u32) -> u64 {
+    let pixels = width as u64 * height as u64;
+    let samples = config.window.msaa as u64;
+    // HDR scene target and a depth buffer at the MSAA sample count
+    let mut bytes = pixels * (8 + 4 * samples);
+    if samples > 1 {
+        // Multisampled HDR target resolved into the scene target
+        bytes += pixels * 8 * samples;
+    }
+    if config.bloom.enabled {
+        // Two half resolution HDR targets
+        bytes += pixels / 4 * 8 * 2;
+    }
+    let crossings = config.grid.width as u64 * config.grid.depth as u64;
+    let vertex_size = std::mem::size_of::<Vertex>() as u64;
+    // Line strips in both directions, then the filled mode's vertices and
+    // triangle indices
+    bytes += crossings * 2 * vertex_size + crossings * (vertex_size + 6 * 4);
+    bytes
+}
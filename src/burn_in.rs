@@ -0,0 +1,61 @@
+use crate::config::BurnInConfig;
+use std::f64::consts::TAU;
+use std::time::Instant;
+
+// Vertical drift frequency relative to the horizontal one. Irrational, so
+// the path never retraces itself and wears the whole square evenly.
+const VERTICAL_RATE: f64 = 0.618_034;
+
+// Keeps a picture that runs around the clock from burning into OLED and LED
+// panels. The picture drifts a few pixels along a slow path, so edges such as
+// the letterbox bars and overlays never sit on the same pixels for long, and
+// overlays are inverted every other interval.
+pub struct BurnInGuard {
+    config: BurnInConfig,
+    start: Instant,
+}
+
+impl BurnInGuard {
+    pub fn new(config: &BurnInConfig) -> Self {
+        Self {
+            config: config.clone(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: &BurnInConfig) {
+        self.config = config.clone();
+    }
+
+    // Pixels left free on every side for the picture to drift into
+    pub fn margin(&self) -> u32 {
+        if self.config.enabled {
+            self.config.shift
+        } else {
+            0
+        }
+    }
+
+    // Offset of the picture from its place now, at most the margin
+    pub fn offset(&self) -> [i32; 2] {
+        if !self.config.enabled {
+            return [0, 0];
+        }
+        let phase = self.elapsed() / self.config.period as f64 * TAU;
+        let shift = self.config.shift as f64;
+        [
+            (shift * phase.sin()).round() as i32,
+            (shift * (phase * VERTICAL_RATE).sin()).round() as i32,
+        ]
+    }
+
+    // Whether overlays are drawn in inverted colors now
+    pub fn overlays_inverted(&self) -> bool {
+        let interval = self.config.invert_interval as f64;
+        self.config.enabled && interval > 0.0 && (self.elapsed() / interval) as u64 % 2 == 1
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
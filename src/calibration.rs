@@ -0,0 +1,197 @@
+use crate::audio::Levels;
+use crate::config::AudioConfig;
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+use std::time::{Duration, Instant};
+
+// How long each step listens for
+const STEP_DURATION: Duration = Duration::from_secs(5);
+// RMS level in dBFS loud input is brought to by the gain, and the most the
+// gain may change it by
+const TARGET_LEVEL: f32 = -20.0;
+const MAX_GAIN: f32 = 24.0;
+// dB above the noise floor for the gate and the bottom of the band range
+const NOISE_MARGIN: f32 = 3.0;
+// Narrowest band range in dB, so a noisy room still shows some movement
+const MIN_RANGE: f32 = 20.0;
+// Share of the measurements counted as the noise floor and the peak; the
+// rest are treated as one-off bumps and clicks
+const PERCENTILE: f32 = 0.9;
+
+const FONT_SIZE: f32 = 18.0;
+const PADDING: f32 = 12.0;
+const METER_HEIGHT: f32 = 8.0;
+// dBFS at the ends of the level meter
+const METER_RANGE: [f32; 2] = [-90.0, 0.0];
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const METER_COLOR: [f32; 4] = [0.4, 0.8, 1.0, 1.0];
+const METER_BACKGROUND: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Quiet,
+    Loud,
+}
+
+// Guided measurement of the live input: a few seconds of the room's
+// background noise, then a few of the loudest sound expected, from which
+// the gain, gate and band range are worked out. Expects the dynamics to be
+// neutral while it listens.
+pub struct Calibration {
+    step: Step,
+    started: Instant,
+    quiet: Vec<Levels>,
+    loud: Vec<Levels>,
+    last: Levels,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Calibration {
+    pub fn new() -> Self {
+        Self {
+            step: Step::Quiet,
+            started: Instant::now(),
+            quiet: Vec::new(),
+            loud: Vec::new(),
+            last: Levels::default(),
+        }
+    }
+
+    // Take the levels of the window just analyzed. Returns the settings
+    // found from `current` once both steps are done.
+    pub fn measure(&mut self, levels: Levels, current: &AudioConfig) -> Option<AudioConfig> {
+        self.last = levels;
+        match self.step {
+            Step::Quiet => self.quiet.push(levels),
+            Step::Loud => self.loud.push(levels),
+        }
+        if self.started.elapsed() < STEP_DURATION {
+            return None;
+        }
+        match self.step {
+            Step::Quiet => {
+                self.step = Step::Loud;
+                self.started = Instant::now();
+                None
+            }
+            Step::Loud => Some(calibrated(&self.quiet, &self.loud, current)),
+        }
+    }
+
+    // Lay out the instructions and a level meter in the middle of the
+    // picture
+    pub fn push(&self, viewport: Viewport, text: &mut TextRenderer) {
+        let instruction = match self.step {
+            Step::Quiet => "Calibrating 1/2: keep the room quiet",
+            Step::Loud => "Calibrating 2/2: play the loudest part of the music",
+        };
+        let left = STEP_DURATION
+            .saturating_sub(self.started.elapsed())
+            .as_secs_f32()
+            .ceil();
+        let message = format!(
+            "{}\n{:.0} s left, input at {:.0} dBFS (F8 cancels)",
+            instruction,
+            left,
+            self.last.rms.max(METER_RANGE[0])
+        );
+        let line_height = text.line_height(FONT_SIZE);
+        let size = [
+            text.measure(&message, FONT_SIZE) + PADDING * 2.0,
+            line_height * 2.0 + METER_HEIGHT + PADDING * 3.0,
+        ];
+        let x = ((viewport.width as f32 - size[0]) / 2.0).round();
+        let y = ((viewport.height as f32 - size[1]) / 2.0).round();
+        text.add_rect([x, y], [x + size[0], y + size[1]], BACKDROP_COLOR);
+        text.add_text(&message, [x + PADDING, y + PADDING], FONT_SIZE, TEXT_COLOR);
+
+        let meter_top = y + PADDING * 2.0 + line_height * 2.0;
+        let meter_width = size[0] - PADDING * 2.0;
+        let fill =
+            ((self.last.rms - METER_RANGE[0]) / (METER_RANGE[1] - METER_RANGE[0])).clamp(0.0, 1.0);
+        let meter = [x + PADDING, meter_top];
+        text.add_rect(
+            meter,
+            [meter[0] + meter_width, meter_top + METER_HEIGHT],
+            METER_BACKGROUND,
+        );
+        text.add_rect(
+            meter,
+            [meter[0] + meter_width * fill, meter_top + METER_HEIGHT],
+            METER_COLOR,
+        );
+    }
+}
+
+// Settings that bring the loud measurements to the target level and gate
+// out the quiet ones, keeping the compressor from `current`
+fn calibrated(quiet: &[Levels], loud: &[Levels], current: &AudioConfig) -> AudioConfig {
+    let noise = percentile(quiet.iter().map(|levels| levels.rms));
+    let peak = percentile(loud.iter().map(|levels| levels.rms)).max(noise);
+    let noise_band = percentile(quiet.iter().map(|levels| levels.loudest_band));
+    let peak_band = percentile(loud.iter().map(|levels| levels.loudest_band));
+
+    let gain = (TARGET_LEVEL - peak).clamp(-MAX_GAIN, MAX_GAIN);
+    let low = noise_band + gain + NOISE_MARGIN;
+    let high = (peak_band + gain).max(low + MIN_RANGE);
+    AudioConfig {
+        gain,
+        gate: noise + gain + NOISE_MARGIN,
+        range: [low, high],
+        ..current.clone()
+    }
+}
+
+// The PERCENTILE point of `values`, ignoring silence measured as -inf
+fn percentile(values: impl Iterator<Item = f32>) -> f32 {
+    let mut values: Vec<f32> = values.filter(|value| value.is_finite()).collect();
+    if values.is_empty() {
+        return -100.0;
+    }
+    values.sort_by(f32::total_cmp);
+    let index = ((values.len() - 1) as f32 * PERCENTILE).round() as usize;
+    values[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(rms: f32, loudest_band: f32) -> Levels {
+        Levels { rms, loudest_band }
+    }
+
+    #[test]
+    fn quiet_rooms_are_turned_up_and_gated() {
+        let quiet = [levels(-70.0, -80.0); 10];
+        let loud: Vec<Levels> = (0..10)
+            .map(|i| levels(-40.0 - i as f32, -30.0 - i as f32))
+            .collect();
+        let settings = calibrated(&quiet, &loud, &AudioConfig::default());
+        // The 90th percentile of the loud levels is -41 dBFS
+        assert_eq!(settings.gain, 21.0);
+        assert_eq!(settings.gate, -70.0 + 21.0 + NOISE_MARGIN);
+        assert_eq!(settings.range, [-80.0 + 21.0 + NOISE_MARGIN, -31.0 + 21.0]);
+        assert_eq!(settings.ratio, AudioConfig::default().ratio);
+    }
+
+    #[test]
+    fn range_is_kept_open_when_noise_is_close_to_the_peak() {
+        let quiet = [levels(-30.0, -25.0); 4];
+        let loud = [levels(-28.0, -24.0); 4];
+        let settings = calibrated(&quiet, &loud, &AudioConfig::default());
+        assert_eq!(settings.range[1] - settings.range[0], MIN_RANGE);
+    }
+
+    #[test]
+    fn silence_counts_as_the_quietest_level() {
+        assert_eq!(percentile([f32::NEG_INFINITY; 3].into_iter()), -100.0);
+    }
+}
@@ -0,0 +1,319 @@
+use cgmath::*;
+
+// Vertical field of view and clip planes shared by all views of the grid
+const FOV_Y: Deg<f32> = Deg(45.0);
+const Z_NEAR: f32 = 0.1;
+const Z_FAR: f32 = 100.0;
+
+// Radians of yaw or pitch per pixel of mouse motion
+const MOUSE_SENSITIVITY: f32 = 0.003;
+
+// Stop just short of straight up or down so the view never flips
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+// Orbit distance limits, the fraction of the distance covered per zoom step
+// and the pan per pixel relative to the distance. Like all distances here,
+// the limits are in grid units and scale with the world.
+const MIN_ORBIT_DISTANCE: f32 = 0.5;
+const MAX_ORBIT_DISTANCE: f32 = 50.0;
+const ZOOM_STEP: f32 = 0.1;
+const PAN_SPEED: f32 = 0.001;
+
+// The available camera controllers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    Free,
+    Orbit,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Free => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Free,
+        }
+    }
+}
+
+// Turns keyboard and mouse input into a view. Each controller interprets the
+// movement calls in its own way; the ones it has no use for are ignored.
+// Movement amounts are in grid units, where the grid spans -1..1, and are
+// multiplied by the world scale so speeds stay the same relative to the grid.
+pub trait CameraController {
+    fn mode(&self) -> CameraMode;
+
+    // World units per grid unit
+    fn world_scale(&self) -> f32;
+
+    fn position(&self) -> Point3<f32>;
+
+    // Rotation from the camera's local axes to the world; the camera looks
+    // along local +Z with +Y up
+    fn orientation(&self) -> Quaternion<f32>;
+
+    fn forward(&self) -> Vector3<f32> {
+        self.orientation().rotate_vector(Vector3::unit_z())
+    }
+
+    fn view_matrix(&self) -> Matrix4<f32> {
+        let orientation = self.orientation();
+        Matrix4::look_to_rh(
+            self.position(),
+            orientation.rotate_vector(Vector3::unit_z()),
+            orientation.rotate_vector(Vector3::unit_y()),
+        )
+    }
+
+    fn move_forward(&mut self, amount: f32);
+
+    fn strafe(&mut self, amount: f32);
+
+    fn move_up(&mut self, amount: f32);
+
+    // Relative mouse movement in pixels
+    fn look(&mut self, dx: f32, dy: f32);
+
+    // Bank around the look direction; positive amounts tilt the view right
+    fn roll(&mut self, amount: f32);
+
+    fn pan(&mut self, _dx: f32, _dy: f32) {}
+
+    // Positive amounts zoom in, one unit per scroll wheel notch
+    fn zoom(&mut self, amount: f32) {
+        self.move_forward(amount * ZOOM_STEP);
+    }
+
+    // Clip planes scale with the world so the same part of the grid is visible
+    fn view_proj(&self, aspect: f32) -> Matrix4<f32> {
+        let scale = self.world_scale();
+        perspective(FOV_Y, aspect, Z_NEAR * scale, Z_FAR * scale) * self.view_matrix()
+    }
+}
+
+// A controller of the given mode starting at `position` with the given
+// orientation, so switching modes keeps the view in place
+pub fn create_controller(
+    mode: CameraMode,
+    position: Point3<f32>,
+    orientation: Quaternion<f32>,
+    world_scale: f32,
+) -> Box<dyn CameraController> {
+    let (yaw, pitch, roll) = yaw_pitch_roll(orientation);
+    match mode {
+        CameraMode::Free => Box::new(Camera {
+            position,
+            yaw,
+            pitch,
+            roll,
+            world_scale,
+        }),
+        CameraMode::Orbit => Box::new(OrbitCamera {
+            target: Point3::origin(),
+            distance: position.distance(Point3::origin()).clamp(
+                MIN_ORBIT_DISTANCE * world_scale,
+                MAX_ORBIT_DISTANCE * world_scale,
+            ),
+            yaw,
+            pitch,
+            roll,
+            world_scale,
+        }),
+    }
+}
+
+// The same kind of camera a quarter turn around the vertical axis through
+// the middle of the grid, still looking at it the same way, for cutting
+// between views
+pub fn quarter_turn(camera: &dyn CameraController) -> Box<dyn CameraController> {
+    let turn = Quaternion::from_angle_y(Rad(std::f32::consts::FRAC_PI_2));
+    create_controller(
+        camera.mode(),
+        turn.rotate_point(camera.position()),
+        turn * camera.orientation(),
+        camera.world_scale(),
+    )
+}
+
+// Free-look first-person camera. Yaw turns around the Y axis, pitch tilts up
+// and down and roll banks around the look direction; at zero yaw and pitch it
+// looks along +Z. The angles are combined as quaternions.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    pub world_scale: f32,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            position: Point3::new(0.0, 0.5, -5.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            world_scale: 1.0,
+        }
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        right(self.orientation())
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraController for Camera {
+    fn mode(&self) -> CameraMode {
+        CameraMode::Free
+    }
+
+    fn world_scale(&self) -> f32 {
+        self.world_scale
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.position
+    }
+
+    fn orientation(&self) -> Quaternion<f32> {
+        orientation(self.yaw, self.pitch, self.roll)
+    }
+
+    // Move along the look direction, including pitch
+    fn move_forward(&mut self, amount: f32) {
+        self.position += self.forward() * amount * self.world_scale;
+    }
+
+    fn strafe(&mut self, amount: f32) {
+        self.position += self.right() * amount * self.world_scale;
+    }
+
+    fn move_up(&mut self, amount: f32) {
+        self.position.y += amount * self.world_scale;
+    }
+
+    fn look(&mut self, dx: f32, dy: f32) {
+        (self.yaw, self.pitch) = turn(self.yaw, self.pitch, dx, dy);
+    }
+
+    fn roll(&mut self, amount: f32) {
+        self.roll += amount;
+    }
+}
+
+// Circles a target point, by default the grid center. The mouse orbits and
+// pans, the scroll wheel zooms, and the movement keys orbit and zoom.
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitCamera {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    pub world_scale: f32,
+}
+
+impl OrbitCamera {
+    fn clamp_distance(&self, distance: f32) -> f32 {
+        distance.clamp(
+            MIN_ORBIT_DISTANCE * self.world_scale,
+            MAX_ORBIT_DISTANCE * self.world_scale,
+        )
+    }
+}
+
+impl CameraController for OrbitCamera {
+    fn mode(&self) -> CameraMode {
+        CameraMode::Orbit
+    }
+
+    fn world_scale(&self) -> f32 {
+        self.world_scale
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.target - self.forward() * self.distance
+    }
+
+    fn orientation(&self) -> Quaternion<f32> {
+        orientation(self.yaw, self.pitch, self.roll)
+    }
+
+    fn move_forward(&mut self, amount: f32) {
+        self.distance = self.clamp_distance(self.distance - amount * self.world_scale);
+    }
+
+    // Keys orbit by the angle that moves the camera `amount` along its circle
+    fn strafe(&mut self, amount: f32) {
+        self.yaw += amount * self.world_scale / self.distance;
+    }
+
+    fn move_up(&mut self, amount: f32) {
+        self.pitch =
+            (self.pitch - amount * self.world_scale / self.distance).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    fn look(&mut self, dx: f32, dy: f32) {
+        (self.yaw, self.pitch) = turn(self.yaw, self.pitch, dx, dy);
+    }
+
+    fn roll(&mut self, amount: f32) {
+        self.roll += amount;
+    }
+
+    // Drag the target with the view so the grid follows the mouse
+    fn pan(&mut self, dx: f32, dy: f32) {
+        let orientation = self.orientation();
+        let up = orientation.rotate_vector(Vector3::unit_y());
+        self.target += (right(orientation) * -dx + up * dy) * self.distance * PAN_SPEED;
+    }
+
+    fn zoom(&mut self, amount: f32) {
+        self.distance = self.clamp_distance(self.distance * (1.0 - amount * ZOOM_STEP));
+    }
+}
+
+// Yaw around world Y, then pitch around the camera's X axis, then roll around
+// its look direction
+pub fn orientation(yaw: f32, pitch: f32, roll: f32) -> Quaternion<f32> {
+    Quaternion::from_angle_y(Rad(yaw))
+        * Quaternion::from_angle_x(Rad(-pitch))
+        * Quaternion::from_angle_z(Rad(roll))
+}
+
+fn right(orientation: Quaternion<f32>) -> Vector3<f32> {
+    orientation
+        .rotate_vector(Vector3::unit_z())
+        .cross(orientation.rotate_vector(Vector3::unit_y()))
+}
+
+// Apply a relative mouse movement to a yaw and pitch
+fn turn(yaw: f32, pitch: f32, dx: f32, dy: f32) -> (f32, f32) {
+    (
+        yaw - dx * MOUSE_SENSITIVITY,
+        (pitch - dy * MOUSE_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH),
+    )
+}
+
+// Inverse of orientation(). Pitch is clamped, so a straight up or down view
+// comes back slightly tilted.
+fn yaw_pitch_roll(orientation: Quaternion<f32>) -> (f32, f32, f32) {
+    let forward = orientation.rotate_vector(Vector3::unit_z()).normalize();
+    let yaw = forward.x.atan2(forward.z);
+    let pitch = forward
+        .y
+        .clamp(-1.0, 1.0)
+        .asin()
+        .clamp(-MAX_PITCH, MAX_PITCH);
+
+    // Whatever is left after undoing yaw and pitch is a rotation around Z
+    let rest = self::orientation(yaw, pitch, 0.0).conjugate() * orientation;
+    let roll = 2.0 * rest.v.z.atan2(rest.s);
+    (yaw, pitch, roll)
+}
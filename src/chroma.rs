@@ -0,0 +1,130 @@
+use crate::pitch::NOTE_NAMES;
+
+pub const PITCH_CLASSES: usize = 12;
+
+// Bins counted towards the chromagram. Below the lower end the FFT bins are
+// wider than a semitone, so low notes only count through their overtones.
+const MIN_FREQUENCY: f32 = 400.0;
+const MAX_FREQUENCY: f32 = 5000.0;
+// Total power below which a window counts as silence and has no chroma
+const MIN_POWER: f32 = 1e-6;
+// Weakest correlation with a key profile taken as being in that key
+const MIN_CORRELATION: f32 = 0.5;
+
+// Krumhansl-Kessler key profiles: how well each pitch class fits a key,
+// from its tonic up
+const MAJOR_PROFILE: [f32; PITCH_CLASSES] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; PITCH_CLASSES] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+// Power per pitch class, C first, of a power spectrum from 0 Hz up in bins
+// `bin_width` Hz apart, scaled so the strongest is 1. All zeros in silence.
+pub fn chromagram(power: &[f32], bin_width: f32) -> [f32; PITCH_CLASSES] {
+    let mut chroma = [0.0; PITCH_CLASSES];
+    let first = (MIN_FREQUENCY / bin_width).ceil() as usize;
+    let last = ((MAX_FREQUENCY / bin_width) as usize).min(power.len().saturating_sub(1));
+    for (bin, power) in power.iter().enumerate().take(last + 1).skip(first) {
+        // Semitones above C0, with A4 at 440 Hz
+        let semitones = 12.0 * (bin as f32 * bin_width / 440.0).log2() + 57.0;
+        chroma[(semitones.round() as i32).rem_euclid(PITCH_CLASSES as i32) as usize] += power;
+    }
+    let total: f32 = chroma.iter().sum();
+    let strongest = chroma.iter().copied().fold(0.0, f32::max);
+    if total < MIN_POWER {
+        return [0.0; PITCH_CLASSES];
+    }
+    chroma.map(|power| power / strongest)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    // Pitch class of the tonic, 0 for C up to 11 for B
+    pub tonic: usize,
+    pub minor: bool,
+}
+
+impl Key {
+    pub fn name(&self) -> String {
+        let mode = if self.minor { "minor" } else { "major" };
+        format!("{} {}", NOTE_NAMES[self.tonic], mode)
+    }
+}
+
+// The major or minor key whose profile `chroma` matches best, or None if it
+// matches none of them well, e.g. in silence or noise
+pub fn detect_key(chroma: &[f32; PITCH_CLASSES]) -> Option<Key> {
+    let mut best = None;
+    let mut best_correlation = MIN_CORRELATION;
+    for (profile, minor) in [(&MAJOR_PROFILE, false), (&MINOR_PROFILE, true)] {
+        for tonic in 0..PITCH_CLASSES {
+            let rotated: [f32; PITCH_CLASSES] =
+                std::array::from_fn(|i| profile[(i + PITCH_CLASSES - tonic) % PITCH_CLASSES]);
+            let correlation = correlation(chroma, &rotated);
+            if correlation > best_correlation {
+                best_correlation = correlation;
+                best = Some(Key { tonic, minor });
+            }
+        }
+    }
+    best
+}
+
+// Pearson correlation, 0 when either side is flat
+fn correlation(a: &[f32; PITCH_CLASSES], b: &[f32; PITCH_CLASSES]) -> f32 {
+    let mean = |values: &[f32; PITCH_CLASSES]| values.iter().sum::<f32>() / PITCH_CLASSES as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (a, b) in a.iter().zip(b) {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+    }
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN_WIDTH: f32 = 48000.0 / 2048.0;
+
+    // Power spectrum with a peak at each frequency
+    fn spectrum(frequencies: &[f32]) -> Vec<f32> {
+        let mut power = vec![0.0; 1024];
+        for frequency in frequencies {
+            power[(frequency / BIN_WIDTH).round() as usize] += 1.0;
+        }
+        power
+    }
+
+    #[test]
+    fn peaks_land_on_their_pitch_class() {
+        // A5, and E6 a fifth above it
+        let chroma = chromagram(&spectrum(&[880.0, 1318.5]), BIN_WIDTH);
+        assert_eq!(chroma[9], 1.0);
+        assert_eq!(chroma[4], 1.0);
+        assert_eq!(chroma.iter().sum::<f32>(), 2.0);
+    }
+
+    #[test]
+    fn silence_has_no_chroma_or_key() {
+        let chroma = chromagram(&[0.0; 1024], BIN_WIDTH);
+        assert_eq!(chroma, [0.0; PITCH_CLASSES]);
+        assert_eq!(detect_key(&chroma), None);
+    }
+
+    #[test]
+    fn scales_are_recognized_as_their_key() {
+        // The notes of each scale, the tonic triad strongest
+        let g_major = [0.4, 0.0, 0.9, 0.0, 0.4, 0.0, 0.4, 1.0, 0.0, 0.4, 0.0, 0.8];
+        assert_eq!(detect_key(&g_major).unwrap().name(), "G major");
+        let a_minor = [0.8, 0.0, 0.4, 0.0, 0.9, 0.4, 0.0, 0.4, 0.0, 1.0, 0.0, 0.4];
+        assert_eq!(detect_key(&a_minor).unwrap().name(), "A minor");
+    }
+}
@@ -0,0 +1,104 @@
+use crate::chroma::{Key, PITCH_CLASSES};
+use crate::palette;
+use crate::pitch::NOTE_NAMES;
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+use std::f32::consts::TAU;
+
+// Inner radius of the ring as a share of the shorter side of the picture,
+// and its thickness in pixels with no chroma and at full
+const RADIUS: f32 = 0.42;
+const MIN_THICKNESS: f32 = 3.0;
+const MAX_THICKNESS: f32 = 28.0;
+// Share of each pitch class's slice left as a gap to the next
+const GAP: f32 = 0.12;
+// Straight pieces each slice's arc is made of
+const STEPS: usize = 4;
+const LABEL_FONT_SIZE: f32 = 14.0;
+const KEY_FONT_SIZE: f32 = 18.0;
+// Pixels between the outside of the ring and the note names
+const LABEL_MARGIN: f32 = 6.0;
+// Opacity of a slice with no chroma and at full
+const MIN_OPACITY: f32 = 0.25;
+const MAX_OPACITY: f32 = 0.9;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+
+// Ring around the picture with a slice per pitch class, C at the top and
+// clockwise up the chromatic scale, each as thick and bright as that pitch
+// class is strong and in its pitch color. The key found is written inside
+// it at the top.
+#[derive(Default)]
+pub struct ChromaOverlay {
+    chroma: [f32; PITCH_CLASSES],
+    key: Option<Key>,
+}
+
+impl ChromaOverlay {
+    pub fn set_chroma(&mut self, chroma: &[f32; PITCH_CLASSES], key: Option<Key>) {
+        self.chroma = *chroma;
+        self.key = key;
+    }
+
+    // Lay out the ring in `text` for the next draw into `viewport`
+    pub fn push(&self, viewport: Viewport, text: &mut TextRenderer) {
+        let center = [viewport.width as f32 / 2.0, viewport.height as f32 / 2.0];
+        let inner = viewport.width.min(viewport.height) as f32 * RADIUS;
+        // Clockwise from the top, in screen coordinates with y down
+        let point = |angle: f32, radius: f32| {
+            [
+                center[0] + radius * angle.sin(),
+                center[1] - radius * angle.cos(),
+            ]
+        };
+        let slice = TAU / PITCH_CLASSES as f32;
+        for (class, value) in self.chroma.iter().enumerate() {
+            let value = value.clamp(0.0, 1.0);
+            let outer = inner + MIN_THICKNESS + (MAX_THICKNESS - MIN_THICKNESS) * value;
+            let opacity = MIN_OPACITY + (MAX_OPACITY - MIN_OPACITY) * value;
+            let color = palette::with_hue([1.0, 1.0, 1.0, opacity], class as f32 / 12.0);
+            // Centered on the pitch class's angle
+            let start = (class as f32 - 0.5 + GAP / 2.0) * slice;
+            let step = slice * (1.0 - GAP) / STEPS as f32;
+            for i in 0..STEPS {
+                let (from, to) = (start + i as f32 * step, start + (i + 1) as f32 * step);
+                text.add_quad(
+                    [
+                        point(from, inner),
+                        point(from, outer),
+                        point(to, outer),
+                        point(to, inner),
+                    ],
+                    color,
+                );
+            }
+
+            let name = NOTE_NAMES[class];
+            let label = point(
+                class as f32 * slice,
+                inner + MAX_THICKNESS + LABEL_MARGIN + LABEL_FONT_SIZE / 2.0,
+            );
+            let size = [
+                text.measure(name, LABEL_FONT_SIZE),
+                text.line_height(LABEL_FONT_SIZE),
+            ];
+            text.add_text(
+                name,
+                [label[0] - size[0] / 2.0, label[1] - size[1] / 2.0],
+                LABEL_FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+
+        if let Some(key) = self.key {
+            let name = key.name();
+            let width = text.measure(&name, KEY_FONT_SIZE);
+            let top = center[1] - inner + LABEL_MARGIN;
+            text.add_text(
+                &name,
+                [center[0] - width / 2.0, top],
+                KEY_FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::Instant;
+
+// Seconds of animation per simulation step
+pub const FIXED_STEP: f32 = 1.0 / 60.0;
+// Longest frame that is caught up on; after a stall, such as a window drag,
+// the animation resumes rather than jumping ahead
+const MAX_FRAME_TIME: f32 = 0.25;
+// Range of the playback speed
+pub const MIN_SPEED: f32 = 1.0 / 16.0;
+pub const MAX_SPEED: f32 = 4.0;
+
+// The animation timeline. Wall clock time is turned into fixed simulation
+// steps, so the animation runs at the same speed at any refresh rate, and
+// frames are drawn interpolated between the last two steps. The timeline can
+// be paused, slowed down or sped up, stepped and scrubbed independently of
+// the render loop.
+//
+// `rate` arguments are animation seconds per second of timeline, e.g. to
+// slow the waves down for reduced motion.
+pub struct Clock {
+    last: Option<Instant>,
+    accumulator: f32,
+    speed: f32,
+    paused: bool,
+    // Animation time at the last two steps, and how far between them the
+    // frame being drawn lies
+    time: f32,
+    previous_time: f32,
+    blend: f32,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            accumulator: 0.0,
+            speed: 1.0,
+            paused: false,
+            time: 0.0,
+            previous_time: 0.0,
+            blend: 1.0,
+        }
+    }
+
+    // Animation time of the frame being drawn
+    pub fn time(&self) -> f32 {
+        self.previous_time + (self.time - self.previous_time) * self.blend
+    }
+
+    // Jump to an animation time
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+        self.previous_time = time;
+        self.blend = 1.0;
+    }
+
+    // Timeline seconds per wall clock second
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // Run the steps due since the last call. The first call only starts the
+    // clock, and time passed while paused is skipped. Returns the wall clock
+    // seconds since the last call, capped like the steps are.
+    pub fn tick(&mut self, rate: f32) -> f32 {
+        let now = Instant::now();
+        let elapsed = self
+            .last
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32())
+            .min(MAX_FRAME_TIME);
+        self.last = Some(now);
+        if self.paused {
+            return elapsed;
+        }
+
+        self.accumulator += elapsed * self.speed;
+        while self.accumulator >= FIXED_STEP {
+            self.accumulator -= FIXED_STEP;
+            self.step(FIXED_STEP * rate);
+        }
+        self.blend = self.accumulator / FIXED_STEP;
+        elapsed
+    }
+
+    // Advance by `dt` seconds at the playback speed, regardless of the wall
+    // clock or pausing, e.g. a fixed step per recorded video frame
+    pub fn advance(&mut self, dt: f32, rate: f32) {
+        self.step(dt * self.speed * rate);
+        self.blend = 1.0;
+    }
+
+    // Move by whole simulation steps, backwards for negative `steps`, e.g.
+    // to go through a paused animation frame by frame
+    pub fn step_frames(&mut self, steps: i32, rate: f32) {
+        self.scrub(steps as f32 * FIXED_STEP, rate);
+    }
+
+    // Jump `seconds` of timeline forwards or backwards
+    pub fn scrub(&mut self, seconds: f32, rate: f32) {
+        self.set_time(self.time() + seconds * rate);
+    }
+
+    fn step(&mut self, dt: f32) {
+        self.previous_time = self.time;
+        self.time += dt;
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // A clock last ticked `seconds` ago
+    fn ticked_ago(seconds: f32) -> Clock {
+        let mut clock = Clock::new();
+        clock.last = Some(Instant::now() - Duration::from_secs_f32(seconds));
+        clock
+    }
+
+    fn assert_near(value: f32, expected: f32) {
+        // Slack for the time the test itself takes
+        assert!(
+            (value - expected).abs() < FIXED_STEP * 0.1,
+            "{} is not near {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_first_tick_only_starts_the_clock() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.tick(1.0), 0.0);
+        assert_eq!(clock.time(), 0.0);
+    }
+
+    #[test]
+    fn frames_are_interpolated_between_steps() {
+        let mut clock = ticked_ago(FIXED_STEP * 2.5);
+        assert_near(clock.tick(1.0), FIXED_STEP * 2.5);
+        // Two steps taken, drawn halfway from the first to the second
+        assert_eq!(clock.time, FIXED_STEP * 2.0);
+        assert_near(clock.blend, 0.5);
+        assert_near(clock.time(), FIXED_STEP * 1.5);
+    }
+
+    #[test]
+    fn a_stall_is_capped_instead_of_caught_up() {
+        let mut clock = ticked_ago(5.0);
+        assert_eq!(clock.tick(1.0), MAX_FRAME_TIME);
+        assert!(clock.time <= MAX_FRAME_TIME + 1e-4);
+        assert!(clock.time() >= MAX_FRAME_TIME - FIXED_STEP);
+    }
+
+    #[test]
+    fn pausing_skips_the_time_passed() {
+        let mut clock = ticked_ago(1.0);
+        clock.set_paused(true);
+        assert_eq!(clock.tick(1.0), MAX_FRAME_TIME);
+        assert_eq!(clock.time(), 0.0);
+    }
+
+    #[test]
+    fn speed_and_rate_scale_the_timeline() {
+        let mut clock = ticked_ago(FIXED_STEP * 3.0);
+        clock.set_speed(2.0);
+        clock.tick(0.5);
+        // Six steps of half a step each
+        assert_near(clock.time, FIXED_STEP * 3.0);
+
+        clock.set_speed(100.0);
+        assert_eq!(clock.speed(), MAX_SPEED);
+        clock.set_speed(0.0);
+        assert_eq!(clock.speed(), MIN_SPEED);
+    }
+
+    #[test]
+    fn fixed_advances_steps_and_scrubbing_ignore_pausing() {
+        let mut clock = Clock::new();
+        clock.set_paused(true);
+        clock.set_speed(2.0);
+        clock.advance(0.5, 1.0);
+        assert_eq!(clock.time(), 1.0);
+        clock.step_frames(-30, 1.0);
+        assert_near(clock.time(), 0.5);
+        clock.scrub(2.0, 0.25);
+        assert_near(clock.time(), 1.0);
+    }
+}
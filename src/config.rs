@@ -0,0 +1,847 @@
+use crate::data;
+use crate::events::{Trigger, TriggerAction};
+use crate::grid::GridSize;
+use crate::heightfield::{GerstnerWave, WaveFunction, MAX_GERSTNER_WAVES};
+use crate::palette::{Palette, PaletteInput};
+use crate::post::{AntiAliasing, BloomSettings, ExposureSettings, Lut, OutputSettings};
+use crate::qr_overlay::{self, Corner};
+use crate::schedule::ScheduleEntry;
+use crate::uniforms::FogMode;
+use crate::watch::FileWatcher;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Startup settings read from a TOML file. Every field is optional and falls
+// back to the built-in default, so an empty file is a valid config.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub world: WorldConfig,
+    pub window: WindowConfig,
+    pub grid: GridSize,
+    pub waves: WaveConfig,
+    pub heightmap: HeightmapConfig,
+    pub colors: ColorConfig,
+    pub fog: FogConfig,
+    pub bloom: BloomSettings,
+    pub exposure: ExposureSettings,
+    pub output: OutputSettings,
+    pub gpu: GpuConfig,
+    pub text: TextConfig,
+    pub qr: QrConfig,
+    pub logo: LogoConfig,
+    pub burn_in: BurnInConfig,
+    pub camera: CameraConfig,
+    pub accessibility: AccessibilityConfig,
+    pub mutate: MutateConfig,
+    pub beat: BeatConfig,
+    pub audio: AudioConfig,
+    pub playlist: PlaylistConfig,
+    pub pitch: PitchConfig,
+    pub chroma: ChromaConfig,
+    pub loudness: LoudnessConfig,
+    // Presets and output on or off by time of day. Only read from the main
+    // config, not from presets.
+    pub schedule: Vec<ScheduleEntry>,
+    // Actions taken on events detected in the audio. Only read from the
+    // main config, like the schedule.
+    pub triggers: Vec<Trigger>,
+}
+
+// World units per grid unit; the grid spans -1..1 grid units. Camera speeds,
+// clip planes and the camera position below scale along with it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldConfig {
+    pub scale: f32,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self { scale: 1.0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    // Takes precedence over vsync when the surface supports it
+    pub present_mode: Option<PresentMode>,
+    // Picture width/height ratio, with black bars filling the rest
+    pub aspect: Option<f32>,
+    // Fraction of the width and height left black on each side
+    pub overscan: f32,
+    // Samples per pixel for the grid; lowered to what the GPU supports
+    pub msaa: u32,
+    pub anti_aliasing: AntiAliasing,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            fullscreen: false,
+            vsync: true,
+            present_mode: None,
+            aspect: None,
+            overscan: 0.0,
+            msaa: 1,
+            anti_aliasing: AntiAliasing::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PresentMode {
+    Fifo,
+    Mailbox,
+    Immediate,
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveConfig {
+    pub function: WaveFunction,
+    pub amplitude: f32,
+    // Waves across the grid and animation speed, relative to the defaults
+    pub frequency: f32,
+    pub speed: f32,
+    // Whether noise starts enabled, and its amplitude when toggled on
+    pub noise: bool,
+    pub noise_amplitude: f32,
+    pub noise_octaves: u32,
+    // Wave trains summed by the "gerstner" function
+    pub gerstner: Vec<GerstnerWave>,
+}
+
+impl Default for WaveConfig {
+    fn default() -> Self {
+        Self {
+            function: WaveFunction::Sines,
+            amplitude: 1.0,
+            frequency: 1.0,
+            speed: 1.0,
+            noise: false,
+            noise_amplitude: 0.25,
+            noise_octaves: 4,
+            gerstner: GerstnerWave::default_set(),
+        }
+    }
+}
+
+// Terrain from a grayscale image under the waves, e.g. for a flyover
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeightmapConfig {
+    // PNG or EXR, relative to the config; unset keeps the ground flat
+    pub path: Option<PathBuf>,
+    // Grid units of height for white, or an EXR value of 1
+    pub height: f32,
+}
+
+impl Default for HeightmapConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            height: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    pub major: [f32; 4],
+    pub minor: [f32; 4],
+    // Flat uses the colors above; other palettes replace them
+    pub palette: Palette,
+    pub palette_by: PaletteInput,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            major: crate::uniforms::MAJOR_LINE_COLOR,
+            minor: crate::uniforms::MINOR_LINE_COLOR,
+            palette: Palette::Flat,
+            palette_by: PaletteInput::Height,
+        }
+    }
+}
+
+// Distances from the camera in grid units
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FogConfig {
+    pub mode: FogMode,
+    pub color: [f32; 4],
+    // Where the fog begins, and for linear fog where it is complete
+    pub start: f32,
+    pub end: f32,
+    // Thickness per grid unit for the exponential modes
+    pub density: f32,
+}
+
+impl Default for FogConfig {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::Off,
+            color: [0.0, 0.0, 0.0, 1.0],
+            start: 4.0,
+            end: 7.0,
+            density: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpuConfig {
+    // MiB of GPU memory to keep the render targets and grid within, lowering
+    // quality to fit; unset relies on allocation failures alone
+    pub memory_budget: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextConfig {
+    // Font files for characters the bundled font lacks, tried in order
+    pub fallback_fonts: Vec<PathBuf>,
+}
+
+// QR code linking to `url`, shown from the start if enabled and toggled with
+// the `qr` control command
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QrConfig {
+    pub enabled: bool,
+    pub url: String,
+    pub corner: Corner,
+    // Side of the code as a fraction of the shorter side of the picture
+    pub size: f32,
+}
+
+impl Default for QrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            corner: Corner::BottomRight,
+            size: 0.25,
+        }
+    }
+}
+
+// A logo or watermark over a corner of the picture, for branded streams and
+// events. With `pulse` above 0 it grows by up to that fraction with the bass.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogoConfig {
+    // PNG, relative to the config file
+    pub path: Option<PathBuf>,
+    pub corner: Corner,
+    // Width of the logo as a fraction of the width of the picture
+    pub scale: f32,
+    pub opacity: f32,
+    pub pulse: f32,
+}
+
+impl Default for LogoConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            corner: Corner::TopRight,
+            scale: 0.15,
+            opacity: 0.8,
+            pulse: 0.0,
+        }
+    }
+}
+
+// Burn-in prevention for panels showing the picture around the clock. The
+// picture drifts by up to `shift` pixels, going round once every `period`
+// seconds, and overlays are inverted every other `invert_interval` seconds.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BurnInConfig {
+    pub enabled: bool,
+    pub shift: u32,
+    pub period: f32,
+    // 0 never inverts
+    pub invert_interval: f32,
+}
+
+impl Default for BurnInConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shift: 4,
+            period: 600.0,
+            invert_interval: 900.0,
+        }
+    }
+}
+
+// Start position in grid units, with yaw, pitch and roll in degrees
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraConfig {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.5, -5.0],
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessibilityConfig {
+    // Scale of wave motion and camera movement, from 0 for still to 1
+    pub motion: f32,
+    // Caps motion at a low level for viewers sensitive to it; M toggles it
+    pub reduced_motion: bool,
+    // Lines three pixels wide, minor lines as bright as major ones, lighter
+    // palettes and no fog, for viewers with low vision
+    pub high_contrast: bool,
+    // Limits how fast the average brightness of the picture may change, for
+    // photosensitive viewers
+    pub reduce_flashes: bool,
+    // Largest change in average brightness per second with reduce_flashes,
+    // as a fraction of full white
+    pub flash_limit: f32,
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            motion: 1.0,
+            reduced_motion: false,
+            high_contrast: false,
+            reduce_flashes: false,
+            flash_limit: 0.5,
+        }
+    }
+}
+
+// Ranges the mutate action keeps parameters within, and how far a single
+// mutation may move them as a fraction of each range. Strength is also the
+// chance of flipping the wave function and noise.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutateConfig {
+    pub strength: f32,
+    pub amplitude: [f32; 2],
+    pub noise_amplitude: [f32; 2],
+    pub noise_octaves: [u32; 2],
+    // Whether line colors are mutated too
+    pub colors: bool,
+}
+
+impl Default for MutateConfig {
+    fn default() -> Self {
+        Self {
+            strength: 0.2,
+            amplitude: [0.2, 2.0],
+            noise_amplitude: [0.0, 0.6],
+            noise_octaves: [1, 8],
+            colors: true,
+        }
+    }
+}
+
+// Tempo of the beat clock. While randomize is on, the look is mutated on
+// the downbeat of every `bars`th bar.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BeatConfig {
+    pub bpm: f32,
+    pub beats_per_bar: u32,
+    pub bars: u32,
+    pub randomize: bool,
+}
+
+impl Default for BeatConfig {
+    fn default() -> Self {
+        Self {
+            bpm: 120.0,
+            beats_per_bar: 4,
+            bars: 1,
+            randomize: false,
+        }
+    }
+}
+
+// Level shaping of the audio before analysis, in dB: a fixed gain, a gate
+// below which windows count as silence, and a soft-knee compressor above
+// `threshold` that divides the excess by `ratio`. Levels are RMS in dBFS.
+// Band levels from `range` low to high map to no movement through full.
+// Analysis runs once per rendered frame, or `analysis_rate` times a second
+// with the bands interpolated in between.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub gain: f32,
+    pub gate: f32,
+    pub threshold: f32,
+    pub ratio: f32,
+    pub range: [f32; 2],
+    // Windows analyzed per second, or 0 for one per rendered frame
+    pub analysis_rate: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            gain: 0.0,
+            gate: -100.0,
+            threshold: 0.0,
+            ratio: 1.0,
+            range: [-70.0, -10.0],
+            analysis_rate: 0.0,
+        }
+    }
+}
+
+// Playing a directory or M3U playlist: seconds the end of each track
+// overlaps the start of the next, and whether to start over after the last.
+// Only read at startup.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaylistConfig {
+    pub crossfade: f32,
+    pub repeat: bool,
+}
+
+// Tinting the lines by the note being played, C red round the color wheel
+// to B, keeping each color's brightness
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PitchConfig {
+    pub colors: bool,
+}
+
+// Harmony from the chromagram, the strength of each pitch class: a ring
+// around the picture showing it, and tinting the lines by the key's tonic,
+// like the pitch colors. Pitch colors win when both are on.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChromaConfig {
+    pub ring: bool,
+    pub colors: bool,
+}
+
+// Swelling the waves with the momentary loudness, which follows how loud
+// the music sounds more steadily than the level: flat at `quiet` LUFS and
+// at their full amplitude from `loud` up
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoudnessConfig {
+    pub waves: bool,
+    pub quiet: f32,
+    pub loud: f32,
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        Self {
+            waves: false,
+            quiet: -40.0,
+            loud: -10.0,
+        }
+    }
+}
+
+impl LoudnessConfig {
+    // Share of the wave amplitude at `lufs`
+    pub fn wave_scale(&self, lufs: f32) -> f32 {
+        ((lufs - self.quiet) / (self.loud - self.quiet)).clamp(0.0, 1.0)
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&std::fs::read_to_string(path)?, path.parent())
+    }
+
+    // Settings from TOML text, checked like a file. Paths in it are relative
+    // to `dir`, or to the working directory without one.
+    pub fn parse(contents: &str, dir: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let mut config: Config = toml::from_str(contents)?;
+        if config.grid.width < 2 || config.grid.depth < 2 {
+            return Err("grid width and depth must be at least 2".into());
+        }
+        if !config.world.scale.is_finite() || config.world.scale <= 0.0 {
+            return Err("world scale must be positive".into());
+        }
+        if config.window.width == 0 || config.window.height == 0 {
+            return Err("window width and height must be non-zero".into());
+        }
+        if let Some(aspect) = config.window.aspect {
+            if !aspect.is_finite() || aspect <= 0.0 {
+                return Err("window aspect must be positive".into());
+            }
+        }
+        if !(0.0..0.5).contains(&config.window.overscan) {
+            return Err("window overscan must be at least 0 and below 0.5".into());
+        }
+        if !matches!(config.window.msaa, 1 | 2 | 4 | 8 | 16) {
+            return Err("window msaa must be 1, 2, 4, 8 or 16".into());
+        }
+        let waves = &config.waves;
+        if !waves.frequency.is_finite()
+            || waves.frequency <= 0.0
+            || !waves.speed.is_finite()
+            || waves.speed < 0.0
+        {
+            return Err("wave frequency must be positive and speed non-negative".into());
+        }
+        if !waves.amplitude.is_finite()
+            || waves.amplitude < 0.0
+            || !waves.noise_amplitude.is_finite()
+            || waves.noise_amplitude < 0.0
+        {
+            return Err("wave amplitude and noise_amplitude must be non-negative".into());
+        }
+        if waves.gerstner.len() > MAX_GERSTNER_WAVES {
+            return Err(format!(
+                "at most {} gerstner waves are supported",
+                MAX_GERSTNER_WAVES
+            )
+            .into());
+        }
+        for wave in &waves.gerstner {
+            if wave.direction == [0.0, 0.0] || !wave.direction.iter().all(|d| d.is_finite()) {
+                return Err("gerstner wave direction must be a non-zero vector".into());
+            }
+            if !wave.wavelength.is_finite() || wave.wavelength <= 0.0 || !wave.speed.is_finite() {
+                return Err("gerstner wavelength must be positive and speed finite".into());
+            }
+            if !(0.0..=1.0).contains(&wave.steepness) {
+                return Err("gerstner steepness must be between 0 and 1".into());
+            }
+        }
+        if waves
+            .gerstner
+            .iter()
+            .map(|wave| wave.steepness)
+            .sum::<f32>()
+            > 1.0
+        {
+            return Err("gerstner steepness must add up to at most 1".into());
+        }
+        if !config.heightmap.height.is_finite() {
+            return Err("heightmap height must be finite".into());
+        }
+        // NaN fails the range check too
+        let colors = &config.colors;
+        if !colors
+            .major
+            .iter()
+            .chain(&colors.minor)
+            .all(|c| (0.0..=1.0).contains(c))
+        {
+            return Err("line colors must be RGBA in 0..1".into());
+        }
+        if let Some(heightmap) = &mut config.heightmap.path {
+            if let Some(dir) = dir {
+                *heightmap = dir.join(&*heightmap);
+            }
+            // Images only to the header; they are decoded when applied
+            let checked = if data::is_data_file(heightmap) {
+                data::load(heightmap).map(drop)
+            } else {
+                image::image_dimensions(&*heightmap)
+                    .map(drop)
+                    .map_err(Into::into)
+            };
+            checked.map_err(|e| format!("heightmap {}: {}", heightmap.display(), e))?;
+        }
+        let fog = &config.fog;
+        if !fog.start.is_finite() || fog.start < 0.0 || !fog.end.is_finite() || fog.end <= fog.start
+        {
+            return Err("fog start must be non-negative and end beyond it".into());
+        }
+        if !fog.density.is_finite() || fog.density < 0.0 {
+            return Err("fog density must be non-negative".into());
+        }
+        let bloom = &config.bloom;
+        if !bloom.threshold.is_finite()
+            || bloom.threshold < 0.0
+            || !bloom.intensity.is_finite()
+            || bloom.intensity < 0.0
+            || !bloom.radius.is_finite()
+            || bloom.radius < 0.0
+        {
+            return Err("bloom threshold, intensity and radius must be non-negative".into());
+        }
+        let exposure = &config.exposure;
+        if !exposure.key.is_finite() || exposure.key <= 0.0 {
+            return Err("exposure key must be positive".into());
+        }
+        if !exposure.adaptation.is_finite() || exposure.adaptation < 0.0 {
+            return Err("exposure adaptation must be non-negative".into());
+        }
+        if !(exposure.min > 0.0 && exposure.min <= exposure.max && exposure.max.is_finite()) {
+            return Err("exposure min must be positive and max at least min".into());
+        }
+        let output = &config.output;
+        if !output.brightness.is_finite() || output.brightness < 0.0 {
+            return Err("output brightness must be non-negative".into());
+        }
+        if !output.contrast.is_finite()
+            || output.contrast <= 0.0
+            || !output.gamma.is_finite()
+            || output.gamma <= 0.0
+        {
+            return Err("output contrast and gamma must be positive".into());
+        }
+        // The LUT sits next to the config, like a scheduled preset
+        if let Some(lut) = &mut config.output.lut {
+            if let Some(dir) = dir {
+                *lut = dir.join(&*lut);
+            }
+            Lut::load(lut).map_err(|e| format!("output lut {}: {}", lut.display(), e))?;
+        }
+        if config.gpu.memory_budget == Some(0) {
+            return Err("gpu memory_budget must be positive".into());
+        }
+        let qr = &config.qr;
+        if !(qr.size > 0.0 && qr.size <= 1.0) {
+            return Err("qr size must be above 0 and at most 1".into());
+        }
+        if !qr.url.is_empty() {
+            qr_overlay::encode(&qr.url).map_err(|e| format!("qr url doesn't fit: {}", e))?;
+        }
+        let logo = &mut config.logo;
+        if !(logo.scale > 0.0 && logo.scale <= 1.0) {
+            return Err("logo scale must be above 0 and at most 1".into());
+        }
+        if !(0.0..=1.0).contains(&logo.opacity) {
+            return Err("logo opacity must be between 0 and 1".into());
+        }
+        if !logo.pulse.is_finite() || logo.pulse < 0.0 {
+            return Err("logo pulse must be non-negative".into());
+        }
+        if let Some(logo) = &mut logo.path {
+            if let Some(dir) = dir {
+                *logo = dir.join(&*logo);
+            }
+            image::image_dimensions(&*logo)
+                .map_err(|e| format!("logo {}: {}", logo.display(), e))?;
+        }
+        let burn_in = &config.burn_in;
+        if !burn_in.period.is_finite() || burn_in.period <= 0.0 {
+            return Err("burn_in period must be positive".into());
+        }
+        if !burn_in.invert_interval.is_finite() || burn_in.invert_interval < 0.0 {
+            return Err("burn_in invert_interval must be non-negative".into());
+        }
+        if !(0.0..=1.0).contains(&config.accessibility.motion) {
+            return Err("accessibility motion must be between 0 and 1".into());
+        }
+        let flash_limit = config.accessibility.flash_limit;
+        if !flash_limit.is_finite() || flash_limit <= 0.0 {
+            return Err("accessibility flash_limit must be positive".into());
+        }
+        let mutate = &config.mutate;
+        if !(0.0..=1.0).contains(&mutate.strength) {
+            return Err("mutate strength must be between 0 and 1".into());
+        }
+        if mutate.amplitude[0] > mutate.amplitude[1]
+            || mutate.noise_amplitude[0] > mutate.noise_amplitude[1]
+            || mutate.noise_octaves[0] > mutate.noise_octaves[1]
+        {
+            return Err("mutate ranges must be [min, max]".into());
+        }
+        let beat = &config.beat;
+        if !beat.bpm.is_finite() || beat.bpm <= 0.0 {
+            return Err("beat bpm must be positive".into());
+        }
+        if beat.beats_per_bar == 0 || beat.bars == 0 {
+            return Err("beat beats_per_bar and bars must be at least 1".into());
+        }
+        let audio = &config.audio;
+        if ![audio.gain, audio.gate, audio.threshold]
+            .iter()
+            .all(|db| db.is_finite())
+        {
+            return Err("audio gain, gate and threshold must be finite".into());
+        }
+        if !audio.ratio.is_finite() || audio.ratio < 1.0 {
+            return Err("audio ratio must be at least 1".into());
+        }
+        if !audio.range.iter().all(|db| db.is_finite()) || audio.range[0] >= audio.range[1] {
+            return Err("audio range must be [low, high] in dB".into());
+        }
+        let rate = audio.analysis_rate;
+        if !(rate == 0.0 || (10.0..=1000.0).contains(&rate)) {
+            return Err("audio analysis_rate must be 0 or 10 to 1000".into());
+        }
+        if !config.playlist.crossfade.is_finite() || config.playlist.crossfade < 0.0 {
+            return Err("playlist crossfade must be non-negative".into());
+        }
+        let loudness = &config.loudness;
+        if !loudness.quiet.is_finite() || !loudness.loud.is_finite() {
+            return Err("loudness quiet and loud must be finite".into());
+        }
+        if loudness.loud <= loudness.quiet {
+            return Err("loudness loud must be above quiet".into());
+        }
+        for trigger in &config.triggers {
+            if !trigger.cooldown.is_finite() || trigger.cooldown < 0.0 {
+                return Err("trigger cooldown must be non-negative".into());
+            }
+            if trigger.preset.is_some() && trigger.action != TriggerAction::Preset {
+                return Err("only preset triggers can have a preset".into());
+            }
+        }
+        for (index, entry) in config.schedule.iter().enumerate() {
+            if config.schedule[..index].iter().any(|e| e.at == entry.at) {
+                return Err("schedule entries must have different times".into());
+            }
+            if entry.off && entry.preset.is_some() {
+                return Err("a schedule entry can't both be off and have a preset".into());
+            }
+        }
+        Ok(config)
+    }
+}
+
+// Write `audio` into the [audio] section of the config file at `path`,
+// creating either if missing. The rest of the file, including comments and
+// the values left out of the section, stays as it was.
+pub fn save_audio(path: &Path, audio: &AudioConfig) -> Result<(), Box<dyn Error>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut document: toml_edit::DocumentMut = contents.parse()?;
+    let section = document
+        .entry("audio")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or("audio is not a table")?;
+    // To a tenth of a dB, which is as precise as the levels are measured
+    let db = |value: f32| toml_edit::value((f64::from(value) * 10.0).round() / 10.0);
+    section.insert("gain", db(audio.gain));
+    section.insert("gate", db(audio.gate));
+    section.insert("threshold", db(audio.threshold));
+    section.insert("ratio", db(audio.ratio));
+    let range: toml_edit::Array = audio
+        .range
+        .iter()
+        .map(|value| (f64::from(*value) * 10.0).round() / 10.0)
+        .collect();
+    section.insert("range", toml_edit::value(range));
+    std::fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+// Reloads a config file whenever it changes on disk
+pub struct ConfigWatcher {
+    file: FileWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            file: FileWatcher::new(path)?,
+        })
+    }
+
+    // The new config if the file changed since the last call. A file that no
+    // longer parses is reported, and the current settings should be kept.
+    pub fn poll(&self) -> Option<Result<Config, String>> {
+        if !self.file.changed() {
+            return None;
+        }
+
+        let path = self.file.path();
+        Some(match Config::load(path) {
+            Ok(config) => {
+                log::info!("Reloaded {}", path.display());
+                Ok(config)
+            }
+            Err(e) => {
+                log::error!("Failed to reload {}: {}", path.display(), e);
+                Err(format!("Failed to reload {}: {}", path.display(), e))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_audio_keeps_the_rest_of_the_file() {
+        let path = std::env::temp_dir().join(format!("sideharso-{}.toml", std::process::id()));
+        let original = "# Comment\n[audio]\nratio = 4.0 # kept\n\n[beat]\nbpm = 90.0\n";
+        std::fs::write(&path, original).unwrap();
+        let audio = AudioConfig {
+            gain: 12.34,
+            ..AudioConfig::default()
+        };
+        save_audio(&path, &audio).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let config = Config::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(saved.starts_with("# Comment\n"));
+        assert_eq!(config.audio.gain, 12.3);
+        assert_eq!(config.audio.ratio, 1.0);
+        assert_eq!(config.beat.bpm, 90.0);
+    }
+
+    #[test]
+    fn serialized_settings_read_back_the_same() {
+        let contents = r#"
+            [window]
+            present_mode = "mailbox"
+            aspect = 2.39
+            [fog]
+            mode = "exp2"
+            [qr]
+            corner = "top-left"
+            [[schedule]]
+            at = "07:05"
+            preset = "morning.toml"
+            [[triggers]]
+            on = "silence-start"
+            action = "camera-cut"
+        "#;
+        let config: Config = toml::from_str(contents).unwrap();
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(serialized.contains("at = \"07:05\""));
+        assert_eq!(toml::from_str::<Config>(&serialized).unwrap(), config);
+    }
+}
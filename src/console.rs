@@ -0,0 +1,188 @@
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+// Lines kept for scrollback; the oldest are dropped first
+const CAPACITY: usize = 1000;
+// Records at this level and above are kept for the console whatever the
+// RUST_LOG filter, so it works without a terminal. Info records only from
+// this crate and the binary; wgpu logs every resource it creates.
+const CAPTURE_LEVEL: Level = Level::Info;
+const OWN_TARGETS: [&str; 2] = ["sideharso", "rust_glsl"];
+
+const FONT_SIZE: f32 = 14.0;
+// Pixels between the viewport edges, the backdrop and the text
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+// Share of the viewport height the console covers
+const HEIGHT: f32 = 0.5;
+
+const HEADER_COLOR: [f32; 4] = [0.6, 0.8, 1.0, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+const WARN_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const INFO_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+struct LogLine {
+    level: Level,
+    text: String,
+}
+
+static LINES: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+// Passes records on to env_logger as configured, and keeps a copy of the
+// important ones for the console
+struct ConsoleLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= CAPTURE_LEVEL || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        let own = OWN_TARGETS
+            .iter()
+            .any(|target| record.target().starts_with(target));
+        if record.level() > CAPTURE_LEVEL || (record.level() == Level::Info && !own) {
+            return;
+        }
+        let Ok(mut lines) = LINES.lock() else {
+            return;
+        };
+        let time = chrono::Local::now().format("%H:%M:%S");
+        let message = record.args().to_string();
+        // One entry per line, so multi-line messages scroll line by line
+        for (index, line) in message.lines().enumerate() {
+            let text = if index == 0 {
+                format!("{} {:<5} {}", time, record.level(), line)
+            } else {
+                format!("               {}", line)
+            };
+            if lines.len() == CAPACITY {
+                lines.pop_front();
+            }
+            lines.push_back(LogLine {
+                level: record.level(),
+                text,
+            });
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+// Install `logger` as the global logger, with its output also kept for the
+// console
+pub fn init(logger: env_logger::Logger) {
+    let max_level = logger.filter().max(CAPTURE_LEVEL.to_level_filter());
+    if log::set_boxed_logger(Box::new(ConsoleLogger { inner: logger })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+// Recent log output over the top of the picture, so shader errors and audio
+// device problems can be seen in fullscreen and kiosk use. Shows records at
+// `level` and above, scrolled back by `scroll` lines from the newest.
+pub struct Console {
+    pub visible: bool,
+    level: LevelFilter,
+    scroll: usize,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            level: LevelFilter::Info,
+            scroll: 0,
+        }
+    }
+}
+
+impl Console {
+    // Show info and above, then warnings and errors, then errors only
+    pub fn cycle_level(&mut self) {
+        self.level = match self.level {
+            LevelFilter::Info => LevelFilter::Warn,
+            LevelFilter::Warn => LevelFilter::Error,
+            _ => LevelFilter::Info,
+        };
+        self.scroll = 0;
+    }
+
+    // Scroll back by `lines`, or forward for a negative count, stopping at
+    // the newest line
+    pub fn scroll(&mut self, lines: isize) {
+        self.scroll = self.scroll.saturating_add_signed(lines);
+    }
+
+    // Lay out the lines in view in `text` for the next draw
+    pub fn push(&mut self, viewport: Viewport, text: &mut TextRenderer) {
+        if !self.visible {
+            return;
+        }
+        let line_height = text.line_height(FONT_SIZE);
+        let size = [
+            viewport.width as f32 - MARGIN * 2.0,
+            (viewport.height as f32 * HEIGHT).round(),
+        ];
+        let rows = ((size[1] - PADDING * 2.0) / line_height).floor().max(2.0) as usize - 1;
+
+        // Copied out so nothing logged while laying out waits on the lock
+        let (total, start, end, visible) = {
+            let Ok(lines) = LINES.lock() else {
+                return;
+            };
+            let shown: Vec<_> = lines
+                .iter()
+                .filter(|line| line.level <= self.level)
+                .collect();
+            self.scroll = self.scroll.min(shown.len().saturating_sub(rows));
+            let end = shown.len() - self.scroll;
+            let start = end.saturating_sub(rows);
+            let visible: Vec<_> = shown[start..end]
+                .iter()
+                .map(|line| (line.level, line.text.clone()))
+                .collect();
+            (shown.len(), start, end, visible)
+        };
+
+        text.add_rect(
+            [MARGIN, MARGIN],
+            [MARGIN + size[0], MARGIN + size[1]],
+            BACKDROP_COLOR,
+        );
+        let level = self.level.to_string().to_lowercase();
+        let header = match total {
+            0 => format!("Log: {} and above, nothing yet (F7 filter)", level),
+            total => format!(
+                "Log: {} and above, lines {}-{} of {} (F7 filter, PgUp/PgDn scroll)",
+                level,
+                start + 1,
+                end,
+                total
+            ),
+        };
+        let left = MARGIN + PADDING;
+        let mut top = MARGIN + PADDING;
+        text.add_text(&header, [left, top], FONT_SIZE, HEADER_COLOR);
+        for (level, line) in visible {
+            top += line_height;
+            let color = match level {
+                Level::Error => ERROR_COLOR,
+                Level::Warn => WARN_COLOR,
+                _ => INFO_COLOR,
+            };
+            text.add_text(&line, [left, top], FONT_SIZE, color);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Commands accepted on the control socket, one per line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlCommand {
+    Reload,
+    Screenshot,
+    Mutate,
+    Undo,
+    Palette,
+    Qr,
+    Quit,
+}
+
+impl ControlCommand {
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.trim() {
+            "reload" => Some(ControlCommand::Reload),
+            "screenshot" => Some(ControlCommand::Screenshot),
+            "mutate" => Some(ControlCommand::Mutate),
+            "undo" => Some(ControlCommand::Undo),
+            "palette" => Some(ControlCommand::Palette),
+            "qr" => Some(ControlCommand::Qr),
+            "quit" => Some(ControlCommand::Quit),
+            _ => None,
+        }
+    }
+}
+
+// Where to listen: a TCP address such as 127.0.0.1:7770, or otherwise the
+// path of a Unix socket
+#[derive(Clone, Debug)]
+pub enum ControlAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl ControlAddress {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if let Ok(address) = value.parse() {
+            return Ok(ControlAddress::Tcp(address));
+        }
+        #[cfg(unix)]
+        return Ok(ControlAddress::Unix(PathBuf::from(value)));
+        #[cfg(not(unix))]
+        Err(format!("invalid address {:?}, expected HOST:PORT", value))
+    }
+}
+
+impl std::fmt::Display for ControlAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ControlAddress::Tcp(address) => address.fmt(f),
+            #[cfg(unix)]
+            ControlAddress::Unix(path) => path.display().fmt(f),
+        }
+    }
+}
+
+// Plain text remote control for unattended installations, e.g.
+// `echo mutate | nc -q0 127.0.0.1 7770`. Each connection is served on its own
+// thread and commands are queued for the render loop; every line gets an
+// "ok" or an "error" back.
+pub struct ControlServer {
+    commands: Receiver<ControlCommand>,
+}
+
+impl ControlServer {
+    pub fn bind(address: &ControlAddress) -> io::Result<Self> {
+        let (sender, commands) = mpsc::channel();
+        match address {
+            ControlAddress::Tcp(address) => spawn_tcp(TcpListener::bind(address)?, sender),
+            #[cfg(unix)]
+            ControlAddress::Unix(path) => {
+                // A socket left behind by an earlier run would make bind fail
+                if std::os::unix::net::UnixStream::connect(path).is_err() {
+                    let _ = std::fs::remove_file(path);
+                }
+                spawn_unix(std::os::unix::net::UnixListener::bind(path)?, sender);
+            }
+        }
+        Ok(Self { commands })
+    }
+
+    // Use the listening socket passed by systemd socket activation, if any
+    #[cfg(unix)]
+    pub fn from_systemd() -> Option<Self> {
+        use std::os::fd::{FromRawFd, IntoRawFd};
+
+        // First passed descriptor, per sd_listen_fds(3)
+        const LISTEN_FDS_START: i32 = 3;
+
+        let ours = std::env::var("LISTEN_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok())
+            .is_some_and(|pid| pid == std::process::id());
+        let count = std::env::var("LISTEN_FDS")
+            .ok()
+            .and_then(|count| count.parse::<u32>().ok())
+            .unwrap_or(0);
+        if !ours || count == 0 {
+            return None;
+        }
+
+        let (sender, commands) = mpsc::channel();
+        // The descriptor is either kind of stream socket; only TCP ones
+        // have an IP address
+        let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+        match listener.local_addr() {
+            Ok(address) => {
+                log::info!("Control socket passed by systemd on {}", address);
+                spawn_tcp(listener, sender);
+            }
+            Err(_) => {
+                let fd = listener.into_raw_fd();
+                let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+                log::info!("Control socket passed by systemd");
+                spawn_unix(listener, sender);
+            }
+        }
+        Some(Self { commands })
+    }
+
+    // Next queued command, without blocking
+    pub fn poll(&self) -> Option<ControlCommand> {
+        self.commands.try_recv().ok()
+    }
+}
+
+fn spawn_tcp(listener: TcpListener, sender: Sender<ControlCommand>) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let Ok(reader) = stream.try_clone() else {
+                continue;
+            };
+            let sender = sender.clone();
+            std::thread::spawn(move || serve(BufReader::new(reader), stream, sender));
+        }
+    });
+}
+
+#[cfg(unix)]
+fn spawn_unix(listener: std::os::unix::net::UnixListener, sender: Sender<ControlCommand>) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let Ok(reader) = stream.try_clone() else {
+                continue;
+            };
+            let sender = sender.clone();
+            std::thread::spawn(move || serve(BufReader::new(reader), stream, sender));
+        }
+    });
+}
+
+// Read commands until the client hangs up or the app has quit
+fn serve(reader: impl BufRead, mut writer: impl Write, sender: Sender<ControlCommand>) {
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match ControlCommand::parse(&line) {
+            Some(command) => {
+                if sender.send(command).is_err() {
+                    return;
+                }
+                "ok".to_string()
+            }
+            None => format!("error: unknown command {:?}", line.trim()),
+        };
+        if writeln!(writer, "{}", reply).is_err() {
+            return;
+        }
+    }
+}
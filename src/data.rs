@@ -0,0 +1,191 @@
+use image::{ImageBuffer, Luma};
+use std::error::Error;
+use std::path::Path;
+
+// Whether `path` names a data file rather than an image
+pub fn is_data_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["csv", "tsv", "json"]
+                .iter()
+                .any(|data| extension.eq_ignore_ascii_case(data))
+        })
+}
+
+// Numeric series from a CSV or JSON file as a surface, one row of the file
+// per image row and one column per image column, normalized so the smallest
+// value is 0 and the largest 1. Headers, label columns and other cells
+// without a number are left out; gaps inside the data sit at 0.
+//
+// A JSON file holds a list of rows or an object of named series. A row is a
+// list of numbers or an object whose numeric fields are its columns, and a
+// list of plain numbers is a single series.
+pub fn load(path: &Path) -> Result<ImageBuffer<Luma<f32>, Vec<f32>>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let rows = if is_json {
+        parse_json(&text)?
+    } else {
+        parse_csv(&text)
+    };
+    surface(rows)
+}
+
+fn parse_csv(text: &str) -> Vec<Vec<Option<f32>>> {
+    let delimiter = if text.contains('\t') { '\t' } else { ',' };
+    text.lines()
+        .map(|line| {
+            line.split(delimiter)
+                .map(|cell| cell.trim().trim_matches('"').trim().parse().ok())
+                .collect()
+        })
+        .collect()
+}
+
+fn parse_json(text: &str) -> Result<Vec<Vec<Option<f32>>>, Box<dyn Error>> {
+    use serde_json::Value;
+
+    let number = |value: &Value| value.as_f64().map(|value| value as f32);
+    let row = |value: &Value| match value {
+        Value::Array(values) => values.iter().map(number).collect(),
+        Value::Object(fields) => fields.values().map(number).collect(),
+        value => vec![number(value)],
+    };
+    match serde_json::from_str(text)? {
+        Value::Array(values)
+            if values
+                .iter()
+                .all(|value| !value.is_array() && !value.is_object()) =>
+        {
+            Ok(vec![values.iter().map(number).collect()])
+        }
+        Value::Array(values) => Ok(values.iter().map(row).collect()),
+        Value::Object(series) => Ok(series.values().map(row).collect()),
+        _ => Err("expected a list of rows or an object of series".into()),
+    }
+}
+
+fn surface(
+    rows: Vec<Vec<Option<f32>>>,
+) -> Result<ImageBuffer<Luma<f32>, Vec<f32>>, Box<dyn Error>> {
+    let finite = |cell: &&Option<f32>| cell.is_some_and(f32::is_finite);
+    let rows: Vec<_> = rows
+        .into_iter()
+        .filter(|row| row.iter().any(|cell| finite(&cell)))
+        .collect();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let columns: Vec<usize> = (0..width)
+        .filter(|&column| {
+            rows.iter()
+                .any(|row| row.get(column).is_some_and(|cell| finite(&cell)))
+        })
+        .collect();
+    if columns.is_empty() {
+        return Err("no numbers found".into());
+    }
+
+    let values = || rows.iter().flatten().filter(finite).flatten().copied();
+    let min = values().fold(f32::INFINITY, f32::min);
+    let max = values().fold(f32::NEG_INFINITY, f32::max);
+    // A flat series stays flat at the bottom rather than dividing by zero
+    let range = if max > min { max - min } else { 1.0 };
+
+    let mut image = ImageBuffer::new(columns.len() as u32, rows.len() as u32);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &column) in columns.iter().enumerate() {
+            let value = row
+                .get(column)
+                .copied()
+                .flatten()
+                .filter(|value| value.is_finite())
+                .map_or(0.0, |value| (value - min) / range);
+            image.put_pixel(x as u32, y as u32, Luma([value]));
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixels(image: &ImageBuffer<Luma<f32>, Vec<f32>>) -> Vec<Vec<f32>> {
+        image
+            .rows()
+            .map(|row| row.map(|pixel| pixel[0]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn csv_headers_and_labels_are_left_out() {
+        let text = "month,\"sales\",returns\nJan,10,0\nFeb,\"30\",5\n\nMar,20,10\n";
+        let image = surface(parse_csv(text)).unwrap();
+        assert_eq!(
+            pixels(&image),
+            vec![
+                vec![1.0 / 3.0, 0.0],
+                vec![1.0, 1.0 / 6.0],
+                vec![2.0 / 3.0, 1.0 / 3.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn tabs_split_cells_and_gaps_sit_at_zero() {
+        let text = "a\tb\tc\n2\t\t4\n4\tn/a\t6\n";
+        let image = surface(parse_csv(text)).unwrap();
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(pixels(&image), vec![vec![0.0, 0.5], vec![0.5, 1.0]]);
+    }
+
+    #[test]
+    fn json_rows_series_and_plain_lists() {
+        let rows = parse_json("[[1, 2], [3, 4]]").unwrap();
+        assert_eq!(
+            pixels(&surface(rows).unwrap()),
+            vec![vec![0.0, 1.0 / 3.0], vec![2.0 / 3.0, 1.0]]
+        );
+
+        // Fields without a number, like the label, are left out
+        let objects = parse_json(
+            "[{\"day\": \"mon\", \"x\": 1, \"y\": 2}, {\"day\": \"tue\", \"x\": 3, \"y\": 4}]",
+        )
+        .unwrap();
+        assert_eq!(
+            pixels(&surface(objects).unwrap()),
+            vec![vec![0.0, 1.0 / 3.0], vec![2.0 / 3.0, 1.0]]
+        );
+
+        let series = parse_json("{\"a\": [0, 8], \"b\": [4, 2]}").unwrap();
+        assert_eq!(
+            pixels(&surface(series).unwrap()),
+            vec![vec![0.0, 1.0], vec![0.5, 0.25]]
+        );
+
+        let list = parse_json("[5, 7, 6]").unwrap();
+        assert_eq!(pixels(&surface(list).unwrap()), vec![vec![0.0, 1.0, 0.5]]);
+    }
+
+    #[test]
+    fn flat_series_stay_at_zero_and_text_alone_is_rejected() {
+        let image = surface(parse_csv("3,3\n3,3\n")).unwrap();
+        assert_eq!(pixels(&image), vec![vec![0.0, 0.0], vec![0.0, 0.0]]);
+
+        assert!(surface(parse_csv("name,city\nAda,London\n")).is_err());
+        assert!(surface(parse_csv("")).is_err());
+        assert!(parse_json("\"text\"").is_err());
+        assert!(parse_json("[1, 2").is_err());
+    }
+
+    #[test]
+    fn data_files_are_recognized_by_extension() {
+        assert!(is_data_file(Path::new("prices.CSV")));
+        assert!(is_data_file(Path::new("dir/series.json")));
+        assert!(is_data_file(Path::new("table.tsv")));
+        assert!(!is_data_file(Path::new("terrain.png")));
+        assert!(!is_data_file(Path::new("csv")));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::viewport::Viewport;
+use std::collections::VecDeque;
+
+// Number of samples kept per graph, one per frame
+const HISTORY_LENGTH: usize = 240;
+
+// Panel placement in normalized device coordinates (bottom-left corner)
+const PANEL_LEFT: f32 = -0.98;
+const PANEL_WIDTH: f32 = 0.6;
+const PANEL_BOTTOM: f32 = -0.98;
+const GRAPH_HEIGHT: f32 = 0.15;
+const GRAPH_SPACING: f32 = 0.03;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OverlayVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+// A scrolling time series plotted between a fixed min and max
+struct Graph {
+    color: [f32; 4],
+    min: f32,
+    max: f32,
+    samples: VecDeque<f32>,
+}
+
+// Debug overlay with one small scrolling graph per internal signal
+pub struct DebugOverlay {
+    pub visible: bool,
+    graphs: Vec<Graph>,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+    num_vertices: u32,
+}
+
+impl DebugOverlay {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let vertex_capacity = 1024;
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Overlay Vertex Buffer"),
+            size: (vertex_capacity * std::mem::size_of::<OverlayVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            visible: false,
+            graphs: Vec::new(),
+            pipeline: create_overlay_pipeline(device, format),
+            vertex_buffer,
+            vertex_capacity,
+            num_vertices: 0,
+        }
+    }
+
+    // Graphs are stacked bottom-up in the order they are added
+    pub fn add_graph(&mut self, color: [f32; 4], min: f32, max: f32) {
+        self.graphs.push(Graph {
+            color,
+            min,
+            max,
+            samples: VecDeque::with_capacity(HISTORY_LENGTH),
+        });
+    }
+
+    // Append one sample per graph, in the order the graphs were added
+    pub fn push(&mut self, values: &[f32]) {
+        for (graph, value) in self.graphs.iter_mut().zip(values) {
+            if graph.samples.len() == HISTORY_LENGTH {
+                graph.samples.pop_front();
+            }
+            graph.samples.push_back(*value);
+        }
+    }
+
+    // Rebuild the line geometry for the current samples
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.visible {
+            return;
+        }
+
+        let mut vertices = Vec::new();
+        for (index, graph) in self.graphs.iter().enumerate() {
+            let bottom = PANEL_BOTTOM + index as f32 * (GRAPH_HEIGHT + GRAPH_SPACING);
+            let top = bottom + GRAPH_HEIGHT;
+            let right = PANEL_LEFT + PANEL_WIDTH;
+
+            // Dim frame around each graph
+            let frame_color = [0.4, 0.4, 0.4, 1.0];
+            let corners = [
+                [PANEL_LEFT, bottom],
+                [right, bottom],
+                [right, top],
+                [PANEL_LEFT, top],
+            ];
+            for i in 0..corners.len() {
+                vertices.push(OverlayVertex {
+                    position: corners[i],
+                    color: frame_color,
+                });
+                vertices.push(OverlayVertex {
+                    position: corners[(i + 1) % corners.len()],
+                    color: frame_color,
+                });
+            }
+
+            // Plot samples as line segments, newest on the right
+            let step = PANEL_WIDTH / (HISTORY_LENGTH - 1) as f32;
+            let offset = HISTORY_LENGTH - graph.samples.len();
+            let point = |i: usize, value: f32| {
+                let t = ((value - graph.min) / (graph.max - graph.min)).clamp(0.0, 1.0);
+                [
+                    PANEL_LEFT + (offset + i) as f32 * step,
+                    bottom + t * GRAPH_HEIGHT,
+                ]
+            };
+            for (i, pair) in graph
+                .samples
+                .iter()
+                .zip(graph.samples.iter().skip(1))
+                .enumerate()
+            {
+                vertices.push(OverlayVertex {
+                    position: point(i, *pair.0),
+                    color: graph.color,
+                });
+                vertices.push(OverlayVertex {
+                    position: point(i + 1, *pair.1),
+                    color: graph.color,
+                });
+            }
+        }
+
+        // Grow the vertex buffer if more graphs were added
+        if vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Debug Overlay Vertex Buffer"),
+                size: (self.vertex_capacity * std::mem::size_of::<OverlayVertex>())
+                    as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    // Draw the graphs on top of whatever is already in the viewport
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        target: &wgpu::TextureView,
+        viewport: Viewport,
+    ) -> Option<wgpu::CommandBuffer> {
+        if !self.visible || self.num_vertices == 0 {
+            return None;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Debug Overlay Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug Overlay Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            viewport.apply(&mut render_pass);
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..self.num_vertices, 0..1);
+        }
+
+        Some(encoder.finish())
+    }
+}
+
+fn create_overlay_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Overlay Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("overlay.wgsl").into()),
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Overlay Pipeline Layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Overlay Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,46 @@
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+
+const FONT_SIZE: f32 = 14.0;
+// Pixels between the viewport edges, the backdrop and the text
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+// Long validation messages are cut off after this many lines
+const MAX_LINES: usize = 12;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKDROP_COLOR: [f32; 4] = [0.45, 0.0, 0.0, 0.8];
+
+// Lay out `message` under `title` in a box along the bottom of the picture,
+// e.g. for a shader that failed to compile. It is shown for as long as it
+// is pushed each frame.
+pub fn push(title: &str, message: &str, viewport: Viewport, text: &mut TextRenderer) {
+    let max_width = viewport.width as f32 - (MARGIN + PADDING) * 2.0;
+    // wgpu separates the parts of its messages with blank lines
+    let message: Vec<_> = message.lines().filter(|l| !l.trim().is_empty()).collect();
+    let wrapped = text.wrap(
+        &format!("{}\n{}", title, message.join("\n")),
+        FONT_SIZE,
+        max_width,
+    );
+    let mut lines: Vec<_> = wrapped.lines().collect();
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES - 1);
+        lines.push("...");
+    }
+    let label = lines.join("\n");
+
+    let height = lines.len() as f32 * text.line_height(FONT_SIZE) + PADDING * 2.0;
+    let bottom = viewport.height as f32 - MARGIN;
+    text.add_rect(
+        [MARGIN, bottom - height],
+        [viewport.width as f32 - MARGIN, bottom],
+        BACKDROP_COLOR,
+    );
+    text.add_text(
+        &label,
+        [MARGIN + PADDING, bottom - height + PADDING],
+        FONT_SIZE,
+        TEXT_COLOR,
+    );
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Instant;
+
+// Onsets: rise in band levels over the average rise that counts as one, the
+// smallest rise that does, and the shortest gap between two
+const ONSET_FACTOR: f32 = 3.0;
+const MIN_ONSET_FLUX: f32 = 0.02;
+const MIN_ONSET_INTERVAL: f64 = 0.1;
+// Seconds the average rise follows over
+const FLUX_TIME: f32 = 1.0;
+// Drops: the level over DROP_FAST seconds falling below DROP_RATIO of the
+// level over DROP_SLOW seconds, once that is at least MIN_DROP_LEVEL. Another
+// drop needs the level back up to REARM_RATIO of the slow one first.
+const DROP_FAST: f32 = 0.1;
+const DROP_SLOW: f32 = 2.0;
+const DROP_RATIO: f32 = 0.4;
+const REARM_RATIO: f32 = 0.8;
+const MIN_DROP_LEVEL: f32 = 0.1;
+// Silence: mean band level below SILENCE_LEVEL for SILENCE_HOLD seconds
+const SILENCE_LEVEL: f32 = 0.01;
+const SILENCE_HOLD: f64 = 1.5;
+
+// Something that happened in the music
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioEvent {
+    // A note or hit starting, where the levels jump
+    Onset,
+    // The energy falling away sharply, e.g. into a breakdown
+    Drop,
+    SilenceStart,
+    SilenceEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriggerAction {
+    // Switch to `preset`, or back to the main config without one
+    Preset,
+    // Randomly vary the look, as R does
+    Mutate,
+    // Jump the camera a quarter turn around the grid
+    CameraCut,
+}
+
+// An action taken when an event is detected, at most once per `cooldown`
+// seconds
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Trigger {
+    pub on: AudioEvent,
+    pub action: TriggerAction,
+    // Config file for the preset action, relative to the main one
+    #[serde(default)]
+    pub preset: Option<PathBuf>,
+    #[serde(default = "default_cooldown")]
+    pub cooldown: f32,
+}
+
+fn default_cooldown() -> f32 {
+    1.0
+}
+
+// Finds events in the band levels handed to the renderer, frame by frame
+#[derive(Default)]
+pub struct EventDetector {
+    bands: Vec<f32>,
+    time: Option<f64>,
+    flux: f32,
+    fast: f32,
+    slow: f32,
+    last_onset: Option<f64>,
+    dropped: bool,
+    // When the level fell below silence, and whether it has lasted
+    quiet_since: Option<f64>,
+    silent: bool,
+}
+
+impl EventDetector {
+    // The events in `bands`, the levels at `time` seconds
+    pub fn process(&mut self, bands: &[f32], time: f64) -> Vec<AudioEvent> {
+        let mut events = Vec::new();
+        let elapsed = self.time.map_or(0.0, |last| (time - last).max(0.0)) as f32;
+        self.time = Some(time);
+        let level = bands.iter().sum::<f32>() / bands.len().max(1) as f32;
+        // Share of the way an average over `seconds` moves this frame
+        let follow = |seconds: f32| 1.0 - (-elapsed / seconds).exp();
+
+        let flux = if self.bands.len() == bands.len() {
+            let rise: f32 = bands
+                .iter()
+                .zip(&self.bands)
+                .map(|(band, last)| (band - last).max(0.0))
+                .sum();
+            rise / bands.len().max(1) as f32
+        } else {
+            0.0
+        };
+        self.bands.clear();
+        self.bands.extend_from_slice(bands);
+        if flux > MIN_ONSET_FLUX.max(self.flux * ONSET_FACTOR)
+            && self
+                .last_onset
+                .is_none_or(|last| time - last >= MIN_ONSET_INTERVAL)
+        {
+            self.last_onset = Some(time);
+            events.push(AudioEvent::Onset);
+        }
+        self.flux += (flux - self.flux) * follow(FLUX_TIME);
+
+        if elapsed > 0.0 {
+            self.fast += (level - self.fast) * follow(DROP_FAST);
+            self.slow += (level - self.slow) * follow(DROP_SLOW);
+        }
+        if self.dropped {
+            self.dropped = self.fast < self.slow * REARM_RATIO;
+        } else if self.slow >= MIN_DROP_LEVEL && self.fast < self.slow * DROP_RATIO {
+            self.dropped = true;
+            events.push(AudioEvent::Drop);
+        }
+
+        if level < SILENCE_LEVEL {
+            let since = *self.quiet_since.get_or_insert(time);
+            if !self.silent && time - since >= SILENCE_HOLD {
+                self.silent = true;
+                events.push(AudioEvent::SilenceStart);
+            }
+        } else {
+            self.quiet_since = None;
+            if self.silent {
+                self.silent = false;
+                events.push(AudioEvent::SilenceEnd);
+            }
+        }
+        events
+    }
+}
+
+// Publishes the events found in each frame's levels to the triggers
+// listening for them, and queues the triggers that fire until the app takes
+// them
+pub struct EventBus {
+    detector: EventDetector,
+    triggers: Vec<Trigger>,
+    // When each trigger last fired
+    fired_at: Vec<Option<f64>>,
+    fired: Vec<Trigger>,
+    start: Instant,
+}
+
+impl EventBus {
+    pub fn new(triggers: &[Trigger]) -> Self {
+        let mut bus = Self {
+            detector: EventDetector::default(),
+            triggers: Vec::new(),
+            fired_at: Vec::new(),
+            fired: Vec::new(),
+            start: Instant::now(),
+        };
+        bus.set_triggers(triggers);
+        bus
+    }
+
+    // Replace the triggers, e.g. from a reloaded config
+    pub fn set_triggers(&mut self, triggers: &[Trigger]) {
+        if triggers != self.triggers {
+            self.triggers = triggers.to_vec();
+            self.fired_at = vec![None; triggers.len()];
+        }
+    }
+
+    // Detect events in this frame's band levels
+    pub fn process(&mut self, bands: &[f32]) {
+        let time = self.start.elapsed().as_secs_f64();
+        for event in self.detector.process(bands, time) {
+            self.publish(event, time);
+        }
+    }
+
+    fn publish(&mut self, event: AudioEvent, time: f64) {
+        log::debug!("Audio event: {:?}", event);
+        for (trigger, fired_at) in self.triggers.iter().zip(&mut self.fired_at) {
+            if trigger.on != event || fired_at.is_some_and(|at| time - at < trigger.cooldown as f64)
+            {
+                continue;
+            }
+            *fired_at = Some(time);
+            self.fired.push(trigger.clone());
+        }
+    }
+
+    // Triggers fired since the last call, oldest first
+    pub fn take_fired(&mut self) -> Vec<Trigger> {
+        std::mem::take(&mut self.fired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f64 = 1.0 / 60.0;
+
+    // Events found over `seconds` of frames at `level` in every band,
+    // starting at `*time`
+    fn run(
+        detector: &mut EventDetector,
+        level: f32,
+        seconds: f64,
+        time: &mut f64,
+    ) -> Vec<AudioEvent> {
+        let mut events = Vec::new();
+        let end = *time + seconds;
+        while *time < end {
+            events.extend(detector.process(&[level; 8], *time));
+            *time += FRAME;
+        }
+        events
+    }
+
+    #[test]
+    fn a_jump_in_level_is_one_onset() {
+        let mut detector = EventDetector::default();
+        let mut time = 0.0;
+        assert!(run(&mut detector, 0.2, 1.0, &mut time).is_empty());
+        assert_eq!(
+            run(&mut detector, 0.8, 0.5, &mut time),
+            vec![AudioEvent::Onset]
+        );
+    }
+
+    #[test]
+    fn energy_falling_away_is_a_drop() {
+        let mut detector = EventDetector::default();
+        let mut time = 0.0;
+        run(&mut detector, 0.8, 3.0, &mut time);
+        assert_eq!(
+            run(&mut detector, 0.1, 0.5, &mut time),
+            vec![AudioEvent::Drop]
+        );
+    }
+
+    #[test]
+    fn silence_starts_after_the_hold_and_ends_with_sound() {
+        let mut detector = EventDetector::default();
+        let mut time = 0.0;
+        assert!(run(&mut detector, 0.0, 1.0, &mut time).is_empty());
+        assert_eq!(
+            run(&mut detector, 0.0, 1.0, &mut time),
+            vec![AudioEvent::SilenceStart]
+        );
+        assert_eq!(
+            run(&mut detector, 0.5, FRAME, &mut time),
+            vec![AudioEvent::Onset, AudioEvent::SilenceEnd]
+        );
+    }
+
+    #[test]
+    fn triggers_fire_once_per_cooldown() {
+        let trigger = Trigger {
+            on: AudioEvent::Onset,
+            action: TriggerAction::Mutate,
+            preset: None,
+            cooldown: 2.0,
+        };
+        let mut bus = EventBus::new(std::slice::from_ref(&trigger));
+        bus.publish(AudioEvent::Drop, 0.0);
+        bus.publish(AudioEvent::Onset, 0.5);
+        bus.publish(AudioEvent::Onset, 1.5);
+        assert_eq!(bus.take_fired(), vec![trigger.clone()]);
+        bus.publish(AudioEvent::Onset, 2.5);
+        assert_eq!(bus.take_fired(), vec![trigger]);
+    }
+}
@@ -0,0 +1,355 @@
+use crate::camera;
+use crate::config::Config;
+use crate::mutate::{self, Rng};
+use crate::renderer::WaveformRenderer;
+use crate::viewport::Viewport;
+use std::time::Instant;
+
+// Candidates per generation, shown as a 3x3 grid
+pub const CANDIDATES: usize = 9;
+const COLUMNS: u32 = 3;
+
+// A generation of candidate looks and the ones picked to breed the next.
+// The genes are the parameters mutate() varies.
+pub struct Population {
+    generation: u32,
+    candidates: Vec<Config>,
+    selected: [bool; CANDIDATES],
+    rng: Rng,
+}
+
+impl Population {
+    // The first generation: `parent` itself and mutations of it
+    pub fn new(parent: &Config, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut candidates = vec![parent.clone()];
+        while candidates.len() < CANDIDATES {
+            candidates.push(mutate::mutate(parent, rng.next_u64()));
+        }
+
+        Self {
+            generation: 1,
+            candidates,
+            selected: [false; CANDIDATES],
+            rng,
+        }
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn candidates(&self) -> &[Config] {
+        &self.candidates
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected[index]
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(selected) = self.selected.get_mut(index) {
+            *selected = !*selected;
+        }
+    }
+
+    // The first picked candidate
+    pub fn favorite(&self) -> Option<&Config> {
+        self.selected
+            .iter()
+            .position(|&selected| selected)
+            .map(|index| &self.candidates[index])
+    }
+
+    // Breed the next generation from the picked candidates, or from all of
+    // them if none are picked. Children cross two random parents over and
+    // are then mutated; the favorite survives unchanged so a good look is
+    // never lost.
+    pub fn breed(&mut self) {
+        let mut parents: Vec<&Config> = self
+            .candidates
+            .iter()
+            .zip(self.selected)
+            .filter_map(|(candidate, selected)| selected.then_some(candidate))
+            .collect();
+        if parents.is_empty() {
+            parents = self.candidates.iter().collect();
+        }
+
+        let mut next = vec![parents[0].clone()];
+        while next.len() < CANDIDATES {
+            let a = parents[self.rng.below(parents.len())];
+            let b = parents[self.rng.below(parents.len())];
+            let child = crossover(a, b, &mut self.rng);
+            next.push(mutate::mutate(&child, self.rng.next_u64()));
+        }
+
+        self.candidates = next;
+        self.selected = [false; CANDIDATES];
+        self.generation += 1;
+    }
+}
+
+// Uniform crossover: each gene comes from either parent
+fn crossover(a: &Config, b: &Config, rng: &mut Rng) -> Config {
+    let mut child = a.clone();
+    let (waves, other) = (&mut child.waves, &b.waves);
+    if rng.chance(0.5) {
+        waves.function = other.function;
+    }
+    if rng.chance(0.5) {
+        waves.amplitude = other.amplitude;
+    }
+    if rng.chance(0.5) {
+        waves.noise = other.noise;
+    }
+    if rng.chance(0.5) {
+        waves.noise_amplitude = other.noise_amplitude;
+    }
+    if rng.chance(0.5) {
+        waves.noise_octaves = other.noise_octaves;
+    }
+    if rng.chance(0.5) {
+        child.colors.major = b.colors.major;
+    }
+    if rng.chance(0.5) {
+        child.colors.minor = b.colors.minor;
+    }
+    child
+}
+
+// Interactive evolution of looks. Every candidate is drawn offscreen by one
+// small renderer in turn, then the thumbnails are scaled into a grid.
+pub struct Evolution {
+    pub population: Population,
+    renderer: WaveformRenderer,
+    // The config last applied to the renderer
+    applied: Config,
+    format: wgpu::TextureFormat,
+    thumbnails: Vec<Thumbnail>,
+    sampler: wgpu::Sampler,
+    pipeline: wgpu::RenderPipeline,
+}
+
+struct Thumbnail {
+    view: wgpu::TextureView,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Evolution {
+    // Start from `parent`, shown in the tiles of `viewport`
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        viewport: Viewport,
+        parent: &Config,
+        seed: u64,
+    ) -> Self {
+        let tile = tile(viewport, 0);
+        let mut renderer = WaveformRenderer::new(device, format, tile.width, tile.height);
+        renderer.apply_config(device, parent, &Config::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Thumbnail Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let start = Instant::now();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Thumbnail Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("thumbnail.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Thumbnail Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        log::info!("Thumbnail pipeline created in {:?}", start.elapsed());
+
+        let thumbnails = create_thumbnails(device, format, &pipeline, &sampler, tile);
+
+        Self {
+            population: Population::new(parent, seed),
+            renderer,
+            applied: parent.clone(),
+            format,
+            thumbnails,
+            sampler,
+            pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, viewport: Viewport) {
+        let tile = tile(viewport, 0);
+        self.renderer.resize(device, tile.width, tile.height);
+        self.thumbnails =
+            create_thumbnails(device, self.format, &self.pipeline, &self.sampler, tile);
+    }
+
+    // Band levels in 0..1 from low to high frequency
+    pub fn set_spectrum(&self, queue: &wgpu::Queue, bands: &[f32]) {
+        self.renderer.set_spectrum(queue, bands);
+    }
+
+    // Draw every candidate at the animation time and camera of `main`, and
+    // encode the grid of thumbnails into `viewport` of `target`. Candidates
+    // share the renderer's uniforms, so each one is submitted right away.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        main: &WaveformRenderer,
+        target: &wgpu::TextureView,
+        viewport: Viewport,
+    ) -> wgpu::CommandBuffer {
+        self.renderer.camera = camera::create_controller(
+            main.camera.mode(),
+            main.camera.position(),
+            main.camera.orientation(),
+            main.camera.world_scale(),
+        );
+        self.renderer.motion_intensity = main.motion_intensity;
+        self.renderer.line_style = main.line_style;
+        self.renderer.set_time(main.time());
+
+        for (index, (candidate, thumbnail)) in self
+            .population
+            .candidates()
+            .iter()
+            .zip(&self.thumbnails)
+            .enumerate()
+        {
+            self.renderer.apply_config(device, candidate, &self.applied);
+            self.applied = candidate.clone();
+            self.renderer.advance(queue, 0.0);
+            let selected = self.population.is_selected(index) as u32;
+            queue.write_buffer(
+                &thumbnail.params_buffer,
+                0,
+                bytemuck::cast_slice(&[selected, 0, 0, 0]),
+            );
+            queue.submit(self.renderer.render(device, &thumbnail.view));
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Thumbnail Grid Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Thumbnail Grid Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            for (index, thumbnail) in self.thumbnails.iter().enumerate() {
+                tile(viewport, index).apply(&mut render_pass);
+                render_pass.set_bind_group(0, &thumbnail.bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+
+        encoder.finish()
+    }
+}
+
+// Tile `index` of the grid, numbered row by row from the top left like the
+// number keys that pick them
+fn tile(viewport: Viewport, index: usize) -> Viewport {
+    let width = (viewport.width / COLUMNS).max(1);
+    let height = (viewport.height / COLUMNS).max(1);
+    Viewport {
+        x: viewport.x + index as u32 % COLUMNS * width,
+        y: viewport.y + index as u32 / COLUMNS * height,
+        width,
+        height,
+    }
+}
+
+fn create_thumbnails(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    pipeline: &wgpu::RenderPipeline,
+    sampler: &wgpu::Sampler,
+    tile: Viewport,
+) -> Vec<Thumbnail> {
+    (0..CANDIDATES)
+        .map(|_| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Thumbnail Texture"),
+                size: wgpu::Extent3d {
+                    width: tile.width,
+                    height: tile.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Thumbnail Params Buffer"),
+                size: std::mem::size_of::<u32>() as wgpu::BufferAddress * 4,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Thumbnail Bind Group"),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            });
+
+            Thumbnail {
+                view,
+                params_buffer,
+                bind_group,
+            }
+        })
+        .collect()
+}
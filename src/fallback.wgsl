@@ -0,0 +1,56 @@
+// Grid shader used while a reloaded shader fails to compile. It takes the
+// same vertices and bindings as shader.wgsl and draws the displaced grid in
+// solid magenta, so a broken shader is obvious but the scene keeps moving.
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) major: f32,
+    @location(2) distance: f32,
+};
+
+// The start of the grid shader's uniform block, up to the model matrix
+struct Uniforms {
+    time: f32,
+    motion: f32,
+    line_style: u32,
+    view_proj: mat4x4<f32>,
+    major_color: vec4<f32>,
+    minor_color: vec4<f32>,
+    model: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read> heights: array<f32>;
+
+// Nearest height field sample; the height field covers -1..1 on X and Z
+fn sample_height(x: f32, z: f32) -> f32 {
+    let size = u32(round(sqrt(f32(arrayLength(&heights)))));
+    let uv = clamp((vec2<f32>(x, z) + 1.0) * 0.5, vec2<f32>(0.0), vec2<f32>(1.0));
+    let cell = vec2<u32>(round(uv * f32(size - 1u)));
+    return heights[cell.y * size + cell.x];
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) major: f32,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var pos = model.position;
+    pos.y += sample_height(pos.x, pos.z) * uniforms.motion;
+
+    var out: VertexOutput;
+    out.clip_position = uniforms.view_proj * uniforms.model * vec4<f32>(pos, 1.0);
+    out.major = model.major;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Connectors between strips are hidden, as in the grid shader
+    if in.major < 0.0 {
+        discard;
+    }
+    return vec4<f32>(1.0, 0.0, 1.0, 1.0);
+}
@@ -0,0 +1,125 @@
+use crate::mutate::Rng;
+use crate::playback::AudioFile;
+use std::f64::consts::PI;
+
+pub const SAMPLE_RATE: u32 = 48000;
+// Seconds after which every signal repeats, so it can loop without a seam in
+// the sweep or the impulse train
+pub const PERIOD: f64 = 10.0;
+
+// Logarithmic sweep range in Hz, covering it once per period
+const SWEEP_FROM: f64 = 20.0;
+const SWEEP_TO: f64 = 20000.0;
+// Seconds between impulses, a beat at 120 BPM
+const IMPULSE_INTERVAL: f64 = 0.5;
+// Peak levels; the noise is scaled to about the same loudness as the sweep
+const SWEEP_LEVEL: f64 = 0.5;
+const NOISE_LEVEL: f32 = 0.1;
+const NOISE_SEED: u64 = 0x5eed;
+
+// Test signal to drive the grid without any audio source, for demos and for
+// checking the analysis: a sweep walks through the bands one at a time, pink
+// noise fills them all evenly, and impulses hit them all at once on a beat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Signal {
+    Sweep,
+    PinkNoise,
+    Impulses,
+}
+
+impl Signal {
+    // `seconds` of the signal, mono at SAMPLE_RATE, the same every time
+    pub fn generate(self, seconds: f64) -> AudioFile {
+        let frames = (seconds * SAMPLE_RATE as f64).round() as usize;
+        let samples = match self {
+            Signal::Sweep => sweep(frames),
+            Signal::PinkNoise => pink_noise(frames),
+            Signal::Impulses => impulses(frames),
+        };
+        AudioFile {
+            samples,
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+        }
+    }
+}
+
+// Exponential sine sweep, restarting every period, so each octave takes the
+// same time
+fn sweep(frames: usize) -> Vec<f32> {
+    let octaves = (SWEEP_TO / SWEEP_FROM).ln();
+    (0..frames)
+        .map(|i| {
+            let t = (i as f64 / SAMPLE_RATE as f64) % PERIOD;
+            let phase =
+                2.0 * PI * SWEEP_FROM * PERIOD / octaves * ((t / PERIOD * octaves).exp() - 1.0);
+            (SWEEP_LEVEL * phase.sin()) as f32
+        })
+        .collect()
+}
+
+// White noise through Paul Kellet's economy filter, falling 3 dB per octave
+fn pink_noise(frames: usize) -> Vec<f32> {
+    let mut rng = Rng::new(NOISE_SEED);
+    let mut b = [0.0f32; 3];
+    (0..frames)
+        .map(|_| {
+            let white = rng.next_f32() * 2.0 - 1.0;
+            b[0] = 0.99765 * b[0] + white * 0.0990460;
+            b[1] = 0.96300 * b[1] + white * 0.2965164;
+            b[2] = 0.57000 * b[2] + white * 1.0526913;
+            (b[0] + b[1] + b[2] + white * 0.1848) * NOISE_LEVEL
+        })
+        .collect()
+}
+
+// Single full-scale samples every IMPULSE_INTERVAL, flat across the spectrum
+fn impulses(frames: usize) -> Vec<f32> {
+    let interval = (IMPULSE_INTERVAL * SAMPLE_RATE as f64) as usize;
+    (0..frames)
+        .map(|i| if i % interval == 0 { 1.0 } else { 0.0 })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SpectrumAnalyzer;
+
+    // Index of the loudest band in the analysis window ending at `second`
+    fn loudest_band(file: &AudioFile, second: f64) -> usize {
+        let mut analyzer = SpectrumAnalyzer::new();
+        let bands = analyzer.process(&file.window((second * SAMPLE_RATE as f64) as usize));
+        (0..bands.len())
+            .max_by(|&a, &b| bands[a].total_cmp(&bands[b]))
+            .unwrap()
+    }
+
+    #[test]
+    fn the_sweep_rises_through_the_bands_and_repeats() {
+        let file = Signal::Sweep.generate(PERIOD * 2.0);
+        let early = loudest_band(&file, 2.0);
+        let late = loudest_band(&file, 8.0);
+        assert!(early < late, "{} then {}", early, late);
+        assert_eq!(loudest_band(&file, PERIOD + 2.0), early);
+    }
+
+    #[test]
+    fn signals_are_reproducible_and_in_range() {
+        for signal in [Signal::Sweep, Signal::PinkNoise, Signal::Impulses] {
+            let file = signal.generate(1.0);
+            assert_eq!(file.frames(), SAMPLE_RATE as usize);
+            assert!(file.samples.iter().all(|s| s.abs() <= 1.0));
+            assert_eq!(file.samples, signal.generate(1.0).samples);
+        }
+    }
+
+    #[test]
+    fn impulses_fall_on_the_beat() {
+        let file = Signal::Impulses.generate(1.0);
+        let hits: Vec<usize> = (0..file.frames())
+            .filter(|&i| file.samples[i] > 0.0)
+            .collect();
+        assert_eq!(hits, vec![0, SAMPLE_RATE as usize / 2]);
+    }
+}
@@ -0,0 +1,39 @@
+// Gerstner ocean waves, shared by the height field and the shader tests.
+// The including shader declares the waves as
+// `var<storage, read> gerstner_waves: array<GerstnerWave>`, of which the
+// first `count` are summed.
+
+struct GerstnerWave {
+    // Unit vector on the XZ plane
+    direction: vec2<f32>,
+    wavelength: f32,
+    // Crest sharpness in 0..1
+    steepness: f32,
+    // Phase speed in grid units per second
+    speed: f32,
+};
+
+// Displacement of the water surface point that rests at `p`
+fn gerstner_offset(p: vec2<f32>, time: f32, count: u32) -> vec3<f32> {
+    var offset = vec3<f32>(0.0);
+    for (var i = 0u; i < count; i++) {
+        let wave = gerstner_waves[i];
+        let k = 6.2831853 / wave.wavelength;
+        let amplitude = wave.steepness / k;
+        let phase = k * (dot(wave.direction, p) - wave.speed * time);
+        offset += vec3<f32>(wave.direction.x * cos(phase), sin(phase), wave.direction.y * cos(phase)) * amplitude;
+    }
+    return offset;
+}
+
+// Ocean swell with sharp crests and wide troughs. Gerstner waves also move
+// the surface sideways, so first find the point that ends up above (x, z);
+// this converges to within a height field cell in a few steps.
+fn gerstner_height(x: f32, z: f32, time: f32, count: u32) -> f32 {
+    let p = vec2<f32>(x, z);
+    var rest = p;
+    for (var i = 0; i < 4; i++) {
+        rest = p - gerstner_offset(rest, time, count).xz;
+    }
+    return gerstner_offset(rest, time, count).y;
+}
@@ -0,0 +1,85 @@
+use std::error::Error;
+
+// Device features used where the adapter has them. Timestamps feed the GPU
+// time in the stats overlay, and adapter specific format features allow more
+// MSAA sample counts.
+const OPTIONAL_FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+
+// Find an adapter that can draw to `surface`, or any adapter without one.
+// The preferred hardware adapter is tried first, then a software one such as
+// WARP or llvmpipe, so machines without a working GPU driver still run.
+pub async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+) -> Result<wgpu::Adapter, Box<dyn Error>> {
+    for force_fallback_adapter in [false, true] {
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: surface,
+            force_fallback_adapter,
+        };
+        if let Some(adapter) = instance.request_adapter(&options).await {
+            let info = adapter.get_info();
+            if force_fallback_adapter {
+                log::warn!("No hardware GPU adapter found, falling back to software rendering");
+            }
+            log::info!(
+                "Using {} ({:?}, {:?})",
+                info.name,
+                info.backend,
+                info.device_type
+            );
+            return Ok(adapter);
+        }
+    }
+
+    let adapters: Vec<_> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .map(|adapter| {
+            let info = adapter.get_info();
+            format!("{} ({:?})", info.name, info.backend)
+        })
+        .collect();
+    if adapters.is_empty() {
+        Err(
+            "no GPU adapter found, check that a Vulkan, Metal, DirectX 12 or OpenGL \
+             driver is installed"
+                .into(),
+        )
+    } else {
+        Err(format!(
+            "none of the GPU adapters can draw to the window: {}",
+            adapters.join(", ")
+        )
+        .into())
+    }
+}
+
+// Open a device with whichever optional features the adapter has. An
+// adapter below the default limits gets all it offers instead, which may be
+// too little for the largest settings.
+pub async fn request_device(
+    adapter: &wgpu::Adapter,
+) -> Result<(wgpu::Device, wgpu::Queue), Box<dyn Error>> {
+    let mut limits = wgpu::Limits::default();
+    if !limits.check_limits(&adapter.limits()) {
+        log::warn!(
+            "{} is below the default GPU limits, using what it supports",
+            adapter.get_info().name
+        );
+        limits = adapter.limits();
+    }
+    let device = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: adapter.features() & OPTIONAL_FEATURES,
+                limits,
+            },
+            None,
+        )
+        .await
+        .map_err(|e| format!("failed to open {}: {}", adapter.get_info().name, e))?;
+    Ok(device)
+}
@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// GPU time between two points in the submitted work, from timestamp
+// queries. One measurement is in flight at a time; frames submitted while
+// it is being read back are not measured.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    // Set while the timestamps of the current frame are being written
+    recording: bool,
+    mapped: Option<Arc<AtomicBool>>,
+    last: Option<f32>,
+}
+
+impl GpuTimer {
+    // None unless the device was created with TIMESTAMP_QUERY
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU Timer Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let size = 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timer Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timer Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            recording: false,
+            mapped: None,
+            last: None,
+        })
+    }
+
+    // Milliseconds between the last measured `begin` and `end`
+    pub fn last(&self) -> Option<f32> {
+        self.last
+    }
+
+    // Mark the start of the measured work; submit before it
+    pub fn begin(&mut self, device: &wgpu::Device) -> Option<wgpu::CommandBuffer> {
+        if self.mapped.is_some() {
+            return None;
+        }
+        self.recording = true;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Timer Begin Encoder"),
+        });
+        encoder.write_timestamp(&self.query_set, 0);
+        Some(encoder.finish())
+    }
+
+    // Mark the end of the measured work and copy the timestamps out; submit
+    // after it
+    pub fn end(&mut self, device: &wgpu::Device) -> Option<wgpu::CommandBuffer> {
+        if !self.recording {
+            return None;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Timer End Encoder"),
+        });
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.readback_buffer.size(),
+        );
+        Some(encoder.finish())
+    }
+
+    // Start reading back the timestamps. Must be called after the `end`
+    // command buffer has been submitted.
+    pub fn submitted(&mut self) {
+        if !self.recording {
+            return;
+        }
+        self.recording = false;
+
+        let mapped = Arc::new(AtomicBool::new(false));
+        let flag = mapped.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| match result {
+                Ok(()) => flag.store(true, Ordering::Release),
+                Err(e) => log::error!("GPU timer mapping failed: {:?}", e),
+            });
+        self.mapped = Some(mapped);
+    }
+
+    // Pick up a finished measurement without blocking
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Some(mapped) = &self.mapped else {
+            return;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        if !mapped.load(Ordering::Acquire) {
+            return;
+        }
+
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            let ticks = timestamps[1].wrapping_sub(timestamps[0]);
+            self.last = Some(ticks as f32 * self.period / 1_000_000.0);
+        }
+        self.readback_buffer.unmap();
+        self.mapped = None;
+    }
+}
@@ -0,0 +1,282 @@
+// Every Nth grid line is drawn as a brighter major line
+pub const MAJOR_LINE_INTERVAL: u32 = 10;
+
+// Base mesh the waves are applied to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridShape {
+    Cartesian,
+    Polar,
+}
+
+// Vertex structure for our grid points
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub major: f32,    // 1.0 on major lines, 0.0 on minor lines, negative if hidden
+    pub distance: f32, // Arclength from the start of the line, for dashes
+}
+
+// Lines across and along the square grid; the polar grid gets half as many
+// rings and spokes, with three segments per ring per line across
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridSize {
+    pub shape: GridShape,
+    pub width: u32,
+    pub depth: u32,
+    pub major_interval: u32,
+}
+
+impl Default for GridSize {
+    fn default() -> Self {
+        Self {
+            shape: GridShape::Cartesian,
+            width: 80,
+            depth: 60,
+            major_interval: MAJOR_LINE_INTERVAL,
+        }
+    }
+}
+
+pub fn create_mesh(size: GridSize) -> (Vec<Vertex>, usize) {
+    match size.shape {
+        GridShape::Cartesian => create_grid(size.width, size.depth, size.major_interval),
+        GridShape::Polar => create_polar_grid(
+            (size.depth / 2).max(1),
+            (size.width / 2).max(1),
+            size.width * 3 / 2,
+            size.major_interval,
+        ),
+    }
+}
+
+pub fn create_grid(width: u32, depth: u32, major_interval: u32) -> (Vec<Vertex>, usize) {
+    let mut vertices = Vec::new();
+    let mut vertex_count = 0;
+    let line_style = |index: u32| {
+        if index.is_multiple_of(major_interval) {
+            1.0
+        } else {
+            0.0
+        }
+    };
+
+    // Create horizontal lines
+    for z in 0..depth {
+        let z_pos = (z as f32 * 2.0 / depth as f32) - 1.0;
+        let major = line_style(z);
+
+        // Add vertices for each horizontal line
+        for x in 0..width {
+            let x_pos = (x as f32 * 2.0 / width as f32) - 1.0;
+            vertices.push(Vertex {
+                position: [x_pos, 0.0, z_pos],
+                major,
+                distance: x_pos + 1.0,
+            });
+            vertex_count += 1;
+        }
+
+        // Add degenerate vertices to move to next line
+        if z < depth - 1 {
+            vertices.push(Vertex {
+                position: [1.0, 0.0, z_pos], // Last vertex of current line
+                major,
+                distance: 2.0,
+            });
+            vertices.push(Vertex {
+                position: [-1.0, 0.0, (z + 1) as f32 * 2.0 / depth as f32 - 1.0], // First vertex of next line
+                major: line_style(z + 1),
+                distance: 0.0,
+            });
+            vertex_count += 2;
+        }
+    }
+
+    // Create vertical lines
+    for x in 0..width {
+        let x_pos = (x as f32 * 2.0 / width as f32) - 1.0;
+        let major = line_style(x);
+
+        // Add vertices for each vertical line
+        for z in 0..depth {
+            let z_pos = (z as f32 * 2.0 / depth as f32) - 1.0;
+            vertices.push(Vertex {
+                position: [x_pos, 0.0, z_pos],
+                major,
+                distance: z_pos + 1.0,
+            });
+            vertex_count += 1;
+        }
+
+        // Add degenerate vertices to move to next line
+        if x < width - 1 {
+            vertices.push(Vertex {
+                position: [x_pos, 0.0, 1.0], // Last vertex of current line
+                major,
+                distance: 2.0,
+            });
+            vertices.push(Vertex {
+                position: [(x + 1) as f32 * 2.0 / width as f32 - 1.0, 0.0, -1.0], // First vertex of next line
+                major: line_style(x + 1),
+                distance: 0.0,
+            });
+            vertex_count += 2;
+        }
+    }
+
+    (vertices, vertex_count)
+}
+
+// Concentric rings plus radial spokes, as one continuous line strip. Rings
+// are joined along the first spoke and spokes alternate direction, so no
+// stray connecting lines are drawn.
+pub fn create_polar_grid(
+    rings: u32,
+    spokes: u32,
+    segments: u32,
+    major_interval: u32,
+) -> (Vec<Vertex>, usize) {
+    let mut vertices = Vec::new();
+    let line_style = |index: u32| {
+        if index.is_multiple_of(major_interval) {
+            1.0
+        } else {
+            0.0
+        }
+    };
+
+    // Create rings from the innermost outwards
+    for ring in 1..=rings {
+        let radius = ring as f32 / rings as f32;
+        let major = line_style(ring);
+
+        for segment in 0..=segments {
+            let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+            vertices.push(Vertex {
+                position: [radius * angle.cos(), 0.0, radius * angle.sin()],
+                major,
+                distance: radius * angle,
+            });
+        }
+    }
+
+    // Create spokes, alternating outwards and inwards
+    for spoke in 0..spokes {
+        let angle = spoke as f32 / spokes as f32 * std::f32::consts::TAU;
+        let major = line_style(spoke);
+
+        for step in 0..=rings {
+            let step = if spoke % 2 == 0 { step } else { rings - step };
+            let radius = step as f32 / rings as f32;
+            vertices.push(Vertex {
+                position: [radius * angle.cos(), 0.0, radius * angle.sin()],
+                major,
+                distance: radius,
+            });
+        }
+    }
+
+    let vertex_count = vertices.len();
+    (vertices, vertex_count)
+}
+
+// The grid of `size` as an indexed triangle list for the filled render mode
+pub fn create_surface(size: GridSize) -> (Vec<Vertex>, Vec<u32>) {
+    match size.shape {
+        GridShape::Cartesian => create_triangle_grid(size.width, size.depth, size.major_interval),
+        GridShape::Polar => create_polar_triangle_grid(
+            (size.depth / 2).max(1),
+            (size.width / 2).max(1),
+            size.width * 3 / 2,
+            size.major_interval,
+        ),
+    }
+}
+
+// The same sample points as create_grid, as an indexed triangle list for the
+// filled render mode. Vertices on a major row or column are marked major.
+pub fn create_triangle_grid(
+    width: u32,
+    depth: u32,
+    major_interval: u32,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::with_capacity((width * depth) as usize);
+    for z in 0..depth {
+        let z_pos = (z as f32 * 2.0 / depth as f32) - 1.0;
+        for x in 0..width {
+            let x_pos = (x as f32 * 2.0 / width as f32) - 1.0;
+            let major = x.is_multiple_of(major_interval) || z.is_multiple_of(major_interval);
+            vertices.push(Vertex {
+                position: [x_pos, 0.0, z_pos],
+                major: if major { 1.0 } else { 0.0 },
+                distance: x_pos + 1.0,
+            });
+        }
+    }
+
+    // Two triangles per cell
+    let mut indices = Vec::with_capacity(((width - 1) * (depth - 1) * 6) as usize);
+    for z in 0..depth - 1 {
+        for x in 0..width - 1 {
+            let i = z * width + x;
+            indices.extend_from_slice(&[i, i + width, i + 1, i + 1, i + width, i + width + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+// The disc of create_polar_grid as an indexed triangle list: a fan around the
+// center and a band of quads between each pair of rings. Vertices on a major
+// ring or spoke are marked major.
+pub fn create_polar_triangle_grid(
+    rings: u32,
+    spokes: u32,
+    segments: u32,
+    major_interval: u32,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let segments = segments.max(3);
+    let major_spoke = |segment: u32| {
+        // Segments that fall exactly on a spoke
+        (segment * spokes).is_multiple_of(segments)
+            && (segment * spokes / segments).is_multiple_of(major_interval)
+    };
+
+    let mut vertices = vec![Vertex {
+        position: [0.0, 0.0, 0.0],
+        major: 1.0,
+        distance: 0.0,
+    }];
+    // Each ring repeats its first vertex at the end, as in the line strip
+    for ring in 1..=rings {
+        let radius = ring as f32 / rings as f32;
+        for segment in 0..=segments {
+            let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+            let major = ring.is_multiple_of(major_interval) || major_spoke(segment % segments);
+            vertices.push(Vertex {
+                position: [radius * angle.cos(), 0.0, radius * angle.sin()],
+                major: if major { 1.0 } else { 0.0 },
+                distance: radius * angle,
+            });
+        }
+    }
+
+    let ring_start = |ring: u32| 1 + (ring - 1) * (segments + 1);
+    let mut indices = Vec::with_capacity((rings * segments * 6) as usize);
+    for segment in 0..segments {
+        let i = ring_start(1) + segment;
+        indices.extend_from_slice(&[0, i, i + 1]);
+    }
+    for ring in 1..rings {
+        for segment in 0..segments {
+            let inner = ring_start(ring) + segment;
+            let outer = ring_start(ring + 1) + segment;
+            indices.extend_from_slice(&[inner, outer, inner + 1, inner + 1, outer, outer + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
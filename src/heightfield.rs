@@ -0,0 +1,446 @@
+use crate::audio::SPECTRUM_BANDS;
+use crate::webcam::LumaFrame;
+use std::error::Error;
+use std::path::Path;
+use std::time::Instant;
+use wgpu::util::DeviceExt;
+
+// Height samples per side, covering -1..1 on X and Z
+pub const HEIGHTFIELD_SIZE: u32 = 128;
+
+// Capacity of the Gerstner wave buffer
+pub const MAX_GERSTNER_WAVES: usize = 16;
+
+// Procedural base shape of the height field
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WaveFunction {
+    Sines = 0,
+    Ripple = 1,
+    Gerstner = 2,
+    Terrain = 3,
+    Interference = 4,
+}
+
+impl WaveFunction {
+    // In shader order, which is also the order of the number key presets
+    pub const ALL: [WaveFunction; 5] = [
+        WaveFunction::Sines,
+        WaveFunction::Ripple,
+        WaveFunction::Gerstner,
+        WaveFunction::Terrain,
+        WaveFunction::Interference,
+    ];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+// One train of ocean waves for the Gerstner wave function. Lengths are in
+// grid units, where the grid spans 2 across.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GerstnerWave {
+    // Direction of travel on the XZ plane; normalized on upload
+    pub direction: [f32; 2],
+    pub wavelength: f32,
+    // Crest sharpness in 0..1; the sum over all waves must stay at or below 1
+    // or the crests loop over themselves
+    pub steepness: f32,
+    // Grid units per second. Real deep water waves move at
+    // sqrt(g * wavelength / 2π), so long waves outrun short ones.
+    pub speed: f32,
+}
+
+impl GerstnerWave {
+    // A calm swell from four directions, with deep water speeds for a
+    // gravity scaled down to suit a grid two units wide
+    pub fn default_set() -> Vec<GerstnerWave> {
+        [
+            ([0.0, 1.0], 2.5, 0.25),
+            ([0.6, 0.8], 1.7, 0.25),
+            ([-0.7, 0.71], 1.1, 0.2),
+            ([0.2, -0.98], 0.6, 0.15),
+        ]
+        .into_iter()
+        .map(|(direction, wavelength, steepness)| GerstnerWave {
+            direction,
+            wavelength,
+            steepness,
+            speed: (2.0 * wavelength / std::f32::consts::TAU).sqrt(),
+        })
+        .collect()
+    }
+}
+
+// A wave train as laid out in the storage buffer read by gerstner.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuGerstnerWave {
+    direction: [f32; 2],
+    wavelength: f32,
+    steepness: f32,
+    speed: f32,
+    _padding: f32,
+}
+
+impl From<&GerstnerWave> for GpuGerstnerWave {
+    // The shader expects a unit direction
+    fn from(wave: &GerstnerWave) -> Self {
+        let [x, z] = wave.direction;
+        let length = x.hypot(z).max(f32::EPSILON);
+        Self {
+            direction: [x / length, z / length],
+            wavelength: wave.wavelength,
+            steepness: wave.steepness,
+            speed: wave.speed,
+            _padding: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    time: f32,
+    wave_amplitude: f32,
+    noise_amplitude: f32,
+    noise_octaves: u32,
+    wave_mode: u32,
+    size: u32,
+    video_amount: f32,
+    wave_frequency: f32,
+    gerstner_count: u32,
+    heightmap_amount: f32,
+    _padding: [u32; 2],
+}
+
+// Compute pass that evaluates waves, noise, audio, video luminance and a
+// heightmap into a height buffer once per frame, so the vertex shader only
+// has to sample it
+pub struct HeightField {
+    pub wave_function: WaveFunction,
+    // Scales the wave pattern; 2 fits twice as many waves across the grid
+    pub wave_frequency: f32,
+    pub noise_amplitude: f32,
+    pub noise_octaves: u32,
+    // Displacement of a white video pixel; 0 ignores the video texture
+    pub video_amount: f32,
+    // Height of a heightmap value of 1, which the waves move on top of
+    pub heightmap_amount: f32,
+    // At most MAX_GERSTNER_WAVES; the rest are ignored
+    pub gerstner_waves: Vec<GerstnerWave>,
+    params_buffer: wgpu::Buffer,
+    spectrum_buffer: wgpu::Buffer,
+    heights_buffer: wgpu::Buffer,
+    gerstner_buffer: wgpu::Buffer,
+    heightmap_buffer: wgpu::Buffer,
+    // Heightmap samples set since the last update
+    pending_heightmap: Option<Vec<f32>>,
+    video_texture: wgpu::Texture,
+    video_sampler: wgpu::Sampler,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl HeightField {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Height Field Params Buffer"),
+            size: std::mem::size_of::<Params>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Silent until a spectrum is uploaded
+        let spectrum_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spectrum Buffer"),
+            contents: bytemuck::cast_slice(&[0.0f32; SPECTRUM_BANDS]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let heights_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Height Field Buffer"),
+            size: (HEIGHTFIELD_SIZE * HEIGHTFIELD_SIZE) as wgpu::BufferAddress
+                * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let gerstner_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Gerstner Wave Buffer"),
+            size: (MAX_GERSTNER_WAVES * std::mem::size_of::<GpuGerstnerWave>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Flat until a heightmap is set
+        let heightmap_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Heightmap Buffer"),
+            size: (HEIGHTFIELD_SIZE * HEIGHTFIELD_SIZE) as wgpu::BufferAddress
+                * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Black until a frame is uploaded; replaced when the frame size changes
+        let video_texture = create_video_texture(device, 1, 1);
+        let video_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Video Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let start = Instant::now();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Height Field Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("wave.wgsl"),
+                    include_str!("gerstner.wgsl"),
+                    include_str!("heightfield.wgsl")
+                )
+                .into(),
+            ),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Height Field Pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "main",
+        });
+        log::info!("Height field pipeline created in {:?}", start.elapsed());
+
+        let bind_group = create_bind_group(
+            device,
+            &pipeline,
+            [
+                &params_buffer,
+                &spectrum_buffer,
+                &heights_buffer,
+                &gerstner_buffer,
+                &heightmap_buffer,
+            ],
+            &video_texture,
+            &video_sampler,
+        );
+
+        Self {
+            wave_function: WaveFunction::Sines,
+            wave_frequency: 1.0,
+            noise_amplitude: 0.0,
+            noise_octaves: 4,
+            video_amount: 0.0,
+            heightmap_amount: 0.0,
+            gerstner_waves: GerstnerWave::default_set(),
+            params_buffer,
+            spectrum_buffer,
+            heights_buffer,
+            gerstner_buffer,
+            heightmap_buffer,
+            pending_heightmap: None,
+            video_texture,
+            video_sampler,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    // Read by the grid vertex shader
+    pub fn heights_buffer(&self) -> &wgpu::Buffer {
+        &self.heights_buffer
+    }
+
+    // Band levels in 0..1 from low to high frequency, SPECTRUM_BANDS long
+    pub fn set_spectrum(&self, queue: &wgpu::Queue, bands: &[f32]) {
+        let bands = &bands[..bands.len().min(SPECTRUM_BANDS)];
+        queue.write_buffer(&self.spectrum_buffer, 0, bytemuck::cast_slice(bands));
+    }
+
+    // Base heights from load_heightmap, or None for flat ground. Uploaded
+    // with the next update.
+    pub fn set_heightmap(&mut self, samples: Option<Vec<f32>>) {
+        let size = (HEIGHTFIELD_SIZE * HEIGHTFIELD_SIZE) as usize;
+        self.pending_heightmap = Some(samples.unwrap_or_else(|| vec![0.0; size]));
+    }
+
+    // Upload a luminance frame, e.g. from a webcam, for use as displacement
+    pub fn set_video_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frame: &LumaFrame,
+    ) {
+        let size = self.video_texture.size();
+        if size.width != frame.width || size.height != frame.height {
+            self.video_texture = create_video_texture(device, frame.width, frame.height);
+            self.bind_group = create_bind_group(
+                device,
+                &self.pipeline,
+                [
+                    &self.params_buffer,
+                    &self.spectrum_buffer,
+                    &self.heights_buffer,
+                    &self.gerstner_buffer,
+                    &self.heightmap_buffer,
+                ],
+                &self.video_texture,
+                &self.video_sampler,
+            );
+        }
+
+        queue.write_texture(
+            self.video_texture.as_image_copy(),
+            &frame.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(frame.width),
+                rows_per_image: Some(frame.height),
+            },
+            self.video_texture.size(),
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32, wave_amplitude: f32) {
+        if let Some(samples) = self.pending_heightmap.take() {
+            queue.write_buffer(&self.heightmap_buffer, 0, bytemuck::cast_slice(&samples));
+        }
+        let gerstner_waves: Vec<_> = self
+            .gerstner_waves
+            .iter()
+            .take(MAX_GERSTNER_WAVES)
+            .map(GpuGerstnerWave::from)
+            .collect();
+        queue.write_buffer(
+            &self.gerstner_buffer,
+            0,
+            bytemuck::cast_slice(&gerstner_waves),
+        );
+
+        let params = Params {
+            time,
+            wave_amplitude,
+            noise_amplitude: self.noise_amplitude,
+            noise_octaves: self.noise_octaves,
+            wave_mode: self.wave_function as u32,
+            size: HEIGHTFIELD_SIZE,
+            video_amount: self.video_amount,
+            wave_frequency: self.wave_frequency,
+            gerstner_count: gerstner_waves.len() as u32,
+            heightmap_amount: self.heightmap_amount,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+
+    pub fn encode(&self, device: &wgpu::Device) -> wgpu::CommandBuffer {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Height Field Encoder"),
+        });
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Height Field Pass"),
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            let groups = HEIGHTFIELD_SIZE.div_ceil(8);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+        }
+
+        encoder.finish()
+    }
+}
+
+// Read a grayscale image, such as a 16-bit PNG or an EXR elevation map, into
+// height field samples. PNGs give 0 to 1 from black to white and EXRs their
+// values as they are. The image is stretched over the grid and filtered down
+// to the height field resolution, oriented like the video. CSV and JSON data
+// files are read as a surface with the first row at the back.
+pub fn load_heightmap(path: &Path) -> Result<Vec<f32>, Box<dyn Error>> {
+    let image = if crate::data::is_data_file(path) {
+        crate::data::load(path)?
+    } else {
+        image::open(path)?.to_luma32f()
+    };
+    let image = image::imageops::resize(
+        &image,
+        HEIGHTFIELD_SIZE,
+        HEIGHTFIELD_SIZE,
+        image::imageops::FilterType::Triangle,
+    );
+    let last = HEIGHTFIELD_SIZE - 1;
+    let mut samples = Vec::with_capacity((HEIGHTFIELD_SIZE * HEIGHTFIELD_SIZE) as usize);
+    for z in 0..HEIGHTFIELD_SIZE {
+        for x in 0..HEIGHTFIELD_SIZE {
+            samples.push(image.get_pixel(last - x, last - z).0[0]);
+        }
+    }
+    Ok(samples)
+}
+
+fn create_video_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Video Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::R8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    pipeline: &wgpu::ComputePipeline,
+    [params, spectrum, heights, gerstner, heightmap]: [&wgpu::Buffer; 5],
+    video_texture: &wgpu::Texture,
+    video_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let video_view = video_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Height Field Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: spectrum.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: heights.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&video_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(video_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: gerstner.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: heightmap.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,88 @@
+struct Params {
+    time: f32,
+    wave_amplitude: f32,
+    noise_amplitude: f32,
+    noise_octaves: u32,
+    wave_mode: u32,
+    size: u32,
+    video_amount: f32,
+    wave_frequency: f32,
+    gerstner_count: u32,
+    heightmap_amount: f32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+
+// Audio band levels in 0..1, lowest frequency first; all zero when silent
+@group(0) @binding(1) var<storage, read> spectrum: array<f32>;
+
+// size x size heights covering -1..1 on X and Z, row-major along X
+@group(0) @binding(2) var<storage, read_write> heights: array<f32>;
+
+// Luminance of the latest video frame, e.g. from a webcam
+@group(0) @binding(3) var video_texture: texture_2d<f32>;
+@group(0) @binding(4) var video_sampler: sampler;
+
+// Ocean waves summed by the Gerstner mode; the first gerstner_count are used
+@group(0) @binding(5) var<storage, read> gerstner_waves: array<GerstnerWave>;
+
+// Base heights from an image, laid out like `heights`
+@group(0) @binding(6) var<storage, read> heightmap: array<f32>;
+
+// Peak displacement from a full-scale spectrum band
+const AUDIO_HEIGHT: f32 = 0.6;
+
+// Noise features per grid unit and how fast the noise drifts
+const NOISE_SCALE: f32 = 2.0;
+const NOISE_SPEED: f32 = 0.2;
+
+// Spread the spectrum across the grid, low frequencies on the left as seen
+// from the default camera (which has +X on its left)
+fn audio_height(x: f32) -> f32 {
+    let count = arrayLength(&spectrum);
+    let band = min(u32(clamp((1.0 - x) * 0.5, 0.0, 1.0) * f32(count)), count - 1u);
+    return spectrum[band] * AUDIO_HEIGHT;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size || id.y >= params.size {
+        return;
+    }
+    let x = f32(id.x) / f32(params.size - 1u) * 2.0 - 1.0;
+    let z = f32(id.y) / f32(params.size - 1u) * 2.0 - 1.0;
+
+    let wave = vec2<f32>(x, z) * params.wave_frequency;
+    var height: f32;
+    switch params.wave_mode {
+        case 1u: {
+            height = ripple_height(wave.x, wave.y, params.time);
+        }
+        case 2u: {
+            height = gerstner_height(wave.x, wave.y, params.time, params.gerstner_count);
+        }
+        case 3u: {
+            height = terrain_height(wave.x, wave.y, params.time);
+        }
+        case 4u: {
+            height = interference_height(wave.x, wave.y, params.time);
+        }
+        default: {
+            height = wave_height(wave.x, wave.y, params.time);
+        }
+    }
+    height *= params.wave_amplitude;
+
+    let drift = vec2<f32>(params.time * NOISE_SPEED, 0.0);
+    height += fbm(vec2<f32>(x, z) * NOISE_SCALE + drift, params.noise_octaves) * params.noise_amplitude;
+    height += audio_height(x);
+
+    // Image top is far from the default camera and image left is on its left
+    let uv = vec2<f32>(1.0 - x, 1.0 - z) * 0.5;
+    height += textureSampleLevel(video_texture, video_sampler, uv, 0.0).r * params.video_amount;
+
+    let index = id.y * params.size + id.x;
+    height += heightmap[index] * params.heightmap_amount;
+
+    heights[index] = height;
+}
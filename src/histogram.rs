@@ -0,0 +1,245 @@
+use crate::viewport::Viewport;
+use std::time::Instant;
+
+// Luminance bins; also the compute workgroup size (8x8)
+pub(crate) const HISTOGRAM_BINS: u64 = 64;
+
+// Params of the binning pass in histogram.wgsl, which auto exposure shares
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct BinParams {
+    pub srgb: u32,
+    // Pixel rectangle to bin
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Nonzero to bin log2 luminance from log_min to log_max
+    pub log_scale: u32,
+    pub log_min: f32,
+    pub log_max: f32,
+}
+
+// Luminance histogram of the final frame, computed on the GPU and drawn as
+// a small bar chart in the bottom right corner. Blue and red bars count
+// pixels crushed to black or clipped to white.
+pub struct Histogram {
+    visible: bool,
+    srgb: bool,
+    format: wgpu::TextureFormat,
+    params_buffer: wgpu::Buffer,
+    bins_buffer: wgpu::Buffer,
+    // Created on first show
+    pipelines: Option<Pipelines>,
+}
+
+struct Pipelines {
+    compute: wgpu::ComputePipeline,
+    overlay: wgpu::RenderPipeline,
+    overlay_bind_group: wgpu::BindGroup,
+}
+
+impl Histogram {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Histogram Params Buffer"),
+            size: std::mem::size_of::<BinParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bins_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Histogram Bins Buffer"),
+            size: HISTOGRAM_BINS * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            visible: false,
+            srgb: format.is_srgb(),
+            format,
+            params_buffer,
+            bins_buffer,
+            pipelines: None,
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, device: &wgpu::Device, visible: bool) {
+        if visible && self.pipelines.is_none() {
+            let start = Instant::now();
+            self.pipelines = Some(create_pipelines(device, self.format, &self.bins_buffer));
+            log::info!("Histogram pipelines created in {:?}", start.elapsed());
+        }
+        self.visible = visible;
+    }
+
+    // Bin the viewport of the finished frame in `target`, then draw the chart
+    // on top of it. The target texture needs TEXTURE_BINDING usage.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        viewport: Viewport,
+    ) -> Option<wgpu::CommandBuffer> {
+        let pipelines = self.pipelines.as_ref().filter(|_| self.visible)?;
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[BinParams {
+                srgb: self.srgb as u32,
+                x: viewport.x,
+                y: viewport.y,
+                width: viewport.width,
+                height: viewport.height,
+                ..Default::default()
+            }]),
+        );
+
+        let frame_bind_group = create_bin_bind_group(
+            device,
+            &pipelines.compute,
+            &self.params_buffer,
+            target,
+            &self.bins_buffer,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Histogram Encoder"),
+        });
+        encoder.clear_buffer(&self.bins_buffer, 0, None);
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Histogram Pass"),
+            });
+            compute_pass.set_pipeline(&pipelines.compute);
+            compute_pass.set_bind_group(0, &frame_bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                viewport.width.div_ceil(8),
+                viewport.height.div_ceil(8),
+                1,
+            );
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Histogram Overlay Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            viewport.apply(&mut render_pass);
+            render_pass.set_pipeline(&pipelines.overlay);
+            render_pass.set_bind_group(0, &pipelines.overlay_bind_group, &[]);
+            render_pass.draw(0..6, 0..HISTOGRAM_BINS as u32);
+        }
+
+        Some(encoder.finish())
+    }
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    bins_buffer: &wgpu::Buffer,
+) -> Pipelines {
+    let compute = create_bin_pipeline(device);
+
+    let overlay_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Histogram Overlay Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("histogram_overlay.wgsl").into()),
+    });
+    let overlay = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Histogram Overlay Pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &overlay_shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &overlay_shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    let overlay_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Histogram Overlay Bind Group"),
+        layout: &overlay.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: bins_buffer.as_entire_binding(),
+        }],
+    });
+
+    Pipelines {
+        compute,
+        overlay,
+        overlay_bind_group,
+    }
+}
+
+// Pass counting the pixels of a frame per luminance bin, as set by
+// BinParams, dispatched in 8x8 workgroups over the rectangle
+pub(crate) fn create_bin_pipeline(device: &wgpu::Device) -> wgpu::ComputePipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Histogram Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("histogram.wgsl").into()),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Histogram Pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "main",
+    })
+}
+
+// Binds `frame` to the binning pass, counting into `bins`
+pub(crate) fn create_bin_bind_group(
+    device: &wgpu::Device,
+    pipeline: &wgpu::ComputePipeline,
+    params: &wgpu::Buffer,
+    frame: &wgpu::TextureView,
+    bins: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Histogram Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(frame),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: bins.as_entire_binding(),
+            },
+        ],
+    })
+}
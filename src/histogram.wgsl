@@ -0,0 +1,62 @@
+// Must match HISTOGRAM_BINS in histogram.rs
+const BINS: u32 = 64u;
+
+struct Params {
+    // 1 if the frame is in an sRGB format and reads back as linear
+    srgb: u32,
+    // Pixel rectangle to bin, leaving out letterbox bars
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    // 1 to bin log2 luminance from log_min to log_max instead, for
+    // unclamped frames; black goes in the first bin
+    log_scale: u32,
+    log_min: f32,
+    log_max: f32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var frame: texture_2d<f32>;
+@group(0) @binding(2) var<storage, read_write> bins: array<atomic<u32>>;
+
+var<workgroup> local_bins: array<atomic<u32>, 64>;
+
+fn encode_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        return value * 12.92;
+    }
+    return 1.055 * pow(value, 1.0 / 2.4) - 0.055;
+}
+
+// Count pixels per luminance bin, first per workgroup and then globally to
+// keep contention on the output low
+@compute @workgroup_size(8, 8)
+fn main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    atomicStore(&local_bins[local_index], 0u);
+    workgroupBarrier();
+
+    if id.x < params.width && id.y < params.height {
+        let pixel = vec2<u32>(params.x, params.y) + id.xy;
+        let color = textureLoad(frame, vec2<i32>(pixel), 0).rgb;
+        let luminance = max(dot(color, vec3<f32>(0.2126, 0.7152, 0.0722)), 0.0);
+        var position = min(luminance, 1.0);
+        if params.log_scale != 0u {
+            position = (log2(max(luminance, 1e-9)) - params.log_min) / (params.log_max - params.log_min);
+        } else if params.srgb != 0u {
+            // Bin by the value sent to the display
+            position = encode_srgb(position);
+        }
+        let bin = min(u32(clamp(position, 0.0, 1.0) * f32(BINS)), BINS - 1u);
+        atomicAdd(&local_bins[bin], 1u);
+    }
+    workgroupBarrier();
+
+    let count = atomicLoad(&local_bins[local_index]);
+    if count > 0u {
+        atomicAdd(&bins[local_index], count);
+    }
+}
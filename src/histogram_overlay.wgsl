@@ -0,0 +1,60 @@
+// Must match HISTOGRAM_BINS and the panel placement in histogram.rs
+const BINS: u32 = 64u;
+const PANEL_LEFT: f32 = 0.38;
+const PANEL_WIDTH: f32 = 0.6;
+const PANEL_BOTTOM: f32 = -0.98;
+const PANEL_HEIGHT: f32 = 0.3;
+
+@group(0) @binding(0) var<storage, read> bins: array<u32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+// One quad per bin, two triangles each. Heights are log scaled so a mostly
+// black frame doesn't flatten every other bin.
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) bin: u32,
+) -> VertexOutput {
+    var peak = 1u;
+    for (var i = 0u; i < BINS; i++) {
+        peak = max(peak, bins[i]);
+    }
+    let height = log2(1.0 + f32(bins[bin])) / log2(1.0 + f32(peak));
+
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[vertex];
+    let bar_width = PANEL_WIDTH / f32(BINS);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(
+        PANEL_LEFT + (f32(bin) + corner.x) * bar_width,
+        PANEL_BOTTOM + corner.y * height * PANEL_HEIGHT,
+        0.0,
+        1.0,
+    );
+    // Crushed blacks in blue, clipped whites in red
+    if bin == 0u {
+        out.color = vec4<f32>(0.3, 0.5, 1.0, 0.9);
+    } else if bin == BINS - 1u {
+        out.color = vec4<f32>(1.0, 0.3, 0.3, 0.9);
+    } else {
+        out.color = vec4<f32>(0.8, 0.8, 0.8, 0.7);
+    }
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
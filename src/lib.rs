@@ -0,0 +1,66 @@
+// Waveform grid renderer, usable from any application that owns a wgpu
+// device. The `rust-glsl` binary is a thin winit shell around it.
+
+pub mod audio;
+pub mod beat;
+pub mod budget;
+pub mod burn_in;
+pub mod calibration;
+pub mod camera;
+pub mod chroma;
+pub mod chroma_overlay;
+pub mod clock;
+pub mod config;
+pub mod console;
+pub mod control;
+pub mod data;
+pub mod debug_overlay;
+pub mod error_overlay;
+pub mod events;
+pub mod evolve;
+pub mod generator;
+pub mod gpu;
+pub mod gpu_timer;
+pub mod grid;
+pub mod heightfield;
+pub mod histogram;
+pub mod logo_overlay;
+pub mod loudness;
+pub mod meter_overlay;
+pub mod mutate;
+pub mod offscreen;
+pub mod overview;
+pub mod palette;
+pub mod panel;
+pub mod pitch;
+pub mod playback;
+pub mod playlist;
+pub mod post;
+pub mod progress;
+pub mod qr_overlay;
+pub mod readback;
+pub mod recorder;
+pub mod renderer;
+pub mod resample;
+pub mod schedule;
+pub mod screen;
+pub mod sequence;
+pub mod session;
+pub mod shaders;
+pub mod share;
+pub mod signals;
+pub mod stats_overlay;
+pub mod stereo;
+pub mod svg;
+pub mod telemetry;
+pub mod text;
+pub mod toast;
+pub mod transform;
+pub mod ui_painter;
+pub mod uniforms;
+pub mod viewport;
+pub mod watch;
+pub mod webcam;
+
+pub use camera::{Camera, CameraController};
+pub use renderer::WaveformRenderer;
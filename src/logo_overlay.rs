@@ -0,0 +1,86 @@
+use crate::audio::SPECTRUM_BANDS;
+use crate::config::LogoConfig;
+use crate::qr_overlay::Corner;
+use crate::text::{Image, TextRenderer};
+use crate::viewport::Viewport;
+use std::path::Path;
+
+// Pixels between the viewport edges and the logo at rest
+const MARGIN: f32 = 16.0;
+// Lowest spectrum bands, roughly 40 to 100 Hz, which the pulse follows
+const BASS_BANDS: usize = SPECTRUM_BANDS / 8;
+
+// A PNG logo from the config over a corner of the picture, drawn in its own
+// colors through the shared text batch. It pulses around its center, so it
+// stays in place as it grows.
+pub struct LogoOverlay {
+    config: LogoConfig,
+    image: Option<Image>,
+    // Bass level in 0..1 from the last spectrum
+    level: f32,
+}
+
+impl LogoOverlay {
+    pub fn new(config: &LogoConfig) -> Self {
+        let mut overlay = Self {
+            config: LogoConfig::default(),
+            image: None,
+            level: 0.0,
+        };
+        overlay.set_config(config);
+        overlay
+    }
+
+    // Take new settings, loading the logo again if its path changed
+    pub fn set_config(&mut self, config: &LogoConfig) {
+        if config.path != self.config.path {
+            self.image = config.path.as_deref().and_then(|path| {
+                load(path)
+                    .map_err(|e| log::error!("Failed to load logo {}: {}", path.display(), e))
+                    .ok()
+            });
+        }
+        self.config = config.clone();
+    }
+
+    // Band levels in 0..1, as handed to the renderer
+    pub fn set_spectrum(&mut self, bands: &[f32]) {
+        let bass = &bands[..bands.len().min(BASS_BANDS)];
+        self.level = bass.iter().sum::<f32>() / bass.len().max(1) as f32;
+    }
+
+    // Lay out the logo in `text` for the next draw into `viewport`
+    pub fn push(&self, viewport: Viewport, text: &mut TextRenderer) {
+        let Some(image) = &self.image else {
+            return;
+        };
+        let [width, height] = image.size().map(|side| side as f32);
+        let rest = viewport.width as f32 * self.config.scale;
+        let size = [rest, rest * height / width];
+        let center = [
+            match self.config.corner {
+                Corner::TopLeft | Corner::BottomLeft => MARGIN + size[0] / 2.0,
+                Corner::TopRight | Corner::BottomRight => {
+                    viewport.width as f32 - MARGIN - size[0] / 2.0
+                }
+            },
+            match self.config.corner {
+                Corner::TopLeft | Corner::TopRight => MARGIN + size[1] / 2.0,
+                Corner::BottomLeft | Corner::BottomRight => {
+                    viewport.height as f32 - MARGIN - size[1] / 2.0
+                }
+            },
+        ];
+        let half = size.map(|side| side / 2.0 * (1.0 + self.config.pulse * self.level));
+        text.add_image(
+            image,
+            [center[0] - half[0], center[1] - half[1]],
+            [center[0] + half[0], center[1] + half[1]],
+            self.config.opacity,
+        );
+    }
+}
+
+fn load(path: &Path) -> Result<Image, image::ImageError> {
+    Ok(Image::new(image::open(path)?.to_rgba8()))
+}
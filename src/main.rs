@@ -1,47 +1,89 @@
-use cgmath::*;
-use std::iter;
-use wgpu::util::DeviceExt;
+use clap::Parser;
+use sideharso::audio::{AudioInput, FixedRate, SpectrumAnalyzer, SpectrumSource, ANALYSIS_RATE};
+use sideharso::beat::BeatClock;
+use sideharso::budget::QualityBudget;
+use sideharso::burn_in::BurnInGuard;
+use sideharso::calibration::Calibration;
+use sideharso::camera;
+use sideharso::chroma_overlay::ChromaOverlay;
+use sideharso::clock::{MAX_SPEED, MIN_SPEED};
+use sideharso::config::{
+    self, AudioConfig, BeatConfig, Config, ConfigWatcher, PresentMode, WindowConfig,
+};
+use sideharso::console::{self, Console};
+use sideharso::control::{ControlAddress, ControlCommand, ControlServer};
+use sideharso::data;
+use sideharso::debug_overlay::DebugOverlay;
+use sideharso::error_overlay;
+use sideharso::events::{EventBus, Trigger, TriggerAction};
+use sideharso::evolve::{Evolution, CANDIDATES};
+use sideharso::generator::{self, Signal};
+use sideharso::gpu;
+use sideharso::gpu_timer::GpuTimer;
+use sideharso::grid::{GridShape, Vertex};
+use sideharso::heightfield::WaveFunction;
+use sideharso::histogram::Histogram;
+use sideharso::logo_overlay::LogoOverlay;
+use sideharso::loudness::LoudnessMeter;
+use sideharso::meter_overlay::MeterOverlay;
+use sideharso::mutate::Mutator;
+use sideharso::offscreen::OffscreenTarget;
+use sideharso::overview::{self, Overview};
+use sideharso::panel::Panel;
+use sideharso::pitch::Note;
+use sideharso::playback::{AudioFile, Playback};
+use sideharso::playlist::{self, Playlist};
+use sideharso::post::AntiAliasing;
+use sideharso::progress::{Progress, ProgressFormat};
+use sideharso::qr_overlay::QrOverlay;
+use sideharso::readback::{self, Readback, ReadbackPool, ReadbackPurpose};
+use sideharso::recorder::Recorder;
+use sideharso::renderer::supported_sample_counts;
+use sideharso::schedule::{Schedule, ScheduleEntry};
+use sideharso::screen::{CaptureTarget, ScreenCapture};
+use sideharso::sequence::{self, FrameFormat, FrameInfo, SampleDepth};
+use sideharso::session::SessionStats;
+use sideharso::shaders::{self, ShaderWatcher};
+use sideharso::share;
+use sideharso::signals::Signals;
+use sideharso::stats_overlay::{FrameStats, StatsOverlay};
+use sideharso::telemetry::{self, Telemetry};
+use sideharso::text::TextRenderer;
+use sideharso::toast::Toasts;
+use sideharso::webcam::{FrameSource, Webcam};
+use sideharso::WaveformRenderer;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::{Window, WindowBuilder},
+    window::{CursorGrabMode, Fullscreen, Window, WindowBuilder},
 };
 
-// Vertex structure for our grid points
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 3],
-}
+// Loaded from the working directory when no --config is given
+const DEFAULT_CONFIG: &str = "config.toml";
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct Uniforms {
-    time: f32,
-    _padding: [f32; 3], // Padding to satisfy alignment requirements
-    view_proj: [[f32; 4]; 4],
-}
+// Directory searched for shaders to load and watch instead of the built-in ones
+const DEFAULT_SHADERS: &str = "shaders";
 
-impl Uniforms {
-    fn new() -> Self {
-        let perspective = perspective(Deg(45.0), 800.0 / 600.0, 0.1, 100.0);
-        let view = Matrix4::look_at_rh(
-            Point3::new(0.0, 0.5, -5.0),    // Camera position
-            Point3::new(0.0, 0.5, 0.0),  // Looking straight ahead
-            Vector3::unit_y(),                       // Up vector
-        );
+// Seconds recorded when neither --duration nor an audio file sets the length
+const DEFAULT_DURATION: f32 = 30.0;
 
-        Self {
-            time: 0.0,
-            _padding: [0.0; 3],
-            view_proj: (perspective * view).into(),
-        }
-    }
+// Displacement of white webcam or screen pixels
+const VIDEO_AMOUNT: f32 = 0.5;
 
-    fn update(&mut self, time: f32) {
-        self.time = time;
-    }
-}
+// Radians of camera roll per key press at full motion intensity
+const ROLL_STEP: f32 = 0.05;
+
+// Seconds skipped per arrow key press while the animation plays
+const SCRUB_SECONDS: f32 = 1.0;
+// Seconds of the track skipped per arrow key press during file playback
+const SEEK_SECONDS: f32 = 5.0;
+
+// Log console lines scrolled per Page Up or Page Down
+const CONSOLE_SCROLL_LINES: isize = 10;
 
 struct State {
     surface: wgpu::Surface,
@@ -49,46 +91,86 @@ struct State {
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
-    time: f32,
-    uniform_buffer: wgpu::Buffer,
-    uniform_bind_group: wgpu::BindGroup,
-    depth_texture: wgpu::TextureView,
-    camera_position: Point3<f32>,
-    camera_rotation: f32,
+    renderer: WaveformRenderer,
+    budget: QualityBudget,
+    readback_pool: ReadbackPool,
+    screenshot_requested: bool,
+    // Screenshots being encoded and written, each returning the path it was
+    // written to or the error
+    screenshot_writers: Vec<JoinHandle<Result<String, String>>>,
+    debug_overlay: DebugOverlay,
+    histogram: Histogram,
+    stats_overlay: StatsOverlay,
+    console: Console,
+    overview: Overview,
+    calibration: Option<Calibration>,
+    meters: MeterOverlay,
+    chroma_overlay: ChromaOverlay,
+    // Main config file, where calibration results are saved
+    config_path: Option<PathBuf>,
+    qr_overlay: QrOverlay,
+    logo_overlay: LogoOverlay,
+    burn_in: BurnInGuard,
+    // Shared by every text overlay and drawn in one batch
+    text: TextRenderer,
+    toasts: Toasts,
+    panel: Panel,
+    gpu_timer: Option<GpuTimer>,
+    // CPU time spent on the previous frame, from update to submit
+    frame_start: Instant,
+    cpu_time: f32,
+    // Middle mouse button held to pan the orbit camera
+    panning: bool,
+    // Last cursor position in the window, in pixels
+    cursor: [f32; 2],
+    modifiers: ModifiersState,
+    // Opened on first use, and kept open so copied presets stay available
+    clipboard: Option<arboard::Clipboard>,
+    // Ctrl+V pressed, for the event loop to switch to the pasted preset
+    paste_requested: bool,
+    last_frame: Instant,
+    audio: Option<Box<dyn SpectrumSource>>,
+    video: Option<Box<dyn FrameSource>>,
+    // Settings in use, applied as they change
+    settings: Config,
+    // The main config file's settings, which a scheduled preset may replace
+    main_settings: Config,
+    schedule: Schedule,
+    events: EventBus,
+    // Preset beside the track playing, used instead of the schedule's
+    track_preset: Option<PathBuf>,
+    // Cleared while the schedule has the output off
+    output_enabled: bool,
+    mutator: Mutator,
+    session: SessionStats,
+    beat_clock: BeatClock,
+    evolution: Option<Evolution>,
+    present_modes: Vec<wgpu::PresentMode>,
+    sample_counts: Vec<u32>,
 }
 
 impl State {
-    async fn new(window: &Window) -> Self {
+    async fn new(
+        window: &Window,
+        audio: Option<Box<dyn SpectrumSource>>,
+        window_config: &WindowConfig,
+    ) -> Result<Self, Box<dyn Error>> {
         let size = window.inner_size();
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             dx12_shader_compiler: Default::default(),
         });
 
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::POLYGON_MODE_LINE,
-                    limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let surface = unsafe { instance.create_surface(&window) }
+            .map_err(|e| format!("failed to create a surface for the window: {}", e))?;
+        let adapter = gpu::request_adapter(&instance, Some(&surface)).await?;
+        let (device, queue) = gpu::request_device(&adapter).await?;
+        let budget = QualityBudget::new(&device, None);
+        log::info!(
+            "Max texture size {}, max buffer size {} bytes",
+            device.limits().max_texture_dimension_2d,
+            device.limits().max_buffer_size
+        );
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -98,108 +180,140 @@ impl State {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
 
+        // Allow copying the surface out for screenshots and reading it in the
+        // histogram pass where supported
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_caps.usages
+                & (wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING));
+
+        let max_size = device.limits().max_texture_dimension_2d;
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            width: size.width.min(max_size),
+            height: size.height.min(max_size),
+            present_mode: present_mode(&surface_caps.present_modes, window_config),
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
         surface.configure(&device, &config);
 
-        // Create vertices for the grid
-        let (vertices, num_vertices) = create_grid(80, 60);
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        // Create the shader module (we'll add the actual GLSL shaders next)
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-
-        // Create uniform buffer and bind group layout
-        let uniform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Uniform Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
-
-        let uniforms = Uniforms::new();
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[uniforms]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Uniform Bind Group"),
-            layout: &uniform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
+        let mut renderer =
+            WaveformRenderer::new(&device, config.format, config.width, config.height);
+        renderer.aspect_lock = window_config.aspect;
+        renderer.overscan = window_config.overscan;
 
-        // Create the render pipeline (pass the uniform_bind_group_layout)
-        let render_pipeline =
-            create_render_pipeline(&device, &shader, &config, &uniform_bind_group_layout);
+        // Frame time in ms, motion intensity and camera height
+        let mut debug_overlay = DebugOverlay::new(&device, config.format);
+        debug_overlay.add_graph([1.0, 0.3, 0.3, 1.0], 0.0, 33.3);
+        debug_overlay.add_graph([0.3, 1.0, 0.3, 1.0], 0.0, 1.0);
+        debug_overlay.add_graph([0.3, 0.6, 1.0, 1.0], -2.0, 4.0);
 
-        // Create depth texture
-        let depth_texture = create_depth_texture(&device, &config);
+        let histogram = Histogram::new(&device, config.format);
+        let text = TextRenderer::new(&device, config.format);
+        let sample_counts = supported_sample_counts(&adapter, &device);
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        let panel = Panel::new(&device, config.format, window);
 
-        Self {
+        Ok(Self {
             surface,
             device,
             queue,
             config,
             size,
-            render_pipeline,
-            vertex_buffer,
-            num_vertices: num_vertices as u32,
-            time: 0.0,
-            uniform_buffer,
-            uniform_bind_group,
-            depth_texture,
-            camera_position: Point3::new(0.0, 0.5, -5.0),
-            camera_rotation: 0.0,
-        }
+            renderer,
+            budget,
+            readback_pool: ReadbackPool::new(),
+            screenshot_requested: false,
+            screenshot_writers: Vec::new(),
+            debug_overlay,
+            histogram,
+            stats_overlay: StatsOverlay::default(),
+            console: Console::default(),
+            overview: Overview::default(),
+            calibration: None,
+            meters: MeterOverlay::default(),
+            chroma_overlay: ChromaOverlay::default(),
+            config_path: None,
+            qr_overlay: QrOverlay::new(&Config::default().qr),
+            logo_overlay: LogoOverlay::new(&Config::default().logo),
+            burn_in: BurnInGuard::new(&Config::default().burn_in),
+            text,
+            toasts: Toasts::new(),
+            panel,
+            gpu_timer,
+            frame_start: Instant::now(),
+            cpu_time: 0.0,
+            panning: false,
+            cursor: [0.0; 2],
+            modifiers: ModifiersState::empty(),
+            clipboard: None,
+            paste_requested: false,
+            last_frame: Instant::now(),
+            audio,
+            video: None,
+            settings: Config {
+                window: window_config.clone(),
+                ..Config::default()
+            },
+            main_settings: Config::default(),
+            schedule: Schedule::new(&[]),
+            events: EventBus::new(&[]),
+            track_preset: None,
+            output_enabled: true,
+            present_modes: surface_caps.present_modes,
+            sample_counts,
+            mutator: Mutator::new(seed()),
+            session: SessionStats::new(),
+            beat_clock: {
+                let beat = BeatConfig::default();
+                BeatClock::new(beat.bpm, beat.beats_per_bar)
+            },
+            evolution: None,
+        })
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
+            // Larger windows are scaled up from the largest supported surface
+            let max_size = self.device.limits().max_texture_dimension_2d;
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
+            self.config.width = new_size.width.min(max_size);
+            self.config.height = new_size.height.min(max_size);
             self.surface.configure(&self.device, &self.config);
+            self.renderer
+                .resize(&self.device, self.config.width, self.config.height);
+            if let Some(evolution) = self.evolution.as_mut() {
+                evolution.resize(&self.device, self.renderer.viewport());
+            }
+        }
+    }
 
-            // Recreate depth texture on resize
-            self.depth_texture = create_depth_texture(&self.device, &self.config);
-
-            // Update the uniform buffer with new aspect ratio
-            let mut uniforms = Uniforms::new();
-            uniforms.update(self.time);
-
-            self.queue
-                .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    // Compile a grid shader loaded from `path`. If it fails, the grid is
+    // drawn with the fallback shader and the error is shown on screen until
+    // a fixed version is loaded.
+    fn set_shader_source(&mut self, path: &Path, source: &str) {
+        match self.renderer.set_shader_source(&self.device, source) {
+            Ok(()) => {
+                log::info!("Loaded {}", path.display());
+                self.toasts.info(format!("Loaded {}", path.display()));
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to compile {}, drawing with the fallback shader:\n{}",
+                    path.display(),
+                    e
+                );
+                self.toasts
+                    .error(format!("Failed to compile {}", path.display()));
+            }
         }
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.panel.on_event(event) {
+            return true;
+        }
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -210,318 +324,2385 @@ impl State {
                     },
                 ..
             } => {
-                let movement_speed = 0.1;
-                let rotation_speed = 0.1;
+                let renderer = &mut self.renderer;
+                let movement_speed = 0.1 * renderer.motion_intensity;
+                // Cmd on macOS
+                let shortcut = self.modifiers.ctrl() || self.modifiers.logo();
 
                 match keycode {
+                    VirtualKeyCode::C if shortcut => {
+                        self.copy_preset();
+                        true
+                    }
+                    VirtualKeyCode::V if shortcut => {
+                        self.paste_requested = true;
+                        true
+                    }
                     VirtualKeyCode::W => {
-                        self.camera_position.z += movement_speed * self.camera_rotation.cos();
-                        self.camera_position.x += movement_speed * self.camera_rotation.sin();
+                        renderer.camera.move_forward(movement_speed);
                         true
                     }
                     VirtualKeyCode::S => {
-                        self.camera_position.z -= movement_speed * self.camera_rotation.cos();
-                        self.camera_position.x -= movement_speed * self.camera_rotation.sin();
+                        renderer.camera.move_forward(-movement_speed);
                         true
                     }
                     VirtualKeyCode::A => {
-                        self.camera_rotation -= rotation_speed;
+                        renderer.camera.strafe(-movement_speed);
                         true
                     }
                     VirtualKeyCode::D => {
-                        self.camera_rotation += rotation_speed;
+                        renderer.camera.strafe(movement_speed);
                         true
                     }
                     VirtualKeyCode::Q => {
-                        self.camera_position.y += movement_speed;
+                        renderer.camera.move_up(movement_speed);
                         true
                     }
                     VirtualKeyCode::E => {
-                        self.camera_position.y -= movement_speed;
+                        renderer.camera.move_up(-movement_speed);
+                        true
+                    }
+                    VirtualKeyCode::Z => {
+                        renderer.camera.roll(-ROLL_STEP * renderer.motion_intensity);
+                        true
+                    }
+                    VirtualKeyCode::C => {
+                        renderer.camera.roll(ROLL_STEP * renderer.motion_intensity);
+                        true
+                    }
+                    VirtualKeyCode::M => {
+                        let mut config = self.settings.clone();
+                        let accessibility = &mut config.accessibility;
+                        accessibility.reduced_motion = !accessibility.reduced_motion;
+                        log::info!("Reduced motion: {}", accessibility.reduced_motion);
+                        self.apply_scene_settings(config);
+                        true
+                    }
+                    VirtualKeyCode::P => {
+                        let mut config = self.settings.clone();
+                        config.grid.shape = match config.grid.shape {
+                            GridShape::Cartesian => GridShape::Polar,
+                            GridShape::Polar => GridShape::Cartesian,
+                        };
+                        log::info!("Grid shape: {:?}", config.grid.shape);
+                        self.apply_scene_settings(config);
+                        true
+                    }
+                    VirtualKeyCode::T => {
+                        let mode = renderer.render_mode().next();
+                        renderer.set_render_mode(&self.device, mode);
+                        log::info!("Render mode: {:?}", mode);
+                        true
+                    }
+                    VirtualKeyCode::H => {
+                        let function = self.settings.waves.function.next();
+                        self.set_wave_function(function);
+                        true
+                    }
+                    VirtualKeyCode::N => {
+                        let mut config = self.settings.clone();
+                        config.waves.noise = !config.waves.noise;
+                        log::info!("Noise: {}", config.waves.noise);
+                        self.apply_scene_settings(config);
+                        true
+                    }
+                    VirtualKeyCode::L => {
+                        renderer.line_style = renderer.line_style.next();
+                        log::info!("Line style: {:?}", renderer.line_style);
+                        true
+                    }
+                    VirtualKeyCode::V => {
+                        self.cycle_palette();
+                        true
+                    }
+                    VirtualKeyCode::O => {
+                        let mut config = self.settings.clone();
+                        config.bloom.enabled = !config.bloom.enabled;
+                        log::info!("Bloom: {}", config.bloom.enabled);
+                        self.apply_scene_settings(config);
+                        true
+                    }
+                    VirtualKeyCode::F if !self.budget.effects_allowed() => {
+                        log::warn!("Effects are off to save GPU memory");
+                        true
+                    }
+                    VirtualKeyCode::F => {
+                        let mut config = self.settings.clone();
+                        let window = &mut config.window;
+                        window.anti_aliasing = window.anti_aliasing.next();
+                        log::info!("Anti-aliasing: {:?}", window.anti_aliasing);
+                        self.apply_scene_settings(config);
+                        true
+                    }
+                    VirtualKeyCode::F2 => {
+                        self.debug_overlay.visible = !self.debug_overlay.visible;
+                        true
+                    }
+                    VirtualKeyCode::F3 => {
+                        self.stats_overlay.visible = !self.stats_overlay.visible;
+                        true
+                    }
+                    VirtualKeyCode::F4 => {
+                        self.panel.visible = !self.panel.visible;
+                        true
+                    }
+                    VirtualKeyCode::F5 => {
+                        if self
+                            .config
+                            .usage
+                            .contains(wgpu::TextureUsages::TEXTURE_BINDING)
+                        {
+                            let visible = !self.histogram.visible();
+                            self.histogram.set_visible(&self.device, visible);
+                        } else {
+                            log::warn!("Surface cannot be sampled, histogram unavailable");
+                        }
+                        true
+                    }
+                    VirtualKeyCode::F6 => {
+                        self.console.visible = !self.console.visible;
+                        true
+                    }
+                    VirtualKeyCode::F8 => {
+                        self.toggle_calibration();
+                        true
+                    }
+                    VirtualKeyCode::J => {
+                        self.meters.visible = !self.meters.visible;
+                        true
+                    }
+                    VirtualKeyCode::F7 if self.console.visible => {
+                        self.console.cycle_level();
+                        true
+                    }
+                    VirtualKeyCode::PageUp if self.console.visible => {
+                        self.console.scroll(CONSOLE_SCROLL_LINES);
+                        true
+                    }
+                    VirtualKeyCode::PageDown if self.console.visible => {
+                        self.console.scroll(-CONSOLE_SCROLL_LINES);
+                        true
+                    }
+                    VirtualKeyCode::F12 => {
+                        self.screenshot_requested = true;
+                        true
+                    }
+                    VirtualKeyCode::X => {
+                        self.overview.visible = !self.overview.visible;
+                        true
+                    }
+                    VirtualKeyCode::I => {
+                        let Some(playback) = self.playback() else {
+                            return false;
+                        };
+                        playback.mark_loop();
+                        let rate = playback.sample_rate() as f32;
+                        let message = match (playback.loop_start(), playback.loop_region()) {
+                            (Some(start), _) => {
+                                format!("Loop from {}", overview::format_time(start as f32 / rate))
+                            }
+                            (None, Some((start, end))) => format!(
+                                "Looping {} to {}",
+                                overview::format_time(start as f32 / rate),
+                                overview::format_time(end as f32 / rate)
+                            ),
+                            (None, None) => "Loop off".to_string(),
+                        };
+                        log::info!("{}", message);
+                        self.toasts.info(message);
+                        true
+                    }
+                    VirtualKeyCode::R => {
+                        self.mutate();
+                        true
+                    }
+                    VirtualKeyCode::Back => {
+                        self.undo_mutation();
+                        true
+                    }
+                    VirtualKeyCode::B => {
+                        let beat = &mut self.settings.beat;
+                        beat.randomize = !beat.randomize;
+                        log::info!("Beat-locked randomization: {}", beat.randomize);
+                        true
+                    }
+                    VirtualKeyCode::Space => {
+                        let clock = &mut self.renderer.clock;
+                        clock.set_paused(!clock.paused());
+                        log::info!("Paused: {}", clock.paused());
+                        true
+                    }
+                    // Through the track while playing a file
+                    key @ (VirtualKeyCode::Left | VirtualKeyCode::Right)
+                        if self.playback().is_some() =>
+                    {
+                        let direction = if *key == VirtualKeyCode::Left {
+                            -1.0
+                        } else {
+                            1.0
+                        };
+                        if let Some(playback) = self.playback() {
+                            playback.skip(direction * SEEK_SECONDS);
+                        }
+                        true
+                    }
+                    // Frame by frame while paused, otherwise in bigger jumps
+                    key @ (VirtualKeyCode::Left | VirtualKeyCode::Right) => {
+                        let direction = if *key == VirtualKeyCode::Left { -1 } else { 1 };
+                        let rate = self.renderer.animation_rate();
+                        let clock = &mut self.renderer.clock;
+                        if clock.paused() {
+                            clock.step_frames(direction, rate);
+                        } else {
+                            clock.scrub(direction as f32 * SCRUB_SECONDS, rate);
+                        }
+                        true
+                    }
+                    key @ (VirtualKeyCode::Plus
+                    | VirtualKeyCode::Equals
+                    | VirtualKeyCode::NumpadAdd
+                    | VirtualKeyCode::Minus
+                    | VirtualKeyCode::NumpadSubtract) => {
+                        let faster =
+                            !matches!(key, VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract);
+                        let clock = &mut self.renderer.clock;
+                        let speed = clock.speed();
+                        clock.set_speed(if faster { speed * 2.0 } else { speed / 2.0 });
+                        log::info!("Speed: {}x", clock.speed());
+                        true
+                    }
+                    key @ (VirtualKeyCode::LBracket | VirtualKeyCode::RBracket) => {
+                        // Doubling is capped by the budget, from the device
+                        // limits and any memory budget
+                        let mut config = self.settings.clone();
+                        let grid = &mut config.grid;
+                        if *key == VirtualKeyCode::RBracket {
+                            grid.width = grid.width.saturating_mul(2);
+                            grid.depth = grid.depth.saturating_mul(2);
+                        } else {
+                            grid.width = (grid.width / 2).max(2);
+                            grid.depth = (grid.depth / 2).max(2);
+                        }
+                        self.apply_scene_settings(config);
+                        let grid = self.settings.grid;
+                        log::info!("Grid: {}x{}", grid.width, grid.depth);
+                        true
+                    }
+                    VirtualKeyCode::U => {
+                        self.cycle_present_mode();
+                        true
+                    }
+                    VirtualKeyCode::K => {
+                        self.beat_clock.tap();
+                        log::info!("Tempo: {:.1} BPM", self.beat_clock.bpm());
+                        true
+                    }
+                    // Leaving keeps the first picked candidate, if any
+                    VirtualKeyCode::G => {
+                        match self.evolution.take() {
+                            Some(evolution) => {
+                                if let Some(favorite) = evolution.population.favorite() {
+                                    self.apply_scene_settings(favorite.clone());
+                                }
+                            }
+                            None => {
+                                self.evolution = Some(Evolution::new(
+                                    &self.device,
+                                    self.config.format,
+                                    self.renderer.viewport(),
+                                    &self.settings,
+                                    seed(),
+                                ));
+                            }
+                        }
+                        true
+                    }
+                    key @ (VirtualKeyCode::Key1
+                    | VirtualKeyCode::Key2
+                    | VirtualKeyCode::Key3
+                    | VirtualKeyCode::Key4
+                    | VirtualKeyCode::Key5
+                    | VirtualKeyCode::Key6
+                    | VirtualKeyCode::Key7
+                    | VirtualKeyCode::Key8
+                    | VirtualKeyCode::Key9) => match self.evolution.as_mut() {
+                        Some(evolution) => {
+                            let index = *key as usize - VirtualKeyCode::Key1 as usize;
+                            evolution.population.toggle(index.min(CANDIDATES - 1));
+                            true
+                        }
+                        // Outside evolution mode the first keys pick a wave preset
+                        None => match WaveFunction::ALL
+                            .get(*key as usize - VirtualKeyCode::Key1 as usize)
+                        {
+                            Some(&function) => {
+                                self.set_wave_function(function);
+                                true
+                            }
+                            None => false,
+                        },
+                    },
+                    VirtualKeyCode::Return => match self.evolution.as_mut() {
+                        Some(evolution) => {
+                            evolution.population.breed();
+                            log::info!("Generation {}", evolution.population.generation());
+                            true
+                        }
+                        None => false,
+                    },
+                    VirtualKeyCode::Tab => {
+                        let camera = &self.renderer.camera;
+                        let mode = camera.mode().next();
+                        self.renderer.camera = camera::create_controller(
+                            mode,
+                            camera.position(),
+                            camera.orientation(),
+                            camera.world_scale(),
+                        );
+                        log::info!("Camera mode: {:?}", mode);
                         true
                     }
                     _ => false,
                 }
             }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let amount = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+                };
+                self.renderer.camera.zoom(amount);
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Middle,
+                ..
+            } => {
+                self.panning = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                false
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                false
+            }
+            // A click on the track overview seeks instead of grabbing the
+            // mouse
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let viewport = self.renderer.viewport();
+                let position = [
+                    self.cursor[0] - viewport.x as f32,
+                    self.cursor[1] - viewport.y as f32,
+                ];
+                let Some(fraction) = self.overview.hit(viewport, position) else {
+                    return false;
+                };
+                match self.playback() {
+                    Some(playback) => {
+                        playback.seek((fraction * playback.frames() as f32) as usize);
+                        true
+                    }
+                    None => false,
+                }
+            }
             _ => false,
         }
     }
 
-    fn update(&mut self) {
-        self.time += 1.0 / 60.0;
-
-        // Update camera view matrix
-        let mut uniforms = Uniforms::new();
-        uniforms.time = self.time;
-
-        // Create view matrix from camera position and rotation
-        let view = Matrix4::look_at_rh(
-            self.camera_position,
-            Point3::new(
-                self.camera_position.x + self.camera_rotation.sin(),
-                self.camera_position.y,
-                self.camera_position.z + self.camera_rotation.cos(),
-            ),
-            Vector3::unit_y(),
-        );
+    // Start measuring the live input for its gain, gate and band range, or
+    // cancel a measurement under way
+    fn toggle_calibration(&mut self) {
+        let Some(audio) = self.audio.as_mut().filter(|audio| audio.levels().is_some()) else {
+            self.toasts
+                .error("Calibration needs live input from --microphone");
+            return;
+        };
+        if self.calibration.take().is_some() {
+            audio.set_dynamics(&self.settings.audio);
+            self.toasts.info("Calibration cancelled");
+            return;
+        }
+        // Measured without any level shaping
+        audio.set_dynamics(&AudioConfig::default());
+        self.calibration = Some(Calibration::new());
+    }
 
-        let perspective = perspective(
-            Deg(45.0),
-            self.size.width as f32 / self.size.height as f32,
-            0.1,
-            100.0,
+    // Use the settings a calibration found, and keep them in the config
+    fn finish_calibration(&mut self, audio: AudioConfig) {
+        log::info!(
+            "Calibrated: gain {:.1} dB, gate {:.1} dBFS, range {:.1} to {:.1} dB",
+            audio.gain,
+            audio.gate,
+            audio.range[0],
+            audio.range[1]
         );
-        uniforms.view_proj = (perspective * view).into();
+        let path = self
+            .config_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG));
+        match config::save_audio(&path, &audio) {
+            Ok(()) => self.toasts.info(format!(
+                "Calibrated: gain {:+.1} dB, gate {:.1} dBFS, saved to {}",
+                audio.gain,
+                audio.gate,
+                path.display()
+            )),
+            Err(e) => {
+                log::error!("Failed to save calibration to {}: {}", path.display(), e);
+                self.toasts.error(format!(
+                    "Calibrated, but failed to save to {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+        if let Some(source) = self.audio.as_mut() {
+            source.set_dynamics(&audio);
+        }
+        self.main_settings.audio = audio.clone();
+        let mut settings = self.settings.clone();
+        settings.audio = audio;
+        self.apply_scene_settings(settings);
+    }
 
-        self.queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    // The file being played, if any
+    fn playback(&mut self) -> Option<&mut Playback> {
+        self.audio.as_mut().and_then(|audio| audio.playback())
     }
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // Get the current texture view to render to
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    // Switch to a newly loaded main config, or to the preset the schedule
+    // has in effect instead
+    fn load_config(&mut self, window: &Window, args: &Args, config: Config) {
+        self.schedule.set_entries(&config.schedule);
+        self.events.set_triggers(&config.triggers);
+        self.main_settings = config;
+        self.apply_schedule(window, args);
+    }
 
-        // Create command encoder
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    fn apply_schedule(&mut self, window: &Window, args: &Args) {
+        let entry = self.schedule.current();
+        let output_enabled = !entry.is_some_and(|entry| entry.off);
+        if output_enabled != self.output_enabled {
+            log::info!(
+                "Output {} by schedule",
+                if output_enabled { "on" } else { "off" }
+            );
+            self.output_enabled = output_enabled;
+        }
+        let config = match &self.track_preset {
+            Some(path) => load_preset(args, &self.main_settings, path, &mut self.toasts),
+            None => scheduled_config(args, &self.main_settings, entry, &mut self.toasts),
+        };
+        self.apply_config(window, config);
+    }
 
-        // Begin render pass
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
-
-            // Set pipeline and vertex buffer
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-
-            // Draw the grid
-            render_pass.draw(0..self.num_vertices, 0..1);
-        }
-
-        // Submit command buffer and present
-        self.queue.submit(iter::once(encoder.finish()));
-        output.present();
+    // Take the action of a trigger whose event was detected
+    fn fire_trigger(&mut self, window: &Window, args: &Args, trigger: &Trigger) {
+        log::info!("{:?} on {:?}", trigger.action, trigger.on);
+        match trigger.action {
+            TriggerAction::Preset => {
+                let config = match &trigger.preset {
+                    Some(preset) => load_preset(
+                        args,
+                        &self.main_settings,
+                        &preset_path(args, preset),
+                        &mut self.toasts,
+                    ),
+                    None => self.main_settings.clone(),
+                };
+                self.apply_config(window, config);
+            }
+            TriggerAction::Mutate => self.mutate(),
+            TriggerAction::CameraCut => {
+                self.renderer.camera = camera::quarter_turn(self.renderer.camera.as_ref());
+            }
+        }
+    }
 
-        Ok(())
+    // Announce a new track in a playlist, and switch to the preset named
+    // after it if there is one, or back from the last track's
+    fn track_changed(&mut self, window: &Window, args: &Args, path: &Path) {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        self.toasts
+            .info(format!("Now playing {}", name.to_string_lossy()));
+        let preset = Some(path.with_extension("toml")).filter(|preset| preset.is_file());
+        if preset != self.track_preset {
+            self.track_preset = preset;
+            self.apply_schedule(window, args);
+        }
     }
-}
 
-fn main() {
-    env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_title("Waveform Visualization")
-        .with_inner_size(winit::dpi::PhysicalSize::new(800, 600))
-        .build(&event_loop)
-        .unwrap();
+    // Switch to a config loaded again on request or after a change, or
+    // report why it couldn't be loaded
+    fn reload(&mut self, window: &Window, args: &Args, result: Result<Config, String>) {
+        match result {
+            Ok(config) => {
+                self.load_config(window, args, config);
+                self.session.record_config_reload();
+                self.toasts.info("Config reloaded");
+            }
+            Err(e) => self.toasts.error(e),
+        }
+    }
+
+    // Apply the sections of a config that differ from the current settings.
+    // The renderer starts out matching Config::default() apart from the
+    // window settings passed to new().
+    fn apply_config(&mut self, window: &Window, mut config: Config) {
+        self.budget.set_memory(
+            config
+                .gpu
+                .memory_budget
+                .map(|mib| u64::from(mib) * 1024 * 1024),
+        );
+        self.fit_settings(&mut config);
+        let old = std::mem::replace(&mut self.settings, config);
+        let new = &self.settings;
 
-    let mut state = pollster::block_on(State::new(&window));
+        if new.window != old.window {
+            window.set_inner_size(winit::dpi::PhysicalSize::new(
+                new.window.width,
+                new.window.height,
+            ));
+            window.set_fullscreen(fullscreen(new.window.fullscreen));
+            self.config.present_mode = present_mode(&self.present_modes, &new.window);
+            self.surface.configure(&self.device, &self.config);
+            self.renderer.aspect_lock = new.window.aspect;
+            self.renderer.overscan = new.window.overscan;
+        }
+        if new.text != old.text {
+            self.text.set_fallback_fonts(&new.text.fallback_fonts);
+        }
+        if new.qr != old.qr {
+            self.qr_overlay.set_config(&new.qr);
+        }
+        if new.logo != old.logo {
+            self.logo_overlay.set_config(&new.logo);
+        }
+        if new.burn_in != old.burn_in {
+            self.burn_in.set_config(&new.burn_in);
+        }
+        if new.beat != old.beat {
+            self.beat_clock
+                .set_tempo(new.beat.bpm, new.beat.beats_per_bar);
+        }
+        self.renderer.apply_config(&self.device, new, &old);
+        self.apply_audio_settings(&old);
+        if self.settings.window.msaa != old.window.msaa {
+            self.apply_sample_count();
+        }
+    }
 
-    event_loop.run(move |event, _, control_flow| match event {
-        Event::WindowEvent {
-            ref event,
-            window_id,
-        } if window_id == window.id() => {
-            if !state.input(event) {
-                match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    } => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(physical_size) => {
-                        state.resize(*physical_size);
-                    }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        state.resize(**new_inner_size);
-                    }
-                    _ => {}
-                }
-            }
+    // Switch to settings that differ from the current ones in the scene only,
+    // such as a mutation of them
+    fn apply_scene_settings(&mut self, mut config: Config) {
+        self.fit_settings(&mut config);
+        let old = std::mem::replace(&mut self.settings, config);
+        // The budget may have lowered MSAA
+        if self.settings.window.msaa != old.window.msaa {
+            self.apply_sample_count();
         }
-        Event::RedrawRequested(window_id) if window_id == window.id() => {
-            state.update();
-            match state.render() {
-                Ok(_) => {}
-                Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
-                Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                Err(e) => eprintln!("{:?}", e),
+        self.apply_audio_settings(&old);
+        self.renderer
+            .apply_config(&self.device, &self.settings, &old);
+    }
+
+    fn apply_audio_settings(&mut self, old: &Config) {
+        if self.settings.audio != old.audio {
+            if let Some(audio) = &mut self.audio {
+                audio.set_dynamics(&self.settings.audio);
             }
         }
-        Event::MainEventsCleared => {
-            window.request_redraw();
+    }
+
+    fn apply_sample_count(&mut self) {
+        let count = sample_count(&self.sample_counts, self.settings.window.msaa);
+        self.renderer.set_sample_count(&self.device, count);
+    }
+
+    // Lower settings about to be applied to what the GPU budget allows
+    fn fit_settings(&mut self, config: &mut Config) {
+        let size = (self.config.width, self.config.height);
+        if self.budget.fit(config, size.0, size.1) {
+            log::warn!(
+                "Lowered quality to fit GPU memory: {}x{} grid, {}x MSAA, bloom {}",
+                config.grid.width,
+                config.grid.depth,
+                config.window.msaa,
+                if config.bloom.enabled { "on" } else { "off" }
+            );
         }
-        _ => {}
-    });
-}
+        if !self.budget.effects_allowed() {
+            config.window.anti_aliasing = AntiAliasing::Off;
+        }
+    }
 
-fn create_grid(width: u32, depth: u32) -> (Vec<Vertex>, usize) {
-    let mut vertices = Vec::new();
-    let mut vertex_count = 0;
+    // Lower quality a step after running out of GPU memory, and recreate
+    // whatever may have failed to allocate. Returns false when there is
+    // nothing left to lower.
+    fn reduce_quality(&mut self) -> bool {
+        let effects_in_use = self.settings.bloom.enabled
+            || self.settings.window.msaa > 1
+            || self.settings.window.anti_aliasing != AntiAliasing::Off;
+        if !self.budget.reduce(&self.settings, effects_in_use) {
+            return false;
+        }
+        log::warn!("Out of GPU memory, lowering quality");
+        let config = self.settings.clone();
+        self.apply_scene_settings(config);
+        self.renderer
+            .set_grid_size(&self.device, self.settings.grid);
+        self.renderer
+            .resize(&self.device, self.config.width, self.config.height);
+        true
+    }
 
-    // Create horizontal lines
-    for z in 0..depth {
-        let z_pos = (z as f32 * 2.0 / depth as f32) - 1.0;
+    fn mutate(&mut self) {
+        let (seed, config) = self.mutator.mutate(&self.settings);
+        log::info!("Mutated with seed {}", seed);
+        self.apply_scene_settings(config);
+        self.session.record_mutation();
+    }
 
-        // Add vertices for each horizontal line
-        for x in 0..width {
-            let x_pos = (x as f32 * 2.0 / width as f32) - 1.0;
-            vertices.push(Vertex {
-                position: [x_pos, 0.0, z_pos],
-            });
-            vertex_count += 1;
+    fn undo_mutation(&mut self) {
+        if let Some(config) = self.mutator.back() {
+            log::info!("Undid the last mutation");
+            self.apply_scene_settings(config);
         }
+    }
+
+    // Switch to the next present mode the surface supports. The config's
+    // mode is used again once the window settings change.
+    fn cycle_present_mode(&mut self) {
+        const MODES: [wgpu::PresentMode; 3] = [
+            wgpu::PresentMode::Fifo,
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate,
+        ];
+        let current = MODES
+            .iter()
+            .position(|&mode| mode == self.config.present_mode)
+            .unwrap_or(0);
+        let Some(mode) = (1..=MODES.len())
+            .map(|offset| MODES[(current + offset) % MODES.len()])
+            .find(|mode| self.present_modes.contains(mode))
+        else {
+            return;
+        };
+        self.config.present_mode = mode;
+        self.surface.configure(&self.device, &self.config);
+        log::info!("Present mode: {:?}", mode);
+    }
 
-        // Add degenerate vertices to move to next line
-        if z < depth - 1 {
-            vertices.push(Vertex {
-                position: [1.0, 0.0, z_pos], // Last vertex of current line
-            });
-            vertices.push(Vertex {
-                position: [-1.0, 0.0, (z + 1) as f32 * 2.0 / depth as f32 - 1.0], // First vertex of next line
-            });
-            vertex_count += 2;
+    // Open a file dropped onto the window by its type: play an audio file,
+    // raise the grid into a heightmap or data file, or switch to a preset.
+    // The file is checked first, so one that can't be used leaves the
+    // current settings as they are.
+    fn open_dropped(&mut self, window: &Window, args: &Args, path: &Path) {
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        let result = match extension.as_deref() {
+            _ if playlist::is_audio_file(path) => AudioFile::open(path).map(|file| {
+                self.audio = Some(Box::new(Playback::new(file)));
+                self.track_preset = None;
+                // A file drives the grid on its own, as with --file
+                let mut config = self.settings.clone();
+                config.waves.amplitude = 0.0;
+                self.apply_scene_settings(config);
+                format!("Playing {}", name)
+            }),
+            Some("png" | "exr" | "csv" | "tsv" | "json") => {
+                let checked = if data::is_data_file(path) {
+                    data::load(path).map(drop)
+                } else {
+                    image::image_dimensions(path).map(drop).map_err(Into::into)
+                };
+                checked.map(|()| {
+                    let mut config = self.settings.clone();
+                    config.heightmap.path = Some(path.to_path_buf());
+                    self.apply_scene_settings(config);
+                    format!("Heightmap {}", name)
+                })
+            }
+            Some("toml") => Config::load(path).map(|mut config| {
+                args.override_config(&mut config);
+                self.apply_config(window, config);
+                format!("Preset {}", name)
+            }),
+            _ => Err("not an audio file, heightmap image, data file or .toml preset".into()),
+        };
+        match result {
+            Ok(message) => {
+                log::info!("{}", message);
+                self.toasts.info(message);
+            }
+            Err(e) => {
+                log::error!("Can't open {}: {}", path.display(), e);
+                self.toasts.error(format!("Can't open {}: {}", name, e));
+            }
         }
     }
 
-    // Create vertical lines
-    for x in 0..width {
-        let x_pos = (x as f32 * 2.0 / width as f32) - 1.0;
+    fn clipboard(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.clipboard.as_mut().unwrap())
+    }
 
-        // Add vertices for each vertical line
-        for z in 0..depth {
-            let z_pos = (z as f32 * 2.0 / depth as f32) - 1.0;
-            vertices.push(Vertex {
-                position: [x_pos, 0.0, z_pos],
-            });
-            vertex_count += 1;
+    // Copy the current look to the clipboard as a shared preset
+    fn copy_preset(&mut self) {
+        let result = share::encode(&self.settings).and_then(|text| {
+            self.clipboard()?.set_text(text)?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => self.toasts.info("Preset copied to the clipboard"),
+            Err(e) => {
+                log::error!("Failed to copy the preset: {}", e);
+                self.toasts
+                    .error(format!("Failed to copy the preset: {}", e));
+            }
         }
+    }
 
-        // Add degenerate vertices to move to next line
-        if x < width - 1 {
-            vertices.push(Vertex {
-                position: [x_pos, 0.0, 1.0], // Last vertex of current line
-            });
-            vertices.push(Vertex {
-                position: [(x + 1) as f32 * 2.0 / width as f32 - 1.0, 0.0, -1.0], // First vertex of next line
-            });
-            vertex_count += 2;
+    // Switch to a shared preset from the clipboard, keeping this machine's
+    // window and file settings
+    fn paste_preset(&mut self, window: &Window, args: &Args) {
+        let result = self
+            .clipboard()
+            .map_err(Into::into)
+            .and_then(|clipboard| Ok(clipboard.get_text()?))
+            .and_then(|text| share::decode(&text, &self.settings));
+        match result {
+            Ok(mut config) => {
+                args.override_config(&mut config);
+                self.apply_config(window, config);
+                log::info!("Switched to the pasted preset");
+                self.toasts.info("Preset pasted");
+            }
+            Err(e) => {
+                log::error!("Failed to paste a preset: {}", e);
+                self.toasts
+                    .error(format!("Failed to paste a preset: {}", e));
+            }
         }
     }
 
-    (vertices, vertex_count)
-}
+    fn set_wave_function(&mut self, function: WaveFunction) {
+        let mut config = self.settings.clone();
+        config.waves.function = function;
+        log::info!("Wave function: {:?}", function);
+        self.apply_scene_settings(config);
+    }
 
-// Update the vertex buffer layout in create_render_pipeline
-fn create_render_pipeline(
-    device: &wgpu::Device,
-    shader: &wgpu::ShaderModule,
-    config: &wgpu::SurfaceConfiguration,
-    uniform_bind_group_layout: &wgpu::BindGroupLayout,
-) -> wgpu::RenderPipeline {
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &[uniform_bind_group_layout],
-        push_constant_ranges: &[],
-    });
+    fn cycle_palette(&mut self) {
+        let mut config = self.settings.clone();
+        config.colors.palette = config.colors.palette.next();
+        log::info!("Palette: {:?}", config.colors.palette);
+        self.apply_scene_settings(config);
+    }
 
-    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: shader,
-            entry_point: "vs_main",
-            buffers: &[wgpu::VertexBufferLayout {
-                array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &[wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                }],
-            }],
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::LineStrip,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: None,
-            polygon_mode: wgpu::PolygonMode::Line,
-            unclipped_depth: false,
-            conservative: false,
-        },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        multiview: None,
-    })
-}
+    fn update(&mut self) {
+        self.frame_start = Instant::now();
+        if let Some(audio) = self.audio.as_mut() {
+            let bands = audio.spectrum();
+            self.renderer.set_spectrum(&self.queue, bands);
+            self.logo_overlay.set_spectrum(bands);
+            self.events.process(bands);
+            if let Some(evolution) = &self.evolution {
+                evolution.set_spectrum(&self.queue, bands);
+            }
+        }
+        // Pitch detection is only run when something shows it
+        if self.meters.visible || self.settings.pitch.colors {
+            let pitch = self
+                .audio
+                .as_mut()
+                .and_then(|audio| audio.analyzer())
+                .and_then(|analyzer| analyzer.pitch());
+            self.meters.set_pitch(pitch);
+        }
+        if self.meters.visible {
+            let stereo = self.audio.as_mut().and_then(|audio| audio.stereo());
+            self.meters.set_stereo(stereo);
+        }
+        if self.meters.visible || self.settings.loudness.waves {
+            let loudness = self.audio.as_mut().and_then(|audio| audio.loudness());
+            self.meters.set_loudness(loudness);
+            self.renderer.wave_scale = match loudness {
+                Some(loudness) if self.settings.loudness.waves => {
+                    self.settings.loudness.wave_scale(loudness.momentary)
+                }
+                _ => 1.0,
+            };
+        } else {
+            self.renderer.wave_scale = 1.0;
+        }
+        let analyzer = self.audio.as_mut().and_then(|audio| audio.analyzer());
+        let key = analyzer.as_ref().and_then(|analyzer| analyzer.key());
+        if let Some(analyzer) = analyzer {
+            self.chroma_overlay.set_chroma(analyzer.chroma(), key);
+        }
+        self.renderer.hue = if self.settings.pitch.colors {
+            self.meters
+                .note()
+                .map(|note| note.pitch_class() as f32 / 12.0)
+        } else if self.settings.chroma.colors {
+            key.map(|key| key.tonic as f32 / 12.0)
+        } else {
+            None
+        };
+        let levels = self.audio.as_ref().and_then(|audio| audio.levels());
+        if let (Some(calibration), Some(levels)) = (self.calibration.as_mut(), levels) {
+            if let Some(audio) = calibration.measure(levels, &self.settings.audio) {
+                self.calibration = None;
+                self.finish_calibration(audio);
+            }
+        }
+        if let Some(frame) = self.video.as_ref().and_then(|video| video.take_frame()) {
+            self.renderer
+                .heightfield
+                .set_video_frame(&self.device, &self.queue, &frame);
+        }
+        self.renderer.drift_margin = self.burn_in.margin();
+        self.renderer.drift = self.burn_in.offset();
+        self.text.inverted = self.burn_in.overlays_inverted();
+        self.renderer.update(&self.queue);
 
-fn create_depth_texture(
-    device: &wgpu::Device,
-    config: &wgpu::SurfaceConfiguration,
-) -> wgpu::TextureView {
-    let texture = device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("Depth Texture"),
-        size: wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Depth32Float,
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-    });
+        let now = Instant::now();
+        let frame_time = now.duration_since(self.last_frame).as_secs_f32() * 1000.0;
+        self.last_frame = now;
+        self.debug_overlay.push(&[
+            frame_time,
+            self.renderer.motion_intensity,
+            self.renderer.camera.position().y,
+        ]);
+        self.debug_overlay.upload(&self.device, &self.queue);
+
+        // Mutate on the downbeat; not while picking candidates by hand
+        let beat = &self.settings.beat;
+        if self.beat_clock.advance(frame_time / 1000.0) > 0
+            && beat.randomize
+            && self.beat_clock.bar().is_multiple_of(beat.bars as u64)
+            && self.evolution.is_none()
+        {
+            let (seed, config) = self.mutator.mutate(&self.settings);
+            log::info!("Bar {}: mutated with seed {}", self.beat_clock.bar(), seed);
+            self.apply_scene_settings(config);
+            self.session.record_mutation();
+        }
+        // Under the text overlays
+        if self.settings.chroma.ring {
+            self.chroma_overlay
+                .push(self.renderer.viewport(), &mut self.text);
+        }
+        self.logo_overlay
+            .push(self.renderer.viewport(), &mut self.text);
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.poll(&self.device);
+        }
+        self.stats_overlay.push(
+            &FrameStats {
+                frame_time,
+                cpu_time: self.cpu_time,
+                gpu_time: self.gpu_timer.as_ref().and_then(GpuTimer::last),
+                camera: self.renderer.camera.position(),
+                grid: self.renderer.grid_size(),
+                vertices: self.renderer.vertex_count(),
+            },
+            &mut self.text,
+        );
+        self.qr_overlay
+            .push(self.renderer.viewport(), &mut self.text);
+        if let Some(playback) = self.audio.as_mut().and_then(|audio| audio.playback()) {
+            self.overview
+                .push(playback, self.renderer.viewport(), &mut self.text);
+        }
+        if let Some(calibration) = &self.calibration {
+            calibration.push(self.renderer.viewport(), &mut self.text);
+        }
+        self.meters.push(self.renderer.viewport(), &mut self.text);
+        self.console.push(self.renderer.viewport(), &mut self.text);
+        self.toasts.push(self.renderer.viewport(), &mut self.text);
+        if let Some(error) = self.renderer.shader_error() {
+            error_overlay::push(
+                "Grid shader failed to compile, drawing the fallback",
+                error,
+                self.renderer.viewport(),
+                &mut self.text,
+            );
+        }
+
+        self.handle_readbacks();
+    }
 
-    texture.create_view(&wgpu::TextureViewDescriptor::default())
+    // Hand finished GPU readbacks to their consumers
+    fn handle_readbacks(&mut self) {
+        let (finished, writing) = std::mem::take(&mut self.screenshot_writers)
+            .into_iter()
+            .partition(|writer| writer.is_finished());
+        self.screenshot_writers = writing;
+        self.finish_screenshots(finished);
+        for readback in self.readback_pool.poll(&self.device) {
+            match readback.purpose {
+                ReadbackPurpose::Screenshot => {
+                    self.screenshot_writers.push(save_screenshot(readback));
+                }
+                // Only offscreen targets export, and they wait for their own
+                ReadbackPurpose::Export => {}
+            }
+        }
+    }
+
+    // The event loop exits the process without dropping the state, so stop
+    // the audio and capture threads and finish writing files here
+    fn shutdown(&mut self) {
+        self.audio = None;
+        self.video = None;
+
+        self.device.poll(wgpu::Maintain::Wait);
+        self.handle_readbacks();
+        let writers = std::mem::take(&mut self.screenshot_writers);
+        self.finish_screenshots(writers);
+    }
+
+    // Wait for screenshot writers and report how each went
+    fn finish_screenshots(&mut self, writers: Vec<JoinHandle<Result<String, String>>>) {
+        for writer in writers {
+            match writer.join() {
+                Ok(Ok(path)) => self.toasts.info(format!("Saved {}", path)),
+                Ok(Err(e)) => self.toasts.error(e),
+                Err(_) => log::error!("Screenshot writer panicked"),
+            }
+        }
+    }
+
+    // Lay out the control panel and apply whatever was changed in it
+    fn run_panel(&mut self, window: &Window) {
+        if let Some(config) =
+            self.panel
+                .run(window, &self.device, &mut self.renderer, &self.settings)
+        {
+            self.apply_scene_settings(config);
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // Get the current texture view to render to
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        if !self.output_enabled {
+            self.screenshot_requested = false;
+            self.queue.submit([clear_pass(&self.device, &view)]);
+            output.present();
+            return Ok(());
+        }
+
+        // Each pass encodes into its own command buffer; they are submitted
+        // together in order so passes can be recorded independently
+        let viewport = self.renderer.viewport();
+        let mut command_buffers: Vec<_> = self
+            .gpu_timer
+            .as_mut()
+            .and_then(|timer| timer.begin(&self.device))
+            .into_iter()
+            .collect();
+        command_buffers.extend(match self.evolution.as_mut() {
+            Some(evolution) => {
+                vec![evolution.render(&self.device, &self.queue, &self.renderer, &view, viewport)]
+            }
+            None => self.renderer.render(&self.device, &view),
+        });
+        command_buffers.extend(
+            self.histogram
+                .encode(&self.device, &self.queue, &view, viewport),
+        );
+        command_buffers.extend(self.debug_overlay.encode(&self.device, &view, viewport));
+        command_buffers.extend(self.text.encode(&self.device, &self.queue, &view, viewport));
+        command_buffers.extend(self.panel.encode(
+            &self.device,
+            &self.queue,
+            &view,
+            [self.config.width, self.config.height],
+        ));
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            command_buffers.extend(gpu_timer.end(&self.device));
+        }
+
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            if self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+                let mut encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Readback Encoder"),
+                        });
+                self.readback_pool.copy_texture(
+                    &self.device,
+                    &mut encoder,
+                    &output.texture,
+                    ReadbackPurpose::Screenshot,
+                );
+                command_buffers.push(encoder.finish());
+            } else {
+                log::warn!("Surface does not support copies, screenshot skipped");
+            }
+        }
+
+        // Submit command buffers and present
+        self.queue.submit(command_buffers);
+        self.readback_pool.submitted();
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.submitted();
+        }
+        self.cpu_time = self.frame_start.elapsed().as_secs_f32() * 1000.0;
+        output.present();
+
+        Ok(())
+    }
+}
+
+// Real-time 3D waveform visualization
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    args_conflicts_with_subcommands = true,
+    group(clap::ArgGroup::new("offscreen").args(["record", "headless"]))
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Window width in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+
+    /// Window height in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    height: Option<u32>,
+
+    /// Grid lines across and along, e.g. 200x150
+    #[arg(long, value_name = "WIDTHxDEPTH", value_parser = parse_grid)]
+    grid: Option<(u32, u32)>,
+
+    /// Start in borderless fullscreen
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// Presentation mode, overriding vsync from the config
+    #[arg(long, value_enum)]
+    present_mode: Option<PresentMode>,
+
+    /// Letterbox the picture to an aspect ratio such as 2.39 or 16:9
+    #[arg(long, value_name = "RATIO", value_parser = parse_aspect)]
+    aspect: Option<f32>,
+
+    /// Multisample anti-aliasing samples per pixel (1, 2, 4, 8 or 16),
+    /// lowered to what the GPU supports
+    #[arg(long, value_name = "SAMPLES", value_parser = parse_msaa)]
+    msaa: Option<u32>,
+
+    /// Keep a margin of this fraction of the window black on each side
+    #[arg(long, value_name = "FRACTION", value_parser = parse_overscan)]
+    overscan: Option<f32>,
+
+    /// Animation speed relative to real time, e.g. 0.5 for slow motion.
+    /// Applies to recordings and headless frames as well.
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, value_parser = parse_speed)]
+    speed: f32,
+
+    /// Settings file, watched for changes [default: config.toml if present]
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Directory with a shader.wgsl to use instead of the built-in grid
+    /// shader, recompiled when it changes [default: shaders if present]
+    #[arg(long, value_name = "DIR")]
+    shaders: Option<PathBuf>,
+
+    /// Drive the grid from the default audio input
+    #[arg(long, conflicts_with = "file")]
+    microphone: bool,
+
+    /// Play an audio file, or the tracks in a directory or M3U playlist, and
+    /// drive the grid from it
+    #[arg(long, visible_alias = "play", value_name = "AUDIO")]
+    file: Option<PathBuf>,
+
+    /// Drive the grid from a test signal instead of an audio source: a sine
+    /// sweep from 20 Hz to 20 kHz, pink noise, or impulses on a 120 BPM beat,
+    /// repeating every 10 seconds
+    #[arg(long, value_name = "SIGNAL", conflicts_with_all = ["microphone", "file"])]
+    generate: Option<Signal>,
+
+    /// Play the generated signal, and mux it into --record videos
+    #[arg(long, requires = "generate")]
+    audible: bool,
+
+    /// Replace the grid with the outlines from an SVG file
+    #[arg(long, value_name = "FILE")]
+    svg: Option<PathBuf>,
+
+    /// Raise the grid into terrain from a grayscale PNG or EXR heightmap,
+    /// with the waves on top
+    #[arg(long, value_name = "FILE")]
+    heightmap: Option<PathBuf>,
+
+    /// Plot the numeric series in a CSV or JSON file as a surface, rows
+    /// running front to back, scaled to the heightmap height
+    #[arg(long, value_name = "FILE", conflicts_with = "heightmap")]
+    data: Option<PathBuf>,
+
+    /// Show a PNG logo over a corner of the picture, as set up in the
+    /// config's [logo] section
+    #[arg(long, value_name = "PNG")]
+    logo: Option<PathBuf>,
+
+    /// Displace the grid by the first webcam's brightness
+    #[arg(long, conflicts_with = "screen")]
+    webcam: bool,
+
+    /// Displace the grid by a monitor (index) or window (part of its title)
+    #[arg(long, value_name = "TARGET")]
+    screen: Option<String>,
+
+    /// Use edge strength instead of brightness of the screen capture
+    #[arg(long, requires = "screen")]
+    screen_edges: bool,
+
+    /// Record frame telemetry to a binary log
+    #[arg(long, value_name = "FILE")]
+    telemetry: Option<PathBuf>,
+
+    /// Write a JSON session summary to FILE on exit, in addition to the one
+    /// printed
+    #[arg(long, value_name = "FILE", conflicts_with = "offscreen")]
+    session_report: Option<PathBuf>,
+
+    /// Summarize a telemetry log and exit
+    #[arg(long, value_name = "FILE")]
+    analyze_telemetry: Option<PathBuf>,
+
+    /// Render a video with ffmpeg instead of opening a window, at the window
+    /// size. An audio file given with --file drives the grid and is muxed in.
+    /// With --microphone the video is rendered in real time from the input,
+    /// which is recorded and muxed in as well.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["webcam", "screen", "telemetry"]
+    )]
+    record: Option<PathBuf>,
+
+    /// Length of the recording in seconds [default: the length of the
+    /// --file audio, otherwise 30]
+    #[arg(long, value_name = "SECONDS", requires = "record")]
+    duration: Option<f32>,
+
+    /// Render frames to numbered image files in DIR without creating a
+    /// window, e.g. for golden-image tests or server-side rendering
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["microphone", "webcam", "screen", "telemetry"]
+    )]
+    headless: Option<PathBuf>,
+
+    /// Number of frames to render headless
+    #[arg(
+        long,
+        default_value_t = 1,
+        requires = "headless",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    frames: u32,
+
+    /// Image format of headless frames: 8-bit PNG, or linear floating point
+    /// EXR keeping the values above white, with the frame time and preset
+    /// name in its header
+    #[arg(long, value_enum, default_value_t, requires = "headless")]
+    format: FrameFormat,
+
+    /// Bits per channel of EXR frames, 16 or 32
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        value_name = "BITS",
+        requires = "headless"
+    )]
+    exr_bits: SampleDepth,
+
+    /// Frame rate of the recording or headless frames; each frame advances
+    /// the animation by 1/FPS seconds
+    #[arg(
+        long,
+        default_value_t = 60,
+        requires = "offscreen",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fps: u32,
+
+    /// Report the progress of a recording or headless render on stdout, as
+    /// lines of text or of JSON
+    #[arg(long, value_enum, default_value_t, requires = "offscreen")]
+    progress: ProgressFormat,
+
+    /// Run unattended, e.g. as a systemd service: log with syslog priority
+    /// prefixes for journald instead of printing, and report readiness with
+    /// sd_notify
+    #[arg(long, conflicts_with_all = ["offscreen", "analyze_telemetry"])]
+    daemon: bool,
+
+    /// Accept control commands (reload, screenshot, mutate, undo, palette,
+    /// qr, quit) on a TCP address such as 127.0.0.1:7770 or a Unix socket path.
+    /// A socket passed by systemd socket activation is used if present.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        value_parser = ControlAddress::parse,
+        conflicts_with = "offscreen"
+    )]
+    control: Option<ControlAddress>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Render a music video of a whole audio file offline, faster or slower
+    /// than real time, with the audio muxed in by ffmpeg
+    Render(RenderArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct RenderArgs {
+    /// Audio file to render the video for
+    audio: PathBuf,
+
+    /// Video file to write, its codec following the extension
+    /// [default: the audio file name with .mp4, in the working directory]
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Config file with the look to render
+    #[arg(long, value_name = "FILE")]
+    preset: Option<PathBuf>,
+
+    /// Size of the video
+    #[arg(
+        long,
+        value_name = "WIDTHxHEIGHT",
+        default_value = "1920x1080",
+        value_parser = parse_resolution
+    )]
+    resolution: (u32, u32),
+
+    /// Frame rate of the video
+    #[arg(
+        long,
+        default_value_t = 60,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fps: u32,
+
+    /// Report the progress on stdout as lines of text or of JSON
+    #[arg(long, value_enum, default_value_t)]
+    progress: ProgressFormat,
+}
+
+impl RenderArgs {
+    // The same render as a --record of the whole --file
+    fn apply(self, args: &mut Args) {
+        let output = self.output.unwrap_or_else(|| {
+            let name = self.audio.file_stem().unwrap_or_default();
+            Path::new(name).with_extension("mp4")
+        });
+        args.record = Some(output);
+        args.file = Some(self.audio);
+        args.config = self.preset;
+        (args.width, args.height) = (Some(self.resolution.0), Some(self.resolution.1));
+        args.fps = self.fps;
+        args.progress = self.progress;
+    }
+}
+
+impl Args {
+    // Command line settings win over the config file, including on reload
+    fn override_config(&self, config: &mut Config) {
+        if let Some(width) = self.width {
+            config.window.width = width;
+        }
+        if let Some(height) = self.height {
+            config.window.height = height;
+        }
+        if let Some((width, depth)) = self.grid {
+            config.grid.width = width;
+            config.grid.depth = depth;
+        }
+        if self.heightmap.is_some() {
+            config.heightmap.path = self.heightmap.clone();
+        }
+        if self.logo.is_some() {
+            config.logo.path = self.logo.clone();
+        }
+        // Data is plotted on still ground for inspection
+        if self.data.is_some() {
+            config.heightmap.path = self.data.clone();
+            config.waves.amplitude = 0.0;
+        }
+        if self.fullscreen {
+            config.window.fullscreen = true;
+        }
+        if self.present_mode.is_some() {
+            config.window.present_mode = self.present_mode;
+        }
+        if self.aspect.is_some() {
+            config.window.aspect = self.aspect;
+        }
+        if let Some(overscan) = self.overscan {
+            config.window.overscan = overscan;
+        }
+        if let Some(msaa) = self.msaa {
+            config.window.msaa = msaa;
+        }
+        // A file drives the grid on its own instead of the procedural waves
+        if self.file.is_some() {
+            config.waves.amplitude = 0.0;
+        }
+    }
+}
+
+// Draw the frames of a recording or headless render at a fixed timestep of
+// `1 / fps` into an offscreen target without opening a window, handing each
+// one to `output` as RGBA8, or RGBA half floats for EXR frames. An audio
+// file given with --file is analyzed at each frame's timestamp; `live` input
+// is analyzed as it comes in.
+fn render_offscreen(
+    args: &Args,
+    config: &Config,
+    shader_dir: Option<&Path>,
+    outline: Option<Vec<Vertex>>,
+    mut live: Option<&mut AudioInput>,
+    signals: &Signals,
+    mut output: impl FnMut(u32, &[u8]) -> Result<(), Box<dyn Error>>,
+) -> Result<u32, Box<dyn Error>> {
+    let file = args.file.as_deref().map(AudioFile::open).transpose()?;
+    let frames = match args.record {
+        Some(_) => {
+            let seconds = match (args.duration, &file) {
+                (Some(duration), _) => duration as f64,
+                (None, Some(file)) => file.frames() as f64 / file.sample_rate as f64,
+                (None, None) => DEFAULT_DURATION as f64,
+            };
+            (seconds * args.fps as f64).round() as u32
+        }
+        None => args.frames,
+    };
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        dx12_shader_compiler: Default::default(),
+    });
+    let adapter = pollster::block_on(gpu::request_adapter(&instance, None))?;
+    let (device, queue) = pollster::block_on(gpu::request_device(&adapter))?;
+
+    let (width, height) = (config.window.width, config.window.height);
+    let max_size = device.limits().max_texture_dimension_2d;
+    if width > max_size || height > max_size {
+        return Err(format!(
+            "output size is limited to {}x{} on this GPU",
+            max_size, max_size
+        )
+        .into());
+    }
+    let format = args.format.texture_format();
+    let mut target = OffscreenTarget::new(&device, width, height, format);
+    let mut renderer = WaveformRenderer::new(&device, format, width, height);
+    renderer.aspect_lock = config.window.aspect;
+    renderer.overscan = config.window.overscan;
+    renderer.clock.set_speed(args.speed);
+    let sample_counts = supported_sample_counts(&adapter, &device);
+    renderer.set_sample_count(&device, sample_count(&sample_counts, config.window.msaa));
+    renderer.apply_config(&device, config, &Config::default());
+    if let Some(dir) = shader_dir {
+        let path = dir.join(shaders::GRID_SHADER);
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        renderer
+            .set_shader_source(&device, &source)
+            .map_err(|e| format!("failed to compile {}:\n{}", path.display(), e))?;
+    }
+    if let Some(outline) = outline {
+        renderer.set_mesh(&device, &outline);
+    }
+
+    let audio = match args.generate {
+        Some(signal) => Some(signal.generate(frames as f64 / args.fps as f64)),
+        None => file,
+    };
+    let mut analyzer = audio.as_ref().map(|_| {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.set_dynamics(&config.audio);
+        analyzer
+    });
+    let mut fixed_rate = None;
+    FixedRate::configure(&mut fixed_rate, &config.audio);
+    let mut loudness_meter = audio
+        .as_ref()
+        .map(|file| LoudnessMeter::new(file.sample_rate, file.channels));
+    let mut loudness_position = 0;
+    // The logo and the chroma ring are the overlays kept in rendered output,
+    // e.g. for streams
+    let mut text = TextRenderer::new(&device, format);
+    let mut logo_overlay = LogoOverlay::new(&config.logo);
+    let mut chroma_overlay = ChromaOverlay::default();
+
+    // Reloads change the scene only; the output size stays fixed
+    let mut settings = config.clone();
+    if let Some(input) = live.as_deref_mut() {
+        input.set_dynamics(&config.audio);
+        input.recording().start();
+    }
+    let start = Instant::now();
+    let mut progress = Progress::new(args.progress, frames);
+    let mut frame = 0;
+    while frame < frames {
+        if signals.shutdown_requested() {
+            log::info!("Stopping after {} of {} frames", frame, frames);
+            progress.finish(frame, true);
+            return Ok(frame);
+        }
+        if let Some(new) = signals
+            .take_reload()
+            .then(|| reload_config(args).ok())
+            .flatten()
+        {
+            if new.window.msaa != settings.window.msaa {
+                renderer.set_sample_count(&device, sample_count(&sample_counts, new.window.msaa));
+            }
+            renderer.apply_config(&device, &new, &settings);
+            logo_overlay.set_config(&new.logo);
+            settings = new;
+        }
+        // Live input is shown in real time: wait for this frame's time, and
+        // when rendering falls behind, repeat this frame for every frame due,
+        // so the video keeps in step with the audio
+        let mut repeats = 1;
+        if let Some(input) = live.as_deref_mut() {
+            let due = Duration::from_secs_f64(frame as f64 / args.fps as f64);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            let behind = (start.elapsed().as_secs_f64() * args.fps as f64) as u32;
+            repeats = (behind + 1).saturating_sub(frame).clamp(1, frames - frame);
+            let bands = input.spectrum();
+            renderer.set_spectrum(&queue, bands);
+            logo_overlay.set_spectrum(bands);
+            renderer.wave_scale = match input.loudness() {
+                Some(loudness) if settings.loudness.waves => {
+                    settings.loudness.wave_scale(loudness.momentary)
+                }
+                _ => 1.0,
+            };
+        }
+        if let (Some(file), Some(analyzer)) = (&audio, analyzer.as_mut()) {
+            let position = frame as u64 * file.sample_rate as u64 / args.fps as u64;
+            let bands = match fixed_rate.as_mut() {
+                Some(fixed_rate) => {
+                    let rate = file.sample_rate as u64;
+                    fixed_rate.process(analyzer, position * ANALYSIS_RATE as u64 / rate, |end| {
+                        file.window((end * rate / ANALYSIS_RATE as u64) as usize)
+                    })
+                }
+                None => analyzer.process(&file.window(position as usize)),
+            };
+            renderer.set_spectrum(&queue, bands);
+            logo_overlay.set_spectrum(bands);
+        }
+        let analyzer = match live.as_deref_mut() {
+            Some(input) => input.analyzer(),
+            None => analyzer.as_mut(),
+        };
+        if let Some(analyzer) = analyzer {
+            let key = analyzer.key();
+            chroma_overlay.set_chroma(analyzer.chroma(), key);
+            // The last note found is kept through gaps until the next
+            if settings.pitch.colors {
+                if let Some(frequency) = analyzer.pitch() {
+                    renderer.hue =
+                        Some(Note::from_frequency(frequency).pitch_class() as f32 / 12.0);
+                }
+            } else if settings.chroma.colors {
+                renderer.hue = key.map(|key| key.tonic as f32 / 12.0);
+            } else {
+                renderer.hue = None;
+            }
+        }
+        if let (Some(file), Some(meter)) = (&audio, loudness_meter.as_mut()) {
+            let position = (frame as u64 * file.sample_rate as u64 / args.fps as u64) as usize;
+            file.measure_loudness(meter, loudness_position, position);
+            loudness_position = position;
+            renderer.wave_scale = if settings.loudness.waves {
+                settings.loudness.wave_scale(meter.loudness().momentary)
+            } else {
+                1.0
+            };
+        }
+        renderer.advance(&queue, repeats as f32 / args.fps as f32);
+        let mut command_buffers = renderer.render(&device, target.view());
+        let viewport = renderer.viewport();
+        if settings.chroma.ring {
+            chroma_overlay.push(viewport, &mut text);
+        }
+        logo_overlay.push(viewport, &mut text);
+        command_buffers.extend(text.encode(&device, &queue, target.view(), viewport));
+        let frame_readback = target.read_frame(&device, &queue, command_buffers)?;
+        let pixels = &frame_readback.data;
+        if signals.take_screenshot() {
+            let path = screenshot_path();
+            let rgba = readback::to_rgba8(&frame_readback).unwrap_or_default();
+            match image::save_buffer(&path, &rgba, width, height, image::ColorType::Rgba8) {
+                Ok(()) => log::info!("Saved screenshot to {}", path),
+                Err(e) => log::error!("Failed to save screenshot: {}", e),
+            }
+        }
+        for repeat in 0..repeats {
+            output(frame + repeat, pixels)?;
+        }
+        if repeats > 1 {
+            log::warn!("Rendering fell behind the live input, repeated a frame");
+        }
+
+        frame += repeats;
+        progress.update(frame);
+    }
+    progress.finish(frames, false);
+    Ok(frames)
+}
+
+// Render `--duration` seconds of animation, or the whole --file, into a video
+// file, with the audio file from --file, the microphone as it is captured, or
+// the generated signal with --audible as the soundtrack.
+// A termination signal ends the video early but still leaves a playable
+// file.
+fn record(
+    args: &Args,
+    output: &Path,
+    config: &Config,
+    shader_dir: Option<&Path>,
+    outline: Option<Vec<Vertex>>,
+    signals: &Signals,
+) -> Result<(), Box<dyn Error>> {
+    let duration = args.duration.unwrap_or(DEFAULT_DURATION);
+    if !duration.is_finite() || duration <= 0.0 {
+        return Err("duration must be positive".into());
+    }
+
+    let mut input = args.microphone.then(AudioInput::new).transpose()?;
+    let (width, height) = (config.window.width, config.window.height);
+    // The same signal as render_offscreen generates, handed over frame by
+    // frame like live input
+    let soundtrack = args
+        .generate
+        .filter(|_| args.audible)
+        .map(|signal| signal.generate(duration as f64));
+    let mut recorder = match (&input, &soundtrack) {
+        (Some(input), _) => {
+            let (sample_rate, channels) = input.format();
+            Recorder::with_live_audio(width, height, args.fps, output, sample_rate, channels)?
+        }
+        (None, Some(_)) => {
+            Recorder::with_live_audio(width, height, args.fps, output, generator::SAMPLE_RATE, 1)?
+        }
+        (None, None) => Recorder::new(width, height, args.fps, output, args.file.as_deref())?,
+    };
+    let recording = input.as_ref().map(AudioInput::recording);
+    let rendered = render_offscreen(
+        args,
+        config,
+        shader_dir,
+        outline,
+        input.as_mut(),
+        signals,
+        |frame, pixels| {
+            recorder.write_frame(pixels)?;
+            if let Some(recording) = &recording {
+                recorder.write_audio(&recording.take())?;
+            }
+            if let Some(soundtrack) = &soundtrack {
+                let sample = |frame: u32| {
+                    let sample = frame as u64 * generator::SAMPLE_RATE as u64 / args.fps as u64;
+                    (sample as usize).min(soundtrack.samples.len())
+                };
+                recorder.write_audio(&soundtrack.samples[sample(frame)..sample(frame + 1)])?;
+            }
+            Ok(())
+        },
+    );
+    // Finish the file even if rendering failed part way
+    let finished = recorder.finish();
+    let frames = rendered?;
+    finished?;
+
+    if args.progress == ProgressFormat::Text {
+        println!("Recorded {} frames to {}", frames, output.display());
+    }
+    Ok(())
+}
+
+// Render `--frames` frames as numbered PNG or EXR files in `directory`
+fn render_headless(
+    args: &Args,
+    directory: &Path,
+    config: &Config,
+    shader_dir: Option<&Path>,
+    outline: Option<Vec<Vertex>>,
+    signals: &Signals,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(directory)?;
+    let (width, height) = (config.window.width, config.window.height);
+    let preset = config_path(args)
+        .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "default".to_string());
+    let frames = render_offscreen(
+        args,
+        config,
+        shader_dir,
+        outline,
+        None,
+        signals,
+        |frame, pixels| {
+            let path = directory.join(format!("frame-{:05}.{}", frame, args.format.extension()));
+            match args.format {
+                FrameFormat::Png => {
+                    image::save_buffer(&path, pixels, width, height, image::ColorType::Rgba8)?
+                }
+                FrameFormat::Exr => {
+                    let info = FrameInfo {
+                        frame,
+                        fps: args.fps,
+                        preset: &preset,
+                    };
+                    sequence::write_exr(&path, pixels, width, height, args.exr_bits, &info)?
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    if args.progress == ProgressFormat::Text {
+        println!("Rendered {} frames to {}", frames, directory.display());
+    }
+    Ok(())
+}
+
+// The test signal looping as if it were playing, heard if `audible`
+fn generated(signal: Signal, audible: bool) -> Box<dyn SpectrumSource> {
+    let file = signal.generate(generator::PERIOD);
+    let frames = file.frames();
+    let mut playback = if audible {
+        Playback::new(file)
+    } else {
+        Playback::silent(file)
+    };
+    playback.set_loop(Some((0, frames)));
+    Box::new(playback)
+}
+
+fn parse_resolution(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or("expected WIDTHxHEIGHT, e.g. 1920x1080")?;
+    let parse = |n: &str| {
+        n.parse::<u32>()
+            .ok()
+            .filter(|&n| n >= 1)
+            .ok_or(format!("invalid size {:?}, need a positive number", n))
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+fn parse_grid(value: &str) -> Result<(u32, u32), String> {
+    let (width, depth) = value
+        .split_once('x')
+        .ok_or("expected WIDTHxDEPTH, e.g. 200x150")?;
+    let parse = |n: &str| {
+        n.parse::<u32>().ok().filter(|&n| n >= 2).ok_or(format!(
+            "invalid grid size {:?}, need a number of at least 2",
+            n
+        ))
+    };
+    Ok((parse(width)?, parse(depth)?))
+}
+
+// A plain ratio or WIDTH:HEIGHT
+fn parse_aspect(value: &str) -> Result<f32, String> {
+    let aspect = match value.split_once(':') {
+        Some((width, height)) => width
+            .parse::<f32>()
+            .ok()
+            .zip(height.parse::<f32>().ok())
+            .map(|(width, height)| width / height),
+        None => value.parse::<f32>().ok(),
+    };
+    aspect
+        .filter(|aspect| aspect.is_finite() && *aspect > 0.0)
+        .ok_or(format!(
+            "invalid aspect ratio {:?}, expected e.g. 2.39 or 16:9",
+            value
+        ))
+}
+
+fn parse_overscan(value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|overscan| (0.0..0.5).contains(overscan))
+        .ok_or(format!(
+            "invalid overscan {:?}, expected a fraction from 0 to below 0.5",
+            value
+        ))
+}
+
+fn parse_msaa(value: &str) -> Result<u32, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|samples| matches!(samples, 1 | 2 | 4 | 8 | 16))
+        .ok_or(format!(
+            "invalid sample count {:?}, expected 1, 2, 4, 8 or 16",
+            value
+        ))
+}
+
+fn parse_speed(value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|speed| (MIN_SPEED..=MAX_SPEED).contains(speed))
+        .ok_or(format!(
+            "invalid speed {:?}, expected a factor from {} to {}",
+            value, MIN_SPEED, MAX_SPEED
+        ))
+}
+
+fn main() {
+    let mut args = Args::parse();
+    if let Some(Command::Render(render)) = args.command.take() {
+        render.apply(&mut args);
+    }
+    init_logging(args.daemon);
+    if let Some(path) = &args.analyze_telemetry {
+        analyze_telemetry(path);
+        return;
+    }
+    let mut telemetry = args.telemetry.clone().map(Telemetry::new);
+    let signals = Signals::install().unwrap_or_else(|e| {
+        eprintln!("Failed to install signal handlers: {}", e);
+        std::process::exit(1);
+    });
+
+    let config_path = config_path(&args);
+    let mut config = match &config_path {
+        Some(path) => Config::load(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    args.override_config(&mut config);
+
+    // Like the config, an explicit --shaders must exist
+    let shader_dir = args.shaders.clone().or_else(|| {
+        let default = PathBuf::from(DEFAULT_SHADERS);
+        default
+            .join(shaders::GRID_SHADER)
+            .exists()
+            .then_some(default)
+    });
+    let outline = args.svg.as_ref().map(|path| {
+        sideharso::svg::load_outline(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+
+    if let Some(output) = &args.record {
+        if let Err(e) = record(
+            &args,
+            output,
+            &config,
+            shader_dir.as_deref(),
+            outline,
+            &signals,
+        ) {
+            eprintln!("Recording failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(directory) = &args.headless {
+        if let Err(e) = render_headless(
+            &args,
+            directory,
+            &config,
+            shader_dir.as_deref(),
+            outline,
+            &signals,
+        ) {
+            eprintln!("Headless rendering failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let watcher = config_path
+        .as_deref()
+        .and_then(|path| match ConfigWatcher::new(path) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                log::warn!("Not watching {} for changes: {}", path.display(), e);
+                None
+            }
+        });
+
+    let control = control_server(&args);
+
+    let audio: Option<Box<dyn SpectrumSource>> = match (args.microphone, &args.file) {
+        (true, _) => match AudioInput::new() {
+            Ok(input) => Some(Box::new(input)),
+            Err(e) => {
+                eprintln!("Failed to open microphone: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (false, Some(path)) if playlist::is_playlist(path) => {
+            match playlist::tracks(path).and_then(|tracks| Playlist::new(tracks, &config.playlist))
+            {
+                Ok(playlist) => Some(Box::new(playlist)),
+                Err(e) => {
+                    eprintln!("Failed to play {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        (false, Some(path)) => match AudioFile::open(path) {
+            Ok(file) => Some(Box::new(Playback::new(file))),
+            Err(e) => {
+                eprintln!("Failed to load {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        (false, None) => args.generate.map(|signal| generated(signal, args.audible)),
+    };
+
+    let video: Option<Box<dyn FrameSource>> = match (args.webcam, &args.screen) {
+        (true, _) => match Webcam::new(0) {
+            Ok(webcam) => Some(Box::new(webcam)),
+            Err(e) => {
+                eprintln!("Failed to open webcam: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (false, Some(target)) => {
+            match ScreenCapture::new(CaptureTarget::parse(target), args.screen_edges) {
+                Ok(capture) => Some(Box::new(capture)),
+                Err(e) => {
+                    eprintln!("Failed to capture {}: {}", target, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        (false, None) => None,
+    };
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("Waveform Visualization")
+        .with_inner_size(winit::dpi::PhysicalSize::new(
+            config.window.width,
+            config.window.height,
+        ))
+        .with_fullscreen(fullscreen(config.window.fullscreen))
+        .build(&event_loop)
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to open a window: {}", e);
+            std::process::exit(1);
+        });
+
+    let mut state =
+        pollster::block_on(State::new(&window, audio, &config.window)).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize the GPU: {}", e);
+            std::process::exit(1);
+        });
+    state.config_path = config_path;
+    state.load_config(&window, &args, config);
+    state.renderer.clock.set_speed(args.speed);
+
+    let shader_watcher = shader_dir.map(|dir| {
+        let watcher = ShaderWatcher::new(&dir).unwrap_or_else(|e| {
+            eprintln!("Failed to watch shaders in {}: {}", dir.display(), e);
+            std::process::exit(1);
+        });
+        match watcher.load() {
+            Ok(source) => state.set_shader_source(watcher.path(), &source),
+            Err(e) => log::error!("Failed to read {}: {}", watcher.path().display(), e),
+        }
+        watcher
+    });
+
+    if let Some(outline) = outline {
+        state.renderer.set_mesh(&state.device, &outline);
+    }
+
+    if video.is_some() {
+        state.renderer.heightfield.video_amount = VIDEO_AMOUNT;
+        state.video = video;
+    }
+
+    if args.daemon {
+        if let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Ready]) {
+            log::warn!("Failed to notify systemd: {}", e);
+        }
+    }
+
+    let mut mouse_look = false;
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            ref event,
+            window_id,
+        } if window_id == window.id() && !state.input(event) => match event {
+            // Escape releases the mouse first and quits on the second press
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } if mouse_look => mouse_look = set_mouse_look(&window, false),
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if !mouse_look => mouse_look = set_mouse_look(&window, true),
+            WindowEvent::Focused(false) if mouse_look => {
+                mouse_look = set_mouse_look(&window, false);
+            }
+            WindowEvent::Resized(physical_size) => {
+                state.resize(*physical_size);
+            }
+            WindowEvent::DroppedFile(path) => state.open_dropped(&window, &args, path),
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                state.resize(**new_inner_size);
+            }
+            _ => {}
+        },
+        Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } if mouse_look || state.panning => {
+            let (dx, dy) = (delta.0 as f32, delta.1 as f32);
+            if state.panning {
+                state.renderer.camera.pan(dx, dy);
+            } else {
+                state.renderer.camera.look(dx, dy);
+            }
+        }
+        Event::RedrawRequested(window_id) if window_id == window.id() => {
+            state.update();
+            state.run_panel(&window);
+            let result = state.render();
+            state.session.record_frame(result.is_err());
+            if let Some(telemetry) = telemetry.as_mut() {
+                let flags = if result.is_err() {
+                    telemetry::FLAG_SURFACE_ERROR
+                } else {
+                    0
+                };
+                telemetry.record_frame(flags);
+            }
+            match result {
+                Ok(_) => {}
+                Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+                Err(wgpu::SurfaceError::OutOfMemory) if !state.reduce_quality() => {
+                    log::error!("Out of GPU memory at the lowest quality");
+                    *control_flow = ControlFlow::Exit;
+                }
+                Err(wgpu::SurfaceError::OutOfMemory) => {}
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+        Event::MainEventsCleared => {
+            if signals.shutdown_requested() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            if let Some(result) = watcher.as_ref().and_then(ConfigWatcher::poll) {
+                let result = result.map(|mut config| {
+                    args.override_config(&mut config);
+                    config
+                });
+                state.reload(&window, &args, result);
+            }
+            if signals.take_reload() {
+                state.reload(&window, &args, reload_config(&args));
+            }
+            if state.schedule.poll() {
+                state.apply_schedule(&window, &args);
+            }
+            for trigger in state.events.take_fired() {
+                state.fire_trigger(&window, &args, &trigger);
+            }
+            if let Some(path) = state.audio.as_mut().and_then(|audio| audio.track_change()) {
+                state.track_changed(&window, &args, &path);
+            }
+            if signals.take_screenshot() {
+                state.screenshot_requested = true;
+            }
+            if std::mem::take(&mut state.paste_requested) {
+                state.paste_preset(&window, &args);
+            }
+            while let Some(command) = control.as_ref().and_then(ControlServer::poll) {
+                match command {
+                    ControlCommand::Reload => state.reload(&window, &args, reload_config(&args)),
+                    ControlCommand::Screenshot => state.screenshot_requested = true,
+                    ControlCommand::Mutate => state.mutate(),
+                    ControlCommand::Undo => state.undo_mutation(),
+                    ControlCommand::Palette => state.cycle_palette(),
+                    ControlCommand::Qr => {
+                        let overlay = &mut state.qr_overlay;
+                        overlay.visible = !overlay.visible;
+                    }
+                    ControlCommand::Quit => {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                }
+            }
+            if let Some(watcher) = &shader_watcher {
+                if let Some(source) = watcher.poll() {
+                    state.set_shader_source(watcher.path(), &source);
+                }
+            }
+            // Anything that failed to allocate must be replaced before it is
+            // drawn with
+            if state.budget.take_out_of_memory() && !state.reduce_quality() {
+                log::error!("Out of GPU memory at the lowest quality");
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            window.request_redraw();
+        }
+        Event::LoopDestroyed => {
+            if args.daemon {
+                let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Stopping]);
+            }
+            let xruns = state.audio.as_ref().map_or(0, |audio| audio.xruns());
+            let summary = state.session.summary(xruns);
+            state.shutdown();
+            if let Some(telemetry) = telemetry.as_mut() {
+                telemetry.flush();
+            }
+            if args.daemon {
+                for line in summary.to_string().lines() {
+                    log::info!("{}", line);
+                }
+            } else {
+                println!("{}", summary);
+            }
+            if let Some(path) = &args.session_report {
+                if let Err(e) = std::fs::write(path, summary.to_json()) {
+                    log::error!("Failed to write session report {}: {}", path.display(), e);
+                }
+            }
+        }
+        _ => {}
+    });
+}
+
+// Grab and hide the cursor for mouse look, or release it. Returns whether
+// mouse look is now active.
+fn set_mouse_look(window: &Window, enabled: bool) -> bool {
+    if !enabled {
+        if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("Failed to release cursor: {}", e);
+        }
+        window.set_cursor_visible(true);
+        return false;
+    }
+
+    // Not every platform supports both grab modes
+    let grabbed = window
+        .set_cursor_grab(CursorGrabMode::Locked)
+        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+    match grabbed {
+        Ok(()) => {
+            window.set_cursor_visible(false);
+            true
+        }
+        Err(e) => {
+            log::warn!("Failed to capture cursor: {}", e);
+            false
+        }
+    }
+}
+
+fn fullscreen(enabled: bool) -> Option<Fullscreen> {
+    enabled.then_some(Fullscreen::Borderless(None))
+}
+
+// A black frame, drawn while the output is scheduled off
+fn clear_pass(device: &wgpu::Device, view: &wgpu::TextureView) -> wgpu::CommandBuffer {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Clear Encoder"),
+    });
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Clear Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    encoder.finish()
+}
+
+// An explicit present mode is used if supported. Otherwise vsync maps to
+// FIFO, which is always supported, and no vsync prefers mailbox and then
+// immediate presentation.
+fn present_mode(supported: &[wgpu::PresentMode], window: &WindowConfig) -> wgpu::PresentMode {
+    let requested = window.present_mode.map(wgpu::PresentMode::from);
+    if let Some(mode) = requested.filter(|mode| !supported.contains(mode)) {
+        log::warn!("Present mode {:?} not supported, falling back", mode);
+    }
+    let preferred: &[wgpu::PresentMode] = if window.vsync {
+        &[]
+    } else {
+        &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+    };
+    requested
+        .iter()
+        .chain(preferred)
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
+// Highest supported sample count up to the requested one
+fn sample_count(supported: &[u32], requested: u32) -> u32 {
+    let count = supported
+        .iter()
+        .copied()
+        .filter(|&count| count <= requested)
+        .max()
+        .unwrap_or(1);
+    if count != requested {
+        log::warn!(
+            "{}x MSAA not supported, using {}x instead",
+            requested,
+            count
+        );
+    }
+    count
+}
+
+fn analyze_telemetry(path: &Path) {
+    match telemetry::read_log(path) {
+        Ok(records) => println!("{}", telemetry::summarize(&records)),
+        Err(e) => {
+            eprintln!("Failed to read telemetry from {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Journald reads a syslog priority prefix on each line and timestamps the
+// lines itself. A daemon logs at info level unless RUST_LOG says otherwise.
+fn init_logging(daemon: bool) {
+    use std::io::Write;
+
+    if !daemon {
+        console::init(env_logger::Builder::from_default_env().build());
+        return;
+    }
+    let logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+            .format(|buf, record| {
+                let priority = match record.level() {
+                    log::Level::Error => 3,
+                    log::Level::Warn => 4,
+                    log::Level::Info => 6,
+                    log::Level::Debug | log::Level::Trace => 7,
+                };
+                writeln!(buf, "<{}>{}: {}", priority, record.target(), record.args())
+            })
+            .build();
+    console::init(logger);
+}
+
+// A socket passed by systemd takes precedence over --control
+fn control_server(args: &Args) -> Option<ControlServer> {
+    #[cfg(unix)]
+    if let Some(server) = ControlServer::from_systemd() {
+        return Some(server);
+    }
+    let address = args.control.as_ref()?;
+    match ControlServer::bind(address) {
+        Ok(server) => {
+            log::info!("Listening for control commands on {}", address);
+            Some(server)
+        }
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// An explicit --config must exist; the default one is optional
+fn config_path(args: &Args) -> Option<PathBuf> {
+    args.config.clone().or_else(|| {
+        let default = PathBuf::from(DEFAULT_CONFIG);
+        default.exists().then_some(default)
+    })
+}
+
+// The config to run with while `entry` is in effect: its preset, found
+// relative to the main config, or otherwise the main config itself
+fn scheduled_config(
+    args: &Args,
+    main: &Config,
+    entry: Option<&ScheduleEntry>,
+    toasts: &mut Toasts,
+) -> Config {
+    let Some(preset) = entry.and_then(|entry| entry.preset.as_ref()) else {
+        return main.clone();
+    };
+    load_preset(args, main, &preset_path(args, preset), toasts)
+}
+
+// Where a preset named in the main config is, relative to it
+fn preset_path(args: &Args, preset: &Path) -> PathBuf {
+    match config_path(args).as_deref().and_then(Path::parent) {
+        Some(dir) => dir.join(preset),
+        None => preset.to_path_buf(),
+    }
+}
+
+// The preset at `path` with the command line settings applied, or the main
+// config if it can't be loaded
+fn load_preset(args: &Args, main: &Config, path: &Path, toasts: &mut Toasts) -> Config {
+    match Config::load(path) {
+        Ok(mut config) => {
+            args.override_config(&mut config);
+            log::info!("Switched to preset {}", path.display());
+            toasts.info(format!("Preset {}", path.display()));
+            config
+        }
+        Err(e) => {
+            log::error!("Failed to load preset {}: {}", path.display(), e);
+            toasts.error(format!("Failed to load preset {}: {}", path.display(), e));
+            main.clone()
+        }
+    }
+}
+
+// Load the config file again on SIGHUP. On failure the current settings
+// should be kept, and the error says why.
+fn reload_config(args: &Args) -> Result<Config, String> {
+    let Some(path) = config_path(args) else {
+        log::warn!("No config file to reload");
+        return Err("No config file to reload".to_string());
+    };
+    match Config::load(&path) {
+        Ok(mut config) => {
+            args.override_config(&mut config);
+            log::info!("Reloaded {}", path.display());
+            Ok(config)
+        }
+        Err(e) => {
+            log::error!("Failed to reload {}: {}", path.display(), e);
+            Err(format!("Failed to reload {}: {}", path.display(), e))
+        }
+    }
+}
+
+// Seed for random variations, different on every run
+fn seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+// Timestamped file name in the working directory
+fn screenshot_path() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("screenshot-{}.png", timestamp)
+}
+
+// Encode and write the screenshot off the render thread, returning where it
+// was saved
+fn save_screenshot(readback: Readback) -> JoinHandle<Result<String, String>> {
+    std::thread::spawn(move || {
+        let Some(pixels) = readback::to_rgba8(&readback) else {
+            log::warn!(
+                "Unsupported surface format for screenshots: {:?}",
+                readback.format
+            );
+            return Err(format!(
+                "Unsupported surface format for screenshots: {:?}",
+                readback.format
+            ));
+        };
+        let path = screenshot_path();
+        match image::save_buffer(
+            &path,
+            &pixels,
+            readback.width,
+            readback.height,
+            image::ColorType::Rgba8,
+        ) {
+            Ok(()) => {
+                log::info!("Saved screenshot to {}", path);
+                Ok(path)
+            }
+            Err(e) => {
+                log::error!("Failed to save screenshot: {}", e);
+                Err(format!("Failed to save screenshot: {}", e))
+            }
+        }
+    })
 }
@@ -0,0 +1,248 @@
+use crate::loudness::Loudness;
+use crate::pitch::Note;
+use crate::stereo::Stereo;
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+use std::time::{Duration, Instant};
+
+// How long a note stays up after the pitch is lost, so it doesn't flicker
+// between the notes of a phrase
+const HOLD: Duration = Duration::from_millis(250);
+
+const FONT_SIZE: f32 = 16.0;
+const NOTE_FONT_SIZE: f32 = 32.0;
+// Pixels between the viewport corner, the backdrop and the text
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+
+// Side of the goniometer and height of the correlation bar, in pixels
+const SCOPE_SIZE: f32 = 160.0;
+const BAR_HEIGHT: f32 = 6.0;
+const DOT_SIZE: f32 = 2.0;
+// Loudness bar range in LUFS
+const BAR_MIN: f32 = -60.0;
+const BAR_MAX: f32 = 0.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const AXIS_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.2];
+const DOT_COLOR: [f32; 4] = [0.4, 1.0, 0.6, 0.8];
+// Correlation bar in phase, out of phase and its marker
+const IN_PHASE_COLOR: [f32; 4] = [0.3, 0.8, 0.4, 1.0];
+const OUT_OF_PHASE_COLOR: [f32; 4] = [0.9, 0.3, 0.3, 1.0];
+const LOUDNESS_COLOR: [f32; 4] = [0.4, 0.7, 1.0, 1.0];
+const DIM_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+// Measurements of the audio in the top right corner: the note being played
+// and how far off it is, for use with instruments, the EBU R128 loudness,
+// and below it the stereo image as a goniometer, with mono up the middle,
+// and a correlation bar
+#[derive(Default)]
+pub struct MeterOverlay {
+    pub visible: bool,
+    // The last note detected, its frequency and when
+    note: Option<(Note, f32, Instant)>,
+    loudness: Option<Loudness>,
+    stereo: Option<Stereo>,
+}
+
+impl MeterOverlay {
+    // Take this frame's pitch, if any
+    pub fn set_pitch(&mut self, frequency: Option<f32>) {
+        if let Some(frequency) = frequency {
+            self.note = Some((Note::from_frequency(frequency), frequency, Instant::now()));
+        } else if self
+            .note
+            .is_some_and(|(_, _, detected)| detected.elapsed() > HOLD)
+        {
+            self.note = None;
+        }
+    }
+
+    pub fn set_loudness(&mut self, loudness: Option<Loudness>) {
+        self.loudness = loudness;
+    }
+
+    pub fn set_stereo(&mut self, stereo: Option<Stereo>) {
+        self.stereo = stereo;
+    }
+
+    // The note being played, held briefly through gaps
+    pub fn note(&self) -> Option<Note> {
+        self.note.map(|(note, _, _)| note)
+    }
+
+    // Lay out the meters in `text` for the next draw
+    pub fn push(&self, viewport: Viewport, text: &mut TextRenderer) {
+        if !self.visible {
+            return;
+        }
+        let right = viewport.width as f32 - MARGIN;
+        let bottom = self.push_tuner(right, MARGIN, text);
+        let bottom = self.push_loudness(right, bottom + MARGIN, text);
+        self.push_stereo(right, bottom + MARGIN, text);
+    }
+
+    // The tuner with its top right corner at `right`, `top`, returning
+    // where it ends
+    fn push_tuner(&self, right: f32, top: f32, text: &mut TextRenderer) -> f32 {
+        let (note, detail) = match self.note {
+            Some((note, frequency, _)) => (
+                format!("{}{}", note.name(), note.octave()),
+                format!("{:+.0} cents, {:.1} Hz", note.cents, frequency),
+            ),
+            None => ("-".to_string(), "No pitch".to_string()),
+        };
+        let width = text
+            .measure(&note, NOTE_FONT_SIZE)
+            .max(text.measure(&detail, FONT_SIZE))
+            .max(SCOPE_SIZE);
+        let size = [
+            width + PADDING * 2.0,
+            text.line_height(NOTE_FONT_SIZE) + text.line_height(FONT_SIZE) + PADDING * 2.0,
+        ];
+        let x = right - size[0];
+        text.add_rect([x, top], [right, top + size[1]], BACKDROP_COLOR);
+        let color = if self.note.is_some() {
+            TEXT_COLOR
+        } else {
+            DIM_COLOR
+        };
+        text.add_text(&note, [x + PADDING, top + PADDING], NOTE_FONT_SIZE, color);
+        text.add_text(
+            &detail,
+            [
+                x + PADDING,
+                top + PADDING + text.line_height(NOTE_FONT_SIZE),
+            ],
+            FONT_SIZE,
+            color,
+        );
+        top + size[1]
+    }
+
+    // Momentary and short-term loudness, and a bar of the momentary with a
+    // marker at the short-term, with their top right corner at `right`,
+    // `top`, returning where they end
+    fn push_loudness(&self, right: f32, top: f32, text: &mut TextRenderer) -> f32 {
+        let line_height = text.line_height(FONT_SIZE);
+        let size = [
+            SCOPE_SIZE + PADDING * 2.0,
+            line_height * 2.0 + BAR_HEIGHT + PADDING * 3.0,
+        ];
+        let x = right - size[0];
+        text.add_rect([x, top], [right, top + size[1]], BACKDROP_COLOR);
+        let Some(loudness) = self.loudness else {
+            text.add_text(
+                "No loudness",
+                [x + PADDING, top + PADDING],
+                FONT_SIZE,
+                DIM_COLOR,
+            );
+            return top + size[1];
+        };
+        let format = |lufs: f32| {
+            if lufs.is_finite() {
+                format!("{:.1} LUFS", lufs)
+            } else {
+                "- LUFS".to_string()
+            }
+        };
+        let lines = format!(
+            "M {}\nS {}",
+            format(loudness.momentary),
+            format(loudness.short_term)
+        );
+        text.add_text(&lines, [x + PADDING, top + PADDING], FONT_SIZE, TEXT_COLOR);
+
+        let bar = [x + PADDING, top + PADDING * 2.0 + line_height * 2.0];
+        let along = |lufs: f32| {
+            // Silence, at -inf, clamps to the start
+            bar[0] + ((lufs - BAR_MIN) / (BAR_MAX - BAR_MIN)).clamp(0.0, 1.0) * SCOPE_SIZE
+        };
+        text.add_rect(bar, [bar[0] + SCOPE_SIZE, bar[1] + BAR_HEIGHT], AXIS_COLOR);
+        text.add_rect(
+            bar,
+            [along(loudness.momentary), bar[1] + BAR_HEIGHT],
+            LOUDNESS_COLOR,
+        );
+        let marker = along(loudness.short_term);
+        text.add_rect(
+            [marker - 1.5, bar[1] - 2.0],
+            [marker + 1.5, bar[1] + BAR_HEIGHT + 2.0],
+            TEXT_COLOR,
+        );
+        top + size[1]
+    }
+
+    // The goniometer and correlation bar with their top right corner at
+    // `right`, `top`
+    fn push_stereo(&self, right: f32, top: f32, text: &mut TextRenderer) {
+        let line_height = text.line_height(FONT_SIZE);
+        let size = [
+            SCOPE_SIZE + PADDING * 2.0,
+            SCOPE_SIZE + BAR_HEIGHT + line_height + PADDING * 4.0,
+        ];
+        let x = right - size[0];
+        text.add_rect([x, top], [right, top + size[1]], BACKDROP_COLOR);
+        let scope = [x + PADDING, top + PADDING];
+        let center = [scope[0] + SCOPE_SIZE / 2.0, scope[1] + SCOPE_SIZE / 2.0];
+        text.add_rect(
+            [center[0] - 0.5, scope[1]],
+            [center[0] + 0.5, scope[1] + SCOPE_SIZE],
+            AXIS_COLOR,
+        );
+        text.add_rect(
+            [scope[0], center[1] - 0.5],
+            [scope[0] + SCOPE_SIZE, center[1] + 0.5],
+            AXIS_COLOR,
+        );
+        let bar_top = scope[1] + SCOPE_SIZE + PADDING;
+        let label_top = bar_top + BAR_HEIGHT + PADDING;
+        let Some(stereo) = &self.stereo else {
+            text.add_text("No stereo", [scope[0], label_top], FONT_SIZE, DIM_COLOR);
+            return;
+        };
+
+        // Scaled to the loudest point, so quiet passages fill it too
+        let peak = stereo
+            .points
+            .iter()
+            .map(|[side, mid]| side.abs().max(mid.abs()))
+            .fold(1e-6, f32::max);
+        let scale = SCOPE_SIZE / 2.0 / peak;
+        for [side, mid] in &stereo.points {
+            let point = [center[0] + side * scale, center[1] - mid * scale];
+            text.add_rect(
+                [point[0] - DOT_SIZE / 2.0, point[1] - DOT_SIZE / 2.0],
+                [point[0] + DOT_SIZE / 2.0, point[1] + DOT_SIZE / 2.0],
+                DOT_COLOR,
+            );
+        }
+
+        // Correlation from -1 on the left to 1 on the right
+        let middle = scope[0] + SCOPE_SIZE / 2.0;
+        text.add_rect(
+            [scope[0], bar_top],
+            [middle, bar_top + BAR_HEIGHT],
+            OUT_OF_PHASE_COLOR,
+        );
+        text.add_rect(
+            [middle, bar_top],
+            [scope[0] + SCOPE_SIZE, bar_top + BAR_HEIGHT],
+            IN_PHASE_COLOR,
+        );
+        let marker = middle + stereo.correlation.clamp(-1.0, 1.0) * SCOPE_SIZE / 2.0;
+        text.add_rect(
+            [marker - 1.5, bar_top - 2.0],
+            [marker + 1.5, bar_top + BAR_HEIGHT + 2.0],
+            TEXT_COLOR,
+        );
+        let label = format!(
+            "{:+.2}, width {:.0}%",
+            stereo.correlation,
+            stereo.width * 100.0
+        );
+        text.add_text(&label, [scope[0], label_top], FONT_SIZE, TEXT_COLOR);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::config::Config;
+use std::collections::VecDeque;
+
+// How many mutations can be undone
+const HISTORY_LENGTH: usize = 64;
+
+// Random variations of the current look for exploring the parameter space.
+// Each mutation is derived from its own seed, and the settings it replaced
+// are kept so it can be undone.
+pub struct Mutator {
+    rng: Rng,
+    history: VecDeque<Config>,
+}
+
+impl Mutator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            history: VecDeque::new(),
+        }
+    }
+
+    // A variation of `config` within its mutate ranges, and the seed it was
+    // made from. `config` is remembered for back().
+    pub fn mutate(&mut self, config: &Config) -> (u64, Config) {
+        let seed = self.rng.next_u64();
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(config.clone());
+        (seed, mutate(config, seed))
+    }
+
+    // The settings before the last mutation
+    pub fn back(&mut self) -> Option<Config> {
+        self.history.pop_back()
+    }
+}
+
+// The same seed always gives the same variation of a config
+pub fn mutate(config: &Config, seed: u64) -> Config {
+    let ranges = &config.mutate;
+    let strength = ranges.strength;
+    let mut rng = Rng::new(seed);
+    let mut config = config.clone();
+
+    let waves = &mut config.waves;
+    waves.amplitude = rng.perturb(waves.amplitude, ranges.amplitude, strength);
+    waves.noise_amplitude = rng.perturb(waves.noise_amplitude, ranges.noise_amplitude, strength);
+    let octaves = ranges.noise_octaves.map(|n| n as f32);
+    waves.noise_octaves = rng
+        .perturb(waves.noise_octaves as f32, octaves, strength)
+        .round() as u32;
+    if rng.chance(strength) {
+        waves.function = waves.function.next();
+    }
+    if rng.chance(strength) {
+        waves.noise = !waves.noise;
+    }
+
+    if ranges.colors {
+        let colors = &mut config.colors;
+        for channel in colors.major[..3].iter_mut().chain(&mut colors.minor[..3]) {
+            *channel = rng.perturb(*channel, [0.0, 1.0], strength);
+        }
+    }
+
+    config
+}
+
+// SplitMix64. Plenty for picking parameters, and seeds stay reproducible
+// across platforms and versions.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Uniform in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    // Move `value` by up to `strength` times the width of `range` either way,
+    // staying inside the range
+    pub fn perturb(&mut self, value: f32, [min, max]: [f32; 2], strength: f32) -> f32 {
+        let offset = (self.next_f32() * 2.0 - 1.0) * strength * (max - min);
+        (value + offset).clamp(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_matches_the_splitmix64_reference() {
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+    }
+
+    #[test]
+    fn rng_helpers_stay_in_their_ranges() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&rng.next_f32()));
+            assert!(rng.below(5) < 5);
+            assert!((0.5..=1.5).contains(&rng.perturb(1.0, [0.5, 1.5], 2.0)));
+        }
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+        assert_eq!(rng.perturb(0.3, [0.0, 1.0], 0.0), 0.3);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_variation() {
+        let config = Config::default();
+        assert_eq!(mutate(&config, 42), mutate(&config, 42));
+        assert_ne!(mutate(&config, 42), mutate(&config, 43));
+
+        let (mut first, mut second) = (Mutator::new(1), Mutator::new(1));
+        for _ in 0..3 {
+            assert_eq!(first.mutate(&config), second.mutate(&config));
+        }
+        let (seed, mutated) = Mutator::new(1).mutate(&config);
+        assert_eq!(mutate(&config, seed), mutated);
+    }
+
+    #[test]
+    fn mutations_stay_within_the_ranges() {
+        let mut config = Config::default();
+        config.mutate.strength = 1.0;
+        config.mutate.colors = false;
+        for seed in 0..100 {
+            let mutated = mutate(&config, seed);
+            let waves = &mutated.waves;
+            let ranges = &config.mutate;
+            assert!((ranges.amplitude[0]..=ranges.amplitude[1]).contains(&waves.amplitude));
+            assert!((ranges.noise_amplitude[0]..=ranges.noise_amplitude[1])
+                .contains(&waves.noise_amplitude));
+            assert!(
+                (ranges.noise_octaves[0]..=ranges.noise_octaves[1]).contains(&waves.noise_octaves)
+            );
+            assert_eq!(mutated.colors, config.colors);
+        }
+    }
+
+    #[test]
+    fn back_undoes_mutations_in_reverse_order() {
+        let mut mutator = Mutator::new(9);
+        let original = Config::default();
+        let (_, first) = mutator.mutate(&original);
+        let (_, second) = mutator.mutate(&first);
+        mutator.mutate(&second);
+        assert_eq!(mutator.back(), Some(second));
+        assert_eq!(mutator.back(), Some(first));
+        assert_eq!(mutator.back(), Some(original));
+        assert_eq!(mutator.back(), None);
+    }
+
+    #[test]
+    fn only_the_latest_mutations_can_be_undone() {
+        let mut mutator = Mutator::new(3);
+        let mut config = Config::default();
+        for _ in 0..HISTORY_LENGTH + 10 {
+            config = mutator.mutate(&config).1;
+        }
+        let undone = std::iter::from_fn(|| mutator.back()).count();
+        assert_eq!(undone, HISTORY_LENGTH);
+    }
+}
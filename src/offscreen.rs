@@ -0,0 +1,99 @@
+use crate::readback::{Readback, ReadbackPool, ReadbackPurpose};
+
+// Formats of offscreen targets, for 8-bit output and for linear floating
+// point output that keeps colors brighter than white; create the renderer
+// with the one the target uses
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+pub const OFFSCREEN_HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Render target for rendering without a window. Each frame is read back
+// before the next one is drawn, so nothing is dropped however long a frame
+// takes.
+pub struct OffscreenTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readback_pool: ReadbackPool,
+}
+
+impl OffscreenTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            readback_pool: ReadbackPool::new(),
+        }
+    }
+
+    // Where the frame should be drawn
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format()
+    }
+
+    // Submit the passes drawing a frame into the target and wait for it to be
+    // copied back, as tightly packed rows in the target's format. The copy
+    // goes through a readback pool, so its buffer is reused from frame to
+    // frame.
+    pub fn read_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mut command_buffers: Vec<wgpu::CommandBuffer>,
+    ) -> Result<Readback, wgpu::BufferAsyncError> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Readback Encoder"),
+        });
+        self.readback_pool.copy_texture(
+            device,
+            &mut encoder,
+            &self.texture,
+            ReadbackPurpose::Export,
+        );
+        command_buffers.push(encoder.finish());
+        queue.submit(command_buffers);
+        self.readback_pool.submitted();
+
+        // Mapping callbacks run during the wait, so the poll below finds the
+        // copy finished, or dropped if mapping failed
+        device.poll(wgpu::Maintain::Wait);
+        self.readback_pool
+            .poll(device)
+            .into_iter()
+            .find(|readback| readback.purpose == ReadbackPurpose::Export)
+            .ok_or(wgpu::BufferAsyncError)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::playback::Playback;
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+
+const FONT_SIZE: f32 = 14.0;
+// Pixels between the viewport edges and the strip, and its height
+const MARGIN: f32 = 8.0;
+const HEIGHT: f32 = 48.0;
+const PADDING: f32 = 4.0;
+// Pixels per waveform bar
+const COLUMN: f32 = 2.0;
+const PLAYHEAD_WIDTH: f32 = 2.0;
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const PLAYED_COLOR: [f32; 4] = [0.4, 0.8, 1.0, 0.9];
+const UNPLAYED_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.7];
+const PLAYHEAD_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const LOOP_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.25];
+const LOOP_START_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+
+// Waveform of the whole track being played, along the bottom of the
+// picture, with the play position and any loop marked. Clicking the strip
+// seeks there.
+pub struct Overview {
+    pub visible: bool,
+}
+
+impl Default for Overview {
+    fn default() -> Self {
+        Self { visible: true }
+    }
+}
+
+impl Overview {
+    // Corners of the strip within the viewport
+    fn bounds(viewport: Viewport) -> ([f32; 2], [f32; 2]) {
+        let bottom = viewport.height as f32 - MARGIN;
+        (
+            [MARGIN, bottom - HEIGHT],
+            [viewport.width as f32 - MARGIN, bottom],
+        )
+    }
+
+    // Fraction of the way through the track at `position`, in viewport
+    // pixels, or None if it is off the strip
+    pub fn hit(&self, viewport: Viewport, position: [f32; 2]) -> Option<f32> {
+        let (min, max) = Self::bounds(viewport);
+        let inside =
+            (min[0]..=max[0]).contains(&position[0]) && (min[1]..=max[1]).contains(&position[1]);
+        (self.visible && inside).then(|| (position[0] - min[0]) / (max[0] - min[0]))
+    }
+
+    // Lay out the strip for `playback` in `text` for the next draw
+    pub fn push(&self, playback: &Playback, viewport: Viewport, text: &mut TextRenderer) {
+        if !self.visible || playback.frames() == 0 {
+            return;
+        }
+        let (min, max) = Self::bounds(viewport);
+        text.add_rect(min, max, BACKDROP_COLOR);
+
+        let width = max[0] - min[0];
+        let x_at = |frame: usize| min[0] + width * frame as f32 / playback.frames() as f32;
+        if let Some((start, end)) = playback.loop_region() {
+            text.add_rect([x_at(start), min[1]], [x_at(end), max[1]], LOOP_COLOR);
+        }
+        if let Some(start) = playback.loop_start() {
+            let x = x_at(start).round();
+            text.add_rect([x, min[1]], [x + 1.0, max[1]], LOOP_START_COLOR);
+        }
+        let middle = (min[1] + max[1]) / 2.0;
+        let half_height = HEIGHT / 2.0 - PADDING;
+        let played = playback.position() as f32 / playback.frames() as f32;
+        let peaks = playback.peaks();
+        let columns = (width / COLUMN) as usize;
+        for column in 0..columns {
+            // Loudest of the peaks under the column
+            let start = column * peaks.len() / columns;
+            let end = ((column + 1) * peaks.len() / columns).max(start + 1);
+            let peak = peaks[start..end].iter().copied().fold(0.0, f32::max);
+            let extent = (peak.min(1.0) * half_height).max(0.5);
+            let x = min[0] + column as f32 * COLUMN;
+            let color = if (column as f32 + 0.5) / (columns as f32) < played {
+                PLAYED_COLOR
+            } else {
+                UNPLAYED_COLOR
+            };
+            text.add_rect(
+                [x, middle - extent],
+                [x + COLUMN - 0.5, middle + extent],
+                color,
+            );
+        }
+
+        let x = (min[0] + width * played - PLAYHEAD_WIDTH / 2.0).round();
+        text.add_rect([x, min[1]], [x + PLAYHEAD_WIDTH, max[1]], PLAYHEAD_COLOR);
+
+        let rate = playback.sample_rate() as f32;
+        let label = format!(
+            "{} / {}",
+            format_time(playback.position() as f32 / rate),
+            format_time(playback.frames() as f32 / rate)
+        );
+        let label_y = min[1] - text.line_height(FONT_SIZE);
+        text.add_text(&label, [min[0], label_y], FONT_SIZE, TEXT_COLOR);
+    }
+}
+
+// Minutes and seconds, e.g. 3:07
+pub fn format_time(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
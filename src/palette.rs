@@ -0,0 +1,130 @@
+// Color stops per palette; shorter palettes repeat their last stop
+pub const PALETTE_STOPS: usize = 8;
+
+// Distance from the camera, in grid units, at the far end of a palette
+// applied by distance
+pub const PALETTE_DISTANCE: f32 = 6.0;
+
+// Line color themes. Flat uses the major and minor colors from the config;
+// the others blend through a gradient by height or distance. Viridis and
+// cividis rise steadily in lightness and avoid red against green, so they
+// read the same with the common kinds of color blindness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+    Flat,
+    Synthwave,
+    Matrix,
+    Grayscale,
+    Heatmap,
+    Viridis,
+    Cividis,
+}
+
+impl Palette {
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Flat => Palette::Synthwave,
+            Palette::Synthwave => Palette::Matrix,
+            Palette::Matrix => Palette::Grayscale,
+            Palette::Grayscale => Palette::Heatmap,
+            Palette::Heatmap => Palette::Viridis,
+            Palette::Viridis => Palette::Cividis,
+            Palette::Cividis => Palette::Flat,
+        }
+    }
+
+    // Gradient from low to high (or near to far), None for flat colors
+    fn stops(self) -> Option<&'static [[f32; 3]]> {
+        match self {
+            Palette::Flat => None,
+            Palette::Synthwave => Some(&[
+                [0.12, 0.0, 0.3],
+                [0.55, 0.0, 0.65],
+                [1.0, 0.1, 0.75],
+                [1.0, 0.45, 0.85],
+                [0.1, 0.9, 1.0],
+            ]),
+            Palette::Matrix => Some(&[[0.0, 0.12, 0.0], [0.0, 0.55, 0.1], [0.6, 1.0, 0.6]]),
+            Palette::Grayscale => Some(&[[0.08, 0.08, 0.08], [1.0, 1.0, 1.0]]),
+            Palette::Heatmap => Some(&[
+                [0.05, 0.0, 0.0],
+                [0.7, 0.0, 0.0],
+                [1.0, 0.45, 0.0],
+                [1.0, 0.9, 0.1],
+                [1.0, 1.0, 1.0],
+            ]),
+            // After matplotlib's, as linear colors
+            Palette::Viridis => Some(&[
+                [0.058, 0.0, 0.089],
+                [0.061, 0.033, 0.209],
+                [0.037, 0.107, 0.266],
+                [0.02, 0.212, 0.27],
+                [0.014, 0.356, 0.242],
+                [0.068, 0.533, 0.153],
+                [0.352, 0.701, 0.041],
+                [0.982, 0.799, 0.019],
+            ]),
+            Palette::Cividis => Some(&[
+                [0.0, 0.016, 0.076],
+                [0.017, 0.048, 0.15],
+                [0.074, 0.091, 0.147],
+                [0.147, 0.153, 0.171],
+                [0.254, 0.242, 0.191],
+                [0.413, 0.361, 0.159],
+                [0.638, 0.515, 0.107],
+                [0.991, 0.807, 0.04],
+            ]),
+        }
+    }
+
+    // Stops padded to PALETTE_STOPS for the uniform block
+    pub fn colors(self) -> Option<[[f32; 4]; PALETTE_STOPS]> {
+        let stops = self.stops()?;
+        let mut colors = [[0.0; 4]; PALETTE_STOPS];
+        for (i, color) in colors.iter_mut().enumerate() {
+            let [r, g, b] = stops[i.min(stops.len() - 1)];
+            *color = [r, g, b, 1.0];
+        }
+        Some(colors)
+    }
+
+    // Index of the last real stop; the shader spreads 0..1 over the stops
+    // up to here
+    pub fn last_stop(self) -> u32 {
+        self.stops().map_or(0, |stops| stops.len() as u32 - 1)
+    }
+}
+
+// Scale `color` up until its brightest channel is at least `min`, keeping
+// its hue, at full opacity. Black becomes a grey of that brightness.
+pub fn lift(color: [f32; 4], min: f32) -> [f32; 4] {
+    let [r, g, b, _] = color;
+    let brightest = r.max(g).max(b);
+    if brightest <= 0.0 {
+        return [min, min, min, 1.0];
+    }
+    let scale = (min / brightest).max(1.0);
+    [r * scale, g * scale, b * scale, 1.0]
+}
+
+// `color` turned to `hue`, 0 red through 1 red again, fully saturated and
+// as bright as its brightest channel
+pub fn with_hue(color: [f32; 4], hue: f32) -> [f32; 4] {
+    let brightness = color[0].max(color[1]).max(color[2]);
+    let channel = |offset: f32| {
+        let k = (offset + hue.rem_euclid(1.0) * 6.0) % 6.0;
+        brightness * (1.0 - (k.min(4.0 - k).clamp(0.0, 1.0)))
+    };
+    [channel(5.0), channel(3.0), channel(1.0), color[3]]
+}
+
+// What picks the position along a palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaletteInput {
+    // Wave height, troughs at the start and crests at the end
+    Height = 0,
+    // Distance from the camera, near at the start
+    Distance = 1,
+}
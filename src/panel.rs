@@ -0,0 +1,248 @@
+use crate::config::Config;
+use crate::grid::GridShape;
+use crate::palette::PaletteInput;
+use crate::renderer::WaveformRenderer;
+use crate::ui_painter::{ScreenDescriptor, UiPainter};
+use crate::uniforms::FogMode;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+// Range of the grid density sliders, in lines
+const MAX_GRID_LINES: u32 = 400;
+
+// Live control panel drawn with egui over the frame. Sliders edit a copy of
+// the settings, which the app applies like a config change; mode toggles go
+// straight to the renderer.
+pub struct Panel {
+    pub visible: bool,
+    context: egui::Context,
+    input: egui_winit::State,
+    painter: UiPainter,
+    // Tessellated output of the last run, painted by `encode`
+    output: Option<(Vec<egui::ClippedPrimitive>, egui::TexturesDelta)>,
+}
+
+impl Panel {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, window: &Window) -> Self {
+        let mut input = egui_winit::State::new(window);
+        input.set_pixels_per_point(egui_winit::native_pixels_per_point(window));
+        input.set_max_texture_side(device.limits().max_texture_dimension_2d as usize);
+
+        Self {
+            visible: false,
+            context: egui::Context::default(),
+            input,
+            painter: UiPainter::new(device, format),
+            output: None,
+        }
+    }
+
+    // Feed a window event to the panel. Returns whether the panel used it,
+    // in which case the app should ignore it.
+    pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let response = self.input.on_event(&self.context, event);
+        match event {
+            // egui claims every key press; only keep them while typing
+            WindowEvent::KeyboardInput { .. } => self.context.wants_keyboard_input(),
+            _ => response.consumed,
+        }
+    }
+
+    // Lay out the panel for this frame, returning the edited settings if
+    // any of them changed
+    pub fn run(
+        &mut self,
+        window: &Window,
+        device: &wgpu::Device,
+        renderer: &mut WaveformRenderer,
+        settings: &Config,
+    ) -> Option<Config> {
+        if !self.visible {
+            return None;
+        }
+
+        let mut edited = settings.clone();
+        let raw_input = self.input.take_egui_input(window);
+        let output = self.context.run(raw_input, |context| {
+            egui::Window::new("Controls")
+                .default_pos([16.0, 16.0])
+                .resizable(false)
+                .show(context, |ui| controls(ui, device, renderer, &mut edited));
+        });
+        self.input
+            .handle_platform_output(window, &self.context, output.platform_output);
+        let primitives = self.context.tessellate(output.shapes);
+        self.output = Some((primitives, output.textures_delta));
+
+        (edited != *settings).then_some(edited)
+    }
+
+    // Paint the panel laid out by the last run over `target`
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        size_in_pixels: [u32; 2],
+    ) -> Option<wgpu::CommandBuffer> {
+        let (primitives, textures) = self.output.take()?;
+        let screen = ScreenDescriptor {
+            size_in_pixels,
+            pixels_per_point: self.context.pixels_per_point(),
+        };
+        Some(
+            self.painter
+                .paint(device, queue, target, screen, &primitives, &textures),
+        )
+    }
+}
+
+fn controls(
+    ui: &mut egui::Ui,
+    device: &wgpu::Device,
+    renderer: &mut WaveformRenderer,
+    settings: &mut Config,
+) {
+    ui.heading("Waves");
+    let waves = &mut settings.waves;
+    ui.add(egui::Slider::new(&mut waves.amplitude, 0.0..=3.0).text("Amplitude"));
+    ui.add(egui::Slider::new(&mut waves.frequency, 0.1..=4.0).text("Frequency"));
+    ui.add(egui::Slider::new(&mut waves.speed, 0.0..=4.0).text("Speed"));
+    if ui
+        .button(format!("Function: {:?}", waves.function))
+        .clicked()
+    {
+        waves.function = waves.function.next();
+    }
+    ui.checkbox(&mut waves.noise, "Noise");
+    ui.add_enabled(
+        waves.noise,
+        egui::Slider::new(&mut waves.noise_amplitude, 0.0..=1.0).text("Noise amplitude"),
+    );
+    ui.add_enabled(
+        waves.noise,
+        egui::Slider::new(&mut waves.noise_octaves, 1..=8).text("Noise octaves"),
+    );
+
+    ui.separator();
+    ui.heading("Grid");
+    let grid = &mut settings.grid;
+    ui.add(egui::Slider::new(&mut grid.width, 2..=MAX_GRID_LINES).text("Lines across"));
+    ui.add(egui::Slider::new(&mut grid.depth, 2..=MAX_GRID_LINES).text("Lines along"));
+    ui.horizontal(|ui| {
+        ui.color_edit_button_rgba_unmultiplied(&mut settings.colors.major);
+        ui.label("Major lines");
+        ui.color_edit_button_rgba_unmultiplied(&mut settings.colors.minor);
+        ui.label("Minor lines");
+    });
+    ui.horizontal(|ui| {
+        let colors = &mut settings.colors;
+        if ui
+            .button(format!("Palette: {:?}", colors.palette))
+            .clicked()
+        {
+            colors.palette = colors.palette.next();
+        }
+        ui.radio_value(&mut colors.palette_by, PaletteInput::Height, "By height");
+        ui.radio_value(
+            &mut colors.palette_by,
+            PaletteInput::Distance,
+            "By distance",
+        );
+    });
+
+    ui.horizontal(|ui| {
+        let fog = &mut settings.fog;
+        if ui.button(format!("Fog: {:?}", fog.mode)).clicked() {
+            fog.mode = fog.mode.next();
+        }
+        ui.color_edit_button_rgba_unmultiplied(&mut fog.color);
+    });
+    let fog = &mut settings.fog;
+    let linear = fog.mode == FogMode::Linear;
+    let exponential = fog.mode != FogMode::Off && !linear;
+    let max_end = fog.end.max(20.0);
+    ui.add_enabled(
+        fog.mode != FogMode::Off,
+        egui::Slider::new(&mut fog.start, 0.0..=fog.end - 0.1).text("Fog start"),
+    );
+    ui.add_enabled(
+        linear,
+        egui::Slider::new(&mut fog.end, fog.start + 0.1..=max_end).text("Fog end"),
+    );
+    ui.add_enabled(
+        exponential,
+        egui::Slider::new(&mut fog.density, 0.0..=3.0).text("Fog density"),
+    );
+
+    ui.separator();
+    ui.heading("Audio");
+    let audio = &mut settings.audio;
+    ui.add(egui::Slider::new(&mut audio.gain, -24.0..=24.0).text("Input gain (dB)"));
+    ui.add(egui::Slider::new(&mut audio.gate, -100.0..=-20.0).text("Gate (dB)"));
+    ui.add(egui::Slider::new(&mut audio.threshold, -60.0..=0.0).text("Compressor threshold (dB)"));
+    ui.add(egui::Slider::new(&mut audio.ratio, 1.0..=20.0).text("Compressor ratio"));
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.pitch.colors, "Color by pitch");
+        ui.checkbox(&mut settings.chroma.colors, "Color by key");
+        ui.checkbox(&mut settings.chroma.ring, "Chroma ring");
+    });
+    ui.checkbox(&mut settings.loudness.waves, "Waves follow loudness");
+
+    ui.separator();
+    ui.heading("Rendering");
+    let accessibility = &mut settings.accessibility;
+    ui.add(egui::Slider::new(&mut accessibility.motion, 0.0..=1.0).text("Motion"));
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut accessibility.high_contrast, "High contrast");
+        ui.checkbox(&mut accessibility.reduce_flashes, "Reduce flashes");
+    });
+    ui.horizontal(|ui| {
+        if ui.button(format!("{:?}", renderer.render_mode())).clicked() {
+            let mode = renderer.render_mode().next();
+            renderer.set_render_mode(device, mode);
+        }
+        let grid = &mut settings.grid;
+        if ui.button(format!("{:?}", grid.shape)).clicked() {
+            grid.shape = match grid.shape {
+                GridShape::Cartesian => GridShape::Polar,
+                GridShape::Polar => GridShape::Cartesian,
+            };
+        }
+        if ui.button(format!("{:?}", renderer.line_style)).clicked() {
+            renderer.line_style = renderer.line_style.next();
+        }
+        let window = &mut settings.window;
+        if ui.button(format!("{:?}", window.anti_aliasing)).clicked() {
+            window.anti_aliasing = window.anti_aliasing.next();
+        }
+    });
+    let bloom = &mut settings.bloom;
+    ui.checkbox(&mut bloom.enabled, "Bloom");
+    ui.add_enabled(
+        bloom.enabled,
+        egui::Slider::new(&mut bloom.intensity, 0.0..=3.0).text("Glow intensity"),
+    );
+    ui.add_enabled(
+        bloom.enabled,
+        egui::Slider::new(&mut bloom.threshold, 0.0..=1.5).text("Glow threshold"),
+    );
+    ui.add_enabled(
+        bloom.enabled,
+        egui::Slider::new(&mut bloom.radius, 0.5..=6.0).text("Glow radius"),
+    );
+    let exposure = &mut settings.exposure;
+    ui.checkbox(&mut exposure.auto, "Auto exposure");
+    ui.add_enabled(
+        exposure.auto,
+        egui::Slider::new(&mut exposure.key, 0.05..=1.5).text("Exposure key"),
+    );
+
+    ui.separator();
+    if ui.button("Reset camera").clicked() {
+        renderer.reset_camera(&settings.camera, settings.world.scale);
+    }
+}
@@ -0,0 +1,145 @@
+// Range of fundamentals tracked, from below a bass guitar's low E to the top
+// of most lead instruments
+const MIN_FREQUENCY: f32 = 50.0;
+const MAX_FREQUENCY: f32 = 2000.0;
+// Largest normalized difference accepted as periodic; higher means noisier
+// input is given a pitch too
+const THRESHOLD: f32 = 0.15;
+// Windows below this RMS level in dBFS have no pitch
+const MIN_LEVEL: f32 = -60.0;
+
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+// Fundamental frequency of a monophonic signal with the YIN method, or None
+// if it is too quiet or not periodic enough to tell
+pub fn detect(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32;
+    if 10.0 * (power + 1e-12).log10() < MIN_LEVEL {
+        return None;
+    }
+    let min_lag = (sample_rate as f32 / MAX_FREQUENCY) as usize;
+    let max_lag = ((sample_rate as f32 / MIN_FREQUENCY) as usize).min(samples.len() / 2);
+    if min_lag + 2 >= max_lag {
+        return None;
+    }
+    let width = samples.len() - max_lag;
+
+    // Cumulative mean normalized difference for each lag
+    let mut differences = vec![1.0; max_lag + 1];
+    let mut sum = 0.0;
+    for lag in 1..=max_lag {
+        let difference: f32 = (0..width)
+            .map(|i| {
+                let delta = samples[i] - samples[i + lag];
+                delta * delta
+            })
+            .sum();
+        sum += difference;
+        differences[lag] = if sum > 0.0 {
+            difference * lag as f32 / sum
+        } else {
+            1.0
+        };
+    }
+
+    // The first dip below the threshold, followed down to its bottom
+    let mut lag = (min_lag..max_lag).find(|&lag| differences[lag] < THRESHOLD)?;
+    while lag + 1 < max_lag && differences[lag + 1] < differences[lag] {
+        lag += 1;
+    }
+    // Between samples by fitting a parabola through the bottom
+    let (before, at, after) = (differences[lag - 1], differences[lag], differences[lag + 1]);
+    let curvature = before - 2.0 * at + after;
+    let offset = if curvature > 0.0 {
+        (before - after) / (2.0 * curvature)
+    } else {
+        0.0
+    };
+    Some(sample_rate as f32 / (lag as f32 + offset))
+}
+
+// Nearest equal-tempered note to a frequency, with A4 at 440 Hz
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Note {
+    // MIDI note number, 69 for A4
+    pub number: i32,
+    // How far the frequency is above the note, in hundredths of a semitone
+    pub cents: f32,
+}
+
+impl Note {
+    pub fn from_frequency(frequency: f32) -> Self {
+        let semitones = 69.0 + 12.0 * (frequency / 440.0).log2();
+        let number = semitones.round();
+        Self {
+            number: number as i32,
+            cents: (semitones - number) * 100.0,
+        }
+    }
+
+    // 0 for C up to 11 for B
+    pub fn pitch_class(&self) -> usize {
+        self.number.rem_euclid(12) as usize
+    }
+
+    pub fn octave(&self) -> i32 {
+        self.number.div_euclid(12) - 1
+    }
+
+    pub fn name(&self) -> &'static str {
+        NOTE_NAMES[self.pitch_class()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frequency: f32, sample_rate: u32) -> Vec<f32> {
+        (0..2048)
+            .map(|i| {
+                let phase = std::f32::consts::TAU * frequency * i as f32 / sample_rate as f32;
+                // With overtones, like an instrument
+                0.5 * phase.sin() + 0.3 * (2.0 * phase).sin() + 0.2 * (3.0 * phase).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_fundamental_under_overtones() {
+        for frequency in [82.4, 196.0, 440.0, 1318.5] {
+            let detected = detect(&tone(frequency, 48000), 48000).unwrap();
+            assert!(
+                (detected / frequency - 1.0).abs() < 0.005,
+                "{} Hz detected as {}",
+                frequency,
+                detected
+            );
+        }
+    }
+
+    #[test]
+    fn noise_and_silence_have_no_pitch() {
+        assert_eq!(detect(&[0.0; 2048], 48000), None);
+        // Deterministic white noise
+        let mut state = 1u32;
+        let noise: Vec<f32> = (0..2048)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect();
+        assert_eq!(detect(&noise, 48000), None);
+    }
+
+    #[test]
+    fn notes_are_named_with_their_octave_and_cents() {
+        let note = Note::from_frequency(440.0);
+        assert_eq!((note.name(), note.octave(), note.cents), ("A", 4, 0.0));
+        let note = Note::from_frequency(261.63 * 1.01);
+        assert_eq!((note.name(), note.octave()), ("C", 4));
+        assert!((note.cents - 17.2).abs() < 0.1);
+    }
+}
@@ -0,0 +1,711 @@
+use crate::audio::{FixedRate, SpectrumAnalyzer, SpectrumSource, ANALYSIS_RATE, FFT_SIZE};
+use crate::config::AudioConfig;
+use crate::loudness::{self, Loudness, LoudnessMeter};
+use crate::resample::resample;
+use crate::stereo::{self, Stereo};
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+#[cfg(feature = "playback")]
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "playback")]
+use std::sync::Mutex;
+use std::time::Instant;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// Fully decoded audio, interleaved f32 samples
+pub struct AudioFile {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+impl AudioFile {
+    // Decode a WAV, FLAC, MP3 or Ogg Vorbis file into memory
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let mut format = symphonia::default::get_probe()
+            .format(
+                &hint,
+                source,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )?
+            .format;
+        let track = format.default_track().ok_or("no audio track")?;
+        let track_id = track.id;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or("unknown sample rate")?;
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        let mut samples = Vec::new();
+        let mut channels = 1;
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    break
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            // Skip corrupt packets rather than giving up on the whole file
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(DecodeError::DecodeError(e)) => {
+                    log::warn!("Skipping undecodable packet: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let spec = *decoded.spec();
+            channels = spec.channels.count();
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buffer.samples());
+        }
+
+        Ok(Self {
+            samples,
+            channels,
+            sample_rate,
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    // Loudest absolute sample of the mono mix in each of `buckets` equal
+    // spans of the file, for drawing its waveform
+    pub fn peaks(&self, buckets: usize) -> Vec<f32> {
+        let frames = self.frames();
+        (0..buckets)
+            .map(|bucket| {
+                let start = bucket * frames / buckets;
+                let end = ((bucket + 1) * frames / buckets).max(start + 1).min(frames);
+                self.samples[start * self.channels..end * self.channels]
+                    .chunks(self.channels)
+                    .map(|frame| (frame.iter().sum::<f32>() / self.channels as f32).abs())
+                    .fold(0.0, f32::max)
+            })
+            .collect()
+    }
+
+    // Mono mix of the FFT window ending at `end`, in frames, at the
+    // analysis rate
+    pub fn window(&self, end: usize) -> Vec<f32> {
+        let end = end.min(self.frames());
+        // Enough frames for a full window after conversion, plus some for
+        // the resampler to settle
+        let frames = FFT_SIZE * self.sample_rate as usize / ANALYSIS_RATE as usize + WINDOW_MARGIN;
+        let start = end.saturating_sub(frames);
+        let mono: Vec<f32> = self.samples[start * self.channels..end * self.channels]
+            .chunks(self.channels)
+            .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+            .collect();
+        let mut window = resample(&mono, self.sample_rate, ANALYSIS_RATE);
+        window.drain(..window.len().saturating_sub(FFT_SIZE));
+        window
+    }
+
+    // Stereo image of the analysis window ending at frame `end`, None for
+    // mono files
+    pub fn stereo(&self, end: usize) -> Option<Stereo> {
+        let end = end.min(self.frames());
+        let start =
+            end.saturating_sub(FFT_SIZE * self.sample_rate as usize / ANALYSIS_RATE as usize);
+        stereo::measure(
+            &self.samples[start * self.channels..end * self.channels],
+            self.channels,
+        )
+    }
+
+    // Feed `meter` the frames from `from` up to `to`. After a jump back, or
+    // further ahead than the meter looks, it starts over with the frames it
+    // looks at before `to`.
+    pub fn measure_loudness(&self, meter: &mut LoudnessMeter, from: usize, to: usize) {
+        let to = to.min(self.frames());
+        let span = (loudness::SHORT_TERM * self.sample_rate as f64) as usize;
+        let from = if from > to || to - from > span {
+            meter.reset();
+            to.saturating_sub(span)
+        } else {
+            from
+        };
+        meter.process(&self.samples[from * self.channels..to * self.channels]);
+    }
+}
+
+// Frames read before an analysis window, for the resampler to settle
+const WINDOW_MARGIN: usize = 256;
+
+// Peaks kept for the overview of the whole track
+const OVERVIEW_PEAKS: usize = 2048;
+
+// A decoded file with its overview, ready to play. Working out the peaks
+// takes a while for long files, so playlists prepare the next track on
+// another thread.
+pub struct Track {
+    file: Arc<AudioFile>,
+    peaks: Vec<f32>,
+}
+
+impl Track {
+    pub fn new(file: AudioFile) -> Self {
+        Self {
+            peaks: file.peaks(OVERVIEW_PEAKS),
+            file: Arc::new(file),
+        }
+    }
+}
+
+// Stands for no seek pending in the output's seek request
+#[cfg(feature = "playback")]
+const NO_SEEK: usize = usize::MAX;
+
+// Plays a decoded file and analyzes the window at the current play position
+pub struct Playback {
+    file: Arc<AudioFile>,
+    analyzer: SpectrumAnalyzer,
+    fixed_rate: Option<FixedRate>,
+    peaks: Vec<f32>,
+    // Without an output device, the position is `offset` frames at `start`
+    // plus the wall clock time since
+    start: Instant,
+    offset: usize,
+    // Frames between which playback repeats, and a start marked for the
+    // next one
+    loop_region: Option<(usize, usize)>,
+    loop_start: Option<usize>,
+    // Track to carry on with at the end, and the frames it fades in over
+    next: Option<(Track, usize)>,
+    finished: bool,
+    // Measures the frames played, up to the frame it has been fed
+    loudness_meter: LoudnessMeter,
+    loudness_position: usize,
+    #[cfg(feature = "playback")]
+    output: Option<Output>,
+}
+
+#[cfg(feature = "playback")]
+struct Output {
+    _stream: cpal::Stream,
+    // Frame handed to the output device next
+    frames_played: Arc<AtomicUsize>,
+    // Frame to continue from, taken by the output callback; NO_SEEK if none
+    seek: Arc<AtomicUsize>,
+    // Start and end frame of the loop, the same when not looping
+    loop_region: Arc<[AtomicUsize; 2]>,
+    // Track queued to follow and its fade, only read by the callback
+    next: Arc<Mutex<Option<(Arc<AudioFile>, usize)>>>,
+    // Counts the callback moving on to a queued track, and the count seen
+    track_changes: Arc<AtomicUsize>,
+    tracks_seen: usize,
+    xruns: Arc<AtomicU64>,
+}
+
+impl Playback {
+    pub fn new(file: AudioFile) -> Self {
+        Self::from_track(Track::new(file))
+    }
+
+    pub fn from_track(track: Track) -> Self {
+        Self::start(track, true)
+    }
+
+    // Analyze `file` as if it were playing, without an output device
+    pub fn silent(file: AudioFile) -> Self {
+        Self::start(Track::new(file), false)
+    }
+
+    fn start(track: Track, audible: bool) -> Self {
+        let Track { file, peaks } = track;
+        log::info!(
+            "Playing {:.1} s of audio at {} Hz",
+            file.frames() as f32 / file.sample_rate as f32,
+            file.sample_rate
+        );
+
+        #[cfg(feature = "playback")]
+        let output = match audible.then(|| start_output(&file)) {
+            Some(Ok(output)) => Some(output),
+            Some(Err(e)) => {
+                log::warn!("Audio output unavailable, visualizing silently: {}", e);
+                None
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "playback"))]
+        if audible {
+            log::warn!("Built without the `playback` feature, visualizing silently");
+        }
+
+        Self {
+            analyzer: SpectrumAnalyzer::new(),
+            fixed_rate: None,
+            peaks,
+            loudness_meter: LoudnessMeter::new(file.sample_rate, file.channels),
+            loudness_position: 0,
+            file,
+            start: Instant::now(),
+            offset: 0,
+            loop_region: None,
+            loop_start: None,
+            next: None,
+            finished: false,
+            #[cfg(feature = "playback")]
+            output,
+        }
+    }
+
+    // Current frame, following the output device when there is one and the
+    // wall clock otherwise
+    pub fn position(&self) -> usize {
+        #[cfg(feature = "playback")]
+        if let Some(output) = &self.output {
+            return match output.seek.load(Ordering::Relaxed) {
+                NO_SEEK => output.frames_played.load(Ordering::Relaxed),
+                seek => seek,
+            };
+        }
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let played = (elapsed * self.file.sample_rate as f64) as usize;
+        let position = self.offset + played;
+        match self.loop_region {
+            // Looping once the end is reached from before it
+            Some((start, end)) if self.offset <= end && position >= end => {
+                start + (position - end) % (end - start)
+            }
+            _ => position.min(self.file.frames()),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position() >= self.file.frames()
+    }
+
+    // Length of the file in frames
+    pub fn frames(&self) -> usize {
+        self.file.frames()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.file.sample_rate
+    }
+
+    // Loudest sample in each of a fixed number of equal spans of the file
+    pub fn peaks(&self) -> &[f32] {
+        &self.peaks
+    }
+
+    // Continue playing from `frame`, clamped to the end of the file
+    pub fn seek(&mut self, frame: usize) {
+        let frame = frame.min(self.file.frames());
+        self.finished = false;
+        #[cfg(feature = "playback")]
+        if let Some(output) = &self.output {
+            output.seek.store(frame, Ordering::Relaxed);
+            return;
+        }
+        self.start = Instant::now();
+        self.offset = frame;
+    }
+
+    // Frames between which playback repeats, if it does
+    pub fn loop_region(&self) -> Option<(usize, usize)> {
+        self.loop_region
+    }
+
+    // Start of the next loop, marked but waiting for its end
+    pub fn loop_start(&self) -> Option<usize> {
+        self.loop_start
+    }
+
+    // A-B repeat at the play position: the first call marks the start of a
+    // loop, the second its end, after which the section between repeats,
+    // and the third stops looping
+    pub fn mark_loop(&mut self) {
+        let position = self.position();
+        match (self.loop_start.take(), self.loop_region) {
+            (_, Some(_)) => self.set_loop(None),
+            (None, None) => self.loop_start = Some(position),
+            (Some(start), None) => {
+                let region = (start.min(position), start.max(position));
+                self.set_loop(Some(region).filter(|(start, end)| start < end));
+            }
+        }
+    }
+
+    // Repeat the frames between a start and an end, or play on with None
+    pub fn set_loop(&mut self, region: Option<(usize, usize)>) {
+        let frames = self.file.frames();
+        let region = region
+            .map(|(start, end)| (start.min(frames), end.min(frames)))
+            .filter(|(start, end)| start < end);
+        #[cfg(feature = "playback")]
+        if let Some(output) = &self.output {
+            let (start, end) = region.unwrap_or_default();
+            // Collapsed first, so the callback never sees a half set region
+            output.loop_region[1].store(0, Ordering::Relaxed);
+            output.loop_region[0].store(start, Ordering::Relaxed);
+            output.loop_region[1].store(end, Ordering::Relaxed);
+        }
+        // The wall clock position is measured from here on
+        self.offset = self.position();
+        self.start = Instant::now();
+        self.loop_region = region;
+    }
+
+    // Seek `seconds` forwards or backwards from the current position
+    pub fn skip(&mut self, seconds: f32) {
+        let frames = (seconds.abs() * self.file.sample_rate as f32) as usize;
+        let position = self.position();
+        self.seek(if seconds < 0.0 {
+            position.saturating_sub(frames)
+        } else {
+            position + frames
+        });
+    }
+
+    // Carry on with `track` without a gap at the end of this one, fading
+    // between them over up to `crossfade` seconds. The output stream can
+    // only continue with the same channels and sample rate; other tracks
+    // are handed back, to be played anew once this one finishes.
+    pub fn queue(&mut self, track: Track, crossfade: f32) -> Result<(), Track> {
+        let fade = (crossfade * self.file.sample_rate as f32) as usize;
+        let fade = fade
+            .min(self.file.frames() / 2)
+            .min(track.file.frames() / 2);
+        #[cfg(feature = "playback")]
+        if let Some(output) = &self.output {
+            if track.file.channels != self.file.channels
+                || track.file.sample_rate != self.file.sample_rate
+            {
+                return Err(track);
+            }
+            *output.next.lock().unwrap() = Some((track.file.clone(), fade));
+        }
+        self.next = Some((track, fade));
+        Ok(())
+    }
+
+    // Switch over to the queued track once playback has moved on to it.
+    // Returns whether it did.
+    pub fn advance(&mut self) -> bool {
+        if self.next.is_none() || !self.reached_next() {
+            return false;
+        }
+        let Some((Track { file, peaks }, _)) = self.next.take() else {
+            return false;
+        };
+        // Queued tracks have the same format, so the meter carries on
+        self.loudness_position = 0;
+        self.file = file;
+        self.peaks = peaks;
+        self.loop_region = None;
+        self.loop_start = None;
+        self.finished = false;
+        true
+    }
+
+    // Whether the output callback, or the wall clock, has passed the end of
+    // the file into the queued track
+    fn reached_next(&mut self) -> bool {
+        #[cfg(feature = "playback")]
+        if let Some(output) = &mut self.output {
+            let changes = output.track_changes.load(Ordering::Relaxed);
+            if changes == output.tracks_seen {
+                return false;
+            }
+            output.tracks_seen = changes;
+            output.next.lock().unwrap().take();
+            return true;
+        }
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let position = self.offset + (elapsed * self.file.sample_rate as f64) as usize;
+        if self.loop_region.is_some() || position < self.file.frames() {
+            return false;
+        }
+        // Past the fade, as the output would be
+        let fade = self.next.as_ref().map_or(0, |(_, fade)| *fade);
+        self.offset = fade + position - self.file.frames();
+        self.start = Instant::now();
+        true
+    }
+}
+
+impl SpectrumSource for Playback {
+    fn playback(&mut self) -> Option<&mut Playback> {
+        Some(self)
+    }
+
+    fn spectrum(&mut self) -> &[f32] {
+        if self.is_finished() && !self.finished {
+            self.finished = true;
+            log::info!("Playback finished");
+        }
+
+        let position = self.position();
+        let Some(fixed_rate) = &mut self.fixed_rate else {
+            let window = self.file.window(position);
+            return self.analyzer.process(&window);
+        };
+        let (file, rate) = (&self.file, self.file.sample_rate as u64);
+        fixed_rate.process(
+            &mut self.analyzer,
+            position as u64 * ANALYSIS_RATE as u64 / rate,
+            |end| file.window((end * rate / ANALYSIS_RATE as u64) as usize),
+        )
+    }
+
+    fn set_dynamics(&mut self, settings: &AudioConfig) {
+        self.analyzer.set_dynamics(settings);
+        FixedRate::configure(&mut self.fixed_rate, settings);
+    }
+
+    fn analyzer(&mut self) -> Option<&mut SpectrumAnalyzer> {
+        Some(&mut self.analyzer)
+    }
+
+    fn stereo(&mut self) -> Option<Stereo> {
+        self.file.stereo(self.position())
+    }
+
+    fn loudness(&mut self) -> Option<Loudness> {
+        let position = self.position();
+        self.file
+            .measure_loudness(&mut self.loudness_meter, self.loudness_position, position);
+        self.loudness_position = position;
+        Some(self.loudness_meter.loudness())
+    }
+
+    #[cfg(feature = "playback")]
+    fn xruns(&self) -> u64 {
+        self.output
+            .as_ref()
+            .map_or(0, |output| output.xruns.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(feature = "playback")]
+fn start_output(file: &Arc<AudioFile>) -> Result<Output, Box<dyn Error>> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("no audio output device available")?;
+    let config = cpal::StreamConfig {
+        channels: file.channels as u16,
+        sample_rate: cpal::SampleRate(file.sample_rate),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    let frames_played = Arc::new(AtomicUsize::new(0));
+    let seek = Arc::new(AtomicUsize::new(NO_SEEK));
+    let loop_region = Arc::new([AtomicUsize::new(0), AtomicUsize::new(0)]);
+    let next = Arc::new(Mutex::new(None));
+    let track_changes = Arc::new(AtomicUsize::new(0));
+    let mut file = file.clone();
+    let counter = frames_played.clone();
+    let seek_request = seek.clone();
+    let region = loop_region.clone();
+    let queued = next.clone();
+    let changes = track_changes.clone();
+    let xruns = Arc::new(AtomicU64::new(0));
+    let errors = xruns.clone();
+    let stream = device.build_output_stream(
+        &config,
+        move |data: &mut [f32], _| {
+            let seek = seek_request.swap(NO_SEEK, Ordering::Relaxed);
+            if seek != NO_SEEK {
+                counter.store(seek, Ordering::Relaxed);
+            }
+            let loop_region = region.each_ref().map(|frame| frame.load(Ordering::Relaxed));
+            let position = counter.load(Ordering::Relaxed);
+            // Never waits on the lock; the track is picked up next time.
+            // Once playing it, it stays queued until the change is seen.
+            let next: Option<(Arc<AudioFile>, usize)> = queued
+                .try_lock()
+                .ok()
+                .and_then(|next| next.clone())
+                .filter(|(next, _)| !Arc::ptr_eq(next, &file));
+            let next_fill = next.as_ref().map(|(next, fade)| (&**next, *fade));
+            let (position, switched) = fill(
+                &file,
+                next_fill,
+                position,
+                (loop_region[0], loop_region[1]),
+                data,
+            );
+            if let Some((next, _)) = next.filter(|_| switched) {
+                file = next;
+                region[1].store(0, Ordering::Relaxed);
+                changes.fetch_add(1, Ordering::Relaxed);
+            }
+            counter.store(position, Ordering::Relaxed);
+        },
+        move |e| {
+            errors.fetch_add(1, Ordering::Relaxed);
+            log::error!("Audio output error: {}", e);
+        },
+        None,
+    )?;
+    stream.play()?;
+
+    Ok(Output {
+        _stream: stream,
+        frames_played,
+        seek,
+        loop_region,
+        next,
+        track_changes,
+        tracks_seen: 0,
+        xruns,
+    })
+}
+
+// Copy interleaved samples from `position` into `data`, jumping back to the
+// start of `region` each time its end is reached. Over the last `fade`
+// frames of the file any `next` file fades in, and playback carries on into
+// it at the end; without one the rest is silence. Returns the frame to
+// continue from, and whether that is in the next file.
+#[cfg_attr(not(feature = "playback"), allow(dead_code))]
+fn fill(
+    file: &AudioFile,
+    mut next: Option<(&AudioFile, usize)>,
+    mut position: usize,
+    region: (usize, usize),
+    data: &mut [f32],
+) -> (usize, bool) {
+    let (loop_start, loop_end) = region;
+    let mut looping = loop_start < loop_end;
+    let mut file = file;
+    let mut switched = false;
+    for frame in data.chunks_mut(file.channels) {
+        if looping && position == loop_end {
+            position = loop_start;
+        }
+        if position >= file.frames() {
+            if let Some((incoming, fade)) = next.take() {
+                file = incoming;
+                position = fade;
+                switched = true;
+                looping = false;
+            }
+        }
+        // Frames into the fade, unless held back by the loop
+        let fade = next
+            .filter(|(_, fade)| *fade > 0 && !(looping && position < loop_end))
+            .and_then(|(incoming, fade)| {
+                let into = (position + fade).checked_sub(file.frames())?;
+                Some((incoming, into, into as f32 / fade as f32))
+            });
+        for (channel, out) in frame.iter_mut().enumerate() {
+            let sample = sample_at(file, position, channel);
+            *out = match fade {
+                Some((incoming, into, t)) => {
+                    sample * (1.0 - t) + sample_at(incoming, into, channel) * t
+                }
+                None => sample,
+            };
+        }
+        if position < file.frames() {
+            position += 1;
+        }
+    }
+    (position, switched)
+}
+
+// Sample of `channel` at `frame`, silent past the end
+fn sample_at(file: &AudioFile, frame: usize, channel: usize) -> f32 {
+    file.samples
+        .get(frame * file.channels + channel)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo_ramp(frames: usize) -> AudioFile {
+        AudioFile {
+            samples: (0..frames).flat_map(|i| [i as f32, -(i as f32)]).collect(),
+            channels: 2,
+            sample_rate: 48000,
+        }
+    }
+
+    #[test]
+    fn fill_wraps_at_the_loop_end() {
+        let file = stereo_ramp(10);
+        let mut data = [0.0; 10];
+        let (position, _) = fill(&file, None, 3, (2, 5), &mut data);
+        let left: Vec<f32> = data.iter().step_by(2).copied().collect();
+        assert_eq!(left, [3.0, 4.0, 2.0, 3.0, 4.0]);
+        assert_eq!(position, 5);
+        // Picked up at the end by the next call
+        let (position, _) = fill(&file, None, position, (2, 5), &mut data[..2]);
+        assert_eq!(data[0], 2.0);
+        assert_eq!(position, 3);
+    }
+
+    #[test]
+    fn fill_pads_past_the_end_and_plays_on_after_a_loop() {
+        let file = stereo_ramp(4);
+        let mut data = [1.0; 8];
+        // Past the loop, so it plays to the end of the file
+        let (position, _) = fill(&file, None, 3, (0, 2), &mut data);
+        assert_eq!(data, [3.0, -3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(position, 4);
+    }
+
+    #[test]
+    fn fill_crossfades_into_the_next_file() {
+        let file = stereo_ramp(4);
+        let next = AudioFile {
+            samples: vec![10.0; 8],
+            channels: 2,
+            sample_rate: 48000,
+        };
+        let mut data = [0.0; 8];
+        let (position, switched) = fill(&file, Some((&next, 2)), 1, (0, 0), &mut data);
+        let left: Vec<f32> = data.iter().step_by(2).copied().collect();
+        // Frame 2 starts the fade, half way by frame 3, then the next file
+        // from where the fade left off
+        assert_eq!(left, [1.0, 2.0, 3.0 * 0.5 + 10.0 * 0.5, 10.0]);
+        assert!(switched);
+        assert_eq!(position, 3);
+    }
+
+    #[test]
+    fn fill_plays_on_gaplessly_without_a_fade() {
+        let file = stereo_ramp(2);
+        let next = stereo_ramp(3);
+        let mut data = [0.0; 8];
+        let (position, switched) = fill(&file, Some((&next, 0)), 0, (0, 0), &mut data);
+        let left: Vec<f32> = data.iter().step_by(2).copied().collect();
+        assert_eq!(left, [0.0, 1.0, 0.0, 1.0]);
+        assert!(switched);
+        assert_eq!(position, 2);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::audio::{SpectrumAnalyzer, SpectrumSource};
+use crate::config::{AudioConfig, PlaylistConfig};
+use crate::loudness::Loudness;
+use crate::playback::{AudioFile, Playback, Track};
+use crate::stereo::Stereo;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+// Files picked up from a directory, by extension
+const AUDIO_EXTENSIONS: [&str; 5] = ["wav", "flac", "mp3", "ogg", "oga"];
+
+// Whether `path` names several tracks rather than one file: a directory or
+// an M3U playlist
+pub fn is_playlist(path: &Path) -> bool {
+    path.is_dir() || has_extension(path, &["m3u", "m3u8"])
+}
+
+// The audio files in a directory in name order, or the entries of an M3U
+// playlist, resolved relative to it
+pub fn tracks(path: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let tracks = if path.is_dir() {
+        let mut tracks = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_file() && has_extension(&path, &AUDIO_EXTENSIONS) {
+                tracks.push(path);
+            }
+        }
+        tracks.sort();
+        tracks
+    } else {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        parse_m3u(&std::fs::read_to_string(path)?)
+            .into_iter()
+            .map(|entry| dir.join(entry))
+            .collect()
+    };
+    if tracks.is_empty() {
+        return Err(format!("no tracks in {}", path.display()).into());
+    }
+    Ok(tracks)
+}
+
+// Entries of an M3U playlist; lines starting with # are comments or
+// extended M3U tags
+fn parse_m3u(contents: &str) -> Vec<PathBuf> {
+    contents
+        .lines()
+        .map(|line| line.trim().trim_start_matches('\u{feff}'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect()
+}
+
+// Whether `path` names an audio file that can be played, by its extension
+pub fn is_audio_file(path: &Path) -> bool {
+    has_extension(path, &AUDIO_EXTENSIONS)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+        })
+}
+
+// Plays tracks one after another. Each next track is decoded on another
+// thread while the current one plays, then queued to follow it gaplessly,
+// fading in over the configured crossfade.
+pub struct Playlist {
+    tracks: Vec<PathBuf>,
+    config: PlaylistConfig,
+    playback: Playback,
+    // The next track while it is decoded, and once queued
+    loading: Option<(usize, JoinHandle<Result<Track, String>>)>,
+    queued: Option<usize>,
+    // A next track the output can't carry on with, started anew once the
+    // current one finishes
+    waiting: Option<(usize, Track)>,
+    // Track started since the last look
+    changed: Option<PathBuf>,
+    dynamics: AudioConfig,
+}
+
+impl Playlist {
+    pub fn new(tracks: Vec<PathBuf>, config: &PlaylistConfig) -> Result<Self, Box<dyn Error>> {
+        log::info!("Playlist of {} tracks", tracks.len());
+        // Starting from the first track that can be decoded
+        let (index, file) = tracks
+            .iter()
+            .enumerate()
+            .find_map(|(index, path)| match AudioFile::open(path) {
+                Ok(file) => Some((index, file)),
+                Err(e) => {
+                    log::warn!("Skipping {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .ok_or("no playable tracks")?;
+        let mut playlist = Self {
+            tracks,
+            config: config.clone(),
+            playback: Playback::new(file),
+            loading: None,
+            queued: None,
+            waiting: None,
+            changed: None,
+            dynamics: AudioConfig::default(),
+        };
+        playlist.started(index);
+        Ok(playlist)
+    }
+
+    // Track to follow the one at `index`
+    fn next_index(&self, index: usize) -> Option<usize> {
+        if index + 1 < self.tracks.len() {
+            Some(index + 1)
+        } else {
+            self.config.repeat.then_some(0)
+        }
+    }
+
+    fn started(&mut self, index: usize) {
+        let path = &self.tracks[index];
+        log::info!(
+            "Track {}/{}: {}",
+            index + 1,
+            self.tracks.len(),
+            path.display()
+        );
+        self.changed = Some(path.clone());
+        self.load(self.next_index(index));
+    }
+
+    // Decode the track at `index`, if any, in the background
+    fn load(&mut self, index: Option<usize>) {
+        self.loading = index.map(|index| {
+            let path = self.tracks[index].clone();
+            let handle = std::thread::spawn(move || {
+                AudioFile::open(&path)
+                    .map(Track::new)
+                    .map_err(|e| e.to_string())
+            });
+            (index, handle)
+        });
+    }
+
+    // Queue the next track once decoded, and follow playback on to it
+    fn poll(&mut self) {
+        if self
+            .loading
+            .as_ref()
+            .is_some_and(|(_, handle)| handle.is_finished())
+        {
+            if let Some((index, handle)) = self.loading.take() {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err("decoder panicked".to_string()));
+                match result {
+                    Ok(track) => match self.playback.queue(track, self.config.crossfade) {
+                        Ok(()) => self.queued = Some(index),
+                        Err(track) => self.waiting = Some((index, track)),
+                    },
+                    Err(e) => {
+                        log::warn!("Skipping {}: {}", self.tracks[index].display(), e);
+                        self.load(self.next_index(index));
+                    }
+                }
+            }
+        }
+
+        if self.playback.advance() {
+            if let Some(index) = self.queued.take() {
+                self.started(index);
+            }
+        }
+        if self.waiting.is_some() && self.playback.is_finished() {
+            if let Some((index, track)) = self.waiting.take() {
+                self.playback = Playback::from_track(track);
+                self.playback.set_dynamics(&self.dynamics);
+                self.started(index);
+            }
+        }
+    }
+}
+
+impl SpectrumSource for Playlist {
+    fn spectrum(&mut self) -> &[f32] {
+        self.poll();
+        self.playback.spectrum()
+    }
+
+    fn xruns(&self) -> u64 {
+        self.playback.xruns()
+    }
+
+    fn playback(&mut self) -> Option<&mut Playback> {
+        Some(&mut self.playback)
+    }
+
+    fn track_change(&mut self) -> Option<PathBuf> {
+        self.changed.take()
+    }
+
+    fn set_dynamics(&mut self, settings: &AudioConfig) {
+        self.dynamics = settings.clone();
+        self.playback.set_dynamics(settings);
+    }
+
+    fn analyzer(&mut self) -> Option<&mut SpectrumAnalyzer> {
+        self.playback.analyzer()
+    }
+
+    fn stereo(&mut self) -> Option<Stereo> {
+        self.playback.stereo()
+    }
+
+    fn loudness(&mut self) -> Option<Loudness> {
+        self.playback.loudness()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_skips_comments_and_blank_lines() {
+        let playlist =
+            "\u{feff}#EXTM3U\n#EXTINF:123,Artist - Title\nfirst.flac\n\n  sub/second.mp3  \r\n";
+        assert_eq!(
+            parse_m3u(playlist),
+            [PathBuf::from("first.flac"), PathBuf::from("sub/second.mp3")]
+        );
+    }
+
+    #[test]
+    fn playlists_are_recognized_by_extension() {
+        assert!(is_playlist(Path::new("set.M3U8")));
+        assert!(!is_playlist(Path::new("song.flac")));
+    }
+}
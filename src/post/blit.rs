@@ -0,0 +1,50 @@
+use super::{PostEffect, StageLayout};
+
+// Copies the scene to the target when no effect is active
+pub struct Blit {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Blit {
+    pub fn new(device: &wgpu::Device, stages: &StageLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("fullscreen.wgsl"),
+                    include_str!("output.wgsl"),
+                    include_str!("blit.wgsl")
+                )
+                .into(),
+            ),
+        });
+        let pipeline = super::create_pipeline(
+            device,
+            "Blit Pipeline",
+            &shader,
+            "fs_main",
+            &[&stages.bind_group_layout, &stages.output_layout],
+            format,
+        );
+
+        Self { pipeline }
+    }
+}
+
+impl PostEffect for Blit {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        output_params: &wgpu::BindGroup,
+        output: &wgpu::TextureView,
+    ) {
+        super::draw_fullscreen(
+            encoder,
+            "Blit Pass",
+            &self.pipeline,
+            &[input, output_params],
+            output,
+        );
+    }
+}
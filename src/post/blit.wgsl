@@ -0,0 +1,12 @@
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(1) @binding(0) var<uniform> output_params: OutputParams;
+@group(1) @binding(1) var output_lut: texture_3d<f32>;
+@group(1) @binding(2) var output_lut_sampler: sampler;
+@group(1) @binding(3) var<uniform> output_exposure: OutputExposure;
+
+// Plain copy of the scene, used when no effect is active
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return adjust_output(textureSample(scene_texture, scene_sampler, in.uv), output_params, output_exposure.value, output_lut, output_lut_sampler);
+}
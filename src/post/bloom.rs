@@ -0,0 +1,228 @@
+use super::{PostEffect, Stage, StageLayout, SCENE_FORMAT};
+use wgpu::util::DeviceExt;
+
+// Glow around bright lines. Colors are in the scene's linear, unclamped
+// range, where the grid's lit slopes go up to about 1.4.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    // Brightest channel above which a color starts to glow
+    pub threshold: f32,
+    // Strength of the glow added back onto the scene
+    pub intensity: f32,
+    // Spacing of the blur taps in half resolution pixels; larger spreads the
+    // glow wider
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.6,
+            intensity: 0.8,
+            radius: 2.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomParams {
+    threshold: f32,
+    intensity: f32,
+    radius: f32,
+    _padding: f32,
+}
+
+// Bright pass at half resolution, a separable Gaussian blur and an additive
+// composite onto the input
+pub struct Bloom {
+    params_layout: wgpu::BindGroupLayout,
+    params: wgpu::BindGroup,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    blur_horizontal_pipeline: wgpu::RenderPipeline,
+    blur_vertical_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    // Half resolution ping-pong targets for the blur; the result ends up in
+    // the first
+    targets: [Stage; 2],
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        stages: &StageLayout,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        settings: &BloomSettings,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("fullscreen.wgsl"),
+                    include_str!("output.wgsl"),
+                    include_str!("bloom.wgsl")
+                )
+                .into(),
+            ),
+        });
+
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom Params Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let blur_layouts = [&stages.bind_group_layout, &params_layout];
+        let prefilter_pipeline = super::create_pipeline(
+            device,
+            "Bloom Prefilter Pipeline",
+            &shader,
+            "fs_prefilter",
+            &blur_layouts,
+            SCENE_FORMAT,
+        );
+        let blur_horizontal_pipeline = super::create_pipeline(
+            device,
+            "Bloom Horizontal Blur Pipeline",
+            &shader,
+            "fs_blur_horizontal",
+            &blur_layouts,
+            SCENE_FORMAT,
+        );
+        let blur_vertical_pipeline = super::create_pipeline(
+            device,
+            "Bloom Vertical Blur Pipeline",
+            &shader,
+            "fs_blur_vertical",
+            &blur_layouts,
+            SCENE_FORMAT,
+        );
+        let composite_pipeline = super::create_pipeline(
+            device,
+            "Bloom Composite Pipeline",
+            &shader,
+            "fs_composite",
+            &[
+                &stages.bind_group_layout,
+                &params_layout,
+                &stages.bind_group_layout,
+                &stages.output_layout,
+            ],
+            format,
+        );
+
+        let params = create_params(device, &params_layout, settings);
+        let targets = create_targets(device, stages, width, height);
+
+        Self {
+            params_layout,
+            params,
+            prefilter_pipeline,
+            blur_horizontal_pipeline,
+            blur_vertical_pipeline,
+            composite_pipeline,
+            targets,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, stages: &StageLayout, width: u32, height: u32) {
+        self.targets = create_targets(device, stages, width, height);
+    }
+
+    pub fn set_settings(&mut self, device: &wgpu::Device, settings: &BloomSettings) {
+        self.params = create_params(device, &self.params_layout, settings);
+    }
+}
+
+impl PostEffect for Bloom {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        output_params: &wgpu::BindGroup,
+        output: &wgpu::TextureView,
+    ) {
+        let [first, second] = &self.targets;
+        super::draw_fullscreen(
+            encoder,
+            "Bloom Prefilter Pass",
+            &self.prefilter_pipeline,
+            &[input, &self.params],
+            &first.view,
+        );
+        super::draw_fullscreen(
+            encoder,
+            "Bloom Horizontal Blur Pass",
+            &self.blur_horizontal_pipeline,
+            &[&first.bind_group, &self.params],
+            &second.view,
+        );
+        super::draw_fullscreen(
+            encoder,
+            "Bloom Vertical Blur Pass",
+            &self.blur_vertical_pipeline,
+            &[&second.bind_group, &self.params],
+            &first.view,
+        );
+        super::draw_fullscreen(
+            encoder,
+            "Bloom Composite Pass",
+            &self.composite_pipeline,
+            &[input, &self.params, &first.bind_group, output_params],
+            output,
+        );
+    }
+}
+
+fn create_params(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    settings: &BloomSettings,
+) -> wgpu::BindGroup {
+    let params = BloomParams {
+        threshold: settings.threshold,
+        intensity: settings.intensity,
+        radius: settings.radius,
+        _padding: 0.0,
+    };
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Bloom Params Buffer"),
+        contents: bytemuck::cast_slice(&[params]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bloom Params Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    })
+}
+
+fn create_targets(
+    device: &wgpu::Device,
+    stages: &StageLayout,
+    width: u32,
+    height: u32,
+) -> [Stage; 2] {
+    let (width, height) = ((width / 2).max(1), (height / 2).max(1));
+    [
+        stages.create_stage(device, "Bloom Texture", SCENE_FORMAT, width, height),
+        stages.create_stage(device, "Bloom Texture", SCENE_FORMAT, width, height),
+    ]
+}
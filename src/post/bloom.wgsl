@@ -0,0 +1,61 @@
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+
+struct BloomParams {
+    threshold: f32,
+    intensity: f32,
+    radius: f32,
+    _padding: f32,
+};
+
+@group(1) @binding(0) var<uniform> params: BloomParams;
+
+// Blurred highlights, added back onto the scene by the composite pass
+@group(2) @binding(0) var bloom_texture: texture_2d<f32>;
+@group(2) @binding(1) var bloom_sampler: sampler;
+@group(3) @binding(0) var<uniform> output_params: OutputParams;
+@group(3) @binding(1) var output_lut: texture_3d<f32>;
+@group(3) @binding(2) var output_lut_sampler: sampler;
+@group(3) @binding(3) var<uniform> output_exposure: OutputExposure;
+
+// Gaussian weights for the center tap and four taps on each side
+const WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+// Keep only what is brighter than the threshold. Drawn at half resolution,
+// so each sample also averages the 2x2 pixels around it.
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.uv).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - params.threshold, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    var weights = WEIGHTS;
+    let step = direction * params.radius / vec2<f32>(textureDimensions(input_texture));
+    var color = textureSample(input_texture, input_sampler, uv).rgb * weights[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        color += (textureSample(input_texture, input_sampler, uv + offset).rgb
+            + textureSample(input_texture, input_sampler, uv - offset).rgb) * weights[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_blur_horizontal(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(0.0, 1.0));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(input_texture, input_sampler, in.uv);
+    let glow = textureSample(bloom_texture, bloom_sampler, in.uv).rgb * params.intensity;
+    return adjust_output(vec4<f32>(scene.rgb + glow, scene.a), output_params, output_exposure.value, output_lut, output_lut_sampler);
+}
@@ -0,0 +1,233 @@
+use crate::histogram::{self, BinParams, HISTOGRAM_BINS};
+use std::cell::Cell;
+use wgpu::util::DeviceExt;
+
+// Bytes of the exposure carried between frames, see exposure.wgsl
+pub const STATE_SIZE: wgpu::BufferAddress = 16;
+
+// Range of the log2 luminance bins. Lit slopes reach about 1.4 and bloom a
+// little more; anything below the range counts as the black background.
+const LOG_MIN: f32 = -8.0;
+const LOG_MAX: f32 = 4.0;
+// Shares of the lit pixels left out of the average: the dim half, mostly
+// anti-aliased line edges and fogged rows, and the brightest peaks
+const DARK_SHARE: f32 = 0.5;
+const BRIGHT_SHARE: f32 = 0.05;
+
+// Exposure that follows the brightness of the picture, so dark ambient
+// presets and bright peaks both stay visible. The picture is then tone
+// mapped, rolling highlights off toward white instead of clipping them.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExposureSettings {
+    pub auto: bool,
+    // Scene luminance the lit part of the picture is brought to
+    pub key: f32,
+    // Seconds to adapt about two thirds of the way to a new brightness
+    pub adaptation: f32,
+    // Limits of the exposure, as a factor on the scene
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for ExposureSettings {
+    fn default() -> Self {
+        Self {
+            auto: false,
+            key: 0.4,
+            adaptation: 1.0,
+            min: 0.25,
+            max: 8.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ExposureParams {
+    log_min: f32,
+    log_max: f32,
+    key: f32,
+    adapt: f32,
+    min_exposure: f32,
+    max_exposure: f32,
+    dark_share: f32,
+    bright_share: f32,
+}
+
+// Measures the scene each frame on the GPU, binning its log luminance with
+// the histogram pass, and moves the exposure in the state buffer toward the
+// one bringing the average to the key. The output pass reads it from there,
+// so nothing waits on the CPU.
+pub struct AutoExposure {
+    settings: ExposureSettings,
+    params_buffer: wgpu::Buffer,
+    bins_buffer: wgpu::Buffer,
+    bin_pipeline: wgpu::ComputePipeline,
+    bin_bind_group: wgpu::BindGroup,
+    adapt_pipeline: wgpu::ComputePipeline,
+    adapt_bind_group: wgpu::BindGroup,
+    // Set until the state is cleared, so the first frame measured sets the
+    // exposure outright
+    reset: Cell<bool>,
+    width: u32,
+    height: u32,
+}
+
+impl AutoExposure {
+    pub fn new(
+        device: &wgpu::Device,
+        scene: &wgpu::TextureView,
+        state_buffer: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+        settings: &ExposureSettings,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Exposure Params Buffer"),
+            size: std::mem::size_of::<ExposureParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bins_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Exposure Bins Buffer"),
+            size: HISTOGRAM_BINS * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bin_pipeline = histogram::create_bin_pipeline(device);
+        let bin_bind_group =
+            create_bin_bind_group(device, &bin_pipeline, scene, &bins_buffer, width, height);
+
+        let adapt_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Exposure Adapt Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("exposure.wgsl").into()),
+        });
+        let adapt_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Exposure Adapt Pipeline"),
+            layout: None,
+            module: &adapt_shader,
+            entry_point: "adapt",
+        });
+        let adapt_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Exposure Adapt Bind Group"),
+            layout: &adapt_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: bins_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            settings: *settings,
+            params_buffer,
+            bins_buffer,
+            bin_pipeline,
+            bin_bind_group,
+            adapt_pipeline,
+            adapt_bind_group,
+            reset: Cell::new(true),
+            width,
+            height,
+        }
+    }
+
+    // Measure the new scene texture from now on
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        scene: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        self.bin_bind_group = create_bin_bind_group(
+            device,
+            &self.bin_pipeline,
+            scene,
+            &self.bins_buffer,
+            width,
+            height,
+        );
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_settings(&mut self, settings: &ExposureSettings) {
+        self.settings = *settings;
+    }
+
+    // Write the settings for a frame `dt` seconds after the last
+    pub fn upload(&self, queue: &wgpu::Queue, dt: f32) {
+        let settings = &self.settings;
+        let adapt = if settings.adaptation > 0.0 {
+            1.0 - (-dt / settings.adaptation).exp()
+        } else {
+            1.0
+        };
+        let params = ExposureParams {
+            log_min: LOG_MIN,
+            log_max: LOG_MAX,
+            key: settings.key,
+            adapt,
+            min_exposure: settings.min,
+            max_exposure: settings.max,
+            dark_share: DARK_SHARE,
+            bright_share: BRIGHT_SHARE,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+
+    // Measure the scene and adapt the exposure in `state_buffer`, the one
+    // given to new, ahead of the passes reading it
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, state_buffer: &wgpu::Buffer) {
+        if self.reset.take() {
+            encoder.clear_buffer(state_buffer, 0, None);
+        }
+        encoder.clear_buffer(&self.bins_buffer, 0, None);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Exposure Pass"),
+        });
+        compute_pass.set_pipeline(&self.bin_pipeline);
+        compute_pass.set_bind_group(0, &self.bin_bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.width.div_ceil(8), self.height.div_ceil(8), 1);
+        compute_pass.set_pipeline(&self.adapt_pipeline);
+        compute_pass.set_bind_group(0, &self.adapt_bind_group, &[]);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+}
+
+// Bins the log luminance of the whole scene
+fn create_bin_bind_group(
+    device: &wgpu::Device,
+    pipeline: &wgpu::ComputePipeline,
+    scene: &wgpu::TextureView,
+    bins_buffer: &wgpu::Buffer,
+    width: u32,
+    height: u32,
+) -> wgpu::BindGroup {
+    let params = BinParams {
+        width,
+        height,
+        log_scale: 1,
+        log_min: LOG_MIN,
+        log_max: LOG_MAX,
+        ..Default::default()
+    };
+    let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Exposure Bin Params Buffer"),
+        contents: bytemuck::cast_slice(&[params]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    histogram::create_bin_bind_group(device, pipeline, &params_buffer, scene, bins_buffer)
+}
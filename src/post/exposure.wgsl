@@ -0,0 +1,69 @@
+// Must match HISTOGRAM_BINS in histogram.rs
+const BINS: u32 = 64u;
+
+struct Params {
+    // Range of the log2 luminance bins
+    log_min: f32,
+    log_max: f32,
+    // Scene luminance the lit part of the picture is exposed to
+    key: f32,
+    // Share of the way from the current exposure to the new one this frame
+    adapt: f32,
+    min_exposure: f32,
+    max_exposure: f32,
+    // Shares of the lit pixels left out at the dark and bright ends
+    dark_share: f32,
+    bright_share: f32,
+};
+
+// Carried over between frames and read by the output pass as OutputExposure
+struct State {
+    exposure: f32,
+    // 0 until the first frame has been measured
+    primed: u32,
+    _padding: vec2<u32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> bins: array<u32>;
+@group(0) @binding(2) var<storage, read_write> state: State;
+
+// Average the log luminance of the lit pixels binned by histogram.wgsl and
+// move the exposure toward the one bringing it to the key. The first bin is
+// the black background, which says nothing about how bright the picture
+// looks.
+@compute @workgroup_size(1)
+fn adapt() {
+    var lit = 0u;
+    for (var i = 1u; i < BINS; i++) {
+        lit += bins[i];
+    }
+    // Keep the exposure through a black frame
+    if lit == 0u {
+        return;
+    }
+
+    let start = f32(lit) * params.dark_share;
+    let end = f32(lit) * (1.0 - params.bright_share);
+    let bin_size = (params.log_max - params.log_min) / f32(BINS);
+    var seen = 0.0;
+    var sum = 0.0;
+    var weight = 0.0;
+    for (var i = 1u; i < BINS; i++) {
+        let count = f32(bins[i]);
+        let inside = max(min(seen + count, end) - max(seen, start), 0.0);
+        sum += (params.log_min + (f32(i) + 0.5) * bin_size) * inside;
+        weight += inside;
+        seen += count;
+    }
+    let average = exp2(sum / max(weight, 1e-6));
+    let wanted = clamp(params.key / average, params.min_exposure, params.max_exposure);
+
+    // In stops, so brightening and darkening take the same time
+    if state.primed == 0u {
+        state.exposure = wanted;
+        state.primed = 1u;
+    } else {
+        state.exposure = exp2(mix(log2(state.exposure), log2(wanted), params.adapt));
+    }
+}
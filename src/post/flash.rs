@@ -0,0 +1,262 @@
+use super::blit::Blit;
+use super::{PostEffect, Stage, StageLayout, SCENE_FORMAT};
+use std::cell::Cell;
+
+// Bytes of the state carried between frames, see flash_measure.wgsl
+const STATE_SIZE: wgpu::BufferAddress = 16;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FlashParams {
+    max_change: f32,
+    _padding: [f32; 3],
+}
+
+// Limits how fast the average brightness of the picture may change, for
+// viewers sensitive to flashing. The average luminance is measured on the
+// GPU each frame; when it moves by more than the limit allows, the frame is
+// mixed with the last one shown just enough to stay within it. Steady
+// motion passes through as is.
+pub struct FlashLimiter {
+    // Largest change in average luminance per second, in 0..1
+    limit: f32,
+    params_buffer: wgpu::Buffer,
+    total_buffer: wgpu::Buffer,
+    state_buffer: wgpu::Buffer,
+    measure_pipeline: wgpu::ComputePipeline,
+    update_pipeline: wgpu::ComputePipeline,
+    measure_bind_group: wgpu::BindGroup,
+    blend_pipeline: wgpu::RenderPipeline,
+    state_bind_group: wgpu::BindGroup,
+    output: Blit,
+    // The frames shown, alternating between two; `last` is the newest
+    history: [Stage; 2],
+    last: Cell<usize>,
+    // Set when the history no longer matches the state, e.g. after a resize
+    reset: Cell<bool>,
+    width: u32,
+    height: u32,
+}
+
+impl FlashLimiter {
+    pub fn new(
+        device: &wgpu::Device,
+        stages: &StageLayout,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        limit: f32,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Flash Params Buffer"),
+            size: std::mem::size_of::<FlashParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let total_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Flash Total Buffer"),
+            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let state_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Flash State Buffer"),
+            size: STATE_SIZE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let measure_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Flash Measure Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("flash_measure.wgsl").into()),
+        });
+        let measure_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Flash Measure Bind Group Layout"),
+            entries: &[
+                compute_buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                compute_buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: false }),
+                compute_buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let measure_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Flash Measure Pipeline Layout"),
+                bind_group_layouts: &[&stages.bind_group_layout, &measure_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&measure_pipeline_layout),
+                module: &measure_shader,
+                entry_point,
+            })
+        };
+        let measure_pipeline = compute_pipeline("Flash Measure Pipeline", "measure");
+        let update_pipeline = compute_pipeline("Flash Update Pipeline", "update");
+        let measure_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Flash Measure Bind Group"),
+            layout: &measure_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: total_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let blend_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Flash Blend Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("fullscreen.wgsl"), include_str!("flash.wgsl")).into(),
+            ),
+        });
+        let state_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Flash State Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let blend_pipeline = super::create_pipeline(
+            device,
+            "Flash Blend Pipeline",
+            &blend_shader,
+            "fs_main",
+            &[
+                &stages.bind_group_layout,
+                &stages.bind_group_layout,
+                &state_layout,
+            ],
+            SCENE_FORMAT,
+        );
+        let state_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Flash State Bind Group"),
+            layout: &state_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: state_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            limit,
+            params_buffer,
+            total_buffer,
+            state_buffer,
+            measure_pipeline,
+            update_pipeline,
+            measure_bind_group,
+            blend_pipeline,
+            state_bind_group,
+            output: Blit::new(device, stages, format),
+            history: create_history(device, stages, width, height),
+            last: Cell::new(0),
+            reset: Cell::new(false),
+            width,
+            height,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, stages: &StageLayout, width: u32, height: u32) {
+        self.history = create_history(device, stages, width, height);
+        self.reset.set(true);
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_limit(&mut self, limit: f32) {
+        self.limit = limit;
+    }
+
+    // Write the change allowed for a frame `dt` seconds after the last
+    pub fn upload(&self, queue: &wgpu::Queue, dt: f32) {
+        let params = FlashParams {
+            max_change: self.limit * dt,
+            _padding: [0.0; 3],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+}
+
+impl PostEffect for FlashLimiter {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        output_params: &wgpu::BindGroup,
+        output: &wgpu::TextureView,
+    ) {
+        if self.reset.take() {
+            encoder.clear_buffer(&self.state_buffer, 0, None);
+        }
+        encoder.clear_buffer(&self.total_buffer, 0, None);
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Flash Measure Pass"),
+            });
+            compute_pass.set_bind_group(0, input, &[]);
+            compute_pass.set_bind_group(1, &self.measure_bind_group, &[]);
+            compute_pass.set_pipeline(&self.measure_pipeline);
+            compute_pass.dispatch_workgroups(self.width.div_ceil(8), self.height.div_ceil(8), 1);
+            compute_pass.set_pipeline(&self.update_pipeline);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+
+        let last = &self.history[self.last.get()];
+        let next = 1 - self.last.get();
+        super::draw_fullscreen(
+            encoder,
+            "Flash Blend Pass",
+            &self.blend_pipeline,
+            &[input, &last.bind_group, &self.state_bind_group],
+            &self.history[next].view,
+        );
+        self.output.encode(
+            encoder,
+            &self.history[next].bind_group,
+            output_params,
+            output,
+        );
+        self.last.set(next);
+    }
+}
+
+fn compute_buffer_entry(binding: u32, ty: wgpu::BufferBindingType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_history(
+    device: &wgpu::Device,
+    stages: &StageLayout,
+    width: u32,
+    height: u32,
+) -> [Stage; 2] {
+    [0, 1]
+        .map(|_| stages.create_stage(device, "Flash History Texture", SCENE_FORMAT, width, height))
+}
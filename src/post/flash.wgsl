@@ -0,0 +1,22 @@
+// Written by the update pass in flash_measure.wgsl
+struct State {
+    average: f32,
+    blend: f32,
+    primed: u32,
+    _padding: u32,
+};
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(1) @binding(0) var last_texture: texture_2d<f32>;
+@group(1) @binding(1) var last_sampler: sampler;
+@group(2) @binding(0) var<uniform> state: State;
+
+// The new frame, or for a sudden change in brightness a mix of it with the
+// last frame shown that keeps the change within the limit
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(scene_texture, scene_sampler, in.uv);
+    let last = textureSample(last_texture, last_sampler, in.uv);
+    return mix(last, color, state.blend);
+}
@@ -0,0 +1,68 @@
+// Fixed point scale of the luminance sums, low enough that a 4K frame of
+// full white fits in 32 bits
+const SCALE: f32 = 255.0;
+
+struct Params {
+    // Largest change in average luminance allowed this frame
+    max_change: f32,
+};
+
+// What the blend pass reads, carried over between frames
+struct State {
+    // Average luminance of the last frame shown, in 0..1
+    average: f32,
+    // How far to move from the last frame to the new one
+    blend: f32,
+    // 0 until the first frame has been measured
+    primed: u32,
+    _padding: u32,
+};
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(1) @binding(0) var<uniform> params: Params;
+@group(1) @binding(1) var<storage, read_write> total: atomic<u32>;
+@group(1) @binding(2) var<storage, read_write> state: State;
+
+var<workgroup> local_total: atomic<u32>;
+
+// Sum the luminance of the frame as shown, first per workgroup and then
+// globally to keep contention on the output low
+@compute @workgroup_size(8, 8)
+fn measure(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    if local_index == 0u {
+        atomicStore(&local_total, 0u);
+    }
+    workgroupBarrier();
+
+    let size = textureDimensions(scene_texture);
+    if id.x < size.x && id.y < size.y {
+        let color = clamp(textureLoad(scene_texture, vec2<i32>(id.xy), 0).rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+        let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+        atomicAdd(&local_total, u32(round(luminance * SCALE)));
+    }
+    workgroupBarrier();
+
+    if local_index == 0u {
+        atomicAdd(&total, atomicLoad(&local_total));
+    }
+}
+
+// Move the shown average toward the new frame's by at most the allowed
+// change, and record how far that is for the blend pass
+@compute @workgroup_size(1)
+fn update() {
+    let size = textureDimensions(scene_texture);
+    let average = f32(atomicLoad(&total)) / (SCALE * f32(size.x * size.y));
+    let change = average - state.average;
+    var blend = 1.0;
+    if state.primed != 0u && abs(change) > params.max_change {
+        blend = params.max_change / abs(change);
+    }
+    state.average += change * blend;
+    state.blend = blend;
+    state.primed = 1u;
+}
@@ -0,0 +1,49 @@
+use super::{PostEffect, StageLayout};
+
+pub struct Fxaa {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Fxaa {
+    pub fn new(device: &wgpu::Device, stages: &StageLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FXAA Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("fullscreen.wgsl"),
+                    include_str!("output.wgsl"),
+                    include_str!("fxaa.wgsl")
+                )
+                .into(),
+            ),
+        });
+        let pipeline = super::create_pipeline(
+            device,
+            "FXAA Pipeline",
+            &shader,
+            "fs_main",
+            &[&stages.bind_group_layout, &stages.output_layout],
+            format,
+        );
+
+        Self { pipeline }
+    }
+}
+
+impl PostEffect for Fxaa {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        output_params: &wgpu::BindGroup,
+        output: &wgpu::TextureView,
+    ) {
+        super::draw_fullscreen(
+            encoder,
+            "FXAA Pass",
+            &self.pipeline,
+            &[input, output_params],
+            output,
+        );
+    }
+}
@@ -0,0 +1,56 @@
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(1) @binding(0) var<uniform> output_params: OutputParams;
+@group(1) @binding(1) var output_lut: texture_3d<f32>;
+@group(1) @binding(2) var output_lut_sampler: sampler;
+@group(1) @binding(3) var<uniform> output_exposure: OutputExposure;
+
+const FXAA_SPAN_MAX: f32 = 8.0;
+const FXAA_REDUCE_MUL: f32 = 0.125;
+const FXAA_REDUCE_MIN: f32 = 0.0078125;
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn sample_scene(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(scene_texture, scene_sampler, uv).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(scene_texture));
+
+    // Luma of the pixel and its diagonal neighbours
+    let rgb_m = sample_scene(in.uv);
+    let luma_nw = luma(sample_scene(in.uv + vec2<f32>(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample_scene(in.uv + vec2<f32>(1.0, -1.0) * texel));
+    let luma_sw = luma(sample_scene(in.uv + vec2<f32>(-1.0, 1.0) * texel));
+    let luma_se = luma(sample_scene(in.uv + vec2<f32>(1.0, 1.0) * texel));
+    let luma_m = luma(rgb_m);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur direction runs along the detected edge
+    var dir = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let dir_reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * (0.25 * FXAA_REDUCE_MUL),
+        FXAA_REDUCE_MIN,
+    );
+    let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX)) * texel;
+
+    let rgb_a = 0.5 * (sample_scene(in.uv + dir * (1.0 / 3.0 - 0.5))
+        + sample_scene(in.uv + dir * (2.0 / 3.0 - 0.5)));
+    let rgb_b = rgb_a * 0.5 + 0.25 * (sample_scene(in.uv + dir * -0.5)
+        + sample_scene(in.uv + dir * 0.5));
+
+    // Fall back to the narrower blur if the wide one overshoots the local range
+    let luma_b = luma(rgb_b);
+    let outside = luma_b < luma_min || luma_b > luma_max;
+    return adjust_output(vec4<f32>(select(rgb_b, rgb_a, outside), 1.0), output_params, output_exposure.value, output_lut, output_lut_sampler);
+}
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+// Entries per side allowed by the .cube format
+const MAX_SIZE: u32 = 256;
+
+// A 3D color lookup table from a .cube file, as written by grading and
+// display calibration tools. Entries go with red changing fastest, which is
+// the order of texels in a 3D texture indexed by red, green and blue.
+#[derive(Clone, Debug)]
+pub struct Lut {
+    pub size: u32,
+    // Input range mapped onto the table, usually 0 to 1
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub entries: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            let floats = |words: std::str::SplitWhitespace| {
+                words
+                    .map(|word| word.parse::<f32>().ok().filter(|value| value.is_finite()))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("expected numbers"))
+            };
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err(error("1D LUTs are not supported")),
+                "LUT_3D_SIZE" => {
+                    let value = words
+                        .next()
+                        .and_then(|word| word.parse().ok())
+                        .filter(|size| (2..=MAX_SIZE).contains(size))
+                        .ok_or_else(|| error("size must be 2 to 256"))?;
+                    size = Some(value);
+                }
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let values: [f32; 3] = floats(words)?
+                        .try_into()
+                        .map_err(|_| error("expected three numbers"))?;
+                    if keyword == "DOMAIN_MIN" {
+                        domain_min = values;
+                    } else {
+                        domain_max = values;
+                    }
+                }
+                // Resolve's way to give the same range for all channels
+                "LUT_3D_INPUT_RANGE" => {
+                    let [min, max]: [f32; 2] = floats(words)?
+                        .try_into()
+                        .map_err(|_| error("expected two numbers"))?;
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                }
+                _ => {
+                    let entry: [f32; 3] = floats(line.split_whitespace())?
+                        .try_into()
+                        .map_err(|_| error("expected three numbers"))?;
+                    entries.push(entry);
+                }
+            }
+        }
+
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        if entries.len() != size.pow(3) as usize {
+            return Err(format!(
+                "expected {} entries for size {}, found {}",
+                size.pow(3),
+                size,
+                entries.len()
+            ));
+        }
+        if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return Err("DOMAIN_MAX must be above DOMAIN_MIN".into());
+        }
+        Ok(Self {
+            size,
+            domain_min,
+            domain_max,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Identity table of size 2, with a header before it
+    fn cube(header: &str) -> String {
+        let mut text = format!("TITLE \"test\"\n{}\nLUT_3D_SIZE 2\n", header);
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text += &format!("{} {} {}\n", r, g, b);
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn entries_go_with_red_changing_fastest() {
+        let lut = Lut::parse(&cube("# comment")).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.domain_min, [0.0; 3]);
+        assert_eq!(lut.domain_max, [1.0; 3]);
+        assert_eq!(lut.entries[1], [1.0, 0.0, 0.0]);
+        assert_eq!(lut.entries[2], [0.0, 1.0, 0.0]);
+        assert_eq!(lut.entries[4], [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn domain_is_read_per_channel_or_as_an_input_range() {
+        let lut = Lut::parse(&cube("DOMAIN_MIN 0 -0.5 0\nDOMAIN_MAX 1 1.5 2")).unwrap();
+        assert_eq!(lut.domain_min, [0.0, -0.5, 0.0]);
+        assert_eq!(lut.domain_max, [1.0, 1.5, 2.0]);
+
+        let lut = Lut::parse(&cube("LUT_3D_INPUT_RANGE -0.25 4")).unwrap();
+        assert_eq!(lut.domain_min, [-0.25; 3]);
+        assert_eq!(lut.domain_max, [4.0; 3]);
+
+        assert!(Lut::parse(&cube("DOMAIN_MIN 1 1 1\nDOMAIN_MAX 1 2 2")).is_err());
+        assert!(Lut::parse(&cube("LUT_3D_INPUT_RANGE 0")).is_err());
+    }
+
+    #[test]
+    fn one_dimensional_and_malformed_tables_are_rejected() {
+        let error = Lut::parse("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").unwrap_err();
+        assert!(error.contains("1D"), "{}", error);
+        assert!(Lut::parse("0 0 0\n").is_err());
+        assert!(Lut::parse(&cube("").replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 1")).is_err());
+        // One entry short
+        let text = cube("");
+        let short = &text[..text.trim_end().rfind('\n').unwrap()];
+        assert!(Lut::parse(short).is_err());
+        assert!(Lut::parse(&cube("").replace("1 1 1", "1 nan 1")).is_err());
+    }
+}
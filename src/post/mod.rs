@@ -0,0 +1,561 @@
+mod blit;
+mod bloom;
+mod exposure;
+mod flash;
+mod fxaa;
+mod lut;
+mod output;
+mod thicken;
+
+pub use bloom::BloomSettings;
+pub use exposure::ExposureSettings;
+pub use lut::Lut;
+pub use output::OutputSettings;
+
+use blit::Blit;
+use bloom::Bloom;
+use exposure::AutoExposure;
+use flash::FlashLimiter;
+use fxaa::Fxaa;
+use output::OutputLut;
+use std::time::Instant;
+use thicken::Thicken;
+
+// The scene is drawn in floating point so that lines brighter than white
+// keep their intensity until the chain resolves them to the target
+pub const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Post-based anti-aliasing applied after the scene has been rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AntiAliasing {
+    Off,
+    Fxaa,
+}
+
+impl AntiAliasing {
+    pub fn next(self) -> Self {
+        match self {
+            AntiAliasing::Off => AntiAliasing::Fxaa,
+            AntiAliasing::Fxaa => AntiAliasing::Off,
+        }
+    }
+}
+
+// A full-screen step of the chain. It samples its input through the shared
+// stage layout and draws into `output`, which is in the target format, with
+// `output_params` applied by the pass that draws into it.
+trait PostEffect {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        output_params: &wgpu::BindGroup,
+        output: &wgpu::TextureView,
+    );
+}
+
+// Offscreen HDR scene target plus the chain of effects that resolves it to
+// the surface. Active effects run in a fixed order, each reading the output
+// of the one before; with none active the scene is copied as is.
+pub struct PostProcess {
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    stages: StageLayout,
+    scene: Stage,
+    // Outputs of all but the last effect, alternating between two
+    intermediates: Vec<Stage>,
+    blit: Blit,
+    anti_aliasing: AntiAliasing,
+    bloom_settings: BloomSettings,
+    output_settings: OutputSettings,
+    output_lut: OutputLut,
+    exposure_settings: ExposureSettings,
+    // Exposure adapted on the GPU, read by the output params
+    exposure_state: wgpu::Buffer,
+    // Output trim for the last effect, and the identity for the others.
+    // With auto exposure the first effect, which reads the scene, exposes
+    // and tone maps it as well.
+    output_params: wgpu::BindGroup,
+    identity_params: wgpu::BindGroup,
+    exposed_output_params: wgpu::BindGroup,
+    exposed_params: wgpu::BindGroup,
+    // Created on first use so the default path doesn't pay for them at
+    // startup; bloom is dropped again when disabled
+    fxaa: Option<Fxaa>,
+    bloom: Option<Bloom>,
+    // Accessibility stages, present while enabled
+    thicken: Option<Thicken>,
+    flash: Option<FlashLimiter>,
+    // Present while auto exposure is on
+    exposure: Option<AutoExposure>,
+}
+
+impl PostProcess {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let stages = StageLayout::new(device);
+        let scene = stages.create_stage(device, "Scene Texture", SCENE_FORMAT, width, height);
+        let blit = Blit::new(device, &stages, format);
+        let output_settings = OutputSettings::default();
+        let output_lut = OutputLut::new(device);
+        let exposure_state = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Exposure State Buffer"),
+            size: exposure::STATE_SIZE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let params = |exposed| {
+            output::create_params(
+                device,
+                &stages.output_layout,
+                &output_settings,
+                &output_lut,
+                &exposure_state,
+                exposed,
+            )
+        };
+        let output_params = params(false);
+        let identity_params = params(false);
+        let exposed_output_params = params(true);
+        let exposed_params = params(true);
+
+        Self {
+            format,
+            width,
+            height,
+            stages,
+            scene,
+            intermediates: Vec::new(),
+            blit,
+            anti_aliasing: AntiAliasing::Off,
+            bloom_settings: BloomSettings::default(),
+            output_settings,
+            output_lut,
+            exposure_settings: ExposureSettings::default(),
+            exposure_state,
+            output_params,
+            identity_params,
+            exposed_output_params,
+            exposed_params,
+            fxaa: None,
+            bloom: None,
+            thicken: None,
+            flash: None,
+            exposure: None,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.scene = self
+            .stages
+            .create_stage(device, "Scene Texture", SCENE_FORMAT, width, height);
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.resize(device, &self.stages, width, height);
+        }
+        if let Some(flash) = self.flash.as_mut() {
+            flash.resize(device, &self.stages, width, height);
+        }
+        if let Some(exposure) = self.exposure.as_mut() {
+            exposure.resize(device, &self.scene.view, width, height);
+        }
+        self.intermediates.clear();
+        self.allocate_intermediates(device);
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    pub fn set_anti_aliasing(&mut self, device: &wgpu::Device, anti_aliasing: AntiAliasing) {
+        if anti_aliasing == AntiAliasing::Fxaa && self.fxaa.is_none() {
+            let start = Instant::now();
+            self.fxaa = Some(Fxaa::new(device, &self.stages, self.format));
+            log::info!("FXAA pipeline created in {:?}", start.elapsed());
+        }
+        self.anti_aliasing = anti_aliasing;
+        self.allocate_intermediates(device);
+    }
+
+    pub fn bloom(&self) -> &BloomSettings {
+        &self.bloom_settings
+    }
+
+    pub fn set_bloom(&mut self, device: &wgpu::Device, settings: &BloomSettings) {
+        match self.bloom.as_mut() {
+            // Free its targets rather than keep them around unused
+            _ if !settings.enabled => self.bloom = None,
+            Some(bloom) => bloom.set_settings(device, settings),
+            None => {
+                let start = Instant::now();
+                self.bloom = Some(Bloom::new(
+                    device,
+                    &self.stages,
+                    self.format,
+                    self.width,
+                    self.height,
+                    settings,
+                ));
+                log::info!("Bloom pipelines created in {:?}", start.elapsed());
+            }
+        }
+        self.bloom_settings = *settings;
+        self.allocate_intermediates(device);
+    }
+
+    // Widen lines to three pixels, for the high-contrast mode
+    pub fn set_thick_lines(&mut self, device: &wgpu::Device, enabled: bool) {
+        if enabled && self.thicken.is_none() {
+            let start = Instant::now();
+            self.thicken = Some(Thicken::new(device, &self.stages, self.format));
+            log::info!("Thicken pipeline created in {:?}", start.elapsed());
+        } else if !enabled {
+            self.thicken = None;
+        }
+        self.allocate_intermediates(device);
+    }
+
+    // Limit the change in average luminance to `limit` of full white per
+    // second, or stop limiting it with None
+    pub fn set_flash_limit(&mut self, device: &wgpu::Device, limit: Option<f32>) {
+        match (limit, self.flash.as_mut()) {
+            (None, _) => self.flash = None,
+            (Some(limit), Some(flash)) => flash.set_limit(limit),
+            (Some(limit), None) => {
+                let start = Instant::now();
+                self.flash = Some(FlashLimiter::new(
+                    device,
+                    &self.stages,
+                    self.format,
+                    self.width,
+                    self.height,
+                    limit,
+                ));
+                log::info!("Flash limiter pipelines created in {:?}", start.elapsed());
+            }
+        }
+        self.allocate_intermediates(device);
+    }
+
+    pub fn exposure(&self) -> &ExposureSettings {
+        &self.exposure_settings
+    }
+
+    pub fn set_exposure(&mut self, device: &wgpu::Device, settings: &ExposureSettings) {
+        match self.exposure.as_mut() {
+            _ if !settings.auto => self.exposure = None,
+            Some(exposure) => exposure.set_settings(settings),
+            None => {
+                let start = Instant::now();
+                self.exposure = Some(AutoExposure::new(
+                    device,
+                    &self.scene.view,
+                    &self.exposure_state,
+                    self.width,
+                    self.height,
+                    settings,
+                ));
+                log::info!("Auto exposure pipelines created in {:?}", start.elapsed());
+            }
+        }
+        self.exposure_settings = *settings;
+    }
+
+    pub fn output(&self) -> &OutputSettings {
+        &self.output_settings
+    }
+
+    pub fn set_output(&mut self, device: &wgpu::Device, settings: &OutputSettings) {
+        if settings.lut != self.output_settings.lut {
+            let lut = settings
+                .lut
+                .as_deref()
+                .and_then(|path| match Lut::load(path) {
+                    Ok(lut) => Some(lut),
+                    Err(e) => {
+                        log::error!("Failed to load LUT {}: {}", path.display(), e);
+                        None
+                    }
+                });
+            self.output_lut.set(device, lut);
+        }
+        let params = |exposed| {
+            output::create_params(
+                device,
+                &self.stages.output_layout,
+                settings,
+                &self.output_lut,
+                &self.exposure_state,
+                exposed,
+            )
+        };
+        self.output_params = params(false);
+        self.exposed_output_params = params(true);
+        self.output_settings = settings.clone();
+    }
+
+    // Write data set since the last frame, such as a new LUT, to the GPU,
+    // for a frame `dt` seconds after the last
+    pub fn upload(&mut self, queue: &wgpu::Queue, dt: f32) {
+        self.output_lut.upload(queue);
+        if let Some(flash) = &self.flash {
+            flash.upload(queue, dt);
+        }
+        if let Some(exposure) = &self.exposure {
+            exposure.upload(queue, dt);
+        }
+    }
+
+    // Where the scene should be drawn, in SCENE_FORMAT
+    pub fn scene_target(&self) -> &wgpu::TextureView {
+        &self.scene.view
+    }
+
+    // Active effects in the order they run. Bloom needs the unclamped scene,
+    // so it goes first. The flash limiter comes after everything that adds
+    // light, and anti-aliasing last.
+    fn effects(&self) -> Vec<&dyn PostEffect> {
+        let mut effects: Vec<&dyn PostEffect> = Vec::new();
+        if let Some(bloom) = &self.bloom {
+            effects.push(bloom);
+        }
+        if let Some(thicken) = &self.thicken {
+            effects.push(thicken);
+        }
+        if let Some(flash) = &self.flash {
+            effects.push(flash);
+        }
+        if let (AntiAliasing::Fxaa, Some(fxaa)) = (self.anti_aliasing, &self.fxaa) {
+            effects.push(fxaa);
+        }
+        if effects.is_empty() {
+            effects.push(&self.blit);
+        }
+        effects
+    }
+
+    // Keep as many intermediate targets as the active chain needs
+    fn allocate_intermediates(&mut self, device: &wgpu::Device) {
+        let needed = (self.effects().len() - 1).min(2);
+        self.intermediates.truncate(needed);
+        while self.intermediates.len() < needed {
+            let stage = self.stages.create_stage(
+                device,
+                "Post Intermediate Texture",
+                self.format,
+                self.width,
+                self.height,
+            );
+            self.intermediates.push(stage);
+        }
+    }
+
+    pub fn encode(&self, device: &wgpu::Device, target: &wgpu::TextureView) -> wgpu::CommandBuffer {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Post Pass Encoder"),
+        });
+
+        if let Some(exposure) = &self.exposure {
+            exposure.encode(&mut encoder, &self.exposure_state);
+        }
+
+        let effects = self.effects();
+        let mut input = &self.scene.bind_group;
+        for (i, effect) in effects.iter().enumerate() {
+            let exposed = i == 0 && self.exposure.is_some();
+            if i + 1 == effects.len() {
+                let params = if exposed {
+                    &self.exposed_output_params
+                } else {
+                    &self.output_params
+                };
+                effect.encode(&mut encoder, input, params, target);
+            } else {
+                let stage = &self.intermediates[i % 2];
+                let params = if exposed {
+                    &self.exposed_params
+                } else {
+                    &self.identity_params
+                };
+                effect.encode(&mut encoder, input, params, &stage.view);
+                input = &stage.bind_group;
+            }
+        }
+
+        encoder.finish()
+    }
+}
+
+// A texture in the chain, with the bind group effects sample it through
+struct Stage {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+// Texture and sampler layout shared by every pass that reads a stage,
+// including compute passes that measure it, and the layout of the output
+// params every effect takes
+struct StageLayout {
+    bind_group_layout: wgpu::BindGroupLayout,
+    output_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl StageLayout {
+    fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        Self {
+            bind_group_layout,
+            output_layout: output::create_layout(device),
+            sampler,
+        }
+    }
+
+    fn create_stage(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Stage {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        Stage { view, bind_group }
+    }
+}
+
+// Pipeline drawing a full-screen triangle with the given fragment entry
+// point; the shader must include fullscreen.wgsl for the vertex stage
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn draw_fullscreen(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+    output: &wgpu::TextureView,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: output,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,238 @@
+use super::lut::Lut;
+use std::path::PathBuf;
+use wgpu::util::DeviceExt;
+
+const LUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Final trim of the picture for the display it is shown on, such as a
+// projector or LED wall, on top of what the OS offers. Brightness scales
+// the linear colors, contrast steepens or flattens them around middle grey
+// and gamma above 1 lifts the midtones. A .cube LUT from a calibration tool
+// is applied last.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub gamma: f32,
+    pub lut: Option<PathBuf>,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            contrast: 1.0,
+            gamma: 1.0,
+            lut: None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutputParams {
+    brightness: f32,
+    contrast: f32,
+    gamma: f32,
+    lut_size: f32,
+    lut_domain_min: [f32; 4],
+    lut_domain_max: [f32; 4],
+    exposure: f32,
+    _padding: [f32; 3],
+}
+
+// The output LUT as a 3D texture. Without one it holds a single unused
+// texel, as the bind group needs a texture either way.
+pub struct OutputLut {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    size: u32,
+    domain: [[f32; 4]; 2],
+    // Entries as half floats, written on the next upload
+    pending: Option<Vec<u16>>,
+}
+
+impl OutputLut {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture = create_lut_texture(device, 1);
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            texture,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Output LUT Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            size: 0,
+            domain: [[0.0; 4], [1.0; 4]],
+            pending: None,
+        }
+    }
+
+    // Replace the table, or remove it with None
+    pub fn set(&mut self, device: &wgpu::Device, lut: Option<Lut>) {
+        let max_size = device.limits().max_texture_dimension_3d;
+        let lut = lut.filter(|lut| {
+            let fits = lut.size <= max_size;
+            if !fits {
+                log::error!("LUT size {} is over the GPU's {}", lut.size, max_size);
+            }
+            fits
+        });
+        let Some(lut) = lut else {
+            self.size = 0;
+            self.pending = None;
+            return;
+        };
+
+        self.texture = create_lut_texture(device, lut.size);
+        self.view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.size = lut.size;
+        let [min, max] = [lut.domain_min, lut.domain_max].map(|[r, g, b]| [r, g, b, 0.0]);
+        self.domain = [min, max];
+        self.pending = Some(
+            lut.entries
+                .iter()
+                .flat_map(|&[r, g, b]| [r, g, b, 1.0])
+                .map(|value| half::f16::from_f32(value).to_bits())
+                .collect(),
+        );
+    }
+
+    // Write a table set since the last call to the texture
+    pub fn upload(&mut self, queue: &wgpu::Queue) {
+        let Some(entries) = self.pending.take() else {
+            return;
+        };
+        queue.write_texture(
+            self.texture.as_image_copy(),
+            bytemuck::cast_slice(&entries),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.size * 8),
+                rows_per_image: Some(self.size),
+            },
+            self.texture.size(),
+        );
+    }
+}
+
+fn create_lut_texture(device: &wgpu::Device, size: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Output LUT Texture"),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: LUT_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+pub fn create_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Output Params Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D3,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+// The LUT is only used if `settings` has one, and the adapted exposure in
+// `exposure` only with `auto_exposure`
+pub fn create_params(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    settings: &OutputSettings,
+    lut: &OutputLut,
+    exposure: &wgpu::Buffer,
+    auto_exposure: bool,
+) -> wgpu::BindGroup {
+    let params = OutputParams {
+        brightness: settings.brightness,
+        contrast: settings.contrast,
+        gamma: settings.gamma,
+        lut_size: if settings.lut.is_some() {
+            lut.size as f32
+        } else {
+            0.0
+        },
+        lut_domain_min: lut.domain[0],
+        lut_domain_max: lut.domain[1],
+        exposure: if auto_exposure { 1.0 } else { 0.0 },
+        _padding: [0.0; 3],
+    };
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Output Params Buffer"),
+        contents: bytemuck::cast_slice(&[params]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Output Params Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&lut.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&lut.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: exposure.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,77 @@
+// Brightness, contrast and gamma trim and color LUT for the display,
+// applied by whichever pass draws into the target. Passes into intermediate
+// targets get the identity, all ones and no LUT. With auto exposure, the
+// pass reading the scene also exposes and tone maps it.
+struct OutputParams {
+    brightness: f32,
+    contrast: f32,
+    gamma: f32,
+    // Entries along each side of the LUT, 0 without one
+    lut_size: f32,
+    lut_domain_min: vec4<f32>,
+    lut_domain_max: vec4<f32>,
+    // 1 to apply the auto exposure and tone map
+    exposure: f32,
+};
+
+// Written by the adapt pass in exposure.wgsl
+struct OutputExposure {
+    value: f32,
+    primed: u32,
+    _padding: vec2<u32>,
+};
+
+// Linear middle grey, which contrast pivots around so black stays black
+const MIDDLE_GREY: f32 = 0.18;
+
+fn srgb_encode(linear: vec3<f32>) -> vec3<f32> {
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, linear * 12.92, linear <= vec3<f32>(0.0031308));
+}
+
+fn srgb_decode(encoded: vec3<f32>) -> vec3<f32> {
+    let high = pow((encoded + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, encoded / 12.92, encoded <= vec3<f32>(0.04045));
+}
+
+// LUTs map display encoded values, so the color is looked up in sRGB
+fn apply_lut(
+    rgb: vec3<f32>,
+    params: OutputParams,
+    lut: texture_3d<f32>,
+    lut_sampler: sampler,
+) -> vec3<f32> {
+    let domain = params.lut_domain_max.rgb - params.lut_domain_min.rgb;
+    let encoded = clamp((srgb_encode(rgb) - params.lut_domain_min.rgb) / domain, vec3<f32>(0.0), vec3<f32>(1.0));
+    // Texel centers, so the ends of the domain hit the first and last entries
+    let coords = (encoded * (params.lut_size - 1.0) + 0.5) / params.lut_size;
+    let mapped = textureSampleLevel(lut, lut_sampler, coords, 0.0).rgb;
+    return srgb_decode(max(mapped, vec3<f32>(0.0)));
+}
+
+// Filmic curve rolling highlights off toward white, Krzysztof Narkowicz's
+// fit of the ACES reference transform
+fn tonemap(rgb: vec3<f32>) -> vec3<f32> {
+    let mapped = rgb * (2.51 * rgb + 0.03) / (rgb * (2.43 * rgb + 0.59) + 0.14);
+    return clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn adjust_output(
+    color: vec4<f32>,
+    params: OutputParams,
+    exposure: f32,
+    lut: texture_3d<f32>,
+    lut_sampler: sampler,
+) -> vec4<f32> {
+    var rgb = color.rgb;
+    if params.exposure != 0.0 {
+        rgb = tonemap(max(rgb, vec3<f32>(0.0)) * exposure);
+    }
+    rgb = max(rgb * params.brightness, vec3<f32>(0.0));
+    rgb = MIDDLE_GREY * pow(rgb / MIDDLE_GREY, vec3<f32>(params.contrast));
+    rgb = pow(rgb, vec3<f32>(1.0 / params.gamma));
+    if params.lut_size > 0.0 {
+        rgb = apply_lut(rgb, params, lut, lut_sampler);
+    }
+    return vec4<f32>(rgb, color.a);
+}
@@ -0,0 +1,51 @@
+use super::{PostEffect, StageLayout};
+
+// Widens lines to three pixels for the high-contrast mode, as the GPU
+// draws lines one pixel wide
+pub struct Thicken {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Thicken {
+    pub fn new(device: &wgpu::Device, stages: &StageLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Thicken Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("fullscreen.wgsl"),
+                    include_str!("output.wgsl"),
+                    include_str!("thicken.wgsl")
+                )
+                .into(),
+            ),
+        });
+        let pipeline = super::create_pipeline(
+            device,
+            "Thicken Pipeline",
+            &shader,
+            "fs_main",
+            &[&stages.bind_group_layout, &stages.output_layout],
+            format,
+        );
+
+        Self { pipeline }
+    }
+}
+
+impl PostEffect for Thicken {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        output_params: &wgpu::BindGroup,
+        output: &wgpu::TextureView,
+    ) {
+        super::draw_fullscreen(
+            encoder,
+            "Thicken Pass",
+            &self.pipeline,
+            &[input, output_params],
+            output,
+        );
+    }
+}
@@ -0,0 +1,21 @@
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(1) @binding(0) var<uniform> output_params: OutputParams;
+@group(1) @binding(1) var output_lut: texture_3d<f32>;
+@group(1) @binding(2) var output_lut_sampler: sampler;
+@group(1) @binding(3) var<uniform> output_exposure: OutputExposure;
+
+// Brightest of the pixel and its eight neighbours, so every line spreads
+// by a pixel on each side
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(scene_texture));
+    var color = vec4<f32>(0.0);
+    for (var y = -1; y <= 1; y += 1) {
+        for (var x = -1; x <= 1; x += 1) {
+            let uv = in.uv + vec2<f32>(f32(x), f32(y)) * texel;
+            color = max(color, textureSampleLevel(scene_texture, scene_sampler, uv, 0.0));
+        }
+    }
+    return adjust_output(color, output_params, output_exposure.value, output_lut, output_lut_sampler);
+}
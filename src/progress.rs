@@ -0,0 +1,164 @@
+use std::time::{Duration, Instant};
+
+// How an offline render reports its progress on stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressFormat {
+    // A line for people to read
+    #[default]
+    Text,
+    // A JSON object per line, for scripts and render farms
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Rendering,
+    Done,
+    Cancelled,
+}
+
+impl State {
+    fn name(self) -> &'static str {
+        match self {
+            State::Rendering => "rendering",
+            State::Done => "done",
+            State::Cancelled => "cancelled",
+        }
+    }
+}
+
+// Reports the frames done out of `frames` about once a second, with the
+// render rate and the time left at that rate, and once more at the end
+pub struct Progress {
+    format: ProgressFormat,
+    frames: u32,
+    start: Instant,
+    reported: u64,
+}
+
+impl Progress {
+    pub fn new(format: ProgressFormat, frames: u32) -> Self {
+        Self {
+            format,
+            frames,
+            start: Instant::now(),
+            reported: 0,
+        }
+    }
+
+    // Call after each frame with the number of frames done
+    pub fn update(&mut self, done: u32) {
+        let elapsed = self.start.elapsed();
+        if elapsed.as_secs() > self.reported {
+            self.reported = elapsed.as_secs();
+            println!("{}", self.line(State::Rendering, done, elapsed));
+        }
+    }
+
+    // Report the last frame, `cancelled` if the render stopped before all of
+    // them were done
+    pub fn finish(&self, done: u32, cancelled: bool) {
+        let state = if cancelled {
+            State::Cancelled
+        } else {
+            State::Done
+        };
+        println!("{}", self.line(state, done, self.start.elapsed()));
+    }
+
+    fn line(&self, state: State, done: u32, elapsed: Duration) -> String {
+        let seconds = elapsed.as_secs_f64();
+        let fps = (seconds > 0.0).then(|| done as f64 / seconds);
+        // Unknown until a frame is done
+        let eta = fps
+            .filter(|&fps| fps > 0.0 && state == State::Rendering)
+            .map(|fps| self.frames.saturating_sub(done) as f64 / fps);
+        match self.format {
+            ProgressFormat::Text => {
+                let percent = done as f64 / self.frames.max(1) as f64 * 100.0;
+                let mut line = format!(
+                    "Rendered {} of {} frames ({:.0}%)",
+                    done, self.frames, percent
+                );
+                if let Some(fps) = fps {
+                    line += &format!(", {:.1} fps", fps);
+                }
+                match state {
+                    State::Rendering => {
+                        if let Some(eta) = eta {
+                            line += &format!(", {} left", clock_time(eta));
+                        }
+                    }
+                    State::Done => line += &format!(" in {}", clock_time(seconds)),
+                    State::Cancelled => line += ", cancelled",
+                }
+                line
+            }
+            ProgressFormat::Json => serde_json::json!({
+                "state": state.name(),
+                "frame": done,
+                "frames": self.frames,
+                "elapsed": seconds,
+                "fps": fps,
+                "eta": eta,
+            })
+            .to_string(),
+        }
+    }
+}
+
+// Seconds as minutes:seconds, or hours:minutes:seconds from an hour up
+fn clock_time(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_shows_the_rate_and_time_left() {
+        let progress = Progress::new(ProgressFormat::Text, 600);
+        assert_eq!(
+            progress.line(State::Rendering, 120, Duration::from_secs(4)),
+            "Rendered 120 of 600 frames (20%), 30.0 fps, 0:16 left"
+        );
+        assert_eq!(
+            progress.line(State::Done, 600, Duration::from_secs(3725)),
+            "Rendered 600 of 600 frames (100%), 0.2 fps in 1:02:05"
+        );
+        assert_eq!(
+            progress.line(State::Cancelled, 0, Duration::ZERO),
+            "Rendered 0 of 600 frames (0%), cancelled"
+        );
+    }
+
+    #[test]
+    fn json_has_a_state_and_no_eta_until_a_frame_is_done() {
+        let progress = Progress::new(ProgressFormat::Json, 100);
+        let line: serde_json::Value =
+            serde_json::from_str(&progress.line(State::Rendering, 0, Duration::ZERO)).unwrap();
+        assert_eq!(line["state"], "rendering");
+        assert!(line["eta"].is_null());
+
+        let line: serde_json::Value =
+            serde_json::from_str(&progress.line(State::Rendering, 25, Duration::from_secs(5)))
+                .unwrap();
+        assert_eq!(line["frame"], 25);
+        assert_eq!(line["frames"], 100);
+        assert_eq!(line["fps"], 5.0);
+        assert_eq!(line["eta"], 15.0);
+
+        let line: serde_json::Value =
+            serde_json::from_str(&progress.line(State::Cancelled, 30, Duration::from_secs(6)))
+                .unwrap();
+        assert_eq!(line["state"], "cancelled");
+        assert!(line["eta"].is_null());
+    }
+}
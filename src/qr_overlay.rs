@@ -0,0 +1,110 @@
+use crate::config::QrConfig;
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+use qrcodegen::{QrCode, QrCodeEcc};
+
+// Light modules around the code, which scanners need to find its edges
+const QUIET_ZONE: i32 = 4;
+// Pixels between the viewport edges and the code
+const MARGIN: f32 = 16.0;
+
+const DARK_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const LIGHT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// Corner of the picture the code sits in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// Encode `url` with enough error correction to survive a camera pointed at
+// a screen from across a room
+pub fn encode(url: &str) -> Result<QrCode, String> {
+    QrCode::encode_text(url, QrCodeEcc::Medium).map_err(|e| e.to_string())
+}
+
+// A QR code linking to a URL from the config, e.g. event info or a remote
+// control page, for the audience to scan off the screen. Drawn as boxes in
+// the shared text batch, with whole pixels per module so it stays sharp.
+pub struct QrOverlay {
+    pub visible: bool,
+    code: Option<QrCode>,
+    corner: Corner,
+    // Side of the code, quiet zone included, as a fraction of the shorter
+    // side of the viewport
+    size: f32,
+}
+
+impl QrOverlay {
+    pub fn new(config: &QrConfig) -> Self {
+        let mut overlay = Self {
+            visible: false,
+            code: None,
+            corner: config.corner,
+            size: config.size,
+        };
+        overlay.set_config(config);
+        overlay
+    }
+
+    // Take new settings, showing or hiding the code as configured
+    pub fn set_config(&mut self, config: &QrConfig) {
+        // Config::load has checked that the URL fits
+        self.code = match config.url.as_str() {
+            "" => None,
+            url => encode(url).ok(),
+        };
+        self.visible = config.enabled;
+        self.corner = config.corner;
+        self.size = config.size;
+    }
+
+    // Lay out the code in `text` for the next draw into `viewport`
+    pub fn push(&self, viewport: Viewport, text: &mut TextRenderer) {
+        let Some(code) = self.code.as_ref().filter(|_| self.visible) else {
+            return;
+        };
+        let modules = code.size() + QUIET_ZONE * 2;
+        let shorter = viewport.width.min(viewport.height) as f32;
+        let module = (shorter * self.size / modules as f32).round().max(1.0);
+        let side = module * modules as f32;
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => MARGIN,
+            Corner::TopRight | Corner::BottomRight => viewport.width as f32 - MARGIN - side,
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => MARGIN,
+            Corner::BottomLeft | Corner::BottomRight => viewport.height as f32 - MARGIN - side,
+        };
+
+        text.add_rect([x, y], [x + side, y + side], LIGHT_COLOR);
+        // One box per run of dark modules in a row
+        let origin = [
+            x + QUIET_ZONE as f32 * module,
+            y + QUIET_ZONE as f32 * module,
+        ];
+        for row in 0..code.size() {
+            let top = origin[1] + row as f32 * module;
+            let mut column = 0;
+            while column < code.size() {
+                if !code.get_module(column, row) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < code.size() && code.get_module(column, row) {
+                    column += 1;
+                }
+                text.add_rect(
+                    [origin[0] + start as f32 * module, top],
+                    [origin[0] + column as f32 * module, top + module],
+                    DARK_COLOR,
+                );
+            }
+        }
+    }
+}
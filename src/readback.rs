@@ -0,0 +1,237 @@
+use std::sync::{Arc, OnceLock};
+
+// What a readback was requested for, so completed data can be routed back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadbackPurpose {
+    Screenshot,
+    // A frame of offscreen output, e.g. for a video or image sequence
+    Export,
+}
+
+// Tightly packed pixel data copied back from the GPU
+pub struct Readback {
+    pub purpose: ReadbackPurpose,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub data: Vec<u8>,
+}
+
+struct PendingReadback {
+    purpose: ReadbackPurpose,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+    // Set once mapping has finished, to whether it succeeded
+    mapped: Option<Arc<OnceLock<bool>>>,
+}
+
+impl PendingReadback {
+    // Bytes of the buffer the copy filled, which a pooled buffer may exceed
+    fn used_size(&self) -> wgpu::BufferAddress {
+        self.padded_bytes_per_row as wgpu::BufferAddress * self.height as wgpu::BufferAddress
+    }
+}
+
+// Reusable pool of mappable buffers. Copies are recorded into a frame's
+// encoder, mapped after submission and polled without blocking the queue.
+pub struct ReadbackPool {
+    free: Vec<wgpu::Buffer>,
+    pending: Vec<PendingReadback>,
+}
+
+impl ReadbackPool {
+    pub fn new() -> Self {
+        Self {
+            free: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    // Record a copy of the whole texture into a pooled buffer
+    pub fn copy_texture(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        purpose: ReadbackPurpose,
+    ) {
+        let width = texture.width();
+        let height = texture.height();
+        let format = texture.format();
+        let bytes_per_pixel = format.block_size(None).unwrap_or(4);
+        let padded_bytes_per_row = padded_bytes_per_row(width * bytes_per_pixel);
+        let buffer = self.acquire(
+            device,
+            (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        );
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        self.pending.push(PendingReadback {
+            purpose,
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            format,
+            mapped: None,
+        });
+    }
+
+    // Start mapping every copy recorded since the last call. Must be called
+    // after the encoder holding the copies has been submitted.
+    pub fn submitted(&mut self) {
+        for pending in self.pending.iter_mut().filter(|p| p.mapped.is_none()) {
+            let mapped = Arc::new(OnceLock::new());
+            let flag = mapped.clone();
+            pending.buffer.slice(..pending.used_size()).map_async(
+                wgpu::MapMode::Read,
+                move |result| {
+                    if let Err(e) = &result {
+                        log::error!("Readback mapping failed: {:?}", e);
+                    }
+                    let _ = flag.set(result.is_ok());
+                },
+            );
+            pending.mapped = Some(mapped);
+        }
+    }
+
+    // Collect finished readbacks and return their buffers to the pool
+    pub fn poll(&mut self, device: &wgpu::Device) -> Vec<Readback> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut completed = Vec::new();
+        let mut index = 0;
+        while index < self.pending.len() {
+            let mapped = self.pending[index]
+                .mapped
+                .as_ref()
+                .and_then(|mapped| mapped.get().copied());
+            match mapped {
+                None => {
+                    index += 1;
+                    continue;
+                }
+                // The buffer is dropped along with the readback
+                Some(false) => {
+                    self.pending.swap_remove(index);
+                    continue;
+                }
+                Some(true) => {}
+            }
+
+            let pending = self.pending.swap_remove(index);
+            let unpadded_bytes_per_row =
+                pending.width * pending.format.block_size(None).unwrap_or(4);
+            let mut data = Vec::with_capacity((unpadded_bytes_per_row * pending.height) as usize);
+            {
+                let mapped = pending
+                    .buffer
+                    .slice(..pending.used_size())
+                    .get_mapped_range();
+                let rows = mapped.chunks(pending.padded_bytes_per_row as usize);
+                for row in rows.take(pending.height as usize) {
+                    data.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+                }
+            }
+            pending.buffer.unmap();
+
+            completed.push(Readback {
+                purpose: pending.purpose,
+                width: pending.width,
+                height: pending.height,
+                format: pending.format,
+                data,
+            });
+            self.free.push(pending.buffer);
+        }
+
+        completed
+    }
+
+    fn acquire(&mut self, device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+        if let Some(index) = self.free.iter().position(|b| b.size() >= size) {
+            return self.free.swap_remove(index);
+        }
+
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+impl Default for ReadbackPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Row pitch for texture to buffer copies
+pub fn padded_bytes_per_row(unpadded: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded.div_ceil(align) * align
+}
+
+// Convert readback pixels to RGBA8, swapping channels for BGRA surfaces and
+// encoding half float targets to sRGB, clipped to white
+pub fn to_rgba8(readback: &Readback) -> Option<Vec<u8>> {
+    match readback.format {
+        wgpu::TextureFormat::Rgba16Float => Some(
+            readback
+                .data
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(i, bytes)| {
+                    let value = half::f16::from_le_bytes([bytes[0], bytes[1]]).to_f32();
+                    // Alpha is linear already
+                    let encoded = if i % 4 == 3 {
+                        value
+                    } else {
+                        encode_srgb(value)
+                    };
+                    (encoded.clamp(0.0, 1.0) * 255.0).round() as u8
+                })
+                .collect(),
+        ),
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {
+            Some(readback.data.clone())
+        }
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Some(
+            readback
+                .data
+                .chunks_exact(4)
+                .flat_map(|p| [p[2], p[1], p[0], p[3]])
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn encode_srgb(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+// Offline video encoding. Frames are piped to ffmpeg as raw RGBA, and ffmpeg
+// picks the codec from the file extension.
+pub struct Recorder {
+    ffmpeg: Child,
+    live_audio: Option<LiveAudio>,
+}
+
+// Audio captured while recording, spooled to a file beside the output as
+// raw 32-bit float samples. The video goes beside it until both are muxed
+// into the output when the recording is finished.
+struct LiveAudio {
+    file: BufWriter<File>,
+    path: PathBuf,
+    video: PathBuf,
+    output: PathBuf,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl Recorder {
+    // Start ffmpeg writing `output`, muxing in `audio` if given. The video
+    // ends with the shorter of the two.
+    pub fn new(
+        width: u32,
+        height: u32,
+        fps: u32,
+        output: &Path,
+        audio: Option<&Path>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            ffmpeg: start_ffmpeg(width, height, fps, output, audio)?,
+            live_audio: None,
+        })
+    }
+
+    // Start ffmpeg writing `output`, with `channels` of audio at
+    // `sample_rate` handed over by `write_audio` as it is captured, starting
+    // with the first frame
+    pub fn with_live_audio(
+        width: u32,
+        height: u32,
+        fps: u32,
+        output: &Path,
+        sample_rate: u32,
+        channels: u16,
+    ) -> io::Result<Self> {
+        let beside = |suffix: &str| {
+            let mut name = output.file_stem().unwrap_or_default().to_os_string();
+            name.push(suffix);
+            output.with_file_name(name)
+        };
+        let extension = output.extension().unwrap_or_default().to_string_lossy();
+        let video = beside(&format!(".video.{}", extension));
+        let path = beside(".audio.f32");
+        Ok(Self {
+            ffmpeg: start_ffmpeg(width, height, fps, &video, None)?,
+            live_audio: Some(LiveAudio {
+                file: BufWriter::new(File::create(&path)?),
+                path,
+                video,
+                output: output.to_path_buf(),
+                sample_rate,
+                channels,
+            }),
+        })
+    }
+
+    // Append a frame of tightly packed RGBA8 pixels
+    pub fn write_frame(&mut self, pixels: &[u8]) -> io::Result<()> {
+        self.ffmpeg
+            .stdin
+            .as_mut()
+            .ok_or(io::ErrorKind::BrokenPipe)?
+            .write_all(pixels)
+    }
+
+    // Append interleaved samples of the live audio, if recording it
+    pub fn write_audio(&mut self, samples: &[f32]) -> io::Result<()> {
+        let Some(audio) = &mut self.live_audio else {
+            return Ok(());
+        };
+        for sample in samples {
+            audio.file.write_all(&sample.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Close the stream and wait for ffmpeg to finish the file, then mux in
+    // the live audio if there is any
+    pub fn finish(mut self) -> io::Result<()> {
+        drop(self.ffmpeg.stdin.take());
+        let status = self.ffmpeg.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("ffmpeg exited with {}", status)));
+        }
+        let Some(mut audio) = self.live_audio.take() else {
+            return Ok(());
+        };
+        audio.file.flush()?;
+        let status = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-i"])
+            .arg(&audio.video)
+            .args(["-f", "f32le", "-ar", &audio.sample_rate.to_string()])
+            .args(["-ac", &audio.channels.to_string(), "-i"])
+            .arg(&audio.path)
+            .args(["-map", "0:v", "-map", "1:a", "-c:v", "copy", "-shortest"])
+            .arg(&audio.output)
+            .status()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to start ffmpeg: {}", e)))?;
+        std::fs::remove_file(&audio.path)?;
+        if !status.success() {
+            // The video is kept, so the recording isn't lost
+            return Err(io::Error::other(format!(
+                "muxing the audio failed, ffmpeg exited with {}; the video is in {}",
+                status,
+                audio.video.display()
+            )));
+        }
+        std::fs::remove_file(&audio.video)
+    }
+}
+
+// Start ffmpeg encoding piped frames into `output`, muxing in `audio` if
+// given. The video ends with the shorter of the two.
+fn start_ffmpeg(
+    width: u32,
+    height: u32,
+    fps: u32,
+    output: &Path,
+    audio: Option<&Path>,
+) -> io::Result<Child> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-framerate", &fps.to_string(), "-i", "-"]);
+    if let Some(audio) = audio {
+        command
+            .arg("-i")
+            .arg(audio)
+            .args(["-map", "0:v", "-map", "1:a", "-shortest"]);
+    }
+    // Keep a Ctrl+C in the terminal from reaching ffmpeg, which would stop it
+    // before the last frames are written; the app finishes the file itself
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    // Most players need 4:2:0, which needs even dimensions
+    command
+        .args([
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to start ffmpeg: {}", e)))
+}
@@ -0,0 +1,937 @@
+use crate::camera::{self, Camera, CameraController};
+use crate::clock::Clock;
+use crate::config::{CameraConfig, Config, FogConfig};
+use crate::grid::{self, GridSize, Vertex};
+use crate::heightfield::{self, HeightField};
+use crate::palette::{self, Palette, PaletteInput, PALETTE_DISTANCE};
+use crate::post::{AntiAliasing, BloomSettings, OutputSettings, PostProcess, SCENE_FORMAT};
+use crate::shaders::BUILTIN_GRID_SHADER;
+use crate::transform::{NodeId, Transform, TransformTree};
+use crate::uniforms::{FogMode, LineStyle, Uniforms, MAJOR_LINE_COLOR, MINOR_LINE_COLOR};
+use crate::viewport::Viewport;
+use cgmath::{EuclideanSpace, Matrix4, Point3};
+use std::time::Instant;
+use wgpu::util::DeviceExt;
+
+// How the grid surface is rasterized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Wireframe,
+    Filled,
+    Points,
+}
+
+impl RenderMode {
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Wireframe => RenderMode::Filled,
+            RenderMode::Filled => RenderMode::Points,
+            RenderMode::Points => RenderMode::Wireframe,
+        }
+    }
+}
+
+// Motion scale used by the reduced-motion preset (1.0 is full motion)
+pub const REDUCED_MOTION_INTENSITY: f32 = 0.35;
+
+// Brightest channel every palette stop is raised to in high-contrast mode
+const HIGH_CONTRAST_MIN_BRIGHTNESS: f32 = 0.6;
+
+// Depth buffer format of the grid pass
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Animated waveform grid rendered into any color target. The caller owns the
+// device, queue and target, so the renderer can be embedded in other apps.
+pub struct WaveformRenderer {
+    pub camera: Box<dyn CameraController>,
+    pub motion_intensity: f32,
+    pub line_style: LineStyle,
+    // Procedural waves are turned off when audio drives the grid on its own
+    pub wave_amplitude: f32,
+    // Share of the wave amplitude used, e.g. following the loudness
+    pub wave_scale: f32,
+    // Animation time per second at full motion intensity
+    pub speed: f32,
+    pub heightfield: HeightField,
+    pub major_color: [f32; 4],
+    pub minor_color: [f32; 4],
+    pub palette: Palette,
+    pub palette_input: PaletteInput,
+    pub fog: FogConfig,
+    // Minor lines and fog drawn at full brightness and palettes lifted, for
+    // viewers with low vision; thick lines are set with the post chain
+    pub high_contrast: bool,
+    // Hue the lines are tinted to, 0 to 1, in place of the palette
+    pub hue: Option<f32>,
+    // Scene root with the grid as its child; move the root to move everything
+    pub transforms: TransformTree,
+    // Width/height ratio to letterbox the picture to, e.g. 2.39 for scope;
+    // None fills the whole target
+    pub aspect_lock: Option<f32>,
+    // Fraction of the target width and height kept clear on each side for
+    // displays that crop the edges
+    pub overscan: f32,
+    // Pixels kept clear on each side for the picture to drift into, and its
+    // offset within them, against burn-in
+    pub drift_margin: u32,
+    pub drift: [i32; 2],
+    // Animation timeline, advanced by `update`
+    pub clock: Clock,
+    scene_node: NodeId,
+    grid_node: NodeId,
+    width: u32,
+    height: u32,
+    shader: wgpu::ShaderModule,
+    // Set while the fallback shader stands in for one that failed
+    shader_error: Option<String>,
+    pipeline_layout: wgpu::PipelineLayout,
+    render_mode: RenderMode,
+    render_pipeline: wgpu::RenderPipeline,
+    // Created on first use of their render mode
+    filled_pipeline: Option<wgpu::RenderPipeline>,
+    points_pipeline: Option<wgpu::RenderPipeline>,
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+    // Indexed triangle version of the grid for the filled mode
+    surface_vertex_buffer: wgpu::Buffer,
+    surface_index_buffer: wgpu::Buffer,
+    num_surface_indices: u32,
+    // A custom line strip from set_mesh replaces the grid; it has no
+    // triangles, so the filled mode draws it as lines
+    custom_mesh: bool,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    depth_texture: wgpu::TextureView,
+    // Samples per pixel of the grid pass; above 1 it draws into
+    // `msaa_target`, which is resolved into the post chain's scene target
+    sample_count: u32,
+    msaa_target: Option<wgpu::TextureView>,
+    post: PostProcess,
+    grid_size: GridSize,
+    world_scale: f32,
+}
+
+impl WaveformRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        // Create vertices for the grid
+        let grid_size = GridSize::default();
+        let (vertex_buffer, num_vertices) = create_vertex_buffer(device, grid_size);
+
+        let shader_start = Instant::now();
+        let shader = create_shader(device, BUILTIN_GRID_SHADER);
+        log::info!("Shader module created in {:?}", shader_start.elapsed());
+
+        // Create uniform buffer and bind group layout; binding 1 holds the
+        // height field read by the vertex shader
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Uniform Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let uniforms = Uniforms::new();
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let heightfield = HeightField::new(device);
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Uniform Bind Group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: heightfield.heights_buffer().as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline_start = Instant::now();
+        let render_pipeline = create_render_pipeline(
+            device,
+            &shader,
+            SCENE_FORMAT,
+            &pipeline_layout,
+            RenderMode::Wireframe,
+            1,
+        );
+        log::info!("Render pipeline created in {:?}", pipeline_start.elapsed());
+
+        let (surface_vertex_buffer, surface_index_buffer, num_surface_indices) =
+            create_surface_buffers(device, grid_size);
+
+        let mut transforms = TransformTree::new();
+        let scene_node = transforms.add(None, Transform::identity());
+        let grid_node = transforms.add(Some(scene_node), Transform::identity());
+
+        Self {
+            camera: Box::new(Camera::new()),
+            motion_intensity: 1.0,
+            line_style: LineStyle::Solid,
+            wave_amplitude: 1.0,
+            wave_scale: 1.0,
+            speed: 1.0,
+            major_color: MAJOR_LINE_COLOR,
+            minor_color: MINOR_LINE_COLOR,
+            palette: Palette::Flat,
+            palette_input: PaletteInput::Height,
+            fog: FogConfig::default(),
+            high_contrast: false,
+            hue: None,
+            transforms,
+            aspect_lock: None,
+            overscan: 0.0,
+            drift_margin: 0,
+            drift: [0, 0],
+            clock: Clock::new(),
+            scene_node,
+            grid_node,
+            heightfield,
+            width,
+            height,
+            shader,
+            shader_error: None,
+            pipeline_layout,
+            render_mode: RenderMode::Wireframe,
+            render_pipeline,
+            filled_pipeline: None,
+            points_pipeline: None,
+            vertex_buffer,
+            num_vertices,
+            surface_vertex_buffer,
+            surface_index_buffer,
+            num_surface_indices,
+            custom_mesh: false,
+            uniform_buffer,
+            uniform_bind_group,
+            depth_texture: create_depth_texture(device, width, height, 1),
+            sample_count: 1,
+            msaa_target: None,
+            post: PostProcess::new(device, format, width, height),
+            grid_size,
+            world_scale: 1.0,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.create_targets(device);
+        self.post.resize(device, width, height);
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    // Multisample the grid with `count` samples per pixel, 1 to turn it off.
+    // The count must be in `supported_sample_counts`.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, count: u32) {
+        if count == self.sample_count {
+            return;
+        }
+        let start = Instant::now();
+        self.sample_count = count;
+        self.create_targets(device);
+        self.render_pipeline = create_render_pipeline(
+            device,
+            &self.shader,
+            SCENE_FORMAT,
+            &self.pipeline_layout,
+            RenderMode::Wireframe,
+            count,
+        );
+        self.filled_pipeline = None;
+        self.points_pipeline = None;
+        self.set_render_mode(device, self.render_mode);
+        log::info!("{}x MSAA set up in {:?}", count, start.elapsed());
+    }
+
+    fn create_targets(&mut self, device: &wgpu::Device) {
+        let (width, height, count) = (self.width, self.height, self.sample_count);
+        self.depth_texture = create_depth_texture(device, width, height, count);
+        self.msaa_target = (count > 1).then(|| create_msaa_target(device, width, height, count));
+    }
+
+    // Where the picture goes in the target, leaving out the overscan and
+    // drift margins and any letterbox bars
+    pub fn viewport(&self) -> Viewport {
+        let safe_area = Viewport::full(self.width, self.height)
+            .inset(self.overscan)
+            .drift(self.drift_margin, self.drift);
+        match self.aspect_lock {
+            Some(aspect) => safe_area.letterbox(aspect),
+            None => safe_area,
+        }
+    }
+
+    // Animation time of the frame being drawn
+    pub fn time(&self) -> f32 {
+        self.clock.time()
+    }
+
+    // Jump to an animation time, e.g. to draw another renderer's frame
+    pub fn set_time(&mut self, time: f32) {
+        self.clock.set_time(time);
+    }
+
+    // Animation seconds per second of the clock's timeline. Reduced motion
+    // slows the animation as well as flattening it.
+    pub fn animation_rate(&self) -> f32 {
+        self.motion_intensity * self.speed
+    }
+
+    // World units per grid unit. The grid spans -1..1 grid units.
+    pub fn world_scale(&self) -> f32 {
+        self.world_scale
+    }
+
+    // Scale the grid and everything tied to it together: the camera position,
+    // movement speeds and clip planes
+    pub fn set_world_scale(&mut self, scale: f32) {
+        let ratio = scale / self.world_scale;
+        let position = Point3::from_vec(self.camera.position().to_vec() * ratio);
+        self.camera = camera::create_controller(
+            self.camera.mode(),
+            position,
+            self.camera.orientation(),
+            scale,
+        );
+        self.world_scale = scale;
+    }
+
+    // Apply the sections of a config that differ from `old` and concern the
+    // scene rather than the window
+    pub fn apply_config(&mut self, device: &wgpu::Device, new: &Config, old: &Config) {
+        if new.world != old.world {
+            self.set_world_scale(new.world.scale);
+        }
+        if new.grid != old.grid {
+            self.set_grid_size(device, new.grid);
+        }
+        if new.window.anti_aliasing != old.window.anti_aliasing {
+            self.set_anti_aliasing(device, new.window.anti_aliasing);
+        }
+        if new.waves != old.waves {
+            let heightfield = &mut self.heightfield;
+            heightfield.wave_function = new.waves.function;
+            heightfield.wave_frequency = new.waves.frequency;
+            heightfield.noise_amplitude = if new.waves.noise {
+                new.waves.noise_amplitude
+            } else {
+                0.0
+            };
+            heightfield.noise_octaves = new.waves.noise_octaves;
+            heightfield.gerstner_waves = new.waves.gerstner.clone();
+            self.wave_amplitude = new.waves.amplitude;
+            self.speed = new.waves.speed;
+        }
+        if new.heightmap.path != old.heightmap.path {
+            let samples = new.heightmap.path.as_deref().and_then(|path| {
+                heightfield::load_heightmap(path)
+                    .map_err(|e| log::error!("Failed to load {}: {}", path.display(), e))
+                    .ok()
+            });
+            self.heightfield.set_heightmap(samples);
+        }
+        self.heightfield.heightmap_amount = new.heightmap.height;
+        if new.colors != old.colors {
+            self.major_color = new.colors.major;
+            self.minor_color = new.colors.minor;
+            self.palette = new.colors.palette;
+            self.palette_input = new.colors.palette_by;
+        }
+        if new.fog != old.fog {
+            self.fog = new.fog.clone();
+        }
+        if new.output != old.output {
+            self.set_output(device, &new.output);
+        }
+        if new.bloom != old.bloom {
+            self.set_bloom(device, &new.bloom);
+        }
+        if new.exposure != old.exposure {
+            self.post.set_exposure(device, &new.exposure);
+        }
+        let pose = |camera: &CameraConfig| (camera.position, camera.yaw, camera.pitch, camera.roll);
+        if pose(&new.camera) != pose(&old.camera) {
+            self.reset_camera(&new.camera, new.world.scale);
+        }
+        if new.accessibility != old.accessibility {
+            let accessibility = &new.accessibility;
+            self.motion_intensity = if accessibility.reduced_motion {
+                accessibility.motion.min(REDUCED_MOTION_INTENSITY)
+            } else {
+                accessibility.motion
+            };
+            self.high_contrast = accessibility.high_contrast;
+            self.post
+                .set_thick_lines(device, accessibility.high_contrast);
+            self.post.set_flash_limit(
+                device,
+                Some(accessibility.flash_limit).filter(|_| accessibility.reduce_flashes),
+            );
+        }
+    }
+
+    // Move the camera back to a configured start position, keeping its mode
+    pub fn reset_camera(&mut self, config: &CameraConfig, world_scale: f32) {
+        self.camera = camera::create_controller(
+            self.camera.mode(),
+            Point3::from(config.position) * world_scale,
+            camera::orientation(
+                config.yaw.to_radians(),
+                config.pitch.to_radians(),
+                config.roll.to_radians(),
+            ),
+            world_scale,
+        );
+    }
+
+    pub fn scene_node(&self) -> NodeId {
+        self.scene_node
+    }
+
+    pub fn grid_node(&self) -> NodeId {
+        self.grid_node
+    }
+
+    pub fn grid_size(&self) -> GridSize {
+        self.grid_size
+    }
+
+    // Rebuild the grid meshes at a new resolution or shape. A custom mesh
+    // stays in place of the grid.
+    pub fn set_grid_size(&mut self, device: &wgpu::Device, size: GridSize) {
+        self.grid_size = size;
+        if self.custom_mesh {
+            return;
+        }
+        (self.vertex_buffer, self.num_vertices) = create_vertex_buffer(device, size);
+        (
+            self.surface_vertex_buffer,
+            self.surface_index_buffer,
+            self.num_surface_indices,
+        ) = create_surface_buffers(device, size);
+    }
+
+    // Replace the grid with a custom line strip, e.g. an SVG outline
+    pub fn set_mesh(&mut self, device: &wgpu::Device, vertices: &[Vertex]) {
+        self.vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        self.num_vertices = vertices.len() as u32;
+        self.custom_mesh = true;
+    }
+
+    // Vertices drawn per frame in the current render mode
+    pub fn vertex_count(&self) -> u32 {
+        match (self.render_mode, &self.filled_pipeline) {
+            (RenderMode::Filled, Some(_)) if !self.custom_mesh => self.num_surface_indices,
+            _ => self.num_vertices,
+        }
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    pub fn set_render_mode(&mut self, device: &wgpu::Device, mode: RenderMode) {
+        let pipeline = match mode {
+            RenderMode::Wireframe => None,
+            RenderMode::Filled => Some(&mut self.filled_pipeline),
+            RenderMode::Points => Some(&mut self.points_pipeline),
+        };
+        if let Some(pipeline @ None) = pipeline {
+            let start = Instant::now();
+            *pipeline = Some(create_render_pipeline(
+                device,
+                &self.shader,
+                SCENE_FORMAT,
+                &self.pipeline_layout,
+                mode,
+                self.sample_count,
+            ));
+            log::info!("{:?} pipeline created in {:?}", mode, start.elapsed());
+        }
+        self.render_mode = mode;
+    }
+
+    // Replace the grid shader, e.g. after editing it on disk, and rebuild the
+    // pipelines. The wave functions are added as for the built-in shader.
+    // If the new source fails to compile, the grid is drawn with a solid
+    // magenta fallback shader until a later source compiles, and the
+    // validation message is returned.
+    pub fn set_shader_source(&mut self, device: &wgpu::Device, source: &str) -> Result<(), String> {
+        let start = Instant::now();
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = create_shader(device, source);
+        let render_pipeline = create_render_pipeline(
+            device,
+            &shader,
+            SCENE_FORMAT,
+            &self.pipeline_layout,
+            RenderMode::Wireframe,
+            self.sample_count,
+        );
+        let error = pollster::block_on(device.pop_error_scope()).map(|e| e.to_string());
+        match &error {
+            None => {
+                log::info!("Shader recompiled in {:?}", start.elapsed());
+                self.use_shader(device, shader, render_pipeline);
+            }
+            Some(_) => {
+                let shader = create_fallback_shader(device);
+                let render_pipeline = create_render_pipeline(
+                    device,
+                    &shader,
+                    SCENE_FORMAT,
+                    &self.pipeline_layout,
+                    RenderMode::Wireframe,
+                    self.sample_count,
+                );
+                self.use_shader(device, shader, render_pipeline);
+            }
+        }
+        self.shader_error = error.clone();
+        error.map_or(Ok(()), Err)
+    }
+
+    // Validation message of the last shader that failed to compile, while
+    // the fallback shader is in use
+    pub fn shader_error(&self) -> Option<&str> {
+        self.shader_error.as_deref()
+    }
+
+    fn use_shader(
+        &mut self,
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModule,
+        render_pipeline: wgpu::RenderPipeline,
+    ) {
+        self.shader = shader;
+        self.render_pipeline = render_pipeline;
+        self.filled_pipeline = None;
+        self.points_pipeline = None;
+        self.set_render_mode(device, self.render_mode);
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.post.anti_aliasing()
+    }
+
+    pub fn set_anti_aliasing(&mut self, device: &wgpu::Device, anti_aliasing: AntiAliasing) {
+        self.post.set_anti_aliasing(device, anti_aliasing);
+    }
+
+    pub fn bloom(&self) -> &BloomSettings {
+        self.post.bloom()
+    }
+
+    pub fn set_bloom(&mut self, device: &wgpu::Device, settings: &BloomSettings) {
+        self.post.set_bloom(device, settings);
+    }
+
+    pub fn output(&self) -> &OutputSettings {
+        self.post.output()
+    }
+
+    pub fn set_output(&mut self, device: &wgpu::Device, settings: &OutputSettings) {
+        self.post.set_output(device, settings);
+    }
+
+    // Band levels in 0..1 from low to high frequency
+    pub fn set_spectrum(&self, queue: &wgpu::Queue, bands: &[f32]) {
+        self.heightfield.set_spectrum(queue, bands);
+    }
+
+    // Advance the animation by the wall clock time since the last call,
+    // unless the clock is paused, and upload the uniforms
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        let dt = self.clock.tick(self.animation_rate());
+        self.upload(queue, dt);
+    }
+
+    // Advance the animation by `dt` seconds at the clock's speed, e.g. a
+    // fixed step per recorded video frame, and upload the uniforms
+    pub fn advance(&mut self, queue: &wgpu::Queue, dt: f32) {
+        self.clock.advance(dt, self.animation_rate());
+        self.upload(queue, dt);
+    }
+
+    // `dt` is the time since the last frame, for effects that limit change
+    // over time
+    fn upload(&mut self, queue: &wgpu::Queue, dt: f32) {
+        self.post.upload(queue, dt);
+        let time = self.time();
+
+        let aspect = self.viewport().aspect();
+        let mut uniforms = Uniforms::new();
+        uniforms.major_color = self.major_color;
+        uniforms.minor_color = self.minor_color;
+        if self.high_contrast {
+            uniforms.major_color = palette::lift(self.major_color, 1.0);
+            uniforms.minor_color = uniforms.major_color;
+        }
+        if let Some(hue) = self.hue {
+            uniforms.major_color = palette::with_hue(uniforms.major_color, hue);
+            uniforms.minor_color = palette::with_hue(uniforms.minor_color, hue);
+        } else if let Some(mut colors) = self.palette.colors() {
+            if self.high_contrast {
+                colors = colors.map(|color| palette::lift(color, HIGH_CONTRAST_MIN_BRIGHTNESS));
+            }
+            uniforms.palette = colors;
+            uniforms.palette_mode = 1 + self.palette_input as u32;
+            uniforms.palette_last_stop = self.palette.last_stop();
+            uniforms.palette_range = PALETTE_DISTANCE * self.world_scale;
+        }
+        uniforms.fog_color = self.fog.color;
+        uniforms.fog_mode = if self.high_contrast {
+            FogMode::Off
+        } else {
+            self.fog.mode
+        } as u32;
+        uniforms.fog_start = self.fog.start * self.world_scale;
+        uniforms.fog_end = self.fog.end * self.world_scale;
+        uniforms.fog_density = self.fog.density / self.world_scale;
+        uniforms.model = (Matrix4::from_scale(self.world_scale)
+            * self.transforms.world_matrix(self.grid_node))
+        .into();
+        uniforms.update(
+            time,
+            self.motion_intensity,
+            self.line_style,
+            self.camera.view_proj(aspect),
+        );
+        self.heightfield
+            .update(queue, time, self.wave_amplitude * self.wave_scale);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    // Encode the height field, grid and post passes that draw the scene into
+    // target, in submission order
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        target: &wgpu::TextureView,
+    ) -> Vec<wgpu::CommandBuffer> {
+        vec![
+            self.heightfield.encode(device),
+            self.encode_grid_pass(device),
+            self.post.encode(device, target),
+        ]
+    }
+
+    fn encode_grid_pass(&self, device: &wgpu::Device) -> wgpu::CommandBuffer {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Grid Pass Encoder"),
+        });
+
+        let (view, resolve_target) = match &self.msaa_target {
+            Some(msaa_target) => (msaa_target, Some(self.post.scene_target())),
+            None => (self.post.scene_target(), None),
+        };
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            // The clear above leaves the letterbox bars black
+            self.viewport().apply(&mut render_pass);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+
+            // Draw the grid
+            match (
+                self.render_mode,
+                &self.filled_pipeline,
+                &self.points_pipeline,
+            ) {
+                (RenderMode::Filled, Some(pipeline), _) if !self.custom_mesh => {
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_vertex_buffer(0, self.surface_vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(
+                        self.surface_index_buffer.slice(..),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                    render_pass.draw_indexed(0..self.num_surface_indices, 0, 0..1);
+                }
+                (RenderMode::Points, _, Some(pipeline)) => {
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                    render_pass.draw(0..self.num_vertices, 0..1);
+                }
+                _ => {
+                    render_pass.set_pipeline(&self.render_pipeline);
+                    render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                    render_pass.draw(0..self.num_vertices, 0..1);
+                }
+            }
+        }
+
+        encoder.finish()
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, size: GridSize) -> (wgpu::Buffer, u32) {
+    let (vertices, num_vertices) = grid::create_mesh(size);
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    (vertex_buffer, num_vertices as u32)
+}
+
+// Vertex and index buffers for the filled surface, plus the index count
+fn create_surface_buffers(
+    device: &wgpu::Device,
+    size: GridSize,
+) -> (wgpu::Buffer, wgpu::Buffer, u32) {
+    let (vertices, indices) = grid::create_surface(size);
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Surface Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Surface Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    (vertex_buffer, index_buffer, indices.len() as u32)
+}
+
+// The grid shader needs the wave functions, which live in their own file.
+// They go after it so compile errors report lines of the grid shader as is.
+fn create_shader(device: &wgpu::Device, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: wgpu::ShaderSource::Wgsl(format!("{}{}", source, include_str!("wave.wgsl")).into()),
+    })
+}
+
+// Solid magenta stand-in with the grid shader's vertices and bindings
+fn create_fallback_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fallback Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("fallback.wgsl").into()),
+    })
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    pipeline_layout: &wgpu::PipelineLayout,
+    mode: RenderMode,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let (label, topology, polygon_mode) = match mode {
+        // Line strips are drawn as lines in any polygon mode, so the
+        // wireframe doesn't need POLYGON_MODE_LINE, which GL lacks
+        RenderMode::Wireframe => (
+            "Render Pipeline",
+            wgpu::PrimitiveTopology::LineStrip,
+            wgpu::PolygonMode::Fill,
+        ),
+        RenderMode::Filled => (
+            "Filled Render Pipeline",
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::PolygonMode::Fill,
+        ),
+        RenderMode::Points => (
+            "Points Render Pipeline",
+            wgpu::PrimitiveTopology::PointList,
+            wgpu::PolygonMode::Fill,
+        ),
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[
+                    wgpu::VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: wgpu::VertexFormat::Float32x3,
+                    },
+                    wgpu::VertexAttribute {
+                        offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                        shader_location: 1,
+                        format: wgpu::VertexFormat::Float32,
+                    },
+                    wgpu::VertexAttribute {
+                        offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                        shader_location: 2,
+                        format: wgpu::VertexFormat::Float32,
+                    },
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+fn create_depth_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        // Only ever an attachment; multisampled depth fails to resolve on GL
+        // when it is also bindable
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_msaa_target(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: SCENE_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// Sample counts the grid pass can use on `device`. Beyond the 1 and 4 that
+// every device supports, the adapter's own format capabilities only count
+// with TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES enabled.
+pub fn supported_sample_counts(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Vec<u32> {
+    let features = |format: wgpu::TextureFormat| {
+        if device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        {
+            adapter.get_texture_format_features(format).flags
+        } else {
+            format.guaranteed_format_features(device.features()).flags
+        }
+    };
+    let (color, depth) = (features(SCENE_FORMAT), features(DEPTH_FORMAT));
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&count| color.sample_count_supported(count) && depth.sample_count_supported(count))
+        .collect()
+}
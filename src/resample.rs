@@ -0,0 +1,161 @@
+use std::f64::consts::PI;
+
+// Zero crossings of the sinc kept on each side of a tap at the output rate
+const ZERO_CROSSINGS: usize = 16;
+// Kernel values stored per input sample, interpolated linearly in between
+const OVERSAMPLING: usize = 64;
+// Share of the lower Nyquist frequency passed, leaving room for the
+// filter's transition band
+const PASSBAND: f64 = 0.95;
+
+// Converts a stream of mono samples from one rate to another with a
+// Blackman-windowed sinc filter, cut off below the lower of the two Nyquist
+// frequencies so nothing above it folds back into the output.
+pub struct Resampler {
+    // Input samples per output sample
+    step: f64,
+    // Kernel from its center outwards, OVERSAMPLING values per input sample
+    kernel: Vec<f32>,
+    // Input samples either side of the center that the kernel covers
+    reach: usize,
+    // Input not yet used up, and the position of the next output in it
+    input: Vec<f32>,
+    position: f64,
+}
+
+impl Resampler {
+    pub fn new(from: u32, to: u32) -> Self {
+        let step = from as f64 / to as f64;
+        // Downsampling widens the kernel in input samples
+        let scale = step.max(1.0);
+        let cutoff = PASSBAND / scale;
+        let reach = (ZERO_CROSSINGS as f64 * scale).ceil() as usize;
+        let kernel = (0..=reach * OVERSAMPLING)
+            .map(|i| {
+                let x = i as f64 / OVERSAMPLING as f64;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * cutoff * x).sin() / (PI * cutoff * x)
+                };
+                let phase = PI * (x / reach as f64 + 1.0);
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                (cutoff * sinc * window) as f32
+            })
+            .collect();
+        Self {
+            step,
+            kernel,
+            reach,
+            // Silence before the first sample, so output starts with it
+            input: vec![0.0; reach],
+            position: reach as f64,
+        }
+    }
+
+    // Feed `input` and append the output it completes to `output`. The
+    // output trails the input by the kernel's reach.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        if self.step == 1.0 {
+            output.extend_from_slice(input);
+            return;
+        }
+        self.input.extend_from_slice(input);
+        while self.position + (self.reach as f64) < self.input.len() as f64 {
+            output.push(self.sample_at(self.position));
+            self.position += self.step;
+        }
+        // Keep what the next output still reaches back to
+        let used = (self.position as usize).saturating_sub(self.reach);
+        self.input.drain(..used);
+        self.position -= used as f64;
+    }
+
+    fn sample_at(&self, position: f64) -> f32 {
+        let center = position as usize;
+        let fraction = position - center as f64;
+        let start = center + 1 - self.reach;
+        let end = (center + self.reach).min(self.input.len() - 1);
+        (start..=end)
+            .map(|index| {
+                let distance = (index as f64 - center as f64 - fraction).abs();
+                self.input[index] * self.kernel_at(distance)
+            })
+            .sum()
+    }
+
+    fn kernel_at(&self, distance: f64) -> f32 {
+        let index = distance * OVERSAMPLING as f64;
+        let below = index as usize;
+        let Some(&after) = self.kernel.get(below + 1) else {
+            return 0.0;
+        };
+        let t = (index - below as f64) as f32;
+        self.kernel[below] * (1.0 - t) + after * t
+    }
+}
+
+// Convert a whole block of samples at once
+pub fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    let mut resampler = Resampler::new(from, to);
+    let mut output = Vec::with_capacity((samples.len() as f64 / resampler.step) as usize + 1);
+    resampler.process(samples, &mut output);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, rate: u32, samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| (2.0 * PI * frequency * i as f64 / rate as f64).sin() as f32)
+            .collect()
+    }
+
+    // Amplitude of `frequency` in `samples`, by correlation
+    fn amplitude(samples: &[f32], frequency: f64, rate: u32) -> f64 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (i, sample) in samples.iter().enumerate() {
+            let phase = 2.0 * PI * frequency * i as f64 / rate as f64;
+            re += *sample as f64 * phase.cos();
+            im += *sample as f64 * phase.sin();
+        }
+        2.0 * (re * re + im * im).sqrt() / samples.len() as f64
+    }
+
+    #[test]
+    fn keeps_a_tone_at_its_pitch() {
+        let output = resample(&sine(1000.0, 44100, 44100), 44100, 48000);
+        assert!((output.len() as i64 - 48000).abs() < 64);
+        // Past the start-up, the tone is at 1 kHz at the new rate
+        let settled = &output[1000..output.len() - 1000];
+        assert!((amplitude(settled, 1000.0, 48000) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn filters_what_the_lower_rate_cannot_hold() {
+        let output = resample(&sine(30000.0, 96000, 96000), 96000, 48000);
+        let settled = &output[1000..output.len() - 1000];
+        // 30 kHz would fold back to 18 kHz
+        assert!(amplitude(settled, 18000.0, 48000) < 0.01);
+    }
+
+    #[test]
+    fn streaming_matches_one_block() {
+        let input = sine(440.0, 22050, 4000);
+        let whole = resample(&input, 22050, 48000);
+        let mut resampler = Resampler::new(22050, 48000);
+        let mut streamed = Vec::new();
+        for chunk in input.chunks(333) {
+            resampler.process(chunk, &mut streamed);
+        }
+        assert_eq!(streamed.len(), whole.len());
+        let error = streamed
+            .iter()
+            .zip(&whole)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(error < 1e-5);
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// How often the local time is checked against the schedule
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// A local time of day, "HH:MM" in the config
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    // Minutes since midnight
+    minutes: u32,
+}
+
+impl TimeOfDay {
+    pub fn now() -> Self {
+        let now = chrono::Local::now();
+        Self {
+            minutes: now.hour() * 60 + now.minute(),
+        }
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        value
+            .split_once(':')
+            .and_then(|(hours, minutes)| Some((hours.parse().ok()?, minutes.parse().ok()?)))
+            .filter(|&(hours, minutes): &(u32, u32)| hours < 24 && minutes < 60)
+            .map(|(hours, minutes)| Self {
+                minutes: hours * 60 + minutes,
+            })
+            .ok_or(format!("invalid time {:?}, expected HH:MM", value))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        format!("{:02}:{:02}", time.minutes / 60, time.minutes % 60)
+    }
+}
+
+// Settings that take effect at a time of day and last until the next entry
+// takes over, wrapping around midnight
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleEntry {
+    pub at: TimeOfDay,
+    // Config file to switch to, relative to the main one; unset returns to
+    // the main config
+    #[serde(default)]
+    pub preset: Option<PathBuf>,
+    // Show a black screen instead of the visualizer
+    #[serde(default)]
+    pub off: bool,
+}
+
+// Daily timeline of schedule entries for unattended installations, e.g. a
+// dimmer preset in the evening and the output off overnight
+pub struct Schedule {
+    entries: Vec<ScheduleEntry>,
+    current: Option<usize>,
+    last_check: Option<Instant>,
+}
+
+impl Schedule {
+    pub fn new(entries: &[ScheduleEntry]) -> Self {
+        let mut schedule = Self {
+            entries: Vec::new(),
+            current: None,
+            last_check: None,
+        };
+        schedule.set_entries(entries);
+        schedule
+    }
+
+    // Replace the entries, e.g. from a reloaded config, and pick the one in
+    // effect now
+    pub fn set_entries(&mut self, entries: &[ScheduleEntry]) {
+        self.entries = entries.to_vec();
+        self.entries.sort_by_key(|entry| entry.at);
+        self.current = self.entry_at(TimeOfDay::now());
+        self.last_check = Some(Instant::now());
+    }
+
+    // The entry in effect, None without a schedule
+    pub fn current(&self) -> Option<&ScheduleEntry> {
+        self.current.map(|index| &self.entries[index])
+    }
+
+    // Check the clock every so often and return whether another entry has
+    // taken effect since the last call
+    pub fn poll(&mut self) -> bool {
+        if self
+            .last_check
+            .is_some_and(|last| last.elapsed() < CHECK_INTERVAL)
+        {
+            return false;
+        }
+        self.last_check = Some(Instant::now());
+        let current = self.entry_at(TimeOfDay::now());
+        let changed = current != self.current;
+        self.current = current;
+        changed
+    }
+
+    // The last entry at or before `time`, or the day's last entry before the
+    // first one starts
+    fn entry_at(&self, time: TimeOfDay) -> Option<usize> {
+        self.entries
+            .iter()
+            .rposition(|entry| entry.at <= time)
+            .or_else(|| self.entries.len().checked_sub(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> TimeOfDay {
+        TimeOfDay::try_from(text.to_string()).unwrap()
+    }
+
+    fn entry(at: &str, preset: Option<&str>) -> ScheduleEntry {
+        ScheduleEntry {
+            at: time(at),
+            preset: preset.map(PathBuf::from),
+            off: preset.is_none(),
+        }
+    }
+
+    fn preset_at(schedule: &Schedule, at: &str) -> Option<PathBuf> {
+        let index = schedule.entry_at(time(at))?;
+        Some(
+            schedule.entries[index]
+                .preset
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("off")),
+        )
+    }
+
+    #[test]
+    fn times_are_hours_and_minutes() {
+        assert_eq!(time("00:00"), TimeOfDay { minutes: 0 });
+        assert_eq!(time("23:59"), TimeOfDay { minutes: 1439 });
+        for invalid in ["24:00", "12:60", "12", "noon", "-1:00"] {
+            assert!(
+                TimeOfDay::try_from(invalid.to_string()).is_err(),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(String::from(time("07:05")), "07:05");
+    }
+
+    #[test]
+    fn entries_last_until_the_next_and_wrap_around_midnight() {
+        // Given out of order, as a config may list them
+        let schedule = Schedule::new(&[
+            entry("09:00", Some("day.toml")),
+            entry("22:00", Some("night.toml")),
+            entry("02:00", None),
+        ]);
+        assert_eq!(preset_at(&schedule, "09:00"), Some("day.toml".into()));
+        assert_eq!(preset_at(&schedule, "21:59"), Some("day.toml".into()));
+        assert_eq!(preset_at(&schedule, "23:30"), Some("night.toml".into()));
+        // Past midnight the last entry of the day is still in effect
+        assert_eq!(preset_at(&schedule, "00:00"), Some("night.toml".into()));
+        assert_eq!(preset_at(&schedule, "01:59"), Some("night.toml".into()));
+        assert_eq!(preset_at(&schedule, "02:00"), Some("off".into()));
+        assert_eq!(preset_at(&schedule, "08:59"), Some("off".into()));
+    }
+
+    #[test]
+    fn a_single_entry_lasts_all_day_and_none_means_no_schedule() {
+        let schedule = Schedule::new(&[entry("12:00", Some("only.toml"))]);
+        assert_eq!(preset_at(&schedule, "11:59"), Some("only.toml".into()));
+        assert_eq!(preset_at(&schedule, "12:00"), Some("only.toml".into()));
+
+        let schedule = Schedule::new(&[]);
+        assert_eq!(schedule.entry_at(time("12:00")), None);
+        assert!(schedule.current().is_none());
+    }
+}
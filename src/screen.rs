@@ -0,0 +1,183 @@
+use crate::webcam::{FrameSource, LumaFrame};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// Captures are box-filtered down to at most this width; the height field is
+// far coarser than any screen
+#[cfg(feature = "screen-capture")]
+const MAX_WIDTH: u32 = 480;
+
+// Delay between captures
+#[cfg(feature = "screen-capture")]
+const CAPTURE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(33);
+
+// What to capture: a monitor by index or the first window whose title
+// contains the given text
+#[derive(Clone, Debug)]
+pub enum CaptureTarget {
+    Monitor(usize),
+    Window(String),
+}
+
+impl CaptureTarget {
+    pub fn parse(arg: &str) -> Self {
+        match arg.parse() {
+            Ok(index) => CaptureTarget::Monitor(index),
+            Err(_) => CaptureTarget::Window(arg.to_string()),
+        }
+    }
+}
+
+// Luminance of a monitor or window, captured on a background thread.
+// Optionally reduced to edge strength so text and UI outlines stand out.
+pub struct ScreenCapture {
+    latest: Arc<Mutex<Option<LumaFrame>>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ScreenCapture {
+    #[cfg(feature = "screen-capture")]
+    pub fn new(target: CaptureTarget, edges: bool) -> Result<Self, Box<dyn Error>> {
+        enum Source {
+            Monitor(xcap::Monitor),
+            Window(xcap::Window),
+        }
+
+        let source = match &target {
+            CaptureTarget::Monitor(index) => {
+                let monitor = xcap::Monitor::all()?
+                    .into_iter()
+                    .nth(*index)
+                    .ok_or_else(|| format!("no monitor {}", index))?;
+                log::info!(
+                    "Capturing monitor {} at {}x{}",
+                    monitor.name(),
+                    monitor.width(),
+                    monitor.height()
+                );
+                Source::Monitor(monitor)
+            }
+            CaptureTarget::Window(title) => {
+                let window = xcap::Window::all()?
+                    .into_iter()
+                    .find(|w| w.title().contains(title.as_str()))
+                    .ok_or_else(|| format!("no window titled \"{}\"", title))?;
+                log::info!("Capturing window \"{}\"", window.title());
+                Source::Window(window)
+            }
+        };
+
+        let latest = Arc::new(Mutex::new(None));
+        let running = Arc::new(AtomicBool::new(true));
+        let (shared, keep_running) = (latest.clone(), running.clone());
+        let thread = std::thread::spawn(move || {
+            while keep_running.load(Ordering::Relaxed) {
+                let image = match &source {
+                    Source::Monitor(monitor) => monitor.capture_image(),
+                    Source::Window(window) => window.capture_image(),
+                };
+                let image = match image {
+                    Ok(image) => image,
+                    Err(e) => {
+                        log::error!("Screen capture stopped: {}", e);
+                        return;
+                    }
+                };
+
+                let mut frame = to_luma(image.width(), image.height(), image.as_raw());
+                if edges {
+                    frame = sobel(&frame);
+                }
+                *shared.lock().unwrap() = Some(frame);
+                std::thread::sleep(CAPTURE_INTERVAL);
+            }
+        });
+
+        Ok(Self {
+            latest,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    #[cfg(not(feature = "screen-capture"))]
+    pub fn new(_target: CaptureTarget, _edges: bool) -> Result<Self, Box<dyn Error>> {
+        Err("built without the `screen-capture` feature".into())
+    }
+}
+
+impl Drop for ScreenCapture {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl FrameSource for ScreenCapture {
+    fn take_frame(&self) -> Option<LumaFrame> {
+        self.latest.lock().unwrap().take()
+    }
+}
+
+// Rec. 709 luminance of an RGBA image, averaged over square blocks so the
+// result is at most MAX_WIDTH wide
+#[cfg(feature = "screen-capture")]
+fn to_luma(width: u32, height: u32, rgba: &[u8]) -> LumaFrame {
+    let block = width.div_ceil(MAX_WIDTH).max(1);
+    let (out_width, out_height) = ((width / block).max(1), (height / block).max(1));
+
+    let mut pixels = Vec::with_capacity((out_width * out_height) as usize);
+    for out_y in 0..out_height {
+        for out_x in 0..out_width {
+            let mut sum = 0.0;
+            for y in out_y * block..((out_y + 1) * block).min(height) {
+                for x in out_x * block..((out_x + 1) * block).min(width) {
+                    let i = ((y * width + x) * 4) as usize;
+                    sum += 0.2126 * rgba[i] as f32
+                        + 0.7152 * rgba[i + 1] as f32
+                        + 0.0722 * rgba[i + 2] as f32;
+                }
+            }
+            pixels.push((sum / (block * block) as f32) as u8);
+        }
+    }
+
+    LumaFrame {
+        width: out_width,
+        height: out_height,
+        pixels,
+    }
+}
+
+// Sobel gradient magnitude; border pixels are left black
+#[cfg(feature = "screen-capture")]
+fn sobel(frame: &LumaFrame) -> LumaFrame {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    let at = |x: usize, y: usize| frame.pixels[y * width + x] as f32;
+
+    let mut pixels = vec![0; width * height];
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            pixels[y * width + x] = gx.hypot(gy).min(255.0) as u8;
+        }
+    }
+
+    LumaFrame {
+        width: frame.width,
+        height: frame.height,
+        pixels,
+    }
+}
@@ -0,0 +1,162 @@
+use exr::prelude::*;
+use std::path::Path;
+
+// Image format of the numbered frames of a headless render
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FrameFormat {
+    // 8-bit sRGB, clipped to white
+    #[default]
+    Png,
+    // Linear floating point with the values above white kept, for grading
+    // and compositing
+    Exr,
+}
+
+impl FrameFormat {
+    // Format of the offscreen target the frames are drawn into
+    pub fn texture_format(self) -> wgpu::TextureFormat {
+        match self {
+            FrameFormat::Png => crate::offscreen::OFFSCREEN_FORMAT,
+            FrameFormat::Exr => crate::offscreen::OFFSCREEN_HDR_FORMAT,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Png => "png",
+            FrameFormat::Exr => "exr",
+        }
+    }
+}
+
+// Bits per channel of EXR frames. The frames are drawn in half floats, so 32
+// bits only widens them, for tools that read nothing else.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SampleDepth {
+    #[default]
+    #[value(name = "16")]
+    Half,
+    #[value(name = "32")]
+    Float,
+}
+
+// Where a frame sits in the sequence, written into its header
+pub struct FrameInfo<'a> {
+    pub frame: u32,
+    pub fps: u32,
+    // Name of the preset the frame was drawn with
+    pub preset: &'a str,
+}
+
+// Write tightly packed RGBA half float pixels, as read back from an
+// OFFSCREEN_HDR_FORMAT target, to an EXR file at `path`
+pub fn write_exr(
+    path: &Path,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    depth: SampleDepth,
+    info: &FrameInfo,
+) -> Result<()> {
+    let samples: Vec<f16> = pixels
+        .chunks_exact(2)
+        .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    match depth {
+        SampleDepth::Half => write_layer(path, &samples, width, height, info, |s| s),
+        SampleDepth::Float => write_layer(path, &samples, width, height, info, f16::to_f32),
+    }
+}
+
+fn write_layer<T: IntoSample + Sync>(
+    path: &Path,
+    samples: &[f16],
+    width: u32,
+    height: u32,
+    info: &FrameInfo,
+    convert: impl Fn(f16) -> T + Sync,
+) -> Result<()> {
+    let mut attributes = LayerAttributes {
+        software_name: Some(Text::from(env!("CARGO_PKG_NAME"))),
+        frames_per_second: Some((info.fps as i32, 1)),
+        ..Default::default()
+    };
+    attributes.other.insert(
+        Text::from("frameTime"),
+        AttributeValue::F64(info.frame as f64 / info.fps as f64),
+    );
+    attributes
+        .other
+        .insert(Text::from("frame"), AttributeValue::I32(info.frame as i32));
+    // Header text is Latin-1; other characters become question marks
+    let preset: String = info
+        .preset
+        .chars()
+        .map(|c| if (c as u32) < 256 { c } else { '?' })
+        .collect();
+    if let Some(preset) = Text::new_or_none(preset) {
+        attributes
+            .other
+            .insert(Text::from("preset"), AttributeValue::Text(preset));
+    }
+
+    let width = width as usize;
+    let layer = Layer::new(
+        (width, height as usize),
+        attributes,
+        Encoding::SMALL_LOSSLESS,
+        SpecificChannels::rgba(|Vec2(x, y): Vec2<usize>| {
+            let i = (y * width + x) * 4;
+            (
+                convert(samples[i]),
+                convert(samples[i + 1]),
+                convert(samples[i + 2]),
+                convert(samples[i + 3]),
+            )
+        }),
+    );
+    Image::from_layer(layer).write().to_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_keep_values_above_white_and_their_metadata() {
+        let pixels: Vec<u8> = [2.5f32, 0.5, 0.0, 1.0, 0.25, 0.125, 4.0, 1.0]
+            .iter()
+            .flat_map(|&v| f16::from_f32(v).to_le_bytes())
+            .collect();
+        let info = FrameInfo {
+            frame: 30,
+            fps: 60,
+            preset: "night",
+        };
+        let path = std::env::temp_dir().join(format!("sequence-test-{}.exr", std::process::id()));
+        for depth in [SampleDepth::Half, SampleDepth::Float] {
+            write_exr(&path, &pixels, 2, 1, depth, &info).unwrap();
+            let image = read_first_rgba_layer_from_file(
+                &path,
+                |size, _| vec![(0.0f32, 0.0, 0.0, 0.0); size.width() * size.height()],
+                |pixels, Vec2(x, _), pixel: (f32, f32, f32, f32)| pixels[x] = pixel,
+            )
+            .unwrap();
+            let layer = &image.layer_data;
+            assert_eq!(layer.channel_data.pixels[0], (2.5, 0.5, 0.0, 1.0));
+            assert_eq!(layer.channel_data.pixels[1], (0.25, 0.125, 4.0, 1.0));
+            let other = &layer.attributes.other;
+            assert_eq!(
+                other.get(&Text::from("frameTime")),
+                Some(&AttributeValue::F64(0.5))
+            );
+            assert_eq!(
+                other.get(&Text::from("preset")),
+                Some(&AttributeValue::Text(Text::from("night")))
+            );
+            let sample_type = layer.channel_data.channels.0.sample_type;
+            assert_eq!(sample_type == SampleType::F32, depth == SampleDepth::Float);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
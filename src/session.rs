@@ -0,0 +1,176 @@
+use crate::telemetry::DROPPED_FRAME_US;
+use std::fmt;
+use std::time::Instant;
+
+// Frame times are counted in buckets this wide up to a second, so memory
+// stays fixed however long an installation runs
+const BUCKET_US: u64 = 100;
+const BUCKETS: usize = 10_000;
+
+// Running totals for the summary printed when the app exits
+pub struct SessionStats {
+    start: Instant,
+    last_frame: Instant,
+    frames: u64,
+    total_frame_time_us: u64,
+    frame_time_buckets: Vec<u64>,
+    dropped_frames: u64,
+    surface_errors: u64,
+    mutations: u64,
+    config_reloads: u64,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last_frame: now,
+            frames: 0,
+            total_frame_time_us: 0,
+            frame_time_buckets: vec![0; BUCKETS],
+            dropped_frames: 0,
+            surface_errors: 0,
+            mutations: 0,
+            config_reloads: 0,
+        }
+    }
+
+    pub fn record_frame(&mut self, surface_error: bool) {
+        let now = Instant::now();
+        let frame_time_us = now.duration_since(self.last_frame).as_micros() as u64;
+        self.last_frame = now;
+
+        self.frames += 1;
+        self.total_frame_time_us += frame_time_us;
+        let bucket = ((frame_time_us / BUCKET_US) as usize).min(BUCKETS - 1);
+        self.frame_time_buckets[bucket] += 1;
+        if frame_time_us > DROPPED_FRAME_US as u64 {
+            self.dropped_frames += 1;
+        }
+        if surface_error {
+            self.surface_errors += 1;
+        }
+    }
+
+    pub fn record_mutation(&mut self) {
+        self.mutations += 1;
+    }
+
+    pub fn record_config_reload(&mut self) {
+        self.config_reloads += 1;
+    }
+
+    // Summary so far; audio stream errors are counted by the audio source
+    pub fn summary(&self, audio_xruns: u64) -> SessionSummary {
+        let average_frame_ms = if self.frames > 0 {
+            self.total_frame_time_us as f64 / self.frames as f64 / 1000.0
+        } else {
+            0.0
+        };
+
+        SessionSummary {
+            duration: self.start.elapsed().as_secs_f64(),
+            frames: self.frames,
+            average_frame_ms,
+            low_frame_ms: self.percentile_ms(0.99),
+            dropped_frames: self.dropped_frames,
+            surface_errors: self.surface_errors,
+            audio_xruns,
+            mutations: self.mutations,
+            config_reloads: self.config_reloads,
+        }
+    }
+
+    // Upper edge of the bucket holding the frame time at `p`
+    fn percentile_ms(&self, p: f64) -> f64 {
+        let rank = (self.frames as f64 * p).ceil() as u64;
+        let mut count = 0;
+        for (bucket, frames) in self.frame_time_buckets.iter().enumerate() {
+            count += frames;
+            if count >= rank.max(1) {
+                return ((bucket as u64 + 1) * BUCKET_US) as f64 / 1000.0;
+            }
+        }
+        0.0
+    }
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct SessionSummary {
+    // Seconds
+    pub duration: f64,
+    pub frames: u64,
+    pub average_frame_ms: f64,
+    // 99th percentile frame time, the "1% low"
+    pub low_frame_ms: f64,
+    pub dropped_frames: u64,
+    pub surface_errors: u64,
+    pub audio_xruns: u64,
+    pub mutations: u64,
+    pub config_reloads: u64,
+}
+
+impl SessionSummary {
+    // Machine-readable form for benchmark runs and installation monitoring
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\n  \"duration_s\": {:.3},\n  \"frames\": {},\n  \"average_fps\": {:.2},\n  \
+             \"average_frame_ms\": {:.3},\n  \"low_1_percent_frame_ms\": {:.3},\n  \
+             \"low_1_percent_fps\": {:.2},\n  \"dropped_frames\": {},\n  \
+             \"surface_errors\": {},\n  \"audio_xruns\": {},\n  \"mutations\": {},\n  \
+             \"config_reloads\": {}\n}}\n",
+            self.duration,
+            self.frames,
+            fps(self.average_frame_ms),
+            self.average_frame_ms,
+            self.low_frame_ms,
+            fps(self.low_frame_ms),
+            self.dropped_frames,
+            self.surface_errors,
+            self.audio_xruns,
+            self.mutations,
+            self.config_reloads,
+        )
+    }
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Session: {:.1} s, {} frames\n\
+             Average: {:.2} ms ({:.1} FPS)\n\
+             1% low: {:.2} ms ({:.1} FPS)\n\
+             Dropped frames (> {:.1} ms): {}\n\
+             Surface errors: {}\n\
+             Audio xruns: {}\n\
+             Mutations: {}, config reloads: {}",
+            self.duration,
+            self.frames,
+            self.average_frame_ms,
+            fps(self.average_frame_ms),
+            self.low_frame_ms,
+            fps(self.low_frame_ms),
+            DROPPED_FRAME_US as f64 / 1000.0,
+            self.dropped_frames,
+            self.surface_errors,
+            self.audio_xruns,
+            self.mutations,
+            self.config_reloads,
+        )
+    }
+}
+
+fn fps(frame_ms: f64) -> f64 {
+    if frame_ms > 0.0 {
+        1000.0 / frame_ms
+    } else {
+        0.0
+    }
+}
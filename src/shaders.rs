@@ -0,0 +1,47 @@
+use crate::watch::FileWatcher;
+use std::error::Error;
+use std::path::Path;
+
+// File name of the grid shader inside the shader directory
+pub const GRID_SHADER: &str = "shader.wgsl";
+
+// Built-in copy of the grid shader, used when no shader directory is found
+pub const BUILTIN_GRID_SHADER: &str = include_str!("../shaders/shader.wgsl");
+
+// Loads the grid shader from a directory at runtime and reports edits to it,
+// so it can be tweaked while the app runs. Compiling is left to the renderer,
+// which keeps the last working shader when a new version fails.
+pub struct ShaderWatcher {
+    file: FileWatcher,
+}
+
+impl ShaderWatcher {
+    pub fn new(directory: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            file: FileWatcher::new(&directory.join(GRID_SHADER))?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    pub fn load(&self) -> std::io::Result<String> {
+        std::fs::read_to_string(self.file.path())
+    }
+
+    // The new source if the file changed since the last call
+    pub fn poll(&self) -> Option<String> {
+        if !self.file.changed() {
+            return None;
+        }
+
+        match self.load() {
+            Ok(source) => Some(source),
+            Err(e) => {
+                log::error!("Failed to read {}: {}", self.path().display(), e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::config::Config;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::error::Error;
+
+// Marks a shared preset, so other text pasted by mistake is recognized
+pub const PREFIX: &str = "sideharso:";
+
+// A look shared as one line of text, e.g. in a chat: the settings that
+// differ from the defaults, as TOML in URL-safe base64 after PREFIX. The
+// window, GPU and font settings, file paths, schedule and triggers belong to
+// the machine and are left out.
+pub fn encode(config: &Config) -> Result<String, Box<dyn Error>> {
+    let mut shared = config.clone();
+    keep_local(&mut shared, &Config::default());
+    let value = toml::Value::try_from(&shared)?;
+    let default = toml::Value::try_from(Config::default())?;
+    let changed = changed(value, &default).unwrap_or(toml::Value::Table(Default::default()));
+    let text = toml::to_string(&changed)?;
+    Ok(format!("{}{}", PREFIX, URL_SAFE_NO_PAD.encode(text)))
+}
+
+// The look in a shared preset on top of `current`, which keeps its own
+// machine settings. The preset is checked like a config file.
+pub fn decode(text: &str, current: &Config) -> Result<Config, Box<dyn Error>> {
+    let encoded = text
+        .trim()
+        .strip_prefix(PREFIX)
+        .ok_or("not a shared preset")?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| format!("damaged shared preset: {}", e))?;
+    let mut config = Config::parse(&String::from_utf8(bytes)?, None)?;
+    keep_local(&mut config, current);
+    Ok(config)
+}
+
+// Take the settings that aren't shared from `from`
+fn keep_local(config: &mut Config, from: &Config) {
+    config.window = from.window.clone();
+    config.gpu = from.gpu.clone();
+    config.text = from.text.clone();
+    config.heightmap.path = from.heightmap.path.clone();
+    config.output.lut = from.output.lut.clone();
+    config.logo.path = from.logo.path.clone();
+    config.schedule = from.schedule.clone();
+    config.triggers = from.triggers.clone();
+}
+
+// The entries of `value` that differ from `default`, recursing into tables.
+// Arrays count as one value, as they replace the default when read.
+fn changed(value: toml::Value, default: &toml::Value) -> Option<toml::Value> {
+    match (value, default) {
+        (toml::Value::Table(table), toml::Value::Table(default)) => {
+            let table: toml::Table = table
+                .into_iter()
+                .filter_map(|(key, value)| match default.get(&key) {
+                    Some(default) => changed(value, default).map(|value| (key, value)),
+                    None => Some((key, value)),
+                })
+                .collect();
+            (!table.is_empty()).then_some(toml::Value::Table(table))
+        }
+        (value, default) => (value != *default).then_some(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::Palette;
+    use std::path::PathBuf;
+
+    #[test]
+    fn a_shared_look_comes_back_on_another_machine() {
+        let mut config = Config::default();
+        config.waves.amplitude = 1.7;
+        config.waves.gerstner.truncate(1);
+        config.colors.palette = Palette::Synthwave;
+        config.bloom.enabled = true;
+        config.fog.density = 0.25;
+        config.window.width = 1920;
+        config.heightmap.path = Some(PathBuf::from("/home/me/terrain.png"));
+
+        let mut current = Config::default();
+        current.window.width = 640;
+        let decoded = decode(&encode(&config).unwrap(), &current).unwrap();
+
+        let mut expected = config.clone();
+        expected.window = current.window.clone();
+        expected.heightmap.path = None;
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn only_changes_from_the_defaults_are_encoded() {
+        assert_eq!(encode(&Config::default()).unwrap(), PREFIX);
+
+        let mut config = Config::default();
+        config.bloom.enabled = true;
+        let encoded = encode(&config).unwrap();
+        let text = URL_SAFE_NO_PAD
+            .decode(encoded.strip_prefix(PREFIX).unwrap())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "[bloom]\nenabled = true\n"
+        );
+    }
+
+    #[test]
+    fn other_text_and_invalid_settings_are_rejected() {
+        let current = Config::default();
+        assert!(decode("hello", &current).is_err());
+        assert!(decode("sideharso:not base64!", &current).is_err());
+        let invalid = format!(
+            "{}{}",
+            PREFIX,
+            URL_SAFE_NO_PAD.encode("[grid]\nwidth = 1\n")
+        );
+        assert!(decode(&invalid, &current).is_err());
+        // Pasting often brings a line break along
+        let pasted = format!("  {}\n", encode(&current).unwrap());
+        assert_eq!(decode(&pasted, &current).unwrap(), current);
+    }
+}
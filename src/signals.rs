@@ -0,0 +1,54 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Flags set by Unix signals and polled once per frame, so the process can be
+// run under a service manager:
+// - SIGINT and SIGTERM stop at the end of a frame and clean up, instead of
+//   dying mid-write. A second one exits immediately in case that hangs.
+// - SIGHUP reloads the config file.
+// - SIGUSR1 saves a screenshot.
+#[derive(Clone)]
+pub struct Signals {
+    shutdown: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+    screenshot: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn install() -> io::Result<Self> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+
+        let signals = Self {
+            shutdown: Arc::new(AtomicBool::new(false)),
+            reload: Arc::new(AtomicBool::new(false)),
+            screenshot: Arc::new(AtomicBool::new(false)),
+        };
+        for signal in [SIGINT, SIGTERM] {
+            // Checked before the flag is set, so only fires on the second one
+            signal_hook::flag::register_conditional_shutdown(signal, 1, signals.shutdown.clone())?;
+            signal_hook::flag::register(signal, signals.shutdown.clone())?;
+        }
+        #[cfg(unix)]
+        {
+            use signal_hook::consts::{SIGHUP, SIGUSR1};
+            signal_hook::flag::register(SIGHUP, signals.reload.clone())?;
+            signal_hook::flag::register(SIGUSR1, signals.screenshot.clone())?;
+        }
+        Ok(signals)
+    }
+
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+
+    // Whether a reload was requested since the last call
+    pub fn take_reload(&self) -> bool {
+        self.reload.swap(false, Ordering::Relaxed)
+    }
+
+    // Whether a screenshot was requested since the last call
+    pub fn take_screenshot(&self) -> bool {
+        self.screenshot.swap(false, Ordering::Relaxed)
+    }
+}
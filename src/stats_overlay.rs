@@ -0,0 +1,105 @@
+use crate::grid::GridSize;
+use crate::text::TextRenderer;
+use cgmath::Point3;
+
+// Seconds over which the timings are averaged, so the numbers stay readable
+const REFRESH_INTERVAL: f32 = 0.5;
+
+const FONT_SIZE: f32 = 16.0;
+// Pixels between the viewport corner, the backdrop and the text
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+// One frame's measurements, in milliseconds where timed
+pub struct FrameStats {
+    // Time since the previous frame
+    pub frame_time: f32,
+    // CPU time spent updating and encoding the frame
+    pub cpu_time: f32,
+    // None without timestamp query support
+    pub gpu_time: Option<f32>,
+    pub camera: Point3<f32>,
+    pub grid: GridSize,
+    pub vertices: u32,
+}
+
+// Heads-up display in the top left corner with frame rate, frame timings,
+// camera position and grid size
+#[derive(Default)]
+pub struct StatsOverlay {
+    pub visible: bool,
+    // Sums since the displayed averages were last refreshed
+    frames: u32,
+    frame_time: f32,
+    cpu_time: f32,
+    gpu_time: f32,
+    gpu_frames: u32,
+    timings: String,
+}
+
+impl StatsOverlay {
+    // Accumulate a frame and lay out its text in `text` for the next draw
+    pub fn push(&mut self, stats: &FrameStats, text: &mut TextRenderer) {
+        self.frames += 1;
+        self.frame_time += stats.frame_time;
+        self.cpu_time += stats.cpu_time;
+        if let Some(gpu_time) = stats.gpu_time {
+            self.gpu_time += gpu_time;
+            self.gpu_frames += 1;
+        }
+
+        if self.frame_time >= REFRESH_INTERVAL * 1000.0 {
+            let frames = self.frames as f32;
+            let gpu = if self.gpu_frames > 0 {
+                format!("{:.2} ms", self.gpu_time / self.gpu_frames as f32)
+            } else {
+                "n/a".to_string()
+            };
+            self.timings = format!(
+                "{:.0} fps ({:.2} ms)\ncpu {:.2} ms\ngpu {}",
+                frames * 1000.0 / self.frame_time,
+                self.frame_time / frames,
+                self.cpu_time / frames,
+                gpu,
+            );
+            self.frames = 0;
+            self.frame_time = 0.0;
+            self.cpu_time = 0.0;
+            self.gpu_time = 0.0;
+            self.gpu_frames = 0;
+        }
+
+        if !self.visible || self.timings.is_empty() {
+            return;
+        }
+        let label = format!(
+            "{}\ncamera {:.2} {:.2} {:.2}\ngrid {}x{}, {} vertices",
+            self.timings,
+            stats.camera.x,
+            stats.camera.y,
+            stats.camera.z,
+            stats.grid.width,
+            stats.grid.depth,
+            stats.vertices,
+        );
+        let lines = label.lines().count() as f32;
+        let size = [
+            text.measure(&label, FONT_SIZE) + PADDING * 2.0,
+            lines * text.line_height(FONT_SIZE) + PADDING * 2.0,
+        ];
+        text.add_rect(
+            [MARGIN, MARGIN],
+            [MARGIN + size[0], MARGIN + size[1]],
+            BACKDROP_COLOR,
+        );
+        text.add_text(
+            &label,
+            [MARGIN + PADDING, MARGIN + PADDING],
+            FONT_SIZE,
+            TEXT_COLOR,
+        );
+    }
+}
@@ -0,0 +1,132 @@
+// Goniometer points kept per measurement, from the most recent frames
+const POINTS: usize = 256;
+// Frames quieter than this RMS level in dBFS have no stereo image
+const MIN_LEVEL: f32 = -70.0;
+
+// Stereo image of a stretch of audio
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stereo {
+    // Phase correlation of left and right: 1 for mono, 0 for unrelated
+    // channels and -1 for one channel inverted
+    pub correlation: f32,
+    // Share of the level in the side (L - R) rather than the mid (L + R)
+    // signal: 0 for mono, 0.5 for unrelated channels and 1 for inverted
+    pub width: f32,
+    // -1 for left only through 1 for right only
+    pub balance: f32,
+    // Frames as goniometer points, side across and mid up, in -1..1 for a
+    // full-scale signal
+    pub points: Vec<[f32; 2]>,
+}
+
+// Sums of left and right frames, collected until the next measurement
+#[derive(Default)]
+pub struct StereoMeter {
+    left: f64,
+    right: f64,
+    product: f64,
+    mid: f64,
+    side: f64,
+    frames: usize,
+    points: Vec<[f32; 2]>,
+}
+
+impl StereoMeter {
+    pub fn add(&mut self, left: f32, right: f32) {
+        let (l, r) = (left as f64, right as f64);
+        let mid = (left + right) * std::f32::consts::FRAC_1_SQRT_2;
+        let side = (right - left) * std::f32::consts::FRAC_1_SQRT_2;
+        self.left += l * l;
+        self.right += r * r;
+        self.product += l * r;
+        self.mid += (mid * mid) as f64;
+        self.side += (side * side) as f64;
+        self.frames += 1;
+        // A ring of the most recent frames
+        if self.points.len() < POINTS {
+            self.points.push([side, mid]);
+        } else {
+            self.points[self.frames % POINTS] = [side, mid];
+        }
+    }
+
+    // The stereo image of the frames added since the last call, or None if
+    // they were silent
+    pub fn take(&mut self) -> Option<Stereo> {
+        let meter = std::mem::take(self);
+        let power = (meter.left + meter.right) / (2 * meter.frames.max(1)) as f64;
+        if 10.0 * (power + 1e-12).log10() < MIN_LEVEL as f64 {
+            return None;
+        }
+        let (left, right) = (meter.left.sqrt(), meter.right.sqrt());
+        let (mid, side) = (meter.mid.sqrt(), meter.side.sqrt());
+        let correlation = if left > 0.0 && right > 0.0 {
+            meter.product / (left * right)
+        } else {
+            0.0
+        };
+        Some(Stereo {
+            correlation: correlation as f32,
+            width: (side / (mid + side)) as f32,
+            balance: ((right - left) / (right + left)) as f32,
+            points: meter.points,
+        })
+    }
+}
+
+// The stereo image of interleaved `samples`, None for fewer than two
+// channels or silence. Channels past the first two are left out.
+pub fn measure(samples: &[f32], channels: usize) -> Option<Stereo> {
+    if channels < 2 {
+        return None;
+    }
+    let mut meter = StereoMeter::default();
+    for frame in samples.chunks_exact(channels) {
+        meter.add(frame[0], frame[1]);
+    }
+    meter.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Interleaved stereo frames of a sine on the left and `right` of it on
+    // the right
+    fn stereo(right: impl Fn(f32, usize) -> f32) -> Vec<f32> {
+        (0..4800)
+            .flat_map(|i| {
+                let left = (i as f32 * 0.05).sin() * 0.5;
+                [left, right(left, i)]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mono_is_fully_correlated_and_centered() {
+        let image = measure(&stereo(|left, _| left), 2).unwrap();
+        assert!((image.correlation - 1.0).abs() < 1e-4);
+        assert!(image.width.abs() < 1e-4);
+        assert!(image.balance.abs() < 1e-4);
+    }
+
+    #[test]
+    fn inverted_and_unrelated_channels() {
+        let image = measure(&stereo(|left, _| -left), 2).unwrap();
+        assert!((image.correlation + 1.0).abs() < 1e-4);
+        assert!((image.width - 1.0).abs() < 1e-4);
+
+        // A cosine at the same level, a quarter period out
+        let image = measure(&stereo(|_, i| (i as f32 * 0.05).cos() * 0.5), 2).unwrap();
+        assert!(image.correlation.abs() < 0.02);
+        assert!((image.width - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn balance_follows_the_louder_side() {
+        let image = measure(&stereo(|_, _| 0.0), 2).unwrap();
+        assert!((image.balance + 1.0).abs() < 1e-4);
+        assert_eq!(measure(&[0.0; 512], 2), None);
+        assert_eq!(measure(&[0.5; 512], 1), None);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::grid::Vertex;
+use std::error::Error;
+use std::path::Path;
+use usvg::tiny_skia_path::{PathSegment, Point};
+
+// Line segments per curve when flattening Beziers
+const CURVE_SEGMENTS: u32 = 16;
+
+// Load every path in an SVG as flattened outlines lying on the grid plane,
+// scaled to fit the same -1..1 square as the grid
+pub fn load_outline(path: &Path) -> Result<Vec<Vertex>, Box<dyn Error>> {
+    let tree = usvg::Tree::from_data(&std::fs::read(path)?, &usvg::Options::default())?;
+    let mut polylines = Vec::new();
+    collect_polylines(tree.root(), &mut polylines);
+    if polylines.is_empty() {
+        return Err("no paths found".into());
+    }
+
+    // Center on the bounding box and keep the aspect ratio
+    let (mut min, mut max) = (
+        Point::from_xy(f32::MAX, f32::MAX),
+        Point::from_xy(f32::MIN, f32::MIN),
+    );
+    for point in polylines.iter().flatten() {
+        min = Point::from_xy(min.x.min(point.x), min.y.min(point.y));
+        max = Point::from_xy(max.x.max(point.x), max.y.max(point.y));
+    }
+    let center = Point::from_xy((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+    let scale = 2.0 / (max.x - min.x).max(max.y - min.y).max(f32::EPSILON);
+    // The default camera looks along +Z, so +X is screen left and +Z is away
+    // from the viewer; map SVG right and up onto those
+    let to_grid = |p: Point| [(center.x - p.x) * scale, 0.0, (center.y - p.y) * scale];
+
+    // One line strip with hidden connectors between polylines
+    let mut vertices: Vec<Vertex> = Vec::new();
+    for polyline in &polylines {
+        if let Some(last) = vertices.last().copied() {
+            vertices.push(Vertex {
+                major: -1.0,
+                ..last
+            });
+            vertices.push(Vertex {
+                position: to_grid(polyline[0]),
+                major: -1.0,
+                distance: 0.0,
+            });
+        }
+
+        let mut distance = 0.0;
+        for (i, &point) in polyline.iter().enumerate() {
+            if i > 0 {
+                let previous = polyline[i - 1];
+                distance += (point.x - previous.x).hypot(point.y - previous.y) * scale;
+            }
+            vertices.push(Vertex {
+                position: to_grid(point),
+                major: 1.0,
+                distance,
+            });
+        }
+    }
+
+    Ok(vertices)
+}
+
+fn collect_polylines(group: &usvg::Group, polylines: &mut Vec<Vec<Point>>) {
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => collect_polylines(group, polylines),
+            usvg::Node::Path(path) => flatten_path(path, polylines),
+            _ => {}
+        }
+    }
+}
+
+// Split a path into polylines in canvas coordinates, one per subpath
+fn flatten_path(path: &usvg::Path, polylines: &mut Vec<Vec<Point>>) {
+    let mut flattened = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    let mut finish = |current: &mut Vec<Point>| {
+        if current.len() > 1 {
+            flattened.push(std::mem::take(current));
+        }
+        current.clear();
+    };
+
+    for segment in path.data().segments() {
+        let from = current.last().copied().unwrap_or_default();
+        match segment {
+            PathSegment::MoveTo(p) => {
+                finish(&mut current);
+                current.push(p);
+            }
+            PathSegment::LineTo(p) => current.push(p),
+            PathSegment::QuadTo(c, p) => {
+                for i in 1..=CURVE_SEGMENTS {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    current.push(Point::from_xy(
+                        u * u * from.x + 2.0 * u * t * c.x + t * t * p.x,
+                        u * u * from.y + 2.0 * u * t * c.y + t * t * p.y,
+                    ));
+                }
+            }
+            PathSegment::CubicTo(c1, c2, p) => {
+                for i in 1..=CURVE_SEGMENTS {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    current.push(Point::from_xy(
+                        a * from.x + b * c1.x + c * c2.x + d * p.x,
+                        a * from.y + b * c1.y + c * c2.y + d * p.y,
+                    ));
+                }
+            }
+            PathSegment::Close => {
+                if let Some(&first) = current.first() {
+                    current.push(first);
+                }
+            }
+        }
+    }
+    finish(&mut current);
+
+    let transform = path.abs_transform();
+    for mut polyline in flattened {
+        transform.map_points(&mut polyline);
+        polylines.push(polyline);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Binary log layout: magic, version, then fixed-size little-endian records
+const MAGIC: &[u8; 4] = b"SHTL";
+const VERSION: u32 = 1;
+
+// Roughly ten minutes of frames at 60 Hz
+const CAPACITY: usize = 36_000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+// A frame counts as dropped when it takes longer than two 60 Hz intervals
+pub const DROPPED_FRAME_US: u32 = 33_333;
+
+pub const FLAG_SURFACE_ERROR: u32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+pub struct FrameRecord {
+    pub timestamp_us: u64,
+    pub frame_time_us: u32,
+    pub flags: u32,
+}
+
+// Rolling per-frame telemetry, periodically persisted so a stutter report
+// can be analyzed later on another machine
+pub struct Telemetry {
+    path: PathBuf,
+    records: VecDeque<FrameRecord>,
+    start: Instant,
+    last_frame: Instant,
+    last_flush: Instant,
+}
+
+impl Telemetry {
+    pub fn new(path: PathBuf) -> Self {
+        let now = Instant::now();
+        Self {
+            path,
+            records: VecDeque::with_capacity(CAPACITY),
+            start: now,
+            last_frame: now,
+            last_flush: now,
+        }
+    }
+
+    pub fn record_frame(&mut self, flags: u32) {
+        let now = Instant::now();
+        if self.records.len() == CAPACITY {
+            self.records.pop_front();
+        }
+        self.records.push_back(FrameRecord {
+            timestamp_us: now.duration_since(self.start).as_micros() as u64,
+            frame_time_us: now.duration_since(self.last_frame).as_micros() as u32,
+            flags,
+        });
+        self.last_frame = now;
+
+        if now.duration_since(self.last_flush) >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if let Err(e) = self.write() {
+            log::error!(
+                "Failed to write telemetry to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        for record in &self.records {
+            writer.write_all(&record.timestamp_us.to_le_bytes())?;
+            writer.write_all(&record.frame_time_us.to_le_bytes())?;
+            writer.write_all(&record.flags.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+}
+
+pub fn read_log(path: &Path) -> io::Result<Vec<FrameRecord>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a telemetry log",
+        ));
+    }
+    let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+    if version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported telemetry version {}", version),
+        ));
+    }
+
+    let mut records = Vec::new();
+    let mut record = [0u8; 16];
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) => records.push(FrameRecord {
+                timestamp_us: u64::from_le_bytes(record[..8].try_into().unwrap()),
+                frame_time_us: u32::from_le_bytes(record[8..12].try_into().unwrap()),
+                flags: u32::from_le_bytes(record[12..].try_into().unwrap()),
+            }),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(records)
+}
+
+// Human-readable summary of a telemetry log
+pub fn summarize(records: &[FrameRecord]) -> String {
+    if records.is_empty() {
+        return "No frames recorded".to_string();
+    }
+
+    let mut frame_times: Vec<u32> = records.iter().map(|r| r.frame_time_us).collect();
+    frame_times.sort_unstable();
+    let percentile = |p: f64| {
+        let index = ((frame_times.len() - 1) as f64 * p).round() as usize;
+        frame_times[index] as f64 / 1000.0
+    };
+
+    let total_us: u64 = frame_times.iter().map(|&t| t as u64).sum();
+    let average_ms = total_us as f64 / frame_times.len() as f64 / 1000.0;
+    let duration_s =
+        (records[records.len() - 1].timestamp_us - records[0].timestamp_us) as f64 / 1e6;
+    let dropped = frame_times
+        .iter()
+        .filter(|&&t| t > DROPPED_FRAME_US)
+        .count();
+    let surface_errors = records
+        .iter()
+        .filter(|r| r.flags & FLAG_SURFACE_ERROR != 0)
+        .count();
+
+    format!(
+        "Frames: {}\n\
+         Duration: {:.1} s\n\
+         Average frame time: {:.2} ms ({:.1} FPS)\n\
+         Median frame time: {:.2} ms\n\
+         99th percentile: {:.2} ms (1% low {:.1} FPS)\n\
+         Worst frame: {:.2} ms\n\
+         Dropped frames (> {:.1} ms): {}\n\
+         Surface errors: {}",
+        records.len(),
+        duration_s,
+        average_ms,
+        1000.0 / average_ms,
+        percentile(0.5),
+        percentile(0.99),
+        1000.0 / percentile(0.99),
+        percentile(1.0),
+        DROPPED_FRAME_US as f64 / 1000.0,
+        dropped,
+        surface_errors,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("telemetry-test-{}-{}", std::process::id(), name))
+    }
+
+    fn frames(frame_times_us: impl IntoIterator<Item = u32>) -> Vec<FrameRecord> {
+        let mut timestamp_us = 0;
+        frame_times_us
+            .into_iter()
+            .map(|frame_time_us| {
+                timestamp_us += frame_time_us as u64;
+                FrameRecord {
+                    timestamp_us,
+                    frame_time_us,
+                    flags: 0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn records_come_back_from_the_log() {
+        let path = temp_path("round-trip");
+        let mut telemetry = Telemetry::new(path.clone());
+        telemetry.records.extend(frames([16_667, 50_000, 8_000]));
+        telemetry.records[1].flags = FLAG_SURFACE_ERROR;
+        telemetry.write().unwrap();
+
+        let records = read_log(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let fields = |r: &FrameRecord| (r.timestamp_us, r.frame_time_us, r.flags);
+        assert_eq!(
+            records.iter().map(fields).collect::<Vec<_>>(),
+            telemetry.records.iter().map(fields).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn other_files_and_versions_are_rejected() {
+        let path = temp_path("invalid");
+        std::fs::write(&path, b"PNG\0\x01\0\0\0").unwrap();
+        assert_eq!(
+            read_log(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        std::fs::write(&path, b"SHTL\x02\0\0\0").unwrap();
+        assert_eq!(
+            read_log(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        // A record cut off by a crash mid-write is dropped
+        std::fs::write(&path, b"SHTL\x01\0\0\0\x01\x02\x03").unwrap();
+        assert!(read_log(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn summary_has_the_percentiles_and_dropped_frames() {
+        // 1 to 100 ms, shuffled
+        let mut records = frames((1..=100).map(|i| (i * 37 % 101) * 1000));
+        records[0].flags = FLAG_SURFACE_ERROR;
+        let summary = summarize(&records);
+        assert!(summary.contains("Frames: 100\n"), "{}", summary);
+        assert!(summary.contains("Duration: 5.0 s\n"), "{}", summary);
+        assert!(
+            summary.contains("Average frame time: 50.50 ms"),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.contains("Median frame time: 51.00 ms"),
+            "{}",
+            summary
+        );
+        assert!(summary.contains("99th percentile: 99.00 ms"), "{}", summary);
+        assert!(summary.contains("Worst frame: 100.00 ms"), "{}", summary);
+        assert!(summary.contains("(> 33.3 ms): 67\n"), "{}", summary);
+        assert!(summary.contains("Surface errors: 1"), "{}", summary);
+    }
+
+    #[test]
+    fn an_empty_log_says_so() {
+        assert_eq!(summarize(&[]), "No frames recorded");
+    }
+}
@@ -0,0 +1,406 @@
+use super::font::FontSet;
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use rustybuzz::ttf_parser::{self, GlyphId, RasterImageFormat};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Side of the square atlas texture. Glyphs are added as they are first drawn,
+// in every size in use, and the atlas starts over when it fills up.
+const ATLAS_SIZE: u32 = 1024;
+// Empty pixels around each glyph so linear filtering never bleeds
+const ATLAS_PADDING: u32 = 1;
+
+// Where a glyph sits in the atlas and how to place it relative to the pen
+// position on the baseline, in pixels
+#[derive(Clone, Copy, Default)]
+pub struct GlyphInfo {
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    // Drawn in its own colors, such as an emoji, rather than the text color
+    pub color: bool,
+}
+
+// Font, glyph and pixel height. Heights are rounded so nearby sizes share
+// their glyphs.
+type GlyphKey = (usize, GlyphId, u32);
+
+// A rasterized glyph in RGBA, with its offset from the pen position
+struct Bitmap {
+    offset: [i32; 2],
+    size: [u32; 2],
+    pixels: Vec<u8>,
+    color: bool,
+}
+
+// Rasterized glyphs waiting to be written to the texture
+struct Upload {
+    origin: [u32; 2],
+    size: [u32; 2],
+    pixels: Vec<u8>,
+}
+
+// A picture drawn in its own colors through the atlas, such as a logo.
+// Each gets its own id when created, which the atlas knows it by.
+pub struct Image {
+    id: u64,
+    pixels: image::RgbaImage,
+}
+
+impl Image {
+    // At most half the atlas on a side, so glyphs still fit next to it
+    pub const MAX_SIZE: u32 = ATLAS_SIZE / 2;
+
+    // `pixels` larger than MAX_SIZE are scaled down to fit
+    pub fn new(pixels: image::RgbaImage) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let (width, height) = pixels.dimensions();
+        let scale = (Self::MAX_SIZE as f32 / width.max(height) as f32).min(1.0);
+        let pixels = if scale < 1.0 {
+            image::imageops::resize(
+                &pixels,
+                ((width as f32 * scale).round() as u32).max(1),
+                ((height as f32 * scale).round() as u32).max(1),
+                image::imageops::FilterType::Triangle,
+            )
+        } else {
+            pixels
+        };
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            pixels,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        [self.pixels.width(), self.pixels.height()]
+    }
+}
+
+// Glyphs for all text, packed into one texture so every overlay can be drawn
+// with a single bind group. Outline glyphs are stored as white with their
+// coverage in alpha so they take the text color, and color glyphs as they
+// are.
+pub struct GlyphAtlas {
+    texture: wgpu::Texture,
+    glyphs: HashMap<GlyphKey, GlyphInfo>,
+    images: HashMap<u64, GlyphInfo>,
+    // Shelf packing: the next free spot and the tallest glyph on its row
+    cursor: [u32; 2],
+    row_height: u32,
+    uploads: Vec<Upload>,
+    // A glyph didn't fit; the atlas is cleared once the frame is drawn
+    full: bool,
+}
+
+impl GlyphAtlas {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let mut atlas = Self {
+            texture,
+            glyphs: HashMap::new(),
+            images: HashMap::new(),
+            cursor: [0, 0],
+            row_height: 0,
+            uploads: Vec::new(),
+            full: false,
+        };
+        atlas.clear();
+        atlas
+    }
+
+    pub fn create_view(&self) -> wgpu::TextureView {
+        self.texture
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    // Atlas coordinates of a fully covered pixel, for solid boxes
+    pub fn solid_uv(&self) -> [f32; 2] {
+        [0.5 / ATLAS_SIZE as f32, 0.5 / ATLAS_SIZE as f32]
+    }
+
+    // Look up glyph `id` of `font` at `pixel_height`, rasterizing it on
+    // first use
+    pub fn glyph(
+        &mut self,
+        fonts: &FontSet,
+        font: usize,
+        id: GlyphId,
+        pixel_height: f32,
+    ) -> GlyphInfo {
+        let key = (font, id, pixel_height.round() as u32);
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let face = fonts.font(font).face();
+        let Some(bitmap) =
+            rasterize_image(face, id, key.2).or_else(|| rasterize_outline(face, id, key.2))
+        else {
+            // Nothing to draw, e.g. a space
+            self.glyphs.insert(key, GlyphInfo::default());
+            return GlyphInfo::default();
+        };
+        let Some(info) = self.insert(bitmap) else {
+            return GlyphInfo::default();
+        };
+        self.glyphs.insert(key, info);
+        info
+    }
+
+    // Look up `image`, adding it in its own colors on first use
+    pub fn image(&mut self, image: &Image) -> GlyphInfo {
+        if let Some(info) = self.images.get(&image.id) {
+            return *info;
+        }
+        let Some(info) = self.insert(Bitmap {
+            offset: [0, 0],
+            size: image.size(),
+            pixels: image.pixels.as_raw().clone(),
+            color: true,
+        }) else {
+            return GlyphInfo::default();
+        };
+        self.images.insert(image.id, info);
+        info
+    }
+
+    // Place `bitmap` and queue it for upload. None if it doesn't fit, in
+    // which case it is skipped for this frame only and drawn again after
+    // the clear.
+    fn insert(&mut self, bitmap: Bitmap) -> Option<GlyphInfo> {
+        let Some(origin) = self.allocate(bitmap.size) else {
+            self.full = true;
+            return None;
+        };
+
+        let size = bitmap.size;
+        let scale = ATLAS_SIZE as f32;
+        let info = GlyphInfo {
+            uv_min: [origin[0] as f32 / scale, origin[1] as f32 / scale],
+            uv_max: [
+                (origin[0] + size[0]) as f32 / scale,
+                (origin[1] + size[1]) as f32 / scale,
+            ],
+            offset: [bitmap.offset[0] as f32, bitmap.offset[1] as f32],
+            size: [size[0] as f32, size[1] as f32],
+            color: bitmap.color,
+        };
+        self.uploads.push(Upload {
+            origin,
+            size,
+            pixels: bitmap.pixels,
+        });
+        Some(info)
+    }
+
+    // Write glyphs rasterized since the last call to the texture
+    pub fn upload(&mut self, queue: &wgpu::Queue) {
+        for upload in self.uploads.drain(..) {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: upload.origin[0],
+                        y: upload.origin[1],
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &upload.pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(upload.size[0] * 4),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: upload.size[0],
+                    height: upload.size[1],
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    // Start over if a glyph didn't fit this frame. Call after the frame's
+    // text has been uploaded and encoded.
+    pub fn trim(&mut self) {
+        if self.full {
+            log::debug!(
+                "Glyph atlas full, clearing {} glyphs and {} images",
+                self.glyphs.len(),
+                self.images.len()
+            );
+            self.clear();
+        }
+    }
+
+    // Empty the atlas apart from one solid pixel in the corner
+    pub fn clear(&mut self) {
+        let mut pixels = vec![0; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize];
+        pixels[..4].fill(255);
+        self.uploads.clear();
+        self.uploads.push(Upload {
+            origin: [0, 0],
+            size: [ATLAS_SIZE, ATLAS_SIZE],
+            pixels,
+        });
+        self.glyphs.clear();
+        self.images.clear();
+        self.cursor = [1 + ATLAS_PADDING, ATLAS_PADDING];
+        self.row_height = 0;
+        self.full = false;
+    }
+
+    fn allocate(&mut self, size: [u32; 2]) -> Option<[u32; 2]> {
+        if self.cursor[0] + size[0] + ATLAS_PADDING > ATLAS_SIZE {
+            self.cursor = [
+                ATLAS_PADDING,
+                self.cursor[1] + self.row_height + ATLAS_PADDING * 2,
+            ];
+            self.row_height = 0;
+        }
+        if self.cursor[0] + size[0] + ATLAS_PADDING > ATLAS_SIZE
+            || self.cursor[1] + size[1] + ATLAS_PADDING > ATLAS_SIZE
+        {
+            return None;
+        }
+        let origin = self.cursor;
+        self.cursor[0] += size[0] + ATLAS_PADDING * 2;
+        self.row_height = self.row_height.max(size[1]);
+        Some(origin)
+    }
+}
+
+// Color bitmap glyphs, as in emoji fonts, scaled from the nearest strike
+fn rasterize_image(face: &ttf_parser::Face, id: GlyphId, pixel_height: u32) -> Option<Bitmap> {
+    let image = face.glyph_raster_image(id, pixel_height as u16)?;
+    if image.format != RasterImageFormat::PNG {
+        return None;
+    }
+    let decoded = image::load_from_memory_with_format(image.data, image::ImageFormat::Png)
+        .ok()?
+        .to_rgba8();
+
+    // Metrics are in pixels of the strike, with y the bottom edge
+    let scale = pixel_height as f32 / image.pixels_per_em as f32;
+    let size = [
+        ((decoded.width() as f32 * scale).round() as u32).max(1),
+        ((decoded.height() as f32 * scale).round() as u32).max(1),
+    ];
+    let resized = image::imageops::resize(
+        &decoded,
+        size[0],
+        size[1],
+        image::imageops::FilterType::Triangle,
+    );
+    Some(Bitmap {
+        offset: [
+            (image.x as f32 * scale).round() as i32,
+            -((image.y as f32 + image.height as f32) * scale).round() as i32,
+        ],
+        size,
+        pixels: resized.into_raw(),
+        color: true,
+    })
+}
+
+fn rasterize_outline(face: &ttf_parser::Face, id: GlyphId, pixel_height: u32) -> Option<Bitmap> {
+    let bounds = face.glyph_bounding_box(id)?;
+    let scale = pixel_height as f32 / face.units_per_em() as f32;
+    let left = (bounds.x_min as f32 * scale).floor();
+    let top = (bounds.y_max as f32 * scale).ceil();
+    let width = (bounds.x_max as f32 * scale).ceil() - left;
+    let height = top - (bounds.y_min as f32 * scale).floor();
+    if width < 1.0 || height < 1.0 {
+        return None;
+    }
+
+    let size = [width as u32, height as u32];
+    let mut builder = OutlineRasterizer {
+        rasterizer: Rasterizer::new(size[0] as usize, size[1] as usize),
+        scale,
+        origin: [left, top],
+        start: point(0.0, 0.0),
+        last: point(0.0, 0.0),
+    };
+    face.outline_glyph(id, &mut builder)?;
+
+    let mut pixels = vec![255; (size[0] * size[1] * 4) as usize];
+    builder.rasterizer.for_each_pixel(|index, coverage| {
+        pixels[index * 4 + 3] = (coverage.min(1.0) * 255.0).round() as u8;
+    });
+    Some(Bitmap {
+        offset: [left as i32, -top as i32],
+        size,
+        pixels,
+        color: false,
+    })
+}
+
+// Feeds a glyph outline, in font units with y up, to the rasterizer in
+// pixels with y down
+struct OutlineRasterizer {
+    rasterizer: Rasterizer,
+    scale: f32,
+    origin: [f32; 2],
+    start: Point,
+    last: Point,
+}
+
+impl OutlineRasterizer {
+    fn point(&self, x: f32, y: f32) -> Point {
+        point(
+            x * self.scale - self.origin[0],
+            self.origin[1] - y * self.scale,
+        )
+    }
+}
+
+impl ttf_parser::OutlineBuilder for OutlineRasterizer {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.rasterizer.draw_line(self.last, p);
+        self.last = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p1, p) = (self.point(x1, y1), self.point(x, y));
+        self.rasterizer.draw_quad(self.last, p1, p);
+        self.last = p;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p1, p2, p) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.rasterizer.draw_cubic(self.last, p1, p2, p);
+        self.last = p;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
+    }
+}
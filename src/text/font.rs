@@ -0,0 +1,245 @@
+use rustybuzz::ttf_parser::GlyphId;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use unicode_bidi::BidiInfo;
+
+// Bundled monospace font, see assets/fonts/LICENSE
+const PRIMARY_FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSansMono.ttf");
+
+// Color emoji fonts at their usual install locations, used after any
+// configured fallbacks if present
+const SYSTEM_EMOJI_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/noto/NotoColorEmoji.ttf",
+    "/usr/share/fonts/noto/NotoColorEmoji.ttf",
+    "/usr/share/fonts/google-noto-emoji/NotoColorEmoji.ttf",
+    "/usr/share/fonts/noto-emoji/NotoColorEmoji.ttf",
+    "/System/Library/Fonts/Apple Color Emoji.ttc",
+];
+
+type Face<'a> = rustybuzz::Face<'a>;
+
+self_cell::self_cell!(
+    // Font file contents together with the face parsed from them, which
+    // borrows the data
+    pub struct FontData {
+        owner: Cow<'static, [u8]>,
+        #[covariant]
+        dependent: Face,
+    }
+);
+
+impl FontData {
+    fn parse(data: Cow<'static, [u8]>) -> Result<Self, String> {
+        Self::try_new(data, |data| {
+            Face::from_slice(data, 0).ok_or_else(|| "not a supported font".to_string())
+        })
+    }
+
+    fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::parse(Cow::Owned(data))
+    }
+
+    pub fn face(&self) -> &Face<'_> {
+        self.borrow_dependent()
+    }
+}
+
+// Vertical metrics of a line in pixels; descent is negative
+pub struct LineMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+// A glyph placed by the shaper, in pixels from the start of the baseline
+pub struct PositionedGlyph {
+    pub font: usize,
+    pub id: GlyphId,
+    pub position: [f32; 2],
+}
+
+pub struct ShapedLine {
+    pub glyphs: Vec<PositionedGlyph>,
+    pub width: f32,
+}
+
+// The bundled font followed by fallbacks for what it lacks, such as emoji.
+// Each cluster of characters is drawn with the first font that has all of
+// them.
+pub struct FontSet {
+    fonts: Vec<FontData>,
+}
+
+impl FontSet {
+    pub fn new(fallbacks: &[PathBuf]) -> Self {
+        let primary = FontData::parse(Cow::Borrowed(PRIMARY_FONT)).expect("bundled font is valid");
+        let mut fonts = vec![primary];
+        let system = SYSTEM_EMOJI_FONTS
+            .iter()
+            .map(Path::new)
+            .filter(|path| path.exists());
+        for path in fallbacks.iter().map(PathBuf::as_path).chain(system) {
+            match FontData::load(path) {
+                Ok(font) => {
+                    log::info!("Loaded fallback font {}", path.display());
+                    fonts.push(font);
+                }
+                Err(e) => log::warn!("Failed to load font {}: {}", path.display(), e),
+            }
+        }
+        Self { fonts }
+    }
+
+    pub fn font(&self, index: usize) -> &FontData {
+        &self.fonts[index]
+    }
+
+    // Metrics of the bundled font, which sets the line spacing
+    pub fn metrics(&self, pixel_height: f32) -> LineMetrics {
+        let face = self.fonts[0].face();
+        let scale = pixel_height / face.units_per_em() as f32;
+        LineMetrics {
+            ascent: face.ascender() as f32 * scale,
+            descent: face.descender() as f32 * scale,
+            line_gap: face.line_gap() as f32 * scale,
+        }
+    }
+
+    // Shape a single line of text at `pixel_height`. Glyphs come out in
+    // display order, so right-to-left scripts such as Hebrew and Arabic read
+    // correctly, also when mixed with left-to-right text.
+    pub fn shape(&self, line: &str, pixel_height: f32) -> ShapedLine {
+        let mut glyphs = Vec::new();
+        let mut pen = 0.0;
+        for (direction, range) in directional_runs(line) {
+            let mut runs = self.runs(&line[range.clone()]);
+            if direction == rustybuzz::Direction::RightToLeft {
+                runs.reverse();
+            }
+            for (font, run) in runs {
+                let text = &line[range.start + run.start..range.start + run.end];
+                pen = self.shape_run(text, font, direction, pixel_height, pen, &mut glyphs);
+            }
+        }
+        ShapedLine { glyphs, width: pen }
+    }
+
+    // Shape `text` in one font and direction, placing its glyphs from `pen`
+    // on. Returns the pen position after the run.
+    fn shape_run(
+        &self,
+        text: &str,
+        font: usize,
+        direction: rustybuzz::Direction,
+        pixel_height: f32,
+        mut pen: f32,
+        glyphs: &mut Vec<PositionedGlyph>,
+    ) -> f32 {
+        let face = self.fonts[font].face();
+        let scale = pixel_height / face.units_per_em() as f32;
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(direction);
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(face, &[], buffer);
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+            glyphs.push(PositionedGlyph {
+                font,
+                id: GlyphId(info.glyph_id as u16),
+                position: [
+                    pen + position.x_offset as f32 * scale,
+                    -position.y_offset as f32 * scale,
+                ],
+            });
+            pen += position.x_advance as f32 * scale;
+        }
+        pen
+    }
+
+    // Split `line` into byte ranges that are each shaped with one font
+    fn runs(&self, line: &str) -> Vec<(usize, Range<usize>)> {
+        let faces: Vec<_> = self.fonts.iter().map(FontData::face).collect();
+        let covers =
+            |font: usize, text: &str| text.chars().all(|c| faces[font].glyph_index(c).is_some());
+
+        let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+        for cluster in clusters(line) {
+            let text = &line[cluster.clone()];
+            let base = &text[..text.chars().next().map_or(0, char::len_utf8)];
+            // Missing characters are left to the bundled font, which draws
+            // them as boxes
+            let font = (0..faces.len())
+                .find(|&font| covers(font, text))
+                .or_else(|| (0..faces.len()).find(|&font| covers(font, base)))
+                .unwrap_or(0);
+            match runs.last_mut() {
+                Some((last, range)) if *last == font => range.end = cluster.end,
+                _ => runs.push((font, cluster)),
+            }
+        }
+        runs
+    }
+}
+
+// Byte ranges of `line` with one writing direction each, in display order
+// from left to right per the Unicode bidirectional algorithm
+fn directional_runs(line: &str) -> Vec<(rustybuzz::Direction, Range<usize>)> {
+    let bidi = BidiInfo::new(line, None);
+    if !bidi.has_rtl() {
+        return vec![(rustybuzz::Direction::LeftToRight, 0..line.len())];
+    }
+    let mut runs = Vec::new();
+    for paragraph in &bidi.paragraphs {
+        let (levels, ranges) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for range in ranges {
+            let direction = if levels[range.start].is_rtl() {
+                rustybuzz::Direction::RightToLeft
+            } else {
+                rustybuzz::Direction::LeftToRight
+            };
+            runs.push((direction, range));
+        }
+    }
+    runs
+}
+
+// Byte ranges of base characters together with the marks, joiners,
+// variation selectors and modifiers that attach to them, which must come from
+// the same font
+fn clusters(line: &str) -> Vec<Range<usize>> {
+    let mut clusters: Vec<Range<usize>> = Vec::new();
+    let mut joined = false;
+    for (index, c) in line.char_indices() {
+        let end = index + c.len_utf8();
+        match clusters.last_mut() {
+            Some(cluster) if joined || extends_cluster(c) => cluster.end = end,
+            _ => clusters.push(index..end),
+        }
+        // A zero width joiner also pulls in the character after it, as in
+        // family emoji
+        joined = c == '\u{200d}';
+    }
+    clusters
+}
+
+fn extends_cluster(c: char) -> bool {
+    matches!(
+        c as u32,
+        // Combining diacritical marks and their supplements
+        0x0300..=0x036F
+            | 0x1AB0..=0x1AFF
+            | 0x1DC0..=0x1DFF
+            | 0x20D0..=0x20FF
+            | 0xFE20..=0xFE2F
+            // Zero width non-joiner and joiner
+            | 0x200C..=0x200D
+            // Variation selectors, e.g. U+FE0F for emoji presentation
+            | 0xFE00..=0xFE0F
+            | 0xE0100..=0xE01EF
+            // Emoji skin tone modifiers and tag sequences
+            | 0x1F3FB..=0x1F3FF
+            | 0xE0020..=0xE007F
+    )
+}
@@ -0,0 +1,341 @@
+mod atlas;
+mod font;
+
+pub use atlas::Image;
+
+use crate::viewport::Viewport;
+use atlas::GlyphAtlas;
+use font::FontSet;
+use std::path::PathBuf;
+use std::time::Instant;
+use unicode_linebreak::BreakOpportunity;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+// Screen-space text and solid boxes for every overlay. Overlays add to it
+// during the frame, in any size, and everything is drawn on top of the
+// target in one batched draw by `encode`. Text is shaped, so combining marks,
+// ligatures, emoji sequences and right-to-left scripts come out right, and
+// characters the bundled font lacks are taken from fallback fonts.
+pub struct TextRenderer {
+    // Draw outline text and boxes in inverted colors, e.g. so static
+    // overlays don't burn in
+    pub inverted: bool,
+    fonts: FontSet,
+    atlas: GlyphAtlas,
+    pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+    vertices: Vec<TextVertex>,
+}
+
+impl TextRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let start = Instant::now();
+        let fonts = FontSet::new(&[]);
+        let atlas = GlyphAtlas::new(device);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Glyph Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Params Buffer"),
+            size: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline = create_text_pipeline(device, format);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Text Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas.create_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let vertex_capacity = 1024;
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (vertex_capacity * std::mem::size_of::<TextVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        log::info!(
+            "Glyph atlas and text pipeline created in {:?}",
+            start.elapsed()
+        );
+
+        Self {
+            inverted: false,
+            fonts,
+            atlas,
+            pipeline,
+            params_buffer,
+            bind_group,
+            vertex_buffer,
+            vertex_capacity,
+            vertices: Vec::new(),
+        }
+    }
+
+    // Fonts to try, in order, for characters the bundled font lacks. Color
+    // emoji fonts installed in the usual places are tried after these.
+    pub fn set_fallback_fonts(&mut self, paths: &[PathBuf]) {
+        self.fonts = FontSet::new(paths);
+        self.atlas.clear();
+    }
+
+    pub fn line_height(&self, pixel_height: f32) -> f32 {
+        let metrics = self.fonts.metrics(pixel_height);
+        (metrics.ascent - metrics.descent + metrics.line_gap).ceil()
+    }
+
+    // Width in pixels of the longest line of `text`
+    pub fn measure(&self, text: &str, pixel_height: f32) -> f32 {
+        text.lines()
+            .map(|line| self.fonts.shape(line, pixel_height).width)
+            .fold(0.0, f32::max)
+    }
+
+    // Break `text` into lines at most `max_width` pixels wide, where the
+    // Unicode line breaking rules allow: at spaces, and between most CJK
+    // characters, which are written without them. Words wider than a line
+    // are kept whole.
+    pub fn wrap(&self, text: &str, pixel_height: f32, max_width: f32) -> String {
+        let mut lines = Vec::new();
+        // The current line runs from `start` to the last break that fit
+        let (mut start, mut end) = (0, 0);
+        for (position, opportunity) in unicode_linebreak::linebreaks(text) {
+            let candidate = text[start..position].trim_end();
+            if end > start && self.fonts.shape(candidate, pixel_height).width > max_width {
+                lines.push(text[start..end].trim_end());
+                start = end;
+            }
+            end = position;
+            if opportunity == BreakOpportunity::Mandatory {
+                lines.push(text[start..end].trim_end());
+                start = end;
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Add `text` at `pixel_height` with its top left corner at `position`,
+    // in pixels from the top left of the viewport. Color glyphs keep their
+    // own colors and only take the alpha of `color`.
+    pub fn add_text(&mut self, text: &str, position: [f32; 2], pixel_height: f32, color: [f32; 4]) {
+        let color = self.color(color);
+        let ascent = self.fonts.metrics(pixel_height).ascent;
+        let line_height = self.line_height(pixel_height);
+        for (row, line) in text.lines().enumerate() {
+            let baseline = (position[1] + ascent + row as f32 * line_height).round();
+            let pen = position[0].round();
+            for glyph in self.fonts.shape(line, pixel_height).glyphs {
+                let info = self
+                    .atlas
+                    .glyph(&self.fonts, glyph.font, glyph.id, pixel_height);
+                if info.size[0] == 0.0 {
+                    continue;
+                }
+                let min = [
+                    pen + glyph.position[0] + info.offset[0],
+                    baseline + glyph.position[1] + info.offset[1],
+                ];
+                let max = [min[0] + info.size[0], min[1] + info.size[1]];
+                let color = if info.color {
+                    [1.0, 1.0, 1.0, color[3]]
+                } else {
+                    color
+                };
+                self.push_quad(min, max, info.uv_min, info.uv_max, color);
+            }
+        }
+    }
+
+    // Add a solid box, e.g. a backdrop behind text
+    pub fn add_rect(&mut self, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+        let solid_uv = self.atlas.solid_uv();
+        self.push_quad(min, max, solid_uv, solid_uv, self.color(color));
+    }
+
+    // Add a solid quadrilateral with corners in order around it, e.g. for
+    // shapes that aren't upright boxes
+    pub fn add_quad(&mut self, corners: [[f32; 2]; 4], color: [f32; 4]) {
+        let uv = self.atlas.solid_uv();
+        let color = self.color(color);
+        let vertex = |position| TextVertex {
+            position,
+            uv,
+            color,
+        };
+        self.vertices
+            .extend([0, 1, 2, 0, 2, 3].map(|corner| vertex(corners[corner])));
+    }
+
+    // Add `image` stretched over the box from `min` to `max`, in its own
+    // colors at `opacity`
+    pub fn add_image(&mut self, image: &Image, min: [f32; 2], max: [f32; 2], opacity: f32) {
+        let info = self.atlas.image(image);
+        if info.size[0] == 0.0 {
+            return;
+        }
+        self.push_quad(min, max, info.uv_min, info.uv_max, [1.0, 1.0, 1.0, opacity]);
+    }
+
+    fn color(&self, color: [f32; 4]) -> [f32; 4] {
+        if self.inverted {
+            [1.0 - color[0], 1.0 - color[1], 1.0 - color[2], color[3]]
+        } else {
+            color
+        }
+    }
+
+    // Draw everything added since the last call on top of `viewport` of
+    // `target`
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        viewport: Viewport,
+    ) -> Option<wgpu::CommandBuffer> {
+        self.atlas.upload(queue);
+        if self.vertices.is_empty() {
+            self.atlas.trim();
+            return None;
+        }
+
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[viewport.width as f32, viewport.height as f32]),
+        );
+        if self.vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Text Vertex Buffer"),
+                size: (self.vertex_capacity * std::mem::size_of::<TextVertex>())
+                    as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        let num_vertices = self.vertices.len() as u32;
+        self.vertices.clear();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Text Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Text Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            viewport.apply(&mut render_pass);
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..num_vertices, 0..1);
+        }
+
+        self.atlas.trim();
+        Some(encoder.finish())
+    }
+
+    fn push_quad(
+        &mut self,
+        min: [f32; 2],
+        max: [f32; 2],
+        uv_min: [f32; 2],
+        uv_max: [f32; 2],
+        color: [f32; 4],
+    ) {
+        let corner = |x: usize, y: usize| TextVertex {
+            position: [[min[0], max[0]][x], [min[1], max[1]][y]],
+            uv: [[uv_min[0], uv_max[0]][x], [uv_min[1], uv_max[1]][y]],
+            color,
+        };
+        self.vertices.extend([
+            corner(0, 0),
+            corner(0, 1),
+            corner(1, 0),
+            corner(1, 0),
+            corner(0, 1),
+            corner(1, 1),
+        ]);
+    }
+}
+
+fn create_text_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Text Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("text.wgsl").into()),
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Text Pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<TextVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x2,
+                    1 => Float32x2,
+                    2 => Float32x4
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,37 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Fullscreen triangle covering the viewport, which is set to the tile
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+struct Params {
+    // 1 if the candidate is picked as a parent
+    selected: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var thumbnail_texture: texture_2d<f32>;
+@group(0) @binding(2) var thumbnail_sampler: sampler;
+
+// Frame width in UV units and color of picked candidates
+const BORDER: f32 = 0.02;
+const SELECTED_COLOR: vec4<f32> = vec4<f32>(1.0, 0.7, 0.1, 1.0);
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(thumbnail_texture, thumbnail_sampler, in.uv);
+    let edge = min(min(in.uv.x, 1.0 - in.uv.x), min(in.uv.y, 1.0 - in.uv.y));
+    if params.selected != 0u && edge < BORDER {
+        return SELECTED_COLOR;
+    }
+    return color;
+}
@@ -0,0 +1,112 @@
+use crate::text::TextRenderer;
+use crate::viewport::Viewport;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// How long a notification stays up; errors stay longer so they can be read
+const INFO_DURATION: Duration = Duration::from_secs(3);
+const ERROR_DURATION: Duration = Duration::from_secs(8);
+// The last part of that time, over which it fades out
+const FADE: Duration = Duration::from_millis(500);
+// Older notifications are dropped when more than this many are up
+const MAX_TOASTS: usize = 5;
+
+const FONT_SIZE: f32 = 16.0;
+// Pixels between the viewport top, the backdrops and the text
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const SPACING: f32 = 4.0;
+// Messages wider than this share of the viewport are wrapped
+const MAX_WIDTH: f32 = 0.6;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const INFO_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const ERROR_COLOR: [f32; 4] = [0.45, 0.0, 0.0, 0.85];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToastLevel {
+    Info,
+    Error,
+}
+
+struct Toast {
+    level: ToastLevel,
+    message: String,
+    shown: Instant,
+}
+
+impl Toast {
+    fn duration(&self) -> Duration {
+        match self.level {
+            ToastLevel::Info => INFO_DURATION,
+            ToastLevel::Error => ERROR_DURATION,
+        }
+    }
+}
+
+// Short notifications stacked at the top center of the picture, newest at
+// the bottom, e.g. for saved screenshots and config reloads. Each expires
+// on its own after a few seconds.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.show(ToastLevel::Info, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.show(ToastLevel::Error, message.into());
+    }
+
+    pub fn show(&mut self, level: ToastLevel, message: String) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            level,
+            message,
+            shown: Instant::now(),
+        });
+    }
+
+    // Drop expired notifications and lay out the rest in `text`
+    pub fn push(&mut self, viewport: Viewport, text: &mut TextRenderer) {
+        let now = Instant::now();
+        self.toasts
+            .retain(|toast| now.duration_since(toast.shown) < toast.duration());
+
+        let max_width = viewport.width as f32 * MAX_WIDTH;
+        let mut top = MARGIN;
+        for toast in &self.toasts {
+            let left = toast
+                .duration()
+                .saturating_sub(now.duration_since(toast.shown));
+            let opacity = (left.as_secs_f32() / FADE.as_secs_f32()).min(1.0);
+
+            let message = text.wrap(&toast.message, FONT_SIZE, max_width);
+            let lines = message.lines().count() as f32;
+            let size = [
+                text.measure(&message, FONT_SIZE) + PADDING * 2.0,
+                lines * text.line_height(FONT_SIZE) + PADDING * 2.0,
+            ];
+            let x = ((viewport.width as f32 - size[0]) / 2.0).round();
+            let mut backdrop = match toast.level {
+                ToastLevel::Info => INFO_COLOR,
+                ToastLevel::Error => ERROR_COLOR,
+            };
+            backdrop[3] *= opacity;
+            text.add_rect([x, top], [x + size[0], top + size[1]], backdrop);
+            let mut color = TEXT_COLOR;
+            color[3] *= opacity;
+            text.add_text(&message, [x + PADDING, top + PADDING], FONT_SIZE, color);
+            top += size[1] + SPACING;
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use cgmath::*;
+
+// Translation, rotation and scale of a node relative to its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    // Scale first, then rotate, then translate
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+struct Node {
+    parent: Option<NodeId>,
+    local: Transform,
+}
+
+// Parent/child hierarchy of local transforms, so grouped objects move
+// together. Nodes are only ever added, and parents always exist before their
+// children, so the tree cannot contain cycles.
+pub struct TransformTree {
+    nodes: Vec<Node>,
+}
+
+impl TransformTree {
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    pub fn add(&mut self, parent: Option<NodeId>, local: Transform) -> NodeId {
+        self.nodes.push(Node { parent, local });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn local(&self, id: NodeId) -> &Transform {
+        &self.nodes[id.0].local
+    }
+
+    pub fn local_mut(&mut self, id: NodeId) -> &mut Transform {
+        &mut self.nodes[id.0].local
+    }
+
+    // Local transform of the node composed with those of all its ancestors
+    pub fn world_matrix(&self, id: NodeId) -> Matrix4<f32> {
+        let node = &self.nodes[id.0];
+        let local = node.local.matrix();
+        match node.parent {
+            Some(parent) => self.world_matrix(parent) * local,
+            None => local,
+        }
+    }
+}
+
+impl Default for TransformTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,63 @@
+struct Params {
+    // Target size in egui points
+    screen_size: vec2<f32>,
+    // 1 if the target is in an sRGB format and expects linear colors
+    srgb: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(1) @binding(0) var ui_texture: texture_2d<f32>;
+@group(1) @binding(1) var ui_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    // Premultiplied sRGB from egui
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+fn linear_from_gamma(srgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = srgb < vec3<f32>(0.04045);
+    let lower = srgb / 12.92;
+    let higher = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
+fn gamma_from_linear(linear: vec3<f32>) -> vec3<f32> {
+    let cutoff = linear < vec3<f32>(0.0031308);
+    let lower = linear * 12.92;
+    let higher = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, lower, cutoff);
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(
+        2.0 * model.position.x / params.screen_size.x - 1.0,
+        1.0 - 2.0 * model.position.y / params.screen_size.y,
+        0.0,
+        1.0,
+    );
+    out.uv = model.uv;
+    out.color = model.color;
+    return out;
+}
+
+// egui blends in gamma space, so the texture (sRGB, sampled as linear) is
+// taken back to gamma before tinting
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(ui_texture, ui_sampler, in.uv);
+    let color = in.color * vec4<f32>(gamma_from_linear(texel.rgb), texel.a);
+    if params.srgb != 0u {
+        return vec4<f32>(linear_from_gamma(color.rgb), color.a);
+    }
+    return color;
+}
@@ -0,0 +1,413 @@
+use egui::epaint::{ImageDelta, Primitive, Vertex};
+use egui::{ClippedPrimitive, ImageData, TextureFilter, TextureId, TexturesDelta};
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    screen_size: [f32; 2],
+    srgb: u32,
+    _padding: u32,
+}
+
+// Size of the target the UI is painted into
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenDescriptor {
+    pub size_in_pixels: [u32; 2],
+    pub pixels_per_point: f32,
+}
+
+struct UiTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+// Draws egui's tessellated output with wgpu, on top of the frame
+pub struct UiPainter {
+    srgb: bool,
+    pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    textures: HashMap<TextureId, UiTexture>,
+    // Freed by egui last frame, dropped once that frame has been submitted
+    pending_free: Vec<TextureId>,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+}
+
+impl UiPainter {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let start = Instant::now();
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("UI Params Buffer"),
+            size: std::mem::size_of::<Params>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("UI Params Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("UI Params Bind Group"),
+            layout: &params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("UI Texture Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("UI Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("ui.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("UI Pipeline Layout"),
+            bind_group_layouts: &[&params_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+
+        // egui colors are premultiplied
+        let blend = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("UI Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x2,
+                        2 => Unorm8x4
+                    ],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        log::info!("UI pipeline created in {:?}", start.elapsed());
+
+        Self {
+            srgb: format.is_srgb(),
+            pipeline,
+            params_buffer,
+            params_bind_group,
+            texture_layout,
+            textures: HashMap::new(),
+            pending_free: Vec::new(),
+            vertex_buffer: create_buffer(device, "UI Vertex Buffer", wgpu::BufferUsages::VERTEX, 0),
+            index_buffer: create_buffer(device, "UI Index Buffer", wgpu::BufferUsages::INDEX, 0),
+        }
+    }
+
+    // Apply texture changes and draw `primitives` over `target`
+    pub fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        screen: ScreenDescriptor,
+        primitives: &[ClippedPrimitive],
+        textures: &TexturesDelta,
+    ) -> wgpu::CommandBuffer {
+        let ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point,
+        } = screen;
+        for id in self.pending_free.drain(..) {
+            self.textures.remove(&id);
+        }
+        for (id, delta) in &textures.set {
+            self.set_texture(device, queue, *id, delta);
+        }
+        self.pending_free.extend(&textures.free);
+
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[Params {
+                screen_size: [
+                    size[0] as f32 / pixels_per_point,
+                    size[1] as f32 / pixels_per_point,
+                ],
+                srgb: self.srgb as u32,
+                _padding: 0,
+            }]),
+        );
+
+        // All meshes share one vertex and one index buffer
+        let meshes: Vec<_> = primitives
+            .iter()
+            .filter_map(|clipped| match &clipped.primitive {
+                Primitive::Mesh(mesh) => Some((clipped.clip_rect, mesh)),
+                Primitive::Callback(_) => None,
+            })
+            .collect();
+        let vertices: Vec<Vertex> = meshes
+            .iter()
+            .flat_map(|(_, mesh)| mesh.vertices.iter().copied())
+            .collect();
+        let indices: Vec<u32> = meshes
+            .iter()
+            .flat_map(|(_, mesh)| mesh.indices.iter().copied())
+            .collect();
+        self.upload(device, queue, &vertices, &indices);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("UI Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("UI Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.params_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+            let (mut base_vertex, mut first_index) = (0, 0);
+            for (clip_rect, mesh) in meshes {
+                let index_range = first_index..first_index + mesh.indices.len() as u32;
+                let vertex_offset = base_vertex;
+                base_vertex += mesh.vertices.len() as i32;
+                first_index = index_range.end;
+
+                // Clip rectangle in points to a scissor rectangle in pixels
+                let min_x = (clip_rect.min.x * pixels_per_point).round().max(0.0) as u32;
+                let min_y = (clip_rect.min.y * pixels_per_point).round().max(0.0) as u32;
+                let max_x = ((clip_rect.max.x * pixels_per_point).round() as u32).min(size[0]);
+                let max_y = ((clip_rect.max.y * pixels_per_point).round() as u32).min(size[1]);
+                let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                    continue;
+                };
+                if min_x >= max_x || min_y >= max_y {
+                    continue;
+                }
+
+                render_pass.set_scissor_rect(min_x, min_y, max_x - min_x, max_y - min_y);
+                render_pass.set_bind_group(1, &texture.bind_group, &[]);
+                render_pass.draw_indexed(index_range, vertex_offset, 0..1);
+            }
+        }
+
+        encoder.finish()
+    }
+
+    fn set_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: TextureId,
+        delta: &ImageDelta,
+    ) {
+        let (width, height) = (delta.image.width() as u32, delta.image.height() as u32);
+        let pixels: Vec<u8> = match &delta.image {
+            ImageData::Color(image) => image.pixels.iter().flat_map(|c| c.to_array()).collect(),
+            ImageData::Font(image) => image
+                .srgba_pixels(None)
+                .flat_map(|c| c.to_array())
+                .collect(),
+        };
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        // A patch updates part of an existing texture; anything else
+        // replaces it
+        let origin = match delta.pos {
+            Some([x, y]) => wgpu::Origin3d {
+                x: x as u32,
+                y: y as u32,
+                z: 0,
+            },
+            None => {
+                self.textures
+                    .insert(id, self.create_texture(device, size, delta));
+                wgpu::Origin3d::ZERO
+            }
+        };
+        let Some(texture) = self.textures.get(&id) else {
+            log::warn!("Patch for unknown UI texture {:?}", id);
+            return;
+        };
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture.texture,
+                mip_level: 0,
+                origin,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+    }
+
+    fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        delta: &ImageDelta,
+    ) -> UiTexture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("UI Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let filter = |filter: TextureFilter| match filter {
+            TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            TextureFilter::Linear => wgpu::FilterMode::Linear,
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("UI Sampler"),
+            mag_filter: filter(delta.options.magnification),
+            min_filter: filter(delta.options.minification),
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("UI Texture Bind Group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        UiTexture {
+            texture,
+            bind_group,
+        }
+    }
+
+    // Grow the buffers to the next power of two when the meshes outgrow them
+    fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) {
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(vertices);
+        if vertex_bytes.len() as u64 > self.vertex_buffer.size() {
+            self.vertex_buffer = create_buffer(
+                device,
+                "UI Vertex Buffer",
+                wgpu::BufferUsages::VERTEX,
+                vertex_bytes.len(),
+            );
+        }
+        let index_bytes: &[u8] = bytemuck::cast_slice(indices);
+        if index_bytes.len() as u64 > self.index_buffer.size() {
+            self.index_buffer = create_buffer(
+                device,
+                "UI Index Buffer",
+                wgpu::BufferUsages::INDEX,
+                index_bytes.len(),
+            );
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, vertex_bytes);
+        queue.write_buffer(&self.index_buffer, 0, index_bytes);
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    size: usize,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: size.max(1024).next_power_of_two() as wgpu::BufferAddress,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,121 @@
+use crate::palette::PALETTE_STOPS;
+use cgmath::Matrix4;
+
+// Default colours for major and minor grid lines
+pub const MAJOR_LINE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const MINOR_LINE_COLOR: [f32; 4] = [0.45, 0.45, 0.45, 1.0];
+
+// How grid lines are drawn along their length
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineStyle {
+    Solid = 0,
+    Dashed = 1,
+    Dotted = 2,
+}
+
+impl LineStyle {
+    pub fn next(self) -> Self {
+        match self {
+            LineStyle::Solid => LineStyle::Dashed,
+            LineStyle::Dashed => LineStyle::Dotted,
+            LineStyle::Dotted => LineStyle::Solid,
+        }
+    }
+}
+
+// How the grid fades toward the fog color with distance from the camera
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FogMode {
+    Off = 0,
+    // Ramps from none at the start distance to full at the end
+    Linear = 1,
+    // Thickens with density per unit past the start distance
+    #[serde(rename = "exp")]
+    Exponential = 2,
+    // Like exponential, but clearer up close and thicker far away
+    #[serde(rename = "exp2")]
+    ExponentialSquared = 3,
+}
+
+impl FogMode {
+    pub fn next(self) -> Self {
+        match self {
+            FogMode::Off => FogMode::Linear,
+            FogMode::Linear => FogMode::Exponential,
+            FogMode::Exponential => FogMode::ExponentialSquared,
+            FogMode::ExponentialSquared => FogMode::Off,
+        }
+    }
+}
+
+// Uniform block shared by the grid vertex and fragment shaders
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Uniforms {
+    pub time: f32,
+    pub motion: f32,
+    pub line_style: u32,
+    _padding: f32, // Padding to satisfy alignment requirements
+    pub view_proj: [[f32; 4]; 4],
+    pub major_color: [f32; 4],
+    pub minor_color: [f32; 4],
+    pub model: [[f32; 4]; 4],
+    // 0 for the flat major and minor colors, otherwise 1 + PaletteInput
+    pub palette_mode: u32,
+    pub palette_last_stop: u32,
+    // Camera distance at the end of a palette applied by distance
+    pub palette_range: f32,
+    _palette_padding: f32,
+    pub palette: [[f32; 4]; PALETTE_STOPS],
+    pub fog_color: [f32; 4],
+    pub fog_mode: u32,
+    // Camera distances in world units
+    pub fog_start: f32,
+    pub fog_end: f32,
+    pub fog_density: f32,
+}
+
+impl Uniforms {
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            motion: 1.0,
+            line_style: LineStyle::Solid as u32,
+            _padding: 0.0,
+            view_proj: Matrix4::from_scale(1.0).into(),
+            major_color: MAJOR_LINE_COLOR,
+            minor_color: MINOR_LINE_COLOR,
+            model: Matrix4::from_scale(1.0).into(),
+            palette_mode: 0,
+            palette_last_stop: 0,
+            palette_range: 1.0,
+            _palette_padding: 0.0,
+            palette: [[0.0; 4]; PALETTE_STOPS],
+            fog_color: [0.0, 0.0, 0.0, 1.0],
+            fog_mode: FogMode::Off as u32,
+            fog_start: 0.0,
+            fog_end: 1.0,
+            fog_density: 0.0,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        time: f32,
+        motion: f32,
+        line_style: LineStyle,
+        view_proj: Matrix4<f32>,
+    ) {
+        self.time = time;
+        self.motion = motion;
+        self.line_style = line_style as u32;
+        self.view_proj = view_proj.into();
+    }
+}
+
+impl Default for Uniforms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
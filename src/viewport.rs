@@ -0,0 +1,145 @@
+// Pixel rectangle of the render target that the picture is drawn into. With
+// overscan margins or an aspect lock the rest of the target is left black,
+// and overlays use the same rectangle so they stay inside the picture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    // Shrink by `margin`, a fraction of the width and height, on every side
+    pub fn inset(self, margin: f32) -> Self {
+        let dx = (self.width as f32 * margin).round() as u32;
+        let dy = (self.height as f32 * margin).round() as u32;
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+            width: self.width.saturating_sub(dx * 2).max(1),
+            height: self.height.saturating_sub(dy * 2).max(1),
+        }
+    }
+
+    // Shrink by `margin` pixels on every side and move by `offset`, kept
+    // within the margin
+    pub fn drift(self, margin: u32, offset: [i32; 2]) -> Self {
+        let margin_x = margin.min(self.width.saturating_sub(1) / 2);
+        let margin_y = margin.min(self.height.saturating_sub(1) / 2);
+        let shift = |margin: u32, offset: i32| {
+            (margin as i32 + offset.clamp(-(margin as i32), margin as i32)) as u32
+        };
+        Self {
+            x: self.x + shift(margin_x, offset[0]),
+            y: self.y + shift(margin_y, offset[1]),
+            width: self.width - margin_x * 2,
+            height: self.height - margin_y * 2,
+        }
+    }
+
+    // The largest centered rectangle inside this one with the given
+    // width/height ratio, with bars top and bottom for a narrower rectangle
+    // or left and right for a wider one
+    pub fn letterbox(self, aspect: f32) -> Self {
+        if self.aspect() > aspect {
+            let inner = ((self.height as f32 * aspect).round() as u32).clamp(1, self.width);
+            Self {
+                x: self.x + (self.width - inner) / 2,
+                width: inner,
+                ..self
+            }
+        } else {
+            let inner = ((self.width as f32 / aspect).round() as u32).clamp(1, self.height);
+            Self {
+                y: self.y + (self.height - inner) / 2,
+                height: inner,
+                ..self
+            }
+        }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    // Map clip space to the rectangle and discard anything drawn outside it
+    pub fn apply(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_viewport(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(self.x, self.y, self.width, self.height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(x: u32, y: u32, width: u32, height: u32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn a_wider_target_gets_bars_left_and_right() {
+        let picture = Viewport::full(1920, 1080).letterbox(4.0 / 3.0);
+        assert_eq!(picture, viewport(240, 0, 1440, 1080));
+    }
+
+    #[test]
+    fn a_narrower_target_gets_bars_top_and_bottom() {
+        let picture = Viewport::full(1920, 1080).letterbox(2.39);
+        assert_eq!(picture, viewport(0, 138, 1920, 803));
+        assert!((picture.aspect() - 2.39).abs() < 0.01);
+    }
+
+    #[test]
+    fn a_matching_aspect_keeps_the_whole_target() {
+        let full = Viewport::full(1280, 720);
+        assert_eq!(full.letterbox(16.0 / 9.0), full);
+    }
+
+    #[test]
+    fn extreme_aspects_keep_at_least_a_pixel() {
+        assert_eq!(Viewport::full(100, 100).letterbox(1000.0).height, 1);
+        assert_eq!(Viewport::full(100, 100).letterbox(0.001).width, 1);
+    }
+
+    #[test]
+    fn overscan_takes_a_margin_off_every_side() {
+        let safe_area = Viewport::full(1920, 1080).inset(0.05);
+        assert_eq!(safe_area, viewport(96, 54, 1728, 972));
+        assert_eq!(safe_area.inset(0.0), safe_area);
+    }
+
+    #[test]
+    fn the_letterbox_sits_inside_the_overscan_margins() {
+        let picture = Viewport::full(1920, 1080).inset(0.05).letterbox(2.39);
+        assert_eq!(picture, viewport(96, 178, 1728, 723));
+    }
+
+    #[test]
+    fn margins_near_half_keep_at_least_a_pixel() {
+        let picture = Viewport::full(100, 100).inset(0.4999);
+        assert_eq!((picture.width, picture.height), (1, 1));
+    }
+}
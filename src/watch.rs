@@ -0,0 +1,46 @@
+use notify::Watcher;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+// Reports changes to a single file. The parent directory is watched, since
+// editors often save by replacing the file.
+pub struct FileWatcher {
+    path: PathBuf,
+    _watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = path.canonicalize()?;
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let directory = path.parent().ok_or("file has no parent directory")?;
+        watcher.watch(directory, notify::RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path,
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Whether the file changed since the last call
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    changed |= !event.kind.is_access() && event.paths.contains(&self.path);
+                }
+                Err(e) => log::warn!("Watch error for {}: {}", self.path.display(), e),
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,82 @@
+// Wave displacement shared by the render shader and the shader tests
+
+// Height of the combined waves at a grid point, using both X and Z
+fn wave_height(x: f32, z: f32, time: f32) -> f32 {
+    let wave1 = sin(x * 2.0 + z * 2.0 + time * 2.0) * 0.3;
+    let wave2 = sin(z * 1.5 + x * 2.0 + time * 1.5) * 0.2;
+    let wave3 = sin(x * 3.0 + z * 3.0 + time) * 0.1;
+    return wave1 + wave2 + wave3;
+}
+
+// Rings spreading out from the center, fading towards the edge
+fn ripple_height(x: f32, z: f32, time: f32) -> f32 {
+    let r = length(vec2<f32>(x, z));
+    return sin(r * 12.0 - time * 3.0) * 0.3 * (1.0 - 0.5 * min(r, 1.0));
+}
+
+// Two point sources whose rings reinforce and cancel each other
+fn interference_height(x: f32, z: f32, time: f32) -> f32 {
+    let r1 = distance(vec2<f32>(x, z), vec2<f32>(-0.5, 0.0));
+    let r2 = distance(vec2<f32>(x, z), vec2<f32>(0.5, 0.0));
+    return (sin(r1 * 14.0 - time * 3.0) + sin(r2 * 14.0 - time * 3.0)) * 0.15;
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+// Smoothly interpolated value noise in -1..1
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = hash(i);
+    let b = hash(i + vec2<f32>(1.0, 0.0));
+    let c = hash(i + vec2<f32>(0.0, 1.0));
+    let d = hash(i + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y) * 2.0 - 1.0;
+}
+
+// Fractal noise; each octave doubles the frequency and halves the amplitude
+fn fbm(p: vec2<f32>, octaves: u32) -> f32 {
+    var sum = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0u; i < octaves; i++) {
+        sum += value_noise(q) * amplitude;
+        q *= 2.0;
+        amplitude *= 0.5;
+    }
+    return sum;
+}
+
+fn gradient(i: vec2<f32>) -> vec2<f32> {
+    let angle = hash(i) * 6.2831853;
+    return vec2<f32>(cos(angle), sin(angle));
+}
+
+// Perlin gradient noise, roughly in -1..1
+fn perlin_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    let a = dot(gradient(i), f);
+    let b = dot(gradient(i + vec2<f32>(1.0, 0.0)), f - vec2<f32>(1.0, 0.0));
+    let c = dot(gradient(i + vec2<f32>(0.0, 1.0)), f - vec2<f32>(0.0, 1.0));
+    let d = dot(gradient(i + vec2<f32>(1.0, 1.0)), f - vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y) * 1.4142135;
+}
+
+// Rolling hills scrolling along Z, as if flying over them
+fn terrain_height(x: f32, z: f32, time: f32) -> f32 {
+    var p = vec2<f32>(x, z + time * 0.3) * 1.5;
+    var sum = 0.0;
+    var amplitude = 0.5;
+    for (var i = 0; i < 5; i++) {
+        sum += perlin_noise(p) * amplitude;
+        // Offset each octave so the lattices don't line up at the origin
+        p = p * 2.0 + vec2<f32>(17.0, 31.0);
+        amplitude *= 0.5;
+    }
+    return sum * 0.8;
+}
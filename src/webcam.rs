@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// Requested capture size; drivers may pick the closest they support
+#[cfg(feature = "webcam")]
+const CAPTURE_WIDTH: u32 = 640;
+#[cfg(feature = "webcam")]
+const CAPTURE_HEIGHT: u32 = 480;
+
+// 8-bit luminance image, row-major without padding
+pub struct LumaFrame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+// Anything that can feed luminance frames to the height field
+pub trait FrameSource {
+    // Newest frame captured since the last call, if any
+    fn take_frame(&self) -> Option<LumaFrame>;
+}
+
+// Live luminance frames from a V4L2 camera, captured on a background thread
+// that stops when this is dropped
+pub struct Webcam {
+    latest: Arc<Mutex<Option<LumaFrame>>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Webcam {
+    #[cfg(feature = "webcam")]
+    pub fn new(index: usize) -> Result<Self, Box<dyn Error>> {
+        use v4l::video::Capture;
+
+        let yuyv = v4l::FourCC::new(b"YUYV");
+        let device = v4l::Device::new(index)?;
+        let format = device.set_format(&v4l::Format::new(CAPTURE_WIDTH, CAPTURE_HEIGHT, yuyv))?;
+        if format.fourcc != yuyv {
+            return Err(format!("camera does not support YUYV, got {}", format.fourcc).into());
+        }
+        log::info!("Capturing webcam at {}x{}", format.width, format.height);
+
+        let latest = Arc::new(Mutex::new(None));
+        let running = Arc::new(AtomicBool::new(true));
+        let (shared, keep_running) = (latest.clone(), running.clone());
+        let thread = std::thread::spawn(move || {
+            if let Err(e) = capture(&device, format, &shared, &keep_running) {
+                log::error!("Webcam capture stopped: {}", e);
+            }
+        });
+
+        Ok(Self {
+            latest,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    #[cfg(not(feature = "webcam"))]
+    pub fn new(_index: usize) -> Result<Self, Box<dyn Error>> {
+        Err("built without the `webcam` feature".into())
+    }
+}
+
+impl Drop for Webcam {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl FrameSource for Webcam {
+    fn take_frame(&self) -> Option<LumaFrame> {
+        self.latest.lock().unwrap().take()
+    }
+}
+
+#[cfg(feature = "webcam")]
+fn capture(
+    device: &v4l::Device,
+    format: v4l::Format,
+    latest: &Mutex<Option<LumaFrame>>,
+    running: &AtomicBool,
+) -> std::io::Result<()> {
+    use v4l::io::traits::CaptureStream;
+
+    let mut stream =
+        v4l::prelude::MmapStream::with_buffers(device, v4l::buffer::Type::VideoCapture, 4)?;
+    while running.load(Ordering::Relaxed) {
+        let (buffer, _) = stream.next()?;
+
+        // YUYV packs two pixels into four bytes; every other byte is luma
+        let mut pixels = Vec::with_capacity((format.width * format.height) as usize);
+        for row in buffer
+            .chunks(format.stride as usize)
+            .take(format.height as usize)
+        {
+            pixels.extend(row.iter().step_by(2).take(format.width as usize));
+        }
+
+        *latest.lock().unwrap() = Some(LumaFrame {
+            width: format.width,
+            height: format.height,
+            pixels,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,438 @@
+// Runs the WGSL wave functions through a small compute dispatch and checks
+// the results read back on the CPU against a reference implementation.
+
+use sideharso::heightfield::{GerstnerWave, GpuGerstnerWave};
+use wgpu::util::DeviceExt;
+
+const WAVE_SHADER: &str = include_str!("../src/wave.wgsl");
+const GERSTNER_SHADER: &str = include_str!("../src/gerstner.wgsl");
+
+// Compute entry point appended to the shader under test, with CALL replaced
+// by the function to run on each sample
+const HARNESS: &str = r#"
+struct Sample {
+    x: f32,
+    z: f32,
+    time: f32,
+    height: f32,
+};
+
+@group(0) @binding(0) var<storage, read_write> samples: array<Sample>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= arrayLength(&samples) {
+        return;
+    }
+    let sample = samples[id.x];
+    samples[id.x].height = CALL;
+}
+"#;
+
+// Wave trains for gerstner_height, laid out like GerstnerWave in WGSL
+const GERSTNER_BINDING: &str =
+    "@group(0) @binding(1) var<storage, read> gerstner_waves: array<GerstnerWave>;\n";
+
+const TOLERANCE: f32 = 1e-4;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Sample {
+    x: f32,
+    z: f32,
+    time: f32,
+    height: f32,
+}
+
+fn reference_wave_height(x: f32, z: f32, time: f32) -> f32 {
+    let wave1 = (x * 2.0 + z * 2.0 + time * 2.0).sin() * 0.3;
+    let wave2 = (z * 1.5 + x * 2.0 + time * 1.5).sin() * 0.2;
+    let wave3 = (x * 3.0 + z * 3.0 + time).sin() * 0.1;
+    wave1 + wave2 + wave3
+}
+
+fn reference_ripple_height(x: f32, z: f32, time: f32) -> f32 {
+    let r = x.hypot(z);
+    (r * 12.0 - time * 3.0).sin() * 0.3 * (1.0 - 0.5 * r.min(1.0))
+}
+
+fn reference_interference_height(x: f32, z: f32, time: f32) -> f32 {
+    let r1 = (x + 0.5).hypot(z);
+    let r2 = (x - 0.5).hypot(z);
+    ((r1 * 14.0 - time * 3.0).sin() + (r2 * 14.0 - time * 3.0).sin()) * 0.15
+}
+
+fn reference_gerstner_offset(waves: &[GerstnerWave], p: [f32; 2], time: f32) -> [f32; 3] {
+    let mut offset = [0.0; 3];
+    for wave in waves {
+        let k = std::f32::consts::TAU / wave.wavelength;
+        let amplitude = wave.steepness / k;
+        // Normalized like the direction uploaded to the shader
+        let [dx, dz] = wave.direction;
+        let length = dx.hypot(dz);
+        let (dx, dz) = (dx / length, dz / length);
+        let phase = k * (dx * p[0] + dz * p[1] - wave.speed * time);
+        offset[0] += dx * phase.cos() * amplitude;
+        offset[1] += phase.sin() * amplitude;
+        offset[2] += dz * phase.cos() * amplitude;
+    }
+    offset
+}
+
+fn reference_gerstner_height(waves: &[GerstnerWave], x: f32, z: f32, time: f32) -> f32 {
+    let mut rest = [x, z];
+    for _ in 0..4 {
+        let offset = reference_gerstner_offset(waves, rest, time);
+        rest = [x - offset[0], z - offset[2]];
+    }
+    reference_gerstner_offset(waves, rest, time)[1]
+}
+
+// Highest crest the waves can add up to
+fn gerstner_bound(waves: &[GerstnerWave]) -> f32 {
+    waves
+        .iter()
+        .map(|wave| wave.steepness * wave.wavelength / std::f32::consts::TAU)
+        .sum()
+}
+
+// Any adapter will do, including software ones; None when there is no GPU
+fn create_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        dx12_shader_compiler: Default::default(),
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface: None,
+        force_fallback_adapter: false,
+    }))?;
+    pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("Shader Test Device"),
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::downlevel_defaults(),
+        },
+        None,
+    ))
+    .ok()
+}
+
+fn run_wave_height(device: &wgpu::Device, queue: &wgpu::Queue, inputs: &[Sample]) -> Vec<f32> {
+    run_function(
+        device,
+        queue,
+        "wave_height(sample.x, sample.z, sample.time)",
+        inputs,
+    )
+}
+
+// Evaluate `call`, a WGSL expression of `sample`, for every input
+fn run_function(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    call: &str,
+    inputs: &[Sample],
+) -> Vec<f32> {
+    run_shader(
+        device,
+        queue,
+        &format!("{}{}", WAVE_SHADER, HARNESS.replace("CALL", call)),
+        inputs,
+        None,
+    )
+}
+
+fn run_gerstner_height(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    waves: &[GerstnerWave],
+    inputs: &[Sample],
+) -> Vec<f32> {
+    let call = format!(
+        "gerstner_height(sample.x, sample.z, sample.time, {}u)",
+        waves.len()
+    );
+    let source = format!(
+        "{}{}{}",
+        GERSTNER_SHADER,
+        GERSTNER_BINDING,
+        HARNESS.replace("CALL", &call)
+    );
+    let waves: Vec<_> = waves.iter().map(GpuGerstnerWave::from).collect();
+    let waves = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Wave Test Gerstner Waves"),
+        contents: bytemuck::cast_slice(&waves),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    run_shader(device, queue, &source, inputs, Some(&waves))
+}
+
+fn run_shader(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: &str,
+    inputs: &[Sample],
+    gerstner_waves: Option<&wgpu::Buffer>,
+) -> Vec<f32> {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Wave Test Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Wave Test Pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "main",
+    });
+
+    let size = std::mem::size_of_val(inputs) as wgpu::BufferAddress;
+    let storage = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Wave Test Samples"),
+        contents: bytemuck::cast_slice(inputs),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Wave Test Readback"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut entries = vec![wgpu::BindGroupEntry {
+        binding: 0,
+        resource: storage.as_entire_binding(),
+    }];
+    if let Some(waves) = gerstner_waves {
+        entries.push(wgpu::BindGroupEntry {
+            binding: 1,
+            resource: waves.as_entire_binding(),
+        });
+    }
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Wave Test Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &entries,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Wave Test Encoder"),
+    });
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Wave Test Pass"),
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups((inputs.len() as u32).div_ceil(64), 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&storage, 0, &readback, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let outputs: Vec<Sample> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    outputs.iter().map(|s| s.height).collect()
+}
+
+fn sample(x: f32, z: f32, time: f32) -> Sample {
+    Sample {
+        x,
+        z,
+        time,
+        height: f32::NAN,
+    }
+}
+
+#[test]
+fn wave_height_matches_reference() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("No GPU adapter available, skipping shader test");
+        return;
+    };
+
+    let inputs = sweep();
+    let heights = run_wave_height(&device, &queue, &inputs);
+    for (input, height) in inputs.iter().zip(heights) {
+        let expected = reference_wave_height(input.x, input.z, input.time);
+        assert!(
+            (height - expected).abs() < TOLERANCE,
+            "wave_height({}, {}, {}) = {}, expected {}",
+            input.x,
+            input.z,
+            input.time,
+            height,
+            expected
+        );
+    }
+}
+
+#[test]
+fn wave_height_known_values() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("No GPU adapter available, skipping shader test");
+        return;
+    };
+
+    // Approved outputs: flat at the origin at t=0, bounded by the summed
+    // amplitudes, and periodic in time
+    let inputs = [
+        sample(0.0, 0.0, 0.0),
+        sample(0.25, -0.5, 1.0),
+        sample(0.25, -0.5, 1.0 + 4.0 * std::f32::consts::PI),
+    ];
+    let heights = run_wave_height(&device, &queue, &inputs);
+
+    assert!(heights[0].abs() < TOLERANCE);
+    assert!(heights[1].abs() <= 0.6);
+    assert!((heights[1] - 0.513_786).abs() < TOLERANCE, "{}", heights[1]);
+    assert!((heights[1] - heights[2]).abs() < 1e-3);
+}
+
+// The grid extent swept over a few animation times, t=0 included
+fn sweep() -> Vec<Sample> {
+    let mut inputs = Vec::new();
+    for time in [0.0, 0.5, 3.7, 120.0] {
+        for i in 0..=10 {
+            for j in 0..=10 {
+                inputs.push(sample(i as f32 * 0.2 - 1.0, j as f32 * 0.2 - 1.0, time));
+            }
+        }
+    }
+    inputs
+}
+
+fn expected(inputs: &[Sample], reference: impl Fn(f32, f32, f32) -> f32) -> Vec<f32> {
+    inputs
+        .iter()
+        .map(|input| reference(input.x, input.z, input.time))
+        .collect()
+}
+
+// Check finite heights within ±`bound` that match `expected` within
+// `tolerance`
+fn check_heights(
+    name: &str,
+    inputs: &[Sample],
+    heights: &[f32],
+    expected: &[f32],
+    bound: f32,
+    tolerance: f32,
+) {
+    for ((input, &height), &expected) in inputs.iter().zip(heights).zip(expected) {
+        let (x, z, time) = (input.x, input.z, input.time);
+        assert!(
+            height.is_finite() && height.abs() <= bound + TOLERANCE,
+            "{}({}, {}, {}) = {}, outside ±{}",
+            name,
+            x,
+            z,
+            time,
+            height,
+            bound
+        );
+        assert!(
+            (height - expected).abs() < tolerance,
+            "{}({}, {}, {}) = {}, expected {}",
+            name,
+            x,
+            z,
+            time,
+            height,
+            expected
+        );
+    }
+}
+
+#[test]
+fn ripple_height_matches_reference() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("No GPU adapter available, skipping shader test");
+        return;
+    };
+
+    let inputs = sweep();
+    let call = "ripple_height(sample.x, sample.z, sample.time)";
+    let heights = run_function(&device, &queue, call, &inputs);
+    check_heights(
+        "ripple_height",
+        &inputs,
+        &heights,
+        &expected(&inputs, reference_ripple_height),
+        0.3,
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn interference_height_matches_reference() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("No GPU adapter available, skipping shader test");
+        return;
+    };
+
+    let inputs = sweep();
+    let call = "interference_height(sample.x, sample.z, sample.time)";
+    let heights = run_function(&device, &queue, call, &inputs);
+    check_heights(
+        "interference_height",
+        &inputs,
+        &heights,
+        &expected(&inputs, reference_interference_height),
+        0.3,
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn gerstner_height_matches_reference() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("No GPU adapter available, skipping shader test");
+        return;
+    };
+
+    let waves = GerstnerWave::default_set();
+    let inputs = sweep();
+    let heights = run_gerstner_height(&device, &queue, &waves, &inputs);
+    // The offset search repeats small errors, so allow a little more
+    check_heights(
+        "gerstner_height",
+        &inputs,
+        &heights,
+        &expected(&inputs, |x, z, time| {
+            reference_gerstner_height(&waves, x, z, time)
+        }),
+        gerstner_bound(&waves),
+        1e-3,
+    );
+}
+
+#[test]
+fn terrain_height_scrolls_and_stays_bounded() {
+    let Some((device, queue)) = create_device() else {
+        eprintln!("No GPU adapter available, skipping shader test");
+        return;
+    };
+
+    // The hash-based noise has no exact CPU reference. Instead, the terrain
+    // at any time must be the t=0 terrain scrolled 0.3 grid units per second
+    // along Z.
+    let inputs = sweep();
+    let scrolled: Vec<_> = inputs
+        .iter()
+        .map(|input| sample(input.x, input.z + input.time * 0.3, 0.0))
+        .collect();
+    let call = "terrain_height(sample.x, sample.z, sample.time)";
+    let heights = run_function(&device, &queue, call, &inputs);
+    let at_zero = run_function(&device, &queue, call, &scrolled);
+
+    // Five octaves of roughly -1..1 noise, halving from 0.5, scaled by 0.8
+    check_heights(
+        "terrain_height",
+        &inputs,
+        &heights,
+        &at_zero,
+        0.8 * 1.5,
+        1e-3,
+    );
+}